@@ -0,0 +1,124 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+use opencv::core::Mat;
+use opencv::prelude::*;
+
+use crate::capture::{FrameSource, VideoCaptureSource};
+use crate::screenshot::{ScreenshotOptions, ScreenshotReader};
+
+/// Time spent in each stage of processing a frame, summed over all frames.
+#[derive(Default)]
+struct StageTimings {
+    frames: u32,
+    parsed: u32,
+    capture: Duration,
+    preprocessing: Duration,
+    matching: Duration,
+}
+
+impl StageTimings {
+    fn render(&self) -> Vec<String> {
+        let total = self.capture + self.preprocessing + self.matching;
+        let fps = if total > Duration::ZERO {
+            self.frames as f64 / total.as_secs_f64()
+        } else {
+            0.0
+        };
+
+        let mut lines = vec![format!(
+            "{} frames, {} parsed, {:.1} frames/s",
+            self.frames, self.parsed, fps
+        )];
+        for (stage, time) in [
+            ("capture", self.capture),
+            ("preprocessing", self.preprocessing),
+            ("matching", self.matching),
+        ] {
+            let per_frame_ms = if self.frames > 0 {
+                time.as_secs_f64() * 1000.0 / self.frames as f64
+            } else {
+                0.0
+            };
+            lines.push(format!("  {}: {:.2} ms per frame", stage, per_frame_ms));
+        }
+        lines
+    }
+}
+
+/// Reads the IGT from (up to `max_frames`) frames of the video at `path` as fast as possible and
+/// prints the frame rate and the time spent decoding, preprocessing and matching each frame.
+pub fn run(path: &Path, max_frames: Option<u32>, options: &ScreenshotOptions) -> Result<()> {
+    let mut video = VideoCaptureSource::open_file(path)?;
+    let mut reader = ScreenshotReader::new(options)?;
+
+    let mut timings = StageTimings::default();
+    let mut detected_roi = None;
+    let mut frame = Mat::default();
+    let mut matches = Vec::new();
+    while max_frames.is_none_or(|max| timings.frames < max) {
+        let start = Instant::now();
+        if !video.read(&mut frame)? {
+            break;
+        }
+        if frame.empty() {
+            continue;
+        }
+        let captured = Instant::now();
+
+        // Detecting the ROI isn't part of the hot path, only do it once
+        let roi = match detected_roi {
+            Some(roi) => roi,
+            None => *detected_roi.insert(reader.roi(&frame)?),
+        };
+        let start_preprocessing = Instant::now();
+        let binarized_roi = reader.binarize(&frame, roi)?;
+        let preprocessed = Instant::now();
+
+        matches.clear();
+        let parsed = reader.recognize(&binarized_roi, &mut matches).is_ok();
+        let matched = Instant::now();
+
+        timings.frames += 1;
+        if parsed {
+            timings.parsed += 1;
+        }
+        timings.capture += captured - start;
+        timings.preprocessing += preprocessed - start_preprocessing;
+        timings.matching += matched - preprocessed;
+    }
+
+    if timings.frames == 0 {
+        return Err(anyhow!("No frames in {}", path.display()));
+    }
+    for line in timings.render() {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_frame_rate_and_stage_averages() {
+        let timings = StageTimings {
+            frames: 4,
+            parsed: 3,
+            capture: Duration::from_millis(20),
+            preprocessing: Duration::from_millis(8),
+            matching: Duration::from_millis(12),
+        };
+        assert_eq!(
+            timings.render(),
+            vec![
+                "4 frames, 3 parsed, 100.0 frames/s",
+                "  capture: 5.00 ms per frame",
+                "  preprocessing: 2.00 ms per frame",
+                "  matching: 3.00 ms per frame",
+            ]
+        );
+    }
+}
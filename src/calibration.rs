@@ -0,0 +1,265 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+use opencv::core::{Mat, Rect};
+use opencv::prelude::*;
+
+use crate::capture::{CameraProperties, FrameSource, VideoCaptureSource};
+use crate::ocr;
+use crate::screenshot::{ScreenshotOptions, ScreenshotReader};
+use crate::training::segment_glyphs;
+
+/// Thresholds tried for every character, in hundredths
+const CANDIDATE_THRESHOLDS: std::ops::RangeInclusive<u32> = 50..=99;
+
+/// Horizontal room (in pixels) around a glyph in which the templates are matched against it
+const GLYPH_WINDOW_MARGIN: i32 = 4;
+
+/// Frames read per requested sample before giving up, e.g. because the guidebook was closed
+const MAX_FRAMES_PER_SAMPLE: u32 = 10;
+
+/// Match confidences of one template against the glyphs of its own character (`genuine`) and
+/// against the glyphs of all other characters (`impostor`).
+#[derive(Default)]
+struct Scores {
+    genuine: Vec<f32>,
+    impostor: Vec<f32>,
+}
+
+impl Scores {
+    /// Number of misreads with `threshold`: genuine glyphs below it plus impostors reaching it.
+    fn errors(&self, threshold: f32) -> usize {
+        self.genuine.iter().filter(|&&c| c < threshold).count()
+            + self.impostor.iter().filter(|&&c| c >= threshold).count()
+    }
+}
+
+/// Tries all candidate thresholds and returns the one with the fewest errors, together with that
+/// number of errors. Among equally good thresholds the middle one is picked, as far as possible
+/// from both the genuine and the impostor confidences. `None` if the character wasn't seen.
+fn sweep_threshold(scores: &Scores) -> Option<(f32, usize)> {
+    if scores.genuine.is_empty() {
+        return None;
+    }
+
+    let candidates: Vec<(f32, usize)> = CANDIDATE_THRESHOLDS
+        .map(|hundredths| {
+            let threshold = hundredths as f32 / 100.0;
+            (threshold, scores.errors(threshold))
+        })
+        .collect();
+    let fewest = candidates.iter().map(|&(_, errors)| errors).min()?;
+
+    // Longest run of consecutive candidates with the fewest errors
+    let mut best = (0, 0);
+    let mut start = None;
+    for (i, &(_, errors)) in candidates.iter().enumerate() {
+        match (start, errors == fewest) {
+            (None, true) => start = Some(i),
+            (Some(s), false) => {
+                if i - s > best.1 - best.0 {
+                    best = (s, i);
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start
+        && candidates.len() - s > best.1 - best.0
+    {
+        best = (s, candidates.len());
+    }
+
+    Some(candidates[(best.0 + best.1 - 1) / 2])
+}
+
+/// Part of the binarized ROI in which the templates are matched against `glyph`: the glyph with
+/// some horizontal margin, over the full height of the ROI.
+fn glyph_window(glyph: Rect, width: i32, height: i32) -> Rect {
+    let x = (glyph.x - GLYPH_WINDOW_MARGIN).max(0);
+    let end = (glyph.x + glyph.width + GLYPH_WINDOW_MARGIN).min(width);
+    Rect::new(x, 0, end - x, height)
+}
+
+/// Asks for the IGT shown in the guidebook, as the characters in the ROI.
+fn prompt_expected() -> Result<Vec<char>> {
+    println!(
+        "Open the guidebook, then type the IGT exactly as shown in the ROI (e.g. 117%3:03:23) and press Enter:"
+    );
+    print!("> ");
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    let expected: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    if expected.is_empty() {
+        return Err(anyhow!("No IGT entered"));
+    }
+    if let Some(&c) = expected
+        .iter()
+        .find(|&&c| ocr::template_file_name(c).is_none())
+    {
+        return Err(anyhow!("No template for character '{}'", c));
+    }
+    Ok(expected)
+}
+
+/// Guided calibration of the template thresholds: matches every template against the characters
+/// of the IGT shown in the guidebook over `frames` live frames, sweeps the candidate thresholds per
+/// character and writes the best ones to the thresholds file of the templates directory. Characters
+/// that aren't part of the shown IGT keep their current threshold.
+pub fn run(
+    camera: i32,
+    video: Option<&Path>,
+    frames: u32,
+    options: &ScreenshotOptions,
+) -> Result<()> {
+    let mut reader = ScreenshotReader::new(options)?;
+    let expected = prompt_expected()?;
+
+    let mut source: Box<dyn FrameSource> = match video {
+        Some(path) => Box::new(VideoCaptureSource::open_file(path)?),
+        None => Box::new(VideoCaptureSource::open_camera(
+            camera,
+            &CameraProperties::default(),
+        )?),
+    };
+
+    let mut scores: BTreeMap<char, Scores> = BTreeMap::new();
+    let mut roi = None;
+    let mut samples = 0;
+    let mut read = 0;
+    let mut frame = Mat::default();
+    while samples < frames && read < frames * MAX_FRAMES_PER_SAMPLE {
+        if !source.read(&mut frame)? {
+            break;
+        }
+        read += 1;
+        if frame.empty() {
+            continue;
+        }
+
+        let roi = match roi {
+            Some(roi) => roi,
+            None => *roi.insert(reader.roi(&frame)?),
+        };
+        let binarized = reader.binarize(&frame, roi)?;
+        let glyphs = segment_glyphs(&binarized)?;
+        if glyphs.len() != expected.len() {
+            continue;
+        }
+
+        for (&character, &glyph) in expected.iter().zip(&glyphs) {
+            let window = Mat::roi(
+                &binarized,
+                glyph_window(glyph, binarized.cols(), binarized.rows()),
+            )?;
+            for template in reader.templates().iter() {
+                let Some(confidence) = ocr::best_confidence(&window, template)? else {
+                    continue;
+                };
+                let entry = scores.entry(template.character()).or_default();
+                if template.character() == character {
+                    entry.genuine.push(confidence);
+                } else {
+                    entry.impostor.push(confidence);
+                }
+            }
+        }
+        samples += 1;
+        print!("\rSampled {}/{} frames", samples, frames);
+        io::stdout().flush()?;
+    }
+    println!();
+
+    if samples == 0 {
+        return Err(anyhow!(
+            "None of {} frames showed {} characters in the ROI, is the guidebook open?",
+            read,
+            expected.len()
+        ));
+    }
+
+    let mut thresholds: BTreeMap<char, f32> = BTreeMap::new();
+    println!(
+        "{:>4} {:>8} {:>8} {:>8} {:>9} {:>9} {:>7}",
+        "char", "samples", "genuine", "impostor", "old", "new", "errors"
+    );
+    for template in reader.templates().iter() {
+        let character = template.character();
+        let Some(character_scores) = scores.get(&character) else {
+            continue;
+        };
+        let Some((threshold, errors)) = sweep_threshold(character_scores) else {
+            continue;
+        };
+
+        let genuine = character_scores
+            .genuine
+            .iter()
+            .copied()
+            .fold(f32::MAX, f32::min);
+        let impostor = character_scores.impostor.iter().copied().reduce(f32::max);
+        println!(
+            "{:>4} {:>8} {:>8.3} {:>8} {:>9.2} {:>9.2} {:>7}",
+            character,
+            character_scores.genuine.len(),
+            genuine,
+            impostor.map_or("-".to_string(), |i| format!("{:.3}", i)),
+            template.threshold(),
+            threshold,
+            errors
+        );
+        thresholds.insert(character, threshold);
+    }
+
+    let path = ocr::save_thresholds(reader.templates().dir(), &thresholds)?;
+
+    println!();
+    println!(
+        "Thresholds written to {} (characters not in the IGT kept their threshold)",
+        path.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sweep_picks_middle_of_separating_thresholds() {
+        let scores = Scores {
+            genuine: vec![0.92, 0.9],
+            impostor: vec![0.6, 0.7],
+        };
+        let (threshold, errors) = sweep_threshold(&scores).unwrap();
+        assert!((threshold - 0.8).abs() < 1e-6);
+        assert_eq!(errors, 0);
+
+        // Impostors up to the top of the range
+        let scores = Scores {
+            genuine: vec![0.995],
+            impostor: vec![0.9],
+        };
+        let (threshold, errors) = sweep_threshold(&scores).unwrap();
+        assert!((threshold - 0.95).abs() < 1e-6);
+        assert_eq!(errors, 0);
+
+        assert!(sweep_threshold(&Scores::default()).is_none());
+    }
+
+    #[test]
+    fn sweep_minimizes_errors_of_overlapping_confidences() {
+        let scores = Scores {
+            genuine: vec![0.9, 0.9, 0.6],
+            impostor: vec![0.85, 0.8],
+        };
+        let (threshold, errors) = sweep_threshold(&scores).unwrap();
+        assert!((threshold - 0.88).abs() < 1e-6);
+        assert_eq!(errors, 1);
+    }
+}
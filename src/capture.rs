@@ -0,0 +1,666 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+use opencv::imgproc;
+use opencv::prelude::*;
+use opencv::videoio;
+
+use crate::correction::FrameCorrection;
+
+/// A source of captured frames (capture device, video file, screen, ...).
+pub trait FrameSource {
+    /// Reads the next frame into `frame`. Returns `false` once the source is exhausted (e.g. end of a
+    /// video file). Live sources may return an empty frame if no frame is available right now.
+    fn read(&mut self, frame: &mut Mat) -> Result<bool>;
+
+    /// Skips the next `count` frames, without decoding them if the source supports it.
+    fn skip(&mut self, count: u32) -> Result<()> {
+        let mut frame = Mat::default();
+        for _ in 0..count {
+            if !self.read(&mut frame)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Position of the last read frame: the video timestamp for files, the time since opening for
+    /// live sources.
+    fn position(&self) -> Result<Duration>;
+}
+
+/// Settings of the capture device, left to the driver unless given. Fixing exposure, gain and
+/// focus avoids the flicker of their automatic modes, which makes the OCR fail intermittently.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct CameraProperties {
+    /// Pixel format requested from the capture device, e.g. MJPG or YUYV
+    #[arg(long, value_name = "CODE", value_parser = parse_fourcc, conflicts_with = "source")]
+    pub fourcc: Option<[char; 4]>,
+
+    /// Frame rate requested from the capture device
+    #[arg(long, value_name = "FPS", conflicts_with = "source")]
+    pub fps: Option<f64>,
+
+    /// Fixed exposure of the capture device (disables auto exposure, the range depends on the driver)
+    #[arg(
+        long,
+        value_name = "VALUE",
+        allow_negative_numbers = true,
+        conflicts_with = "source"
+    )]
+    pub exposure: Option<f64>,
+
+    /// Fixed gain of the capture device
+    #[arg(long, value_name = "VALUE", conflicts_with = "source")]
+    pub gain: Option<f64>,
+
+    /// Fixed focus of the capture device (disables autofocus)
+    #[arg(long, value_name = "VALUE", conflicts_with = "source")]
+    pub focus: Option<f64>,
+}
+
+impl CameraProperties {
+    /// Applies the given properties, warning about those the device doesn't support.
+    fn apply(&self, video: &mut videoio::VideoCapture) -> Result<()> {
+        let mut properties = Vec::new();
+        if let Some(fps) = self.fps {
+            properties.push(("fps", videoio::CAP_PROP_FPS, fps));
+        }
+        if let Some(exposure) = self.exposure {
+            // Manual mode is 1 for V4L2 and 0.25 for most other backends
+            let manual = if video.get_backend_name()? == "V4L2" {
+                1.0
+            } else {
+                0.25
+            };
+            properties.push(("auto exposure", videoio::CAP_PROP_AUTO_EXPOSURE, manual));
+            properties.push(("exposure", videoio::CAP_PROP_EXPOSURE, exposure));
+        }
+        if let Some(gain) = self.gain {
+            properties.push(("gain", videoio::CAP_PROP_GAIN, gain));
+        }
+        if let Some(focus) = self.focus {
+            properties.push(("autofocus", videoio::CAP_PROP_AUTOFOCUS, 0.0));
+            properties.push(("focus", videoio::CAP_PROP_FOCUS, focus));
+        }
+
+        for (name, property, value) in properties {
+            if !video.set(property, value)? {
+                println!("The capture device doesn't support setting the {}", name);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a four character code of a pixel format, e.g. MJPG.
+pub fn parse_fourcc(s: &str) -> Result<[char; 4]> {
+    let chars: Vec<char> = s.chars().collect();
+    match chars[..] {
+        [a, b, c, d] if chars.iter().all(|ch| ch.is_ascii_graphic()) => Ok([a, b, c, d]),
+        _ => Err(anyhow!(
+            "Invalid pixel format '{}': must be four characters, e.g. MJPG",
+            s
+        )),
+    }
+}
+
+/// Capture device or video file opened via OpenCV's `VideoCapture`.
+pub struct VideoCaptureSource {
+    video: videoio::VideoCapture,
+    is_file: bool,
+    opened_at: Instant,
+}
+
+impl VideoCaptureSource {
+    /// Opens the capture device (webcam / capture card) with the given index at 1920x1080.
+    pub fn open_camera(index: i32, properties: &CameraProperties) -> Result<Self> {
+        let mut video = videoio::VideoCapture::new(index, videoio::CAP_ANY)?;
+        if !video.is_opened()? {
+            return Err(anyhow!("Unable to open capture device {}", index));
+        }
+
+        // The pixel format has to be set before the resolution
+        if let Some([a, b, c, d]) = properties.fourcc
+            && !video.set(
+                videoio::CAP_PROP_FOURCC,
+                videoio::VideoWriter::fourcc(a, b, c, d)? as f64,
+            )?
+        {
+            println!("The capture device doesn't support setting the pixel format");
+        }
+
+        // Set resolution to 1920x1080
+        video.set(videoio::CAP_PROP_FRAME_WIDTH, 1920.0)?;
+        video.set(videoio::CAP_PROP_FRAME_HEIGHT, 1080.0)?;
+
+        // Optional: read back to verify
+        let width = video.get(videoio::CAP_PROP_FRAME_WIDTH)?;
+        let height = video.get(videoio::CAP_PROP_FRAME_HEIGHT)?;
+        println!("Resolution set to: {}x{}", width, height);
+
+        properties.apply(&mut video)?;
+
+        Ok(Self {
+            video,
+            is_file: false,
+            opened_at: Instant::now(),
+        })
+    }
+
+    /// Opens a recorded video file.
+    pub fn open_file(path: &Path) -> Result<Self> {
+        let video = videoio::VideoCapture::from_file(
+            path.to_str()
+                .ok_or_else(|| anyhow!("Invalid video path: {}", path.display()))?,
+            videoio::CAP_ANY,
+        )?;
+        if !video.is_opened()? {
+            return Err(anyhow!("Unable to open video file {}", path.display()));
+        }
+
+        let width = video.get(videoio::CAP_PROP_FRAME_WIDTH)?;
+        let height = video.get(videoio::CAP_PROP_FRAME_HEIGHT)?;
+        println!("Video resolution: {}x{}", width, height);
+
+        Ok(Self {
+            video,
+            is_file: true,
+            opened_at: Instant::now(),
+        })
+    }
+
+    /// Opens an arbitrary GStreamer pipeline (which must end in an `appsink`) as a live source.
+    pub fn open_gstreamer(pipeline: &str) -> Result<Self> {
+        let video = videoio::VideoCapture::from_file(pipeline, videoio::CAP_GSTREAMER)?;
+        if !video.is_opened()? {
+            return Err(anyhow!(
+                "Unable to open GStreamer pipeline '{}' (is OpenCV built with GStreamer support?)",
+                pipeline
+            ));
+        }
+
+        let width = video.get(videoio::CAP_PROP_FRAME_WIDTH)?;
+        let height = video.get(videoio::CAP_PROP_FRAME_HEIGHT)?;
+        println!("Pipeline resolution: {}x{}", width, height);
+
+        Ok(Self {
+            video,
+            is_file: false,
+            opened_at: Instant::now(),
+        })
+    }
+
+    /// Opens a network stream (RTMP/HTTP/RTSP/...) via FFmpeg as a live source.
+    pub fn open_stream(url: &str) -> Result<Self> {
+        let video = videoio::VideoCapture::from_file(url, videoio::CAP_FFMPEG)?;
+        if !video.is_opened()? {
+            return Err(anyhow!("Unable to open stream '{}'", url));
+        }
+
+        Ok(Self {
+            video,
+            is_file: false,
+            opened_at: Instant::now(),
+        })
+    }
+
+    /// Seeks to the given position in a video file.
+    pub fn seek(&mut self, position: Duration) -> Result<()> {
+        if !self.is_file
+            || !self
+                .video
+                .set(videoio::CAP_PROP_POS_MSEC, position.as_secs_f64() * 1000.0)?
+        {
+            return Err(anyhow!(
+                "Unable to seek to {}",
+                crate::retime::format_video_time(position)
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl FrameSource for VideoCaptureSource {
+    fn read(&mut self, frame: &mut Mat) -> Result<bool> {
+        let has_frame = self.video.read(frame)?;
+        // Capture devices might temporarily fail to deliver a frame, only files can run out of frames
+        Ok(has_frame || !self.is_file)
+    }
+
+    fn skip(&mut self, count: u32) -> Result<()> {
+        for _ in 0..count {
+            if !self.video.grab()? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn position(&self) -> Result<Duration> {
+        if self.is_file {
+            Ok(Duration::from_secs_f64(
+                self.video.get(videoio::CAP_PROP_POS_MSEC)?.max(0.0) / 1000.0,
+            ))
+        } else {
+            Ok(self.opened_at.elapsed())
+        }
+    }
+}
+
+/// Resolutions tried when probing capture devices
+const PROBE_RESOLUTIONS: [(i32, i32); 4] = [(640, 480), (1280, 720), (1920, 1080), (3840, 2160)];
+
+/// A capture device found by `probe_cameras`.
+pub struct CameraInfo {
+    pub index: i32,
+    /// Device name reported by the OS, if available
+    pub name: Option<String>,
+    pub backend: String,
+    /// Supported resolutions out of `PROBE_RESOLUTIONS`, as (width, height)
+    pub resolutions: Vec<(i32, i32)>,
+}
+
+/// Tries to open the capture devices with indices `0..max_index` and reports the ones that exist.
+pub fn probe_cameras(max_index: i32) -> Vec<CameraInfo> {
+    (0..max_index)
+        .filter_map(|index| probe_camera(index).ok().flatten())
+        .collect()
+}
+
+fn probe_camera(index: i32) -> Result<Option<CameraInfo>> {
+    let mut video = videoio::VideoCapture::new(index, videoio::CAP_ANY)?;
+    if !video.is_opened()? {
+        return Ok(None);
+    }
+
+    let backend = video.get_backend_name()?;
+    let mut resolutions = Vec::new();
+    for (width, height) in PROBE_RESOLUTIONS {
+        video.set(videoio::CAP_PROP_FRAME_WIDTH, width as f64)?;
+        video.set(videoio::CAP_PROP_FRAME_HEIGHT, height as f64)?;
+        // Devices fall back to a different resolution if the requested one isn't supported
+        let actual = (
+            video.get(videoio::CAP_PROP_FRAME_WIDTH)? as i32,
+            video.get(videoio::CAP_PROP_FRAME_HEIGHT)? as i32,
+        );
+        if actual == (width, height) {
+            resolutions.push(actual);
+        }
+    }
+
+    Ok(Some(CameraInfo {
+        index,
+        name: camera_name(index),
+        backend,
+        resolutions,
+    }))
+}
+
+/// Name of the capture device, as reported by V4L2.
+#[cfg(target_os = "linux")]
+fn camera_name(index: i32) -> Option<String> {
+    std::fs::read_to_string(format!("/sys/class/video4linux/video{}/name", index))
+        .ok()
+        .map(|name| name.trim().to_string())
+}
+
+/// OpenCV doesn't expose device names, and there's no simple way to get them on other platforms.
+#[cfg(not(target_os = "linux"))]
+fn camera_name(_index: i32) -> Option<String> {
+    None
+}
+
+enum ScreenTarget {
+    Monitor(xcap::Monitor),
+    Window(xcap::Window),
+}
+
+/// Grabs a monitor or window directly, without going through a capture device.
+pub struct ScreenSource {
+    target: ScreenTarget,
+    opened_at: Instant,
+}
+
+impl ScreenSource {
+    /// Captures the monitor with the given index (in the order reported by the OS).
+    pub fn open_monitor(index: usize) -> Result<Self> {
+        let mut monitors =
+            xcap::Monitor::all().map_err(|e| anyhow!("Failed to enumerate monitors: {}", e))?;
+        if index >= monitors.len() {
+            return Err(anyhow!(
+                "Monitor {} not found ({} monitors available)",
+                index,
+                monitors.len()
+            ));
+        }
+        let monitor = monitors.swap_remove(index);
+        println!(
+            "Capturing monitor '{}' ({}x{})",
+            monitor.name(),
+            monitor.width(),
+            monitor.height()
+        );
+
+        Ok(Self {
+            target: ScreenTarget::Monitor(monitor),
+            opened_at: Instant::now(),
+        })
+    }
+
+    /// Captures the first window whose title contains `title` (case-insensitive).
+    pub fn open_window(title: &str) -> Result<Self> {
+        let needle = title.to_lowercase();
+        let window = xcap::Window::all()
+            .map_err(|e| anyhow!("Failed to enumerate windows: {}", e))?
+            .into_iter()
+            .find(|w| w.title().to_lowercase().contains(&needle))
+            .ok_or_else(|| anyhow!("No window with title containing '{}' found", title))?;
+        println!(
+            "Capturing window '{}' ({}x{})",
+            window.title(),
+            window.width(),
+            window.height()
+        );
+
+        Ok(Self {
+            target: ScreenTarget::Window(window),
+            opened_at: Instant::now(),
+        })
+    }
+}
+
+impl FrameSource for ScreenSource {
+    fn read(&mut self, frame: &mut Mat) -> Result<bool> {
+        let image = match &self.target {
+            ScreenTarget::Monitor(monitor) => monitor.capture_image(),
+            ScreenTarget::Window(window) => window.capture_image(),
+        }
+        .map_err(|e| anyhow!("Screen capture failed: {}", e))?;
+
+        let mut rgba = Mat::new_rows_cols_with_default(
+            image.height() as i32,
+            image.width() as i32,
+            opencv::core::CV_8UC4,
+            opencv::core::Scalar::all(0.0),
+        )?;
+        rgba.data_bytes_mut()?.copy_from_slice(image.as_raw());
+
+        imgproc::cvt_color(
+            &rgba,
+            frame,
+            imgproc::COLOR_RGBA2BGR,
+            0,
+            opencv::core::AlgorithmHint::ALGO_HINT_DEFAULT,
+        )?;
+
+        Ok(true)
+    }
+
+    fn position(&self) -> Result<Duration> {
+        Ok(self.opened_at.elapsed())
+    }
+}
+
+/// Wraps a live source and reopens it (with exponential backoff) once it hasn't delivered a frame
+/// for `stall_timeout`.
+pub struct Reconnecting<F> {
+    open: F,
+    source: Option<Box<dyn FrameSource>>,
+    stall_timeout: Duration,
+    last_frame_at: Instant,
+    backoff: Duration,
+    next_attempt_at: Instant,
+    opened_at: Instant,
+}
+
+impl<F> Reconnecting<F>
+where
+    F: FnMut() -> Result<Box<dyn FrameSource>>,
+{
+    const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+    const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+    /// Opens the source using `open`. Failing to open it initially is an error, later failures are
+    /// retried.
+    pub fn new(mut open: F, stall_timeout: Duration) -> Result<Self> {
+        let source = open()?;
+        let now = Instant::now();
+        Ok(Self {
+            open,
+            source: Some(source),
+            stall_timeout,
+            last_frame_at: now,
+            backoff: Self::INITIAL_BACKOFF,
+            next_attempt_at: now,
+            opened_at: now,
+        })
+    }
+
+    fn reconnect(&mut self) {
+        // Not waited for here, so that the capture thread keeps noticing when it should stop
+        if Instant::now() < self.next_attempt_at {
+            return;
+        }
+
+        match (self.open)() {
+            Ok(source) => {
+                println!("Capture reconnected");
+                self.source = Some(source);
+                self.backoff = Self::INITIAL_BACKOFF;
+                self.last_frame_at = Instant::now();
+            }
+            Err(e) => {
+                println!(
+                    "Failed to reconnect capture: {}, retrying in {}s",
+                    e,
+                    self.backoff.as_secs()
+                );
+                self.next_attempt_at = Instant::now() + self.backoff;
+                self.backoff = (self.backoff * 2).min(Self::MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+impl<F> FrameSource for Reconnecting<F>
+where
+    F: FnMut() -> Result<Box<dyn FrameSource>>,
+{
+    fn read(&mut self, frame: &mut Mat) -> Result<bool> {
+        if self.source.is_none() {
+            self.reconnect();
+        }
+
+        let Some(source) = self.source.as_mut() else {
+            // Still disconnected, caller skips the empty frame
+            *frame = Mat::default();
+            return Ok(true);
+        };
+
+        match source.read(frame) {
+            Ok(false) => return Ok(false),
+            Ok(true) if !frame.empty() => self.last_frame_at = Instant::now(),
+            Ok(true) => {}
+            Err(e) => {
+                println!("Failed to read frame: {}", e);
+                *frame = Mat::default();
+            }
+        }
+
+        if self.last_frame_at.elapsed() > self.stall_timeout {
+            println!(
+                "No frames received for {}s, reconnecting capture...",
+                self.stall_timeout.as_secs()
+            );
+            // Dropping the source releases the device / connection
+            self.source = None;
+            self.next_attempt_at = Instant::now();
+        }
+
+        Ok(true)
+    }
+
+    fn position(&self) -> Result<Duration> {
+        Ok(self.opened_at.elapsed())
+    }
+}
+
+/// A frame read by the capture thread, with its position in the source.
+pub struct CapturedFrame {
+    pub frame: Mat,
+    pub position: Duration,
+}
+
+pub struct CaptureOptions {
+    /// Only deliver every Nth frame (the others are skipped without decoding, if supported)
+    pub frame_step: u32,
+    /// Stop once the source position exceeds this
+    pub end: Option<Duration>,
+    /// Drop new frames while the consumer is busy instead of waiting for it. Live sources should do
+    /// this to avoid processing stale frames, files shouldn't so that no frame is missed.
+    pub drop_when_full: bool,
+    /// Crop / perspective correction applied to every frame before it is delivered
+    pub correction: Option<FrameCorrection>,
+}
+
+/// Number of frames handled by the capture thread so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CaptureCounts {
+    /// Frames read from the source (including empty ones)
+    pub read: u64,
+    /// Frames the source delivered without any content
+    pub empty: u64,
+    /// Frames dropped because the consumer was busy
+    pub dropped: u64,
+}
+
+/// Counters updated by the capture thread and read by the consumer.
+#[derive(Default)]
+struct CaptureCounters {
+    read: AtomicU64,
+    empty: AtomicU64,
+    dropped: AtomicU64,
+    /// Set by `CaptureThread::join` to make the capture thread exit
+    stop: AtomicBool,
+}
+
+/// Reads frames on a dedicated thread and hands them to the consumer through a bounded channel, so
+/// that slow OCR doesn't cause a backlog in the capture device.
+pub struct CaptureThread {
+    frames: Receiver<CapturedFrame>,
+    handle: JoinHandle<Result<()>>,
+    counters: Arc<CaptureCounters>,
+}
+
+impl CaptureThread {
+    const CHANNEL_CAPACITY: usize = 2;
+    /// How long to wait after an empty frame before reading again, instead of spinning while a live
+    /// source has nothing to deliver
+    const EMPTY_FRAME_DELAY: Duration = Duration::from_millis(10);
+
+    /// Spawns the capture thread. The source is opened on the capture thread using `open`, errors are
+    /// reported by `join`.
+    pub fn spawn<F>(open: F, options: CaptureOptions) -> Self
+    where
+        F: FnOnce() -> Result<Box<dyn FrameSource>> + Send + 'static,
+    {
+        let (sender, frames) = mpsc::sync_channel(Self::CHANNEL_CAPACITY);
+        let counters = Arc::new(CaptureCounters::default());
+        let thread_counters = counters.clone();
+
+        let handle = thread::spawn(move || -> Result<()> {
+            let counters = thread_counters;
+            let mut source = open()?;
+
+            while !counters.stop.load(Ordering::Relaxed) {
+                source.skip(options.frame_step.saturating_sub(1))?;
+
+                let mut frame = Mat::default();
+                if !source.read(&mut frame)? {
+                    // End of video file
+                    return Ok(());
+                }
+                counters.read.fetch_add(1, Ordering::Relaxed);
+                if frame.empty() {
+                    counters.empty.fetch_add(1, Ordering::Relaxed);
+                    thread::sleep(Self::EMPTY_FRAME_DELAY);
+                    continue;
+                }
+
+                let position = source.position()?;
+                if options.end.is_some_and(|end| position > end) {
+                    return Ok(());
+                }
+
+                if let Some(correction) = &options.correction {
+                    frame = correction.apply(&frame)?;
+                }
+
+                let captured = CapturedFrame { frame, position };
+                if options.drop_when_full {
+                    match sender.try_send(captured) {
+                        Ok(()) => {}
+                        Err(TrySendError::Full(_)) => {
+                            counters.dropped.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(TrySendError::Disconnected(_)) => return Ok(()),
+                    }
+                } else if sender.send(captured).is_err() {
+                    // Consumer is gone
+                    return Ok(());
+                }
+            }
+            Ok(())
+        });
+
+        Self {
+            frames,
+            handle,
+            counters,
+        }
+    }
+
+    /// Number of frames handled by the capture thread so far.
+    pub fn counts(&self) -> CaptureCounts {
+        CaptureCounts {
+            read: self.counters.read.load(Ordering::Relaxed),
+            empty: self.counters.empty.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Waits for the next frame. Returns `None` once the capture thread stopped, call `join` to find
+    /// out whether it failed.
+    pub fn recv(&self) -> Option<CapturedFrame> {
+        self.frames.recv().ok()
+    }
+
+    /// Waits at most `timeout` for the next frame, e.g. to keep reacting to a shutdown request while
+    /// a live source doesn't deliver frames.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<CapturedFrame, RecvTimeoutError> {
+        self.frames.recv_timeout(timeout)
+    }
+
+    /// Stops the capture thread (if still running) and returns its result.
+    pub fn join(self) -> Result<()> {
+        let Self {
+            frames,
+            handle,
+            counters,
+        } = self;
+        // Also stops a capture thread that isn't sending, e.g. while a live source only delivers
+        // empty frames
+        counters.stop.store(true, Ordering::Relaxed);
+        // Disconnects the channel, which makes a capture thread waiting to send exit
+        drop(frames);
+        handle
+            .join()
+            .map_err(|_| anyhow!("Capture thread panicked"))?
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::BTreeMap;
+
+use crate::ocr::TemplateMatch;
+
+/// Confidence distribution of the matches of a single character.
+struct CharacterStats {
+    count: u64,
+    min: f32,
+    max: f32,
+    sum: f64,
+    /// Smallest distance between a confidence and the template's threshold
+    min_margin: f32,
+}
+
+/// Accumulates the match confidences per character, to find out which templates are marginal.
+#[derive(Default)]
+pub struct ConfidenceStats {
+    characters: BTreeMap<char, CharacterStats>,
+}
+
+impl ConfidenceStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the matches of a successfully read IGT.
+    pub fn record(&mut self, matches: &[TemplateMatch]) {
+        for m in matches {
+            let margin = m.confidence - m.threshold;
+            let stats = self
+                .characters
+                .entry(m.character)
+                .or_insert(CharacterStats {
+                    count: 0,
+                    min: f32::MAX,
+                    max: f32::MIN,
+                    sum: 0.0,
+                    min_margin: f32::MAX,
+                });
+            stats.count += 1;
+            stats.min = stats.min.min(m.confidence);
+            stats.max = stats.max.max(m.confidence);
+            stats.sum += m.confidence as f64;
+            stats.min_margin = stats.min_margin.min(margin);
+        }
+    }
+
+    /// Renders one line per character seen so far, sorted by character.
+    pub fn render(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "{:>4} {:>8} {:>6} {:>6} {:>6} {:>7}",
+            "char", "count", "min", "avg", "max", "margin"
+        )];
+        for (character, stats) in &self.characters {
+            lines.push(format!(
+                "{:>4} {:>8} {:>6.3} {:>6.3} {:>6.3} {:>+7.3}",
+                character,
+                stats.count,
+                stats.min,
+                stats.sum / stats.count as f64,
+                stats.max,
+                stats.min_margin
+            ));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opencv::core::Size;
+
+    fn template_match(character: char, confidence: f32, threshold: f32) -> TemplateMatch {
+        TemplateMatch {
+            x: 0,
+            y: 0,
+            bounding_box: Size::new(10, 20),
+            character,
+            confidence,
+            threshold,
+        }
+    }
+
+    #[test]
+    fn tracks_distribution_per_character() {
+        let mut stats = ConfidenceStats::new();
+        stats.record(&[
+            template_match('1', 0.90, 0.83),
+            template_match('%', 0.95, 0.80),
+        ]);
+        stats.record(&[template_match('1', 0.86, 0.83)]);
+
+        assert_eq!(
+            stats.render(),
+            vec![
+                "char    count    min    avg    max  margin",
+                "   %        1  0.950  0.950  0.950  +0.150",
+                "   1        2  0.860  0.880  0.900  +0.030",
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_only_header_without_matches() {
+        assert_eq!(ConfidenceStats::new().render().len(), 1);
+    }
+}
@@ -0,0 +1,33 @@
+use std::io::BufRead;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Commands typed into the terminal while tracking, one per line. Lets users control the tracker
+/// (e.g. toggle the debug view) without restarting it.
+pub struct Console {
+    commands: Receiver<String>,
+}
+
+impl Console {
+    /// Reads stdin on a background thread until it is closed.
+    pub fn spawn() -> Self {
+        let (sender, commands) = mpsc::channel();
+        thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else {
+                    return;
+                };
+                let command = line.trim();
+                if !command.is_empty() && sender.send(command.to_string()).is_err() {
+                    return;
+                }
+            }
+        });
+        Self { commands }
+    }
+
+    /// Returns the next command entered since the last call, if any.
+    pub fn try_recv(&self) -> Option<String> {
+        self.commands.try_recv().ok()
+    }
+}
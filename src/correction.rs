@@ -0,0 +1,133 @@
+use anyhow::{Result, anyhow};
+use opencv::core::{Mat, Point2f, Rect, Scalar, Size, Vector};
+use opencv::imgproc;
+use opencv::prelude::*;
+
+/// Size of the picture the screen corners are warped to, the resolution the default ROI is for
+const WARPED_SIZE: Size = Size {
+    width: 1920,
+    height: 1080,
+};
+
+/// Geometric correction applied to every captured frame before the ROIs are taken: an optional
+/// crop, followed by an optional perspective transform of the screen's corners onto a straight
+/// 1920x1080 picture. For cameras pointed at a screen or otherwise skewed captures.
+pub struct FrameCorrection {
+    crop: Option<Rect>,
+    perspective: Option<Mat>,
+}
+
+impl FrameCorrection {
+    /// `corners` are the top-left, top-right, bottom-right and bottom-left corners of the screen in
+    /// the (cropped) frame.
+    pub fn new(crop: Option<Rect>, corners: Option<[Point2f; 4]>) -> Result<Self> {
+        let perspective = match corners {
+            Some(corners) => {
+                let w = WARPED_SIZE.width as f32;
+                let h = WARPED_SIZE.height as f32;
+                let target = [
+                    Point2f::new(0.0, 0.0),
+                    Point2f::new(w, 0.0),
+                    Point2f::new(w, h),
+                    Point2f::new(0.0, h),
+                ];
+                Some(imgproc::get_perspective_transform(
+                    &Vector::from_slice(&corners),
+                    &Vector::from_slice(&target),
+                    opencv::core::DECOMP_LU,
+                )?)
+            }
+            None => None,
+        };
+        Ok(Self { crop, perspective })
+    }
+
+    pub fn apply(&self, frame: &Mat) -> Result<Mat> {
+        let cropped = match self.crop {
+            Some(crop) => {
+                if crop.x < 0
+                    || crop.y < 0
+                    || crop.x + crop.width > frame.cols()
+                    || crop.y + crop.height > frame.rows()
+                {
+                    return Err(anyhow!(
+                        "Crop {},{},{},{} is outside of the {}x{} frame",
+                        crop.x,
+                        crop.y,
+                        crop.width,
+                        crop.height,
+                        frame.cols(),
+                        frame.rows()
+                    ));
+                }
+                Mat::roi(frame, crop)?.try_clone()?
+            }
+            None => frame.try_clone()?,
+        };
+
+        let Some(perspective) = &self.perspective else {
+            return Ok(cropped);
+        };
+        let mut warped = Mat::default();
+        imgproc::warp_perspective(
+            &cropped,
+            &mut warped,
+            perspective,
+            WARPED_SIZE,
+            imgproc::INTER_LINEAR,
+            opencv::core::BORDER_CONSTANT,
+            Scalar::default(),
+        )?;
+        Ok(warped)
+    }
+}
+
+/// Parses the four corners of the screen as X1,Y1,X2,Y2,X3,Y3,X4,Y4 (top-left, top-right,
+/// bottom-right, bottom-left).
+pub fn parse_corners(s: &str) -> Result<[Point2f; 4]> {
+    let values = s
+        .split(',')
+        .map(|v| v.trim().parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow!("Invalid corners '{}': {}", s, e))?;
+
+    match values[..] {
+        [x1, y1, x2, y2, x3, y3, x4, y4] => Ok([
+            Point2f::new(x1, y1),
+            Point2f::new(x2, y2),
+            Point2f::new(x3, y3),
+            Point2f::new(x4, y4),
+        ]),
+        _ => Err(anyhow!(
+            "Invalid corners '{}': expected X1,Y1,X2,Y2,X3,Y3,X4,Y4",
+            s
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_corners_in_order() {
+        let corners = parse_corners("10,20, 1900,15,1910,1070,5,1075").unwrap();
+        assert_eq!(corners[0], Point2f::new(10.0, 20.0));
+        assert_eq!(corners[2], Point2f::new(1910.0, 1070.0));
+        assert!(parse_corners("10,20,30").is_err());
+        assert!(parse_corners("a,b,c,d,e,f,g,h").is_err());
+    }
+
+    #[test]
+    fn crops_before_warping() {
+        let frame =
+            Mat::new_rows_cols_with_default(100, 200, opencv::core::CV_8UC3, Scalar::all(255.0))
+                .unwrap();
+        let correction = FrameCorrection::new(Some(Rect::new(50, 10, 100, 80)), None).unwrap();
+        let cropped = correction.apply(&frame).unwrap();
+        assert_eq!((cropped.cols(), cropped.rows()), (100, 80));
+
+        let correction = FrameCorrection::new(Some(Rect::new(150, 10, 100, 80)), None).unwrap();
+        assert!(correction.apply(&frame).is_err());
+    }
+}
@@ -0,0 +1,122 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+
+const APP_DIR: &str = "srt-igt-splits";
+
+/// Default location of the splits files: `%APPDATA%\srt-igt-splits` on Windows,
+/// `~/Library/Application Support/srt-igt-splits` on macOS and `$XDG_DATA_HOME/srt-igt-splits`
+/// (by default `~/.local/share/srt-igt-splits`) elsewhere.
+pub fn data_dir() -> Result<PathBuf> {
+    let var = |name: &str| env::var_os(name).filter(|value| !value.is_empty());
+    let base = if cfg!(windows) {
+        var("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        var("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+    base.map(|base| base.join(APP_DIR))
+        .ok_or_else(|| anyhow!("Failed to determine the data directory"))
+}
+
+/// Path of the splits file named `name` (e.g. "spyro3" for spyro3.json, or spyro3.json.gz if
+/// compressed) in `dir`, which has to exist.
+pub fn splits_path(dir: &Path, name: &str) -> Result<PathBuf> {
+    let path = dir.join(name);
+    let path = if path.extension().is_some() {
+        path
+    } else {
+        let plain = path.with_extension("json");
+        if plain.is_file() {
+            plain
+        } else {
+            path.with_extension("json.gz")
+        }
+    };
+    if !path.is_file() {
+        let available = list_splits(dir).unwrap_or_default();
+        return Err(anyhow!(
+            "No splits file {} (available: {})",
+            path.display(),
+            if available.is_empty() {
+                "none".to_string()
+            } else {
+                available.join(", ")
+            }
+        ));
+    }
+    Ok(path)
+}
+
+/// Names of the splits files in `dir`, sorted. Archives (see `Splits::archive_path`) are skipped.
+pub fn list_splits(dir: &Path) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(dir)
+        .map_err(|e| anyhow!("Failed to read data directory {}: {}", dir.display(), e))?
+    {
+        let file_name = entry?.file_name();
+        let file_name = file_name.to_string_lossy();
+        let Some(name) = file_name
+            .strip_suffix(".json")
+            .or_else(|| file_name.strip_suffix(".json.gz"))
+        else {
+            continue;
+        };
+        if !name.ends_with(".archive") {
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+    // Compressed and uncompressed files of the same name
+    names.dedup();
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn resolves_named_splits_files() {
+        let dir = tempdir().unwrap();
+        for file in [
+            "spyro3.json",
+            "spyro3.archive.json",
+            "spyro1.json",
+            "spyro2.json.gz",
+            "spyro2.archive.json.gz",
+            "notes.txt",
+        ] {
+            fs::write(dir.path().join(file), "{}").unwrap();
+        }
+
+        assert_eq!(
+            list_splits(dir.path()).unwrap(),
+            vec!["spyro1", "spyro2", "spyro3"]
+        );
+        assert_eq!(
+            splits_path(dir.path(), "spyro3").unwrap(),
+            dir.path().join("spyro3.json")
+        );
+        assert_eq!(
+            splits_path(dir.path(), "spyro1.json").unwrap(),
+            dir.path().join("spyro1.json")
+        );
+        assert_eq!(
+            splits_path(dir.path(), "spyro2").unwrap(),
+            dir.path().join("spyro2.json.gz")
+        );
+        let error = splits_path(dir.path(), "spyro4").unwrap_err().to_string();
+        assert!(
+            error.contains("available: spyro1, spyro2, spyro3"),
+            "{}",
+            error
+        );
+    }
+}
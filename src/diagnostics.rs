@@ -0,0 +1,108 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use chrono::Utc;
+use opencv::core::Vector;
+use opencv::prelude::*;
+use serde::Serialize;
+
+use crate::ocr::TemplateMatch;
+
+/// Minimum time between two dumps, so that a persistent problem doesn't flood the folder.
+const MIN_DUMP_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Serialize)]
+struct MatchInfo {
+    character: char,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    confidence: f32,
+    threshold: f32,
+}
+
+#[derive(Serialize)]
+struct DumpInfo<'a> {
+    label: &'a str,
+    reason: &'a str,
+    text: String,
+    matches: Vec<MatchInfo>,
+}
+
+/// Saves the binarized ROI and the match list of misreads, for tuning templates and thresholds.
+pub struct Diagnostics {
+    dir: PathBuf,
+    dumps: u64,
+    last_dump_at: Option<Instant>,
+}
+
+impl Diagnostics {
+    pub fn new(dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&dir).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to create diagnostics folder {}: {}",
+                dir.display(),
+                e
+            )
+        })?;
+        Ok(Self {
+            dir,
+            dumps: 0,
+            last_dump_at: None,
+        })
+    }
+
+    /// Writes `<timestamp>_<n>_<label>.png` (the binarized ROI) and a `.json` with the reason and all
+    /// matches next to it.
+    pub fn dump(
+        &mut self,
+        label: &str,
+        binarized_roi: &Mat,
+        matches: &[TemplateMatch],
+        reason: &str,
+    ) -> Result<()> {
+        if self
+            .last_dump_at
+            .is_some_and(|at| at.elapsed() < MIN_DUMP_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.last_dump_at = Some(Instant::now());
+        self.dumps += 1;
+
+        let stem = format!(
+            "{}_{}_{}",
+            Utc::now().format("%Y%m%dT%H%M%S%.3f"),
+            self.dumps,
+            label
+        );
+
+        let image_path = self.dir.join(format!("{}.png", stem));
+        opencv::imgcodecs::imwrite(&image_path.to_string_lossy(), binarized_roi, &Vector::new())?;
+
+        let info = DumpInfo {
+            label,
+            reason,
+            text: matches.iter().map(|m| m.character).collect(),
+            matches: matches
+                .iter()
+                .map(|m| MatchInfo {
+                    character: m.character,
+                    x: m.x,
+                    y: m.y,
+                    width: m.bounding_box.width,
+                    height: m.bounding_box.height,
+                    confidence: m.confidence,
+                    threshold: m.threshold,
+                })
+                .collect(),
+        };
+        let info_file = fs::File::create(self.dir.join(format!("{}.json", stem)))?;
+        serde_json::to_writer_pretty(info_file, &info)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::VecDeque;
+
+use crate::in_game_time::InGameTime;
+
+/// Accepts an IGT only once it has been read in the majority of the last `window` OCR'd frames, so
+/// that single-frame misreads never reach the splits.
+pub struct MajorityVote {
+    window: usize,
+    readings: VecDeque<Option<InGameTime>>,
+}
+
+impl MajorityVote {
+    pub fn new(window: usize) -> Self {
+        let window = window.max(1);
+        Self {
+            window,
+            readings: VecDeque::with_capacity(window),
+        }
+    }
+
+    /// Adds the reading of the latest frame (`None` if nothing could be read) and returns the IGT
+    /// that currently holds the majority, if any.
+    pub fn push(&mut self, reading: Option<InGameTime>) -> Option<InGameTime> {
+        if self.readings.len() == self.window {
+            self.readings.pop_front();
+        }
+        self.readings.push_back(reading);
+
+        self.readings
+            .iter()
+            .flatten()
+            .find(|&candidate| {
+                let votes = self
+                    .readings
+                    .iter()
+                    .flatten()
+                    .filter(|&igt| igt == candidate)
+                    .count();
+                votes * 2 > self.window
+            })
+            .copied()
+    }
+}
+
+/// Accepts an IGT only once it has been read in `count` consecutive frames.
+pub struct Debounce {
+    count: usize,
+    candidate: Option<InGameTime>,
+    seen: usize,
+}
+
+impl Debounce {
+    pub fn new(count: usize) -> Self {
+        Self {
+            count: count.max(1),
+            candidate: None,
+            seen: 0,
+        }
+    }
+
+    /// Adds the reading of the latest frame (`None` if nothing could be read) and returns it if it
+    /// has been stable for long enough.
+    pub fn push(&mut self, reading: Option<InGameTime>) -> Option<InGameTime> {
+        if reading.is_some() && reading == self.candidate {
+            self.seen += 1;
+        } else {
+            self.candidate = reading;
+            self.seen = 1;
+        }
+
+        self.candidate.filter(|_| self.seen >= self.count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn igt(percent: u32, secs: u64) -> InGameTime {
+        InGameTime::from_secs(percent, secs)
+    }
+
+    #[test]
+    fn window_of_one_accepts_every_reading() {
+        let mut vote = MajorityVote::new(1);
+        assert_eq!(vote.push(Some(igt(10, 1))), Some(igt(10, 1)));
+        assert_eq!(vote.push(Some(igt(10, 8))), Some(igt(10, 8)));
+        assert_eq!(vote.push(None), None);
+    }
+
+    #[test]
+    fn requires_majority_of_window() {
+        let mut vote = MajorityVote::new(5);
+        assert_eq!(vote.push(Some(igt(10, 60))), None);
+        assert_eq!(vote.push(Some(igt(10, 60))), None);
+        assert_eq!(vote.push(Some(igt(10, 60))), Some(igt(10, 60)));
+    }
+
+    #[test]
+    fn single_misread_is_outvoted() {
+        let mut vote = MajorityVote::new(3);
+        vote.push(Some(igt(10, 60)));
+        vote.push(Some(igt(10, 60)));
+
+        // 0:01:08 misread as 0:01:00
+        assert_eq!(vote.push(Some(igt(10, 68))), Some(igt(10, 60)));
+        assert_eq!(vote.push(Some(igt(10, 61))), None);
+        assert_eq!(vote.push(Some(igt(10, 61))), Some(igt(10, 61)));
+    }
+
+    #[test]
+    fn debounce_of_one_accepts_every_reading() {
+        let mut debounce = Debounce::new(1);
+        assert_eq!(debounce.push(Some(igt(10, 1))), Some(igt(10, 1)));
+        assert_eq!(debounce.push(Some(igt(10, 2))), Some(igt(10, 2)));
+        assert_eq!(debounce.push(None), None);
+    }
+
+    #[test]
+    fn debounce_requires_consecutive_reads() {
+        let mut debounce = Debounce::new(3);
+        assert_eq!(debounce.push(Some(igt(10, 60))), None);
+        assert_eq!(debounce.push(Some(igt(10, 60))), None);
+        assert_eq!(debounce.push(Some(igt(10, 68))), None);
+        assert_eq!(debounce.push(Some(igt(10, 60))), None);
+        assert_eq!(debounce.push(None), None);
+        assert_eq!(debounce.push(Some(igt(10, 61))), None);
+        assert_eq!(debounce.push(Some(igt(10, 61))), None);
+        assert_eq!(debounce.push(Some(igt(10, 61))), Some(igt(10, 61)));
+        assert_eq!(debounce.push(Some(igt(10, 61))), Some(igt(10, 61)));
+    }
+
+    #[test]
+    fn frames_without_reading_count_against_majority() {
+        let mut vote = MajorityVote::new(3);
+        vote.push(Some(igt(10, 60)));
+        vote.push(Some(igt(10, 60)));
+        assert_eq!(vote.push(None), Some(igt(10, 60)));
+        assert_eq!(vote.push(None), None);
+    }
+}
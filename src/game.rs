@@ -0,0 +1,135 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use opencv::core::{Mat, Size};
+use opencv::imgproc;
+use opencv::prelude::*;
+
+/// Frames and title screens are downscaled to this size before comparing them
+const THUMBNAIL_SIZE: Size = Size {
+    width: 160,
+    height: 90,
+};
+
+/// Minimum correlation between a frame and a title screen to detect the game
+const MIN_SIMILARITY: f64 = 0.85;
+
+/// A game with a title screen screenshot and the splits file to use for it.
+struct Game {
+    name: String,
+    title_screen: Mat,
+    splits_file: PathBuf,
+}
+
+/// Detects which game is being played by comparing frames to screenshots of the title screens.
+///
+/// The games are read from a directory containing a title screen screenshot and a splits file per
+/// game, named after the game, e.g. `spyro1.png` and `spyro1.json` (or `spyro1.json.gz`).
+pub struct GameDetector {
+    games: Vec<Game>,
+}
+
+impl GameDetector {
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut games = Vec::new();
+        for entry in fs::read_dir(dir)
+            .map_err(|e| anyhow!("Failed to read games directory {}: {}", dir.display(), e))?
+        {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "png") {
+                continue;
+            }
+
+            let mut splits_file = path.with_extension("json");
+            let compressed = path.with_extension("json.gz");
+            if !splits_file.is_file() && compressed.is_file() {
+                splits_file = compressed;
+            }
+            if !splits_file.is_file() {
+                return Err(anyhow!(
+                    "No splits file {} for title screen {}",
+                    splits_file.display(),
+                    path.display()
+                ));
+            }
+
+            let image = opencv::imgcodecs::imread(
+                &path.to_string_lossy(),
+                opencv::imgcodecs::IMREAD_COLOR,
+            )?;
+            if image.empty() {
+                return Err(anyhow!("Failed to load title screen {}", path.display()));
+            }
+
+            games.push(Game {
+                name: path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                title_screen: thumbnail(&image)?,
+                splits_file,
+            });
+        }
+
+        if games.is_empty() {
+            return Err(anyhow!(
+                "No title screens (<game>.png next to <game>.json) in {}",
+                dir.display()
+            ));
+        }
+        games.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(Self { games })
+    }
+
+    /// Returns the name and splits file of the game whose title screen `frame` shows, if any.
+    pub fn detect(&self, frame: &Mat) -> Result<Option<(&str, &Path)>> {
+        let frame = thumbnail(frame)?;
+
+        let mut best: Option<(&Game, f64)> = None;
+        for game in &self.games {
+            let similarity = similarity(&frame, &game.title_screen)?;
+            if similarity >= MIN_SIMILARITY && best.is_none_or(|(_, s)| similarity > s) {
+                best = Some((game, similarity));
+            }
+        }
+
+        Ok(best.map(|(game, _)| (game.name.as_str(), game.splits_file.as_path())))
+    }
+}
+
+/// Downscales `image` to a grayscale thumbnail.
+fn thumbnail(image: &Mat) -> Result<Mat> {
+    let mut resized = Mat::default();
+    imgproc::resize(
+        image,
+        &mut resized,
+        THUMBNAIL_SIZE,
+        0.0,
+        0.0,
+        imgproc::INTER_AREA,
+    )?;
+    let mut gray = Mat::default();
+    imgproc::cvt_color(
+        &resized,
+        &mut gray,
+        imgproc::COLOR_BGR2GRAY,
+        0,
+        opencv::core::AlgorithmHint::ALGO_HINT_DEFAULT,
+    )?;
+    Ok(gray)
+}
+
+/// Normalized correlation of two thumbnails of the same size (1.0 for identical images).
+fn similarity(a: &Mat, b: &Mat) -> Result<f64> {
+    let mut result = Mat::default();
+    imgproc::match_template(
+        a,
+        b,
+        &mut result,
+        imgproc::TM_CCOEFF_NORMED,
+        &opencv::core::no_array(),
+    )?;
+    Ok(*result.at_2d::<f32>(0, 0)? as f64)
+}
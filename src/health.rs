@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use crate::capture::CaptureCounts;
+
+/// Accumulates OCR attempts and per-frame processing time, to tell capture problems (few or empty
+/// frames) from OCR problems (few successful parses).
+#[derive(Default)]
+pub struct HealthStats {
+    frames: u64,
+    processing_time: Duration,
+    ocr_attempts: u64,
+    ocr_parsed: u64,
+}
+
+impl HealthStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the time it took to process a received frame.
+    pub fn record_frame(&mut self, processing_time: Duration) {
+        self.frames += 1;
+        self.processing_time += processing_time;
+    }
+
+    /// Adds an attempt to read the IGT, which either could or couldn't be parsed.
+    pub fn record_ocr(&mut self, parsed: bool) {
+        self.ocr_attempts += 1;
+        if parsed {
+            self.ocr_parsed += 1;
+        }
+    }
+
+    pub fn render(&self, capture: CaptureCounts) -> Vec<String> {
+        let parsed_percent = if self.ocr_attempts > 0 {
+            100.0 * self.ocr_parsed as f64 / self.ocr_attempts as f64
+        } else {
+            0.0
+        };
+        let latency_ms = if self.frames > 0 {
+            self.processing_time.as_secs_f64() * 1000.0 / self.frames as f64
+        } else {
+            0.0
+        };
+
+        vec![
+            format!(
+                "Capture: {} frames read, {} empty, {} dropped",
+                capture.read, capture.empty, capture.dropped
+            ),
+            format!(
+                "OCR: {} frames processed, {} attempts, {} parsed ({:.1}%), {:.1} ms per frame",
+                self.frames, self.ocr_attempts, self.ocr_parsed, parsed_percent, latency_ms
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_counts_and_averages() {
+        let mut stats = HealthStats::new();
+        stats.record_frame(Duration::from_millis(10));
+        stats.record_frame(Duration::from_millis(30));
+        stats.record_ocr(true);
+        stats.record_ocr(true);
+        stats.record_ocr(true);
+        stats.record_ocr(false);
+
+        let capture = CaptureCounts {
+            read: 5,
+            empty: 1,
+            dropped: 2,
+        };
+        assert_eq!(
+            stats.render(capture),
+            vec![
+                "Capture: 5 frames read, 1 empty, 2 dropped",
+                "OCR: 2 frames processed, 4 attempts, 3 parsed (75.0%), 20.0 ms per frame",
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_zeros_without_frames() {
+        let lines = HealthStats::new().render(CaptureCounts::default());
+        assert_eq!(
+            lines[1],
+            "OCR: 0 frames processed, 0 attempts, 0 parsed (0.0%), 0.0 ms per frame"
+        );
+    }
+}
@@ -1,74 +1,203 @@
 use anyhow::{Result, anyhow};
+use std::cmp::Ordering;
+use std::ops::{Add, Sub};
 use std::{fmt, time::Duration};
 
-#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
 pub struct InGameTime {
     pub percent: u32,
     pub duration: Duration,
 }
 
-impl InGameTime {
-    /// Parses a string like ": 117% 3:03:23" into an `InGameTime`
-    pub fn parse(s: &str) -> Result<Self> {
-        let s = s.trim();
+/// Formats a duration as H:MM:SS (whole seconds), the way the guidebook and the splits files show
+/// times.
+pub fn format_hms(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
+}
 
-        // Remove optional leading ':'
-        let s = if let Some(rest) = s.strip_prefix(':') {
-            rest.trim()
-        } else {
-            s
-        };
+/// A run of digits and colons in the text read from the guidebook, e.g. "117" or "3:03:23".
+#[derive(Debug, Default, PartialEq)]
+struct Field {
+    text: String,
+    /// Whether the field was followed by a '%'
+    percent: bool,
+}
 
-        let parts: Vec<&str> = s.split_whitespace().collect();
-        if parts.len() != 2 {
-            return Err(anyhow!("Expected two parts: percentage and time"));
+/// Splits `s` into fields. Anything but digits, ':' and '%' separates fields, and a '%' ends one
+/// (or marks the previous one, if it follows a separator). Leading ':' are dropped, the guidebook
+/// shows one in front of the percentage.
+fn tokenize(s: &str) -> Vec<Field> {
+    fn finish(fields: &mut Vec<Field>, current: &mut Field) {
+        let field = std::mem::take(current);
+        let text = field.text.trim_start_matches(':');
+        if !text.is_empty() {
+            fields.push(Field {
+                text: text.to_string(),
+                percent: field.percent,
+            });
         }
+    }
 
-        let percent_str = parts[0].trim_end_matches('%');
-        let percent: u32 = percent_str.parse()?;
-
-        let time_parts: Vec<&str> = parts[1].split(':').collect();
-        if time_parts.len() != 3 {
-            return Err(anyhow!("Invalid time '{}': must be H:MM:SS", parts[1]));
+    let mut fields: Vec<Field> = Vec::new();
+    let mut current = Field::default();
+    for c in s.chars() {
+        match c {
+            '0'..='9' | ':' => current.text.push(c),
+            '%' => {
+                current.percent = true;
+                let before = fields.len();
+                finish(&mut fields, &mut current);
+                if fields.len() == before
+                    && let Some(last) = fields.last_mut()
+                {
+                    last.percent = true;
+                }
+            }
+            _ => finish(&mut fields, &mut current),
         }
+    }
+    finish(&mut fields, &mut current);
+    fields
+}
 
-        let hours_str = time_parts[0];
-        let minutes_str = time_parts[1];
-        let seconds_str = time_parts[2];
-
-        // Enforce exactly two digits for MM and SS
-        if minutes_str.len() != 2 || seconds_str.len() != 2 {
+/// Parses a time given as H:MM:SS, or as MM:SS / M:SS before the HUD shows the hours.
+pub fn parse_time(s: &str) -> Result<Duration> {
+    let time_parts: Vec<&str> = s.split(':').collect();
+    let (hours_str, minutes_str, seconds_str) = match time_parts[..] {
+        [hours, minutes, seconds] => {
+            // Enforce exactly two digits for MM and SS
+            if minutes.len() != 2 || seconds.len() != 2 {
+                return Err(anyhow!(
+                    "Minutes and seconds must be exactly two digits (MM:SS)"
+                ));
+            }
+            (hours, minutes, seconds)
+        }
+        [minutes, seconds] => {
+            if !(1..=2).contains(&minutes.len()) || seconds.len() != 2 {
+                return Err(anyhow!(
+                    "Invalid time '{}': must be M:SS or MM:SS without hours",
+                    s
+                ));
+            }
+            ("0", minutes, seconds)
+        }
+        _ => {
             return Err(anyhow!(
-                "Minutes and seconds must be exactly two digits (MM:SS)"
+                "Invalid time '{}': must be H:MM:SS, MM:SS or M:SS",
+                s
             ));
         }
+    };
+    if [hours_str, minutes_str, seconds_str]
+        .iter()
+        .any(|part| part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()))
+    {
+        return Err(anyhow!("Invalid time '{}': must only contain digits", s));
+    }
+
+    let hours: u64 = hours_str.parse()?;
+    let minutes: u64 = minutes_str.parse()?;
+    let seconds: u64 = seconds_str.parse()?;
 
-        let hours: u64 = hours_str.parse()?;
-        let minutes: u64 = minutes_str.parse()?;
-        let seconds: u64 = seconds_str.parse()?;
+    if minutes >= 60 || seconds >= 60 {
+        return Err(anyhow!("Minutes and seconds must be < 60"));
+    }
+
+    let secs = hours
+        .checked_mul(3600)
+        .and_then(|secs| secs.checked_add(minutes * 60 + seconds))
+        .ok_or_else(|| anyhow!("Invalid time '{}': too large", s))?;
+    Ok(Duration::from_secs(secs))
+}
+
+impl InGameTime {
+    pub fn new(percent: u32, duration: Duration) -> Self {
+        Self { percent, duration }
+    }
 
-        if minutes >= 60 || seconds >= 60 {
-            return Err(anyhow!("Minutes and seconds must be < 60"));
+    pub fn from_secs(percent: u32, secs: u64) -> Self {
+        Self::new(percent, Duration::from_secs(secs))
+    }
+
+    pub fn from_hms(percent: u32, hours: u64, minutes: u64, seconds: u64) -> Self {
+        Self::from_secs(percent, hours * 3600 + minutes * 60 + seconds)
+    }
+
+    /// Parses a string like ": 117% 3:03:23" (or "4% 5:12" before the hours are shown) into an
+    /// `InGameTime`.
+    ///
+    /// The layout may vary between display languages: the time may come first, the '%' may be
+    /// missing and the fields may be separated by other characters than spaces (e.g.
+    /// "3:03:23 / 117").
+    pub fn parse(s: &str) -> Result<Self> {
+        let fields = tokenize(s);
+        if fields.len() != 2 {
+            return Err(anyhow!("Expected two parts: percentage and time"));
         }
 
-        let duration = Duration::from_secs(hours * 3600 + minutes * 60 + seconds);
+        let (time, percent): (Vec<&Field>, Vec<&Field>) =
+            fields.iter().partition(|field| field.text.contains(':'));
+        let (Some(time), Some(percent)) = (time.first(), percent.first()) else {
+            return Err(anyhow!("Expected a percentage and a time (H:MM:SS)"));
+        };
+        if time.percent {
+            return Err(anyhow!("Invalid percentage '{}%'", time.text));
+        }
+
+        let percent: u32 = percent.text.parse()?;
+        let duration = parse_time(&time.text)?;
+
+        Ok(Self::new(percent, duration))
+    }
+}
+
+/// Chronological order: by time, then by percentage.
+impl Ord for InGameTime {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.duration
+            .cmp(&other.duration)
+            .then(self.percent.cmp(&other.percent))
+    }
+}
+
+impl PartialOrd for InGameTime {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Advances the time, keeping the percentage.
+impl Add<Duration> for InGameTime {
+    type Output = InGameTime;
+
+    fn add(self, rhs: Duration) -> InGameTime {
+        InGameTime::new(self.percent, self.duration + rhs)
+    }
+}
+
+/// Turns back the time (saturating at zero), keeping the percentage.
+impl Sub<Duration> for InGameTime {
+    type Output = InGameTime;
 
-        Ok(Self { percent, duration })
+    fn sub(self, rhs: Duration) -> InGameTime {
+        InGameTime::new(self.percent, self.duration.saturating_sub(rhs))
+    }
+}
+
+/// Time elapsed between two IGTs, zero if `rhs` is later.
+impl Sub for InGameTime {
+    type Output = Duration;
+
+    fn sub(self, rhs: InGameTime) -> Duration {
+        self.duration.saturating_sub(rhs.duration)
     }
 }
 
 impl fmt::Display for InGameTime {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let total_secs = self.duration.as_secs();
-        let hours = total_secs / 3600;
-        let minutes = (total_secs % 3600) / 60;
-        let seconds = total_secs % 60;
-
-        write!(
-            f,
-            "{}% {:01}:{:02}:{:02}",
-            self.percent, hours, minutes, seconds
-        )
+        write!(f, "{}% {}", self.percent, format_hms(self.duration))
     }
 }
 
@@ -111,10 +240,89 @@ mod tests {
     }
 
     #[test]
-    fn test_parse_invalid_format_time_wrong() {
-        let input = "85% 59:01";
-        let result = InGameTime::parse(input);
-        assert!(result.is_err());
+    fn test_parse_without_hours() {
+        assert_eq!(
+            InGameTime::parse("12% 59:01").unwrap(),
+            InGameTime {
+                percent: 12,
+                duration: Duration::new(59 * 60 + 1, 0),
+            }
+        );
+        assert_eq!(
+            InGameTime::parse(": 0% 4:07").unwrap(),
+            InGameTime {
+                percent: 0,
+                duration: Duration::new(4 * 60 + 7, 0),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_without_hours_enforces_digit_counts() {
+        assert!(InGameTime::parse("12% 123:01").is_err());
+        assert!(InGameTime::parse("12% 5:1").is_err());
+        assert!(InGameTime::parse("12% :01").is_err());
+        assert!(InGameTime::parse("12% 60:00").is_err());
+    }
+
+    #[test]
+    fn test_parse_time_before_percent() {
+        let expected = InGameTime {
+            percent: 117,
+            duration: Duration::new(3 * 3600 + 3 * 60 + 23, 0),
+        };
+        assert_eq!(InGameTime::parse("3:03:23 117%").unwrap(), expected);
+        assert_eq!(InGameTime::parse("3:03:23 117 %").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_alternate_separators() {
+        let expected = InGameTime {
+            percent: 117,
+            duration: Duration::new(3 * 3600 + 3 * 60 + 23, 0),
+        };
+        assert_eq!(InGameTime::parse("117 3:03:23").unwrap(), expected);
+        assert_eq!(InGameTime::parse(": 117% | 3:03:23").unwrap(), expected);
+        assert_eq!(InGameTime::parse("117%3:03:23").unwrap(), expected);
+        assert_eq!(InGameTime::parse("3:03:23 / 117").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse_rejects_ambiguous_layouts() {
+        assert!(InGameTime::parse("117% 3:03:23 4").is_err());
+        assert!(InGameTime::parse("3:03:23 1:02:03").is_err());
+        assert!(InGameTime::parse("117% 42%").is_err());
+        assert!(InGameTime::parse("3:03:23% 117").is_err());
+    }
+
+    #[test]
+    fn test_ordering_is_chronological() {
+        assert!(InGameTime::from_hms(10, 1, 0, 0) > InGameTime::from_hms(90, 0, 59, 59));
+        assert!(InGameTime::from_secs(11, 60) > InGameTime::from_secs(10, 60));
+        assert_eq!(
+            InGameTime::from_secs(10, 60).max(InGameTime::from_secs(5, 61)),
+            InGameTime::from_secs(5, 61)
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_saturates() {
+        let igt = InGameTime::from_secs(10, 60);
+        assert_eq!(igt + Duration::from_secs(5), InGameTime::from_secs(10, 65));
+        assert_eq!(igt - Duration::from_secs(5), InGameTime::from_secs(10, 55));
+        assert_eq!(igt - Duration::from_secs(90), InGameTime::from_secs(10, 0));
+        assert_eq!(InGameTime::from_secs(12, 90) - igt, Duration::from_secs(30));
+        assert_eq!(igt - InGameTime::from_secs(12, 90), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_format_hms() {
+        assert_eq!(format_hms(Duration::from_secs(0)), "0:00:00");
+        assert_eq!(format_hms(Duration::from_millis(3_723_900)), "1:02:03");
+        assert_eq!(
+            format_hms(Duration::from_secs(123 * 3600 + 59)),
+            "123:00:59"
+        );
     }
 
     #[test]
@@ -131,6 +339,14 @@ mod tests {
         assert_eq!(format!("{}", result), "999% 123:45:59");
     }
 
+    #[test]
+    fn test_parse_time_too_large_for_seconds() {
+        assert!(parse_time("5124095576030431:00:00").is_ok());
+        assert!(parse_time("5124095576030432:00:00").is_err());
+        assert!(parse_time("99999999999999999:00:00").is_err());
+        assert!(InGameTime::parse(": 10% 99999999999999999:00:00").is_err());
+    }
+
     #[test]
     fn test_parse_extra_whitespace() {
         let input = "   :   42%    1:02:03   ";
@@ -0,0 +1,108 @@
+use anyhow::Result;
+use opencv::core::{Mat, Rect, Size};
+use opencv::imgproc;
+use opencv::prelude::*;
+
+/// Rows and columns whose brightest pixel is at most this dark belong to a black bar
+const BAR_MAX_BRIGHTNESS: u8 = 24;
+
+/// Bars narrower than this are ignored, e.g. the dark edges some capture cards add
+const MIN_BAR_SIZE: usize = 8;
+
+/// Finds the area of `frame` showing the game, i.e. without the black bars of letterboxing
+/// (top/bottom) or pillarboxing (left/right). Returns `None` for frames that are entirely dark.
+pub fn detect_content_area(frame: &Mat) -> Result<Option<Rect>> {
+    let mut gray = Mat::default();
+    imgproc::cvt_color(
+        frame,
+        &mut gray,
+        imgproc::COLOR_BGR2GRAY,
+        0,
+        opencv::core::AlgorithmHint::ALGO_HINT_DEFAULT,
+    )?;
+
+    // Brightest pixel of every column and of every row
+    let mut columns = Mat::default();
+    opencv::core::reduce(&gray, &mut columns, 0, opencv::core::REDUCE_MAX, -1)?;
+    let mut rows = Mat::default();
+    opencv::core::reduce(&gray, &mut rows, 1, opencv::core::REDUCE_MAX, -1)?;
+
+    let (Some((left, right)), Some((top, bottom))) = (
+        content_span(columns.data_typed::<u8>()?),
+        content_span(rows.data_typed::<u8>()?),
+    ) else {
+        return Ok(None);
+    };
+
+    Ok(Some(Rect::new(
+        left as i32,
+        top as i32,
+        (right - left) as i32,
+        (bottom - top) as i32,
+    )))
+}
+
+/// Start and end (exclusive) of the part of a brightness profile between the black bars.
+fn content_span(profile: &[u8]) -> Option<(usize, usize)> {
+    let first = profile.iter().position(|&v| v > BAR_MAX_BRIGHTNESS)?;
+    let last = profile.iter().rposition(|&v| v > BAR_MAX_BRIGHTNESS)?;
+
+    let start = if first < MIN_BAR_SIZE { 0 } else { first };
+    let end = if profile.len() - 1 - last < MIN_BAR_SIZE {
+        profile.len()
+    } else {
+        last + 1
+    };
+    Some((start, end))
+}
+
+/// Maps `roi`, given for a game picture filling the whole frame of `frame_size`, into the
+/// `content` area of a letterboxed / pillarboxed frame.
+pub fn map_roi(roi: Rect, frame_size: Size, content: Rect) -> Rect {
+    let scale_x = content.width as f64 / frame_size.width as f64;
+    let scale_y = content.height as f64 / frame_size.height as f64;
+    Rect::new(
+        content.x + (roi.x as f64 * scale_x).round() as i32,
+        content.y + (roi.y as f64 * scale_y).round() as i32,
+        (roi.width as f64 * scale_x).round() as i32,
+        (roi.height as f64 * scale_y).round() as i32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_span_skips_black_bars() {
+        let mut profile = vec![0u8; 100];
+        profile[20..80].fill(200);
+        assert_eq!(content_span(&profile), Some((20, 80)));
+
+        // Thin dark edges aren't bars
+        let mut profile = vec![200u8; 100];
+        profile[..3].fill(5);
+        profile[98..].fill(5);
+        assert_eq!(content_span(&profile), Some((0, 100)));
+
+        assert_eq!(content_span(&[0u8; 100]), None);
+    }
+
+    #[test]
+    fn maps_roi_into_pillarboxed_content() {
+        let frame_size = Size::new(1920, 1080);
+        let content = Rect::new(240, 0, 1440, 1080);
+        assert_eq!(
+            map_roi(Rect::new(1260, 45, 620, 50), frame_size, content),
+            Rect::new(1185, 45, 465, 50)
+        );
+        assert_eq!(
+            map_roi(
+                Rect::new(1260, 45, 620, 50),
+                frame_size,
+                Rect::new(0, 0, 1920, 1080)
+            ),
+            Rect::new(1260, 45, 620, 50)
+        );
+    }
+}
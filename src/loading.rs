@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use opencv::core::{Mat, Scalar, Size};
+use opencv::imgproc;
+use opencv::prelude::*;
+
+/// Frames are downscaled to this size before measuring their brightness
+const THUMBNAIL_SIZE: Size = Size {
+    width: 64,
+    height: 36,
+};
+
+/// Maximum mean brightness (0-255) of a loading frame
+const MAX_MEAN: f64 = 20.0;
+
+/// Maximum brightness deviation of a loading frame (the loading icon is small)
+const MAX_STD_DEV: f64 = 12.0;
+
+/// Returns whether `frame` is a black / loading screen.
+pub fn is_loading_frame(frame: &Mat) -> Result<bool> {
+    let mut thumbnail = Mat::default();
+    imgproc::resize(
+        frame,
+        &mut thumbnail,
+        THUMBNAIL_SIZE,
+        0.0,
+        0.0,
+        imgproc::INTER_AREA,
+    )?;
+    let mut gray = Mat::default();
+    imgproc::cvt_color(
+        &thumbnail,
+        &mut gray,
+        imgproc::COLOR_BGR2GRAY,
+        0,
+        opencv::core::AlgorithmHint::ALGO_HINT_DEFAULT,
+    )?;
+
+    let mut mean = Scalar::default();
+    let mut std_dev = Scalar::default();
+    opencv::core::mean_std_dev(&gray, &mut mean, &mut std_dev, &opencv::core::no_array())?;
+
+    Ok(mean[0] <= MAX_MEAN && std_dev[0] <= MAX_STD_DEV)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LoadEvent {
+    Started { at: Duration },
+    Ended { at: Duration, duration: Duration },
+}
+
+/// Turns the per-frame loading state into start/end events and accumulates the time spent loading.
+#[derive(Default)]
+pub struct LoadTracker {
+    loading_since: Option<Duration>,
+    loads: u32,
+    total: Duration,
+}
+
+impl LoadTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds the state of the frame at `position` and returns the event it caused, if any.
+    pub fn update(&mut self, position: Duration, loading: bool) -> Option<LoadEvent> {
+        match (self.loading_since, loading) {
+            (None, true) => {
+                self.loading_since = Some(position);
+                Some(LoadEvent::Started { at: position })
+            }
+            (Some(since), false) => {
+                let duration = position.saturating_sub(since);
+                self.loading_since = None;
+                self.loads += 1;
+                self.total += duration;
+                Some(LoadEvent::Ended {
+                    at: position,
+                    duration,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.loading_since.is_some()
+    }
+
+    /// Number of completed loads.
+    pub fn loads(&self) -> u32 {
+        self.loads
+    }
+
+    /// Total duration of all completed loads.
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn reports_start_and_end_of_loads() {
+        let mut tracker = LoadTracker::new();
+        assert_eq!(tracker.update(ms(0), false), None);
+        assert_eq!(
+            tracker.update(ms(100), true),
+            Some(LoadEvent::Started { at: ms(100) })
+        );
+        assert!(tracker.is_loading());
+        assert_eq!(tracker.update(ms(116), true), None);
+        assert_eq!(
+            tracker.update(ms(2_100), false),
+            Some(LoadEvent::Ended {
+                at: ms(2_100),
+                duration: ms(2_000)
+            })
+        );
+        assert!(!tracker.is_loading());
+    }
+
+    #[test]
+    fn accumulates_completed_loads() {
+        let mut tracker = LoadTracker::new();
+        tracker.update(ms(0), true);
+        tracker.update(ms(1_000), false);
+        tracker.update(ms(5_000), true);
+        tracker.update(ms(5_500), false);
+        tracker.update(ms(9_000), true);
+
+        assert_eq!(tracker.loads(), 2);
+        assert_eq!(tracker.total(), ms(1_500));
+    }
+}
@@ -1,423 +1,1834 @@
+mod bench;
+mod calibration;
+mod capture;
+mod confidence;
+mod console;
+mod correction;
+mod data_dir;
+mod diagnostics;
+mod filter;
+mod game;
+mod health;
 mod in_game_time;
+mod letterbox;
+mod loading;
+mod observations;
+mod ocr;
+mod overlay;
+mod preprocess;
+mod recording;
+mod regression;
+mod retime;
+mod sampling;
+mod screenshot;
 mod splits;
-
-use in_game_time::InGameTime;
-use splits::{Splits, SplitsDisplay};
-
-use std::collections::HashMap;
-use std::path::PathBuf;
-
-use opencv::core::Rect;
-use opencv::core::Size_;
+mod tracker;
+mod training;
+mod tuning;
+mod watchdog;
+
+use capture::{
+    CameraProperties, CaptureOptions, CaptureThread, CapturedFrame, FrameSource, Reconnecting,
+    ScreenSource, VideoCaptureSource,
+};
+use confidence::ConfidenceStats;
+use console::Console;
+use correction::FrameCorrection;
+use diagnostics::Diagnostics;
+use game::GameDetector;
+use health::HealthStats;
+use loading::{LoadEvent, LoadTracker};
+use observations::ObservationLog;
+use ocr::classifier::GlyphClassifier;
+use ocr::{Recognizer, TemplateMatch, Templates, binarize_roi};
+use overlay::{Decision, RoiOverlay};
+use preprocess::{ColorKey, Deinterlace, Preprocessing};
+use recording::Recorder;
+use retime::{ChapterFormat, Retiming};
+use sampling::Sampler;
+use screenshot::ScreenshotOptions;
+use splits::{
+    Comparison, EventSink, HttpApi, ImportFormat, LiveSplitServer, ReportFormat, RunLog, Splits,
+    WebSocketBroadcast,
+};
+use tracker::{Counter, Tracker};
+use tuning::Tuning;
+
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
+
+use opencv::core::{Point2f, Rect};
 use opencv::highgui;
 use opencv::imgproc;
 use opencv::prelude::*;
-use opencv::videoio;
 
 use anyhow::{Result, anyhow};
-use clap::Parser;
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use colored::Colorize;
 
-struct Template {
-    template: Mat,
-    size: Size_<i32>,
-    threshold: f32,
-    character: char,
-}
+#[derive(Parser, Debug, Clone)]
+#[command(group(ArgGroup::new("source").args(["video", "screen", "window", "gst_pipeline", "stream"])))]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
 
-impl Template {
-    pub fn load_from_file(path: &str, threshold: f32, character: char) -> Result<Self> {
-        let template = opencv::imgcodecs::imread(path, opencv::imgcodecs::IMREAD_GRAYSCALE)?;
-        if template.empty() {
-            panic!("Failed to load template!");
-        }
-
-        let mut binarized_template = Mat::default();
-        opencv::imgproc::threshold(
-            &template,
-            &mut binarized_template,
-            0.0,
-            255.0,
-            imgproc::THRESH_OTSU,
-        )?;
-
-        // TODO: store in proper size
-        let mut template_scaled = Mat::default();
-        opencv::imgproc::resize(
-            &binarized_template,
-            &mut template_scaled,
-            opencv::core::Size {
-                width: (binarized_template.cols() as f32 * 0.75) as i32,
-                height: (binarized_template.rows() as f32 * 0.75) as i32,
-            },
-            0.0,
-            0.0,
-            imgproc::INTER_LINEAR,
-        )?;
+    /// Path to the splits JSON file, gzip-compressed if it ends in .gz (e.g. spyro.json.gz). Pass one file per ROI to track multiple runners (e.g. a side-by-side race). After a misread, remove the last split time by pressing 'u' in the debug window or entering 'u' (or 'u LABEL' for one runner) in the terminal.
+    #[arg(value_name = "SPLITS_FILE", required_unless_present_any = ["games_dir", "splits_names"])]
+    pub splits_files: Vec<PathBuf>,
 
-        let template_size = template_scaled.size()?;
+    /// Name of a splits file in the data directory (e.g. spyro3 for spyro3.json or spyro3.json.gz, see `list`) to use in addition to the SPLITS_FILEs. Can be repeated
+    #[arg(long = "splits", value_name = "NAME", conflicts_with = "games_dir")]
+    pub splits_names: Vec<String>,
 
-        Ok(Self {
-            template: template_scaled,
-            size: template_size,
-            threshold,
-            character,
-        })
-    }
+    /// Select the splits file by detecting the game's title screen, using a screenshot and splits file per game in this directory (e.g. spyro1.png and spyro1.json)
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["splits_files", "auto_roi"])]
+    pub games_dir: Option<PathBuf>,
+
+    /// Region of the frame containing the IGT, as X,Y,WIDTH,HEIGHT. Repeat once per splits file.
+    #[arg(long = "roi", value_name = "X,Y,W,H", value_parser = parse_roi)]
+    pub rois: Vec<Rect>,
+
+    /// Region of a collectible counter (e.g. gems) that can trigger splits, as NAME=X,Y,WIDTH,HEIGHT. Can be repeated.
+    #[arg(long = "counter", value_name = "NAME=X,Y,W,H", value_parser = parse_counter)]
+    pub counters: Vec<Counter>,
+
+    /// Region of the level name banner, read with the letter templates to trigger splits on entering levels
+    #[arg(long, value_name = "X,Y,W,H", value_parser = parse_roi)]
+    pub level_roi: Option<Rect>,
+
+    /// Alternative region of the IGT (e.g. for a letterboxed capture), tried in order when the IGT isn't found in the ROI. The first one showing the IGT is kept. Can be repeated.
+    #[arg(long = "fallback-roi", value_name = "X,Y,W,H", value_parser = parse_roi)]
+    pub fallback_rois: Vec<Rect>,
+
+    /// Locate the IGT automatically by scanning the first frames showing the guidebook
+    #[arg(long, conflicts_with = "rois")]
+    pub auto_roi: bool,
+
+    /// Crop the captured frames to this region before anything else, as X,Y,WIDTH,HEIGHT
+    #[arg(long, value_name = "X,Y,W,H", value_parser = parse_roi)]
+    pub crop: Option<Rect>,
+
+    /// Corners of the screen in the (cropped) frame, as X1,Y1,X2,Y2,X3,Y3,X4,Y4 from the top-left clockwise. The screen is warped to a straight 1920x1080 picture before the ROIs are taken, e.g. for a camera pointed at a screen
+    #[arg(long, value_name = "CORNERS", value_parser = correction::parse_corners)]
+    pub perspective: Option<[Point2f; 4]>,
+
+    /// Detect black bars (e.g. 4:3 in 16:9, windowed capture) in the first frames and move the ROIs into the game picture. ROIs are then given as if the game filled the frame
+    #[arg(long, conflicts_with = "auto_roi")]
+    pub detect_letterbox: bool,
+
+    /// Index of the capture device (webcam / capture card) to read from
+    #[arg(
+        long,
+        value_name = "INDEX",
+        default_value_t = 2,
+        conflicts_with = "source"
+    )]
+    pub camera: i32,
+
+    #[command(flatten)]
+    pub camera_properties: CameraProperties,
+
+    /// Read frames from a recorded video file instead of a capture device
+    #[arg(long, value_name = "PATH")]
+    pub video: Option<PathBuf>,
+
+    /// Capture the monitor with the given index directly instead of using a capture device
+    #[arg(long, value_name = "MONITOR")]
+    pub screen: Option<usize>,
+
+    /// Capture the first window whose title contains the given text
+    #[arg(long, value_name = "TITLE")]
+    pub window: Option<String>,
+
+    /// Read frames from a GStreamer pipeline ending in an appsink, e.g. "v4l2src ! videoconvert ! appsink"
+    #[arg(long, value_name = "PIPELINE")]
+    pub gst_pipeline: Option<String>,
+
+    /// Read frames from a network stream (RTMP/HTTP/RTSP/...), reconnecting whenever it stalls
+    #[arg(long, value_name = "URL")]
+    pub stream: Option<String>,
+
+    /// Seconds without frames after which a stream or capture device is considered stalled and reopened
+    #[arg(long, value_name = "SECS", default_value_t = 10)]
+    pub stall_timeout: u64,
+
+    /// Start processing the video file at this position ([[H:]MM:]SS[.mmm])
+    #[arg(long, value_name = "TIME", requires = "video", value_parser = retime::parse_video_time)]
+    pub start: Option<Duration>,
+
+    /// Stop processing the video file at this position ([[H:]MM:]SS[.mmm])
+    #[arg(long, value_name = "TIME", requires = "video", value_parser = retime::parse_video_time)]
+    pub end: Option<Duration>,
+
+    /// Only decode every Nth frame of the video file
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "video", value_parser = clap::value_parser!(u32).range(1..))]
+    pub frame_step: u32,
+
+    /// Maximum number of frames per second to run OCR on (default: every frame)
+    #[arg(long, value_name = "FPS", value_parser = sampling::parse_sample_rate)]
+    pub sample_rate: Option<f64>,
+
+    /// Frames per second to scan while the IGT isn't visible (e.g. outside of the guidebook)
+    #[arg(long, value_name = "FPS", value_parser = sampling::parse_sample_rate)]
+    pub idle_rate: Option<f64>,
+
+    /// Seconds without a visible IGT after which scanning slows down to --idle-rate
+    #[arg(long, value_name = "SECS", default_value_t = 5, requires = "idle_rate")]
+    pub idle_after: u64,
+
+    /// Only accept an IGT once it was read in the majority of the last N OCR'd frames
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub vote_window: u32,
+
+    /// Only accept a new IGT once it was read in K consecutive OCR'd frames
+    #[arg(long, value_name = "K", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub confirmations: u32,
+
+    /// Once the IGT was read, only match the templates around the positions of its characters (with a full scan every 30 reads or when that fails). Only applies to --ocr-engine templates
+    #[arg(long)]
+    pub lock_slots: bool,
+
+    /// Save changes that don't reach a new split (e.g. the IGT going up while the guidebook stays open) at most every N seconds. New splits, resets, finished runs and PBs are saved right away
+    #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+    pub save_interval: u64,
+
+    /// Replace damaged splits files (e.g. truncated by a crash or a full disk) with their backup (e.g. spyro.json.bak, the version before the last save) instead of failing. The damaged file is kept as e.g. spyro.json.damaged
+    #[arg(long)]
+    pub restore_backup: bool,
+
+    /// Sync the splits files with a WebDAV folder (e.g. https://cloud.example.com/remote.php/dav/files/me/splits/) or S3 bucket (s3://BUCKET/PREFIX) to use them on several machines: changes made elsewhere are pulled on start, merging the runs if both copies changed, and the splits are pushed after every finished run. WebDAV credentials are read from SRT_IGT_SPLITS_SYNC_USER and SRT_IGT_SPLITS_SYNC_PASSWORD, S3 credentials from AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY and AWS_REGION (AWS_ENDPOINT_URL for S3-compatible servers)
+    #[cfg(feature = "sync")]
+    #[arg(long, value_name = "URL", value_parser = splits::parse_remote)]
+    pub sync: Option<splits::Remote>,
+
+    /// Once a splits file has more than N runs, move all but the latest N (and the PB) to an archive file next to it (e.g. spyro.archive.json), keeping the best segments and averages
+    #[arg(long, value_name = "N")]
+    pub archive_after: Option<usize>,
+
+    /// Show the wall clock time since the start of the run (RTA, including loads and menus) in the split view next to the IGT. It is recorded for every split either way
+    #[arg(long)]
+    pub real_time: bool,
+
+    /// Print the statistics of the splits (as with the stats subcommand) when a run finishes
+    #[arg(long)]
+    pub run_summary: bool,
+
+    /// Category of the runs of this session (e.g. when running several categories with the same splits), shown by `runs list`
+    #[arg(long, value_name = "NAME")]
+    pub category: Option<String>,
+
+    /// What the deltas of the split view are computed against. Cycle through the comparisons at runtime by pressing 'c' in the debug window or entering 'c' in the terminal. Race against the golds (and back) by pressing or entering 'g'; the split view then also shows the pace compared to the sum of best segments
+    #[arg(long, value_enum, default_value_t = Comparison::PersonalBest)]
+    pub comparison: Comparison,
+
+    /// Answer tools speaking the LiveSplit Server protocol (e.g. browser overlays or stream deck plugins) about the current time, split and delta on this address (default 127.0.0.1:16834, LiveSplit's port). Deltas are against --comparison unless a command names another comparison; commands controlling the timer are ignored, it follows the IGT
+    #[arg(long, value_name = "ADDRESS", num_args = 0..=1, default_missing_value = "127.0.0.1:16834")]
+    pub livesplit_server: Option<SocketAddr>,
+
+    /// Push the timer events and the state of the splits (times, deltas against --comparison, PB, sum of best) as JSON to WebSocket clients connecting to this address (default 127.0.0.1:16835), e.g. for overlays or dashboards
+    #[arg(long, value_name = "ADDRESS", num_args = 0..=1, default_missing_value = "127.0.0.1:16835")]
+    pub websocket: Option<SocketAddr>,
+
+    /// Serve the state of the splits as JSON over HTTP on this address (default 127.0.0.1:16836) for scripts and stream tools: /state (the active run, as pushed over --websocket), /splits, /runs and /pb
+    #[arg(long, value_name = "ADDRESS", num_args = 0..=1, default_missing_value = "127.0.0.1:16836")]
+    pub http_api: Option<SocketAddr>,
+
+    /// Publish the timer events as JSON lines to the clients of a Unix socket created at this path, or on Windows of the named pipe with this name (e.g. \\.\pipe\srt-igt-splits), for local scripts such as polybar modules or AutoHotkey scripts reacting to splits without networking
+    #[arg(long, value_name = "PATH")]
+    pub event_socket: Option<PathBuf>,
+
+    /// Warn (with a terminal bell) when no IGT was read for N minutes while a run is active, e.g. because the capture froze
+    #[arg(long, value_name = "MINUTES", value_parser = clap::value_parser!(u64).range(1..))]
+    pub watchdog: Option<u64>,
+
+    /// Run preprocessing and template matching on the GPU via OpenCL (if available)
+    #[arg(long)]
+    pub gpu: bool,
+
+    /// Retime the video file: print every IGT change with its video timestamp and summarize the splits at the end
+    #[arg(long, requires = "video")]
+    pub retime: bool,
+
+    /// With --retime, write the video timestamp at which each percentage was first seen to this file, as JSON (.json) or Markdown (.md)
+    #[arg(long, value_name = "FILE", requires = "retime", value_parser = retime::parse_report_path)]
+    pub retime_report: Option<PathBuf>,
+
+    /// With --retime, write chapter markers with one chapter per split to this file (following the splits of the first runner)
+    #[arg(long, value_name = "FILE", requires = "retime")]
+    pub chapters: Option<PathBuf>,
+
+    /// Format of the --chapters file
+    #[arg(long, value_enum, default_value_t = ChapterFormat::Youtube)]
+    pub chapter_format: ChapterFormat,
+
+    /// Record the captured frames processed by the splitter to this video file (.mp4, .mkv or .avi)
+    #[arg(long, value_name = "FILE", value_parser = recording::parse_recording_path)]
+    pub record: Option<PathBuf>,
+
+    /// Frame rate written into the --record file. Should match the capture, frames dropped while the splitter was busy are missing from the recording
+    #[arg(long, value_name = "FPS", default_value_t = 60.0, requires = "record")]
+    pub record_fps: f64,
+
+    /// Append every new accepted IGT reading with its frame index and timestamps to this file (JSON lines)
+    #[arg(long, value_name = "FILE")]
+    pub observation_log: Option<PathBuf>,
+
+    /// Append the accepted IGT readings and the splits, golds, resets and PBs of every run to a JSON lines file per run (named after the run ID) in this folder
+    #[arg(long, value_name = "DIR")]
+    pub run_log: Option<PathBuf>,
+
+    /// Save the binarized ROI and the template matches of misreads (unparseable or implausible IGTs) to this folder
+    #[arg(long, value_name = "DIR")]
+    pub diagnostics_dir: Option<PathBuf>,
+
+    /// Print per-character match confidence statistics every N seconds (and at the end)
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub confidence_stats: Option<u64>,
+
+    /// Print capture and OCR health statistics (frames read/empty/dropped, parse rate, latency) every N seconds (and at the end)
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub stats_interval: Option<u64>,
+
+    /// Directory with the character templates (reloaded when its files change) [default: ./templates, or next to the executable]
+    #[arg(long, value_name = "DIR")]
+    pub templates_dir: Option<PathBuf>,
+
+    /// Use the templates in this subdirectory of the templates directory (e.g. for another display language)
+    #[arg(long, value_name = "NAME")]
+    pub template_set: Option<String>,
+
+    /// OCR engine used to read the IGT
+    #[arg(long, value_enum, default_value_t = OcrEngine::Templates)]
+    pub ocr_engine: OcrEngine,
+
+    /// Preprocessing of the grayscale ROI, as comma separated steps: clahe[=CLIP_LIMIT], blur[=SIZE], threshold=VALUE, otsu, invert
+    #[arg(long, value_name = "STEPS", value_parser = preprocess::parse_preprocessing, default_value = "otsu")]
+    pub preprocess: Preprocessing,
+
+    /// Deinterlace the ROI before preprocessing, for interlaced captures
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub deinterlace: Option<Deinterlace>,
+
+    /// Only treat pixels within this HSV range (hue 0-179, saturation/value 0-255) as text, instead of converting to grayscale
+    #[arg(long, value_name = "H,S,V-H,S,V", value_parser = preprocess::parse_color_key)]
+    pub color_key: Option<ColorKey>,
+
+    /// Detect black / loading screens and pause OCR while they are shown
+    #[arg(long)]
+    pub detect_loads: bool,
+
+    /// Show the annotated preview window, with trackbars for the binarization and match thresholds ('s' in the window saves them). Toggle it at runtime by pressing 'd' in the window or entering 'd' in the terminal
+    #[arg(long)]
+    pub debug: bool,
 }
 
-#[derive(Hash, Eq, PartialEq)]
-enum Character {
-    Percent,
-    Colon,
-    Zero,
-    One,
-    Two,
-    Three,
-    Four,
-    Five,
-    Six,
-    Seven,
-    Eight,
-    Nine,
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Create templates from screenshots labeled with a .txt file each (containing e.g. ": 117% 3:03:23")
+    TrainTemplates {
+        /// Directory with the labeled screenshots
+        #[arg(long, value_name = "DIR")]
+        input: PathBuf,
+
+        /// Directory to write the templates to
+        #[arg(long, value_name = "DIR", default_value = "trained-templates")]
+        output: PathBuf,
+
+        /// Region of the screenshots containing the IGT, as X,Y,WIDTH,HEIGHT
+        #[arg(long, value_name = "X,Y,W,H", value_parser = parse_roi)]
+        roi: Option<Rect>,
+
+        /// Preprocessing of the ROI, should be the same as when tracking (see the top-level --preprocess)
+        #[arg(long, value_name = "STEPS", value_parser = preprocess::parse_preprocessing, default_value = "otsu")]
+        preprocess: Preprocessing,
+
+        /// Deinterlace the screenshots before preprocessing
+        #[arg(long, value_enum, value_name = "MODE")]
+        deinterlace: Option<Deinterlace>,
+
+        /// Color key applied to the screenshots instead of converting them to grayscale
+        #[arg(long, value_name = "H,S,V-H,S,V", value_parser = preprocess::parse_color_key)]
+        color_key: Option<ColorKey>,
+    },
+    /// Read the IGT from a single screenshot and print the matched characters, e.g. for tuning thresholds or bug reports
+    ReadImage {
+        /// Screenshot to read
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        #[command(flatten)]
+        options: ScreenshotOptions,
+    },
+    /// Read a directory of screenshots named after the IGT they show (e.g. 117_3-03-23.png) and report the accuracy per character and per image
+    Regression {
+        /// Directory with the labeled screenshots
+        #[arg(value_name = "DIR")]
+        dir: PathBuf,
+
+        #[command(flatten)]
+        options: ScreenshotOptions,
+    },
+    /// Read the IGT from the frames of a video as fast as possible and report the frames per second and the time spent per stage
+    Bench {
+        /// Video file to read
+        #[arg(long, value_name = "FILE")]
+        video: PathBuf,
+
+        /// Stop after this many frames [default: the whole video]
+        #[arg(long, value_name = "N")]
+        frames: Option<u32>,
+
+        #[command(flatten)]
+        options: ScreenshotOptions,
+    },
+    /// Guided calibration of the template thresholds: open the guidebook, enter the IGT it shows, and the best threshold per character is determined from live frames and written to the templates directory
+    Calibrate {
+        /// Index of the capture device to read from
+        #[arg(long, value_name = "INDEX", default_value_t = 2)]
+        camera: i32,
+
+        /// Read frames from a recording of the guidebook instead of a capture device
+        #[arg(long, value_name = "FILE")]
+        video: Option<PathBuf>,
+
+        /// Number of frames to sample
+        #[arg(long, value_name = "N", default_value_t = 60, value_parser = clap::value_parser!(u32).range(1..))]
+        frames: u32,
+
+        #[command(flatten)]
+        options: ScreenshotOptions,
+    },
+    /// Print the PB, sum of best segments and possible timesave of splits files, the completion rate of the runs, how many runs reached each split and the spread of the segment times
+    Stats {
+        /// Splits files to summarize
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// List, delete or prune the runs of a splits file
+    Runs {
+        #[command(subcommand)]
+        command: RunsCommand,
+    },
+    /// Check a splits file for inconsistencies (split times of unknown runs, a PB that isn't the fastest run, split times going down within a run) without changing it
+    Check {
+        /// Splits file
+        file: PathBuf,
+        /// Repair what can be repaired and save the file, after copying it to FILE.bak
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Combine the runs of two splits files with the same splits, e.g. recorded on two machines. Runs in both files are kept once, the PB and best segments are determined from all runs
+    Merge {
+        /// Splits file, whose archived split times are kept
+        a: PathBuf,
+        /// Splits file with the runs to add
+        b: PathBuf,
+        /// File to write the merged splits to
+        #[arg(short, long, value_name = "FILE")]
+        output: PathBuf,
+    },
+    /// Create a splits file, asking for the name and percentage of every split unless they are given with --split or copied from another file with --from
+    New {
+        /// Splits file to create
+        file: PathBuf,
+        /// Split as NAME=PERCENT, e.g. "Sunny Villa=10". Repeat in the order of the route
+        #[arg(long = "split", value_name = "NAME=PERCENT", value_parser = splits::parse_split, conflicts_with = "from")]
+        splits: Vec<(String, u32)>,
+        /// Splits file whose splits, triggers and options to use, without its times and runs
+        #[arg(long, value_name = "FILE")]
+        from: Option<PathBuf>,
+    },
+    /// Convert the splits file of another timer (Splitterino, Urn or Flitter) with its PB, best segments and history, if any
+    Import {
+        /// Splits file of the other timer
+        file: PathBuf,
+        /// Format of the file
+        #[arg(long, value_enum)]
+        format: ImportFormat,
+        /// Percentage of each split, e.g. 10,20,30 (by default taken from the split names, e.g. "Sunny Beach 12%")
+        #[arg(long, value_name = "PERCENTS", value_delimiter = ',')]
+        percents: Vec<u32>,
+        /// File to write the splits to
+        #[arg(short, long, value_name = "FILE")]
+        output: PathBuf,
+    },
+    /// Export the splits in a format for sharing
+    Export {
+        #[command(subcommand)]
+        command: ExportCommand,
+    },
+    /// List the splits files in the data directory (%APPDATA%\srt-igt-splits, ~/Library/Application Support/srt-igt-splits or ~/.local/share/srt-igt-splits), which --splits selects by name
+    List,
+    /// List the available capture devices and their resolutions, to find the index for --camera
+    ListCameras {
+        /// Number of device indices to probe
+        #[arg(long, value_name = "N", default_value_t = 10)]
+        max_index: i32,
+    },
 }
 
-struct Templates {
-    indices: HashMap<Character, usize>,
-    templates: Vec<Template>,
+#[derive(Subcommand, Debug, Clone)]
+pub enum RunsCommand {
+    /// List the runs with their ID, start time, final time (or where they were reset), category, tags and notes
+    List {
+        /// Splits file
+        file: PathBuf,
+    },
+    /// Delete a run and its split times. A new PB and best segments are determined from the remaining runs
+    Delete {
+        /// Splits file
+        file: PathBuf,
+        /// ID of the run, as shown by `runs list`
+        id: uuid::Uuid,
+    },
+    /// Move all runs but the latest ones and the PB to an archive file next to the splits file (e.g. spyro.archive.json). Best segments and averages are kept
+    Archive {
+        /// Splits file
+        file: PathBuf,
+        /// Number of latest runs to keep
+        #[arg(long, value_name = "N")]
+        keep_last: usize,
+    },
+    /// Delete all runs but the latest ones and the PB. Best segments are kept
+    Prune {
+        /// Splits file
+        file: PathBuf,
+        /// Number of latest runs to keep
+        #[arg(long, value_name = "N")]
+        keep_last: usize,
+    },
+    /// Set the category, tags or notes of a run
+    Edit {
+        /// Splits file
+        file: PathBuf,
+        /// ID of the run, as shown by `runs list`
+        id: uuid::Uuid,
+        /// Category of the run, e.g. when running several categories with the same splits
+        #[arg(long, value_name = "NAME")]
+        category: Option<String>,
+        /// Tag of the run (repeat for several tags), replacing the previous tags
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// Free-form notes on the run
+        #[arg(long)]
+        notes: Option<String>,
+    },
+    /// Correct a split time of a run, e.g. a misread time. The final time, PB and best segments are updated accordingly
+    Correct {
+        /// Splits file
+        file: PathBuf,
+        /// Correct time (H:MM:SS)
+        #[arg(value_parser = in_game_time::parse_time)]
+        time: Duration,
+        /// ID of the run, as shown by `runs list` (by default the active run)
+        #[arg(long, value_name = "ID")]
+        run: Option<uuid::Uuid>,
+        /// Name of the split (by default the last split the run reached)
+        #[arg(long, value_name = "NAME")]
+        split: Option<String>,
+    },
+    /// Derive best segments from the split times of all runs, e.g. for splits recorded before best segments were tracked
+    Golds {
+        /// Splits file
+        file: PathBuf,
+        /// Drop stored best segments that no run in the history matches (e.g. from deleted or archived runs)
+        #[arg(long)]
+        reset: bool,
+    },
 }
 
-impl Templates {
-    pub fn load() -> Result<Self> {
-        let mut templates = vec![];
-        let mut indices = HashMap::new();
-
-        macro_rules! load_template {
-            ($char_enum:ident, $filename:expr, $threshold:expr, $display_char:expr) => {{
-                let template = Template::load_from_file(
-                    concat!("templates/", $filename),
-                    $threshold,
-                    $display_char,
-                )?;
-                indices.insert(Character::$char_enum, templates.len());
-                templates.push(template);
-            }};
-        }
+#[derive(Subcommand, Debug, Clone)]
+pub enum ExportCommand {
+    /// Write a report of the splits, e.g. for sharing on Discord: the PB with its splits and segments, sum of best, golds, how many runs reached each split and the latest runs
+    Report {
+        /// Splits file
+        file: PathBuf,
+        /// Format of the report
+        #[arg(long, value_enum, default_value_t = ReportFormat::Markdown)]
+        format: ReportFormat,
+        /// File to write the report to [default: print it]
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+        /// Heading of the report [default: the name of the splits file]
+        #[arg(long)]
+        title: Option<String>,
+    },
+}
 
-        load_template!(Percent, "percent.png", 0.80, '%');
-        load_template!(Colon, "colon.png", 0.75, ':');
-        load_template!(Zero, "zero.png", 0.80, '0');
-        load_template!(One, "one.png", 0.83, '1');
-        load_template!(Two, "two.png", 0.83, '2');
-        load_template!(Three, "three.png", 0.83, '3');
-        load_template!(Four, "four.png", 0.85, '4');
-        load_template!(Five, "five.png", 0.85, '5');
-        load_template!(Six, "six.png", 0.83, '6');
-        load_template!(Seven, "seven.png", 0.85, '7');
-        load_template!(Eight, "eight.png", 0.80, '8');
-        load_template!(Nine, "nine.png", 0.80, '9');
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum OcrEngine {
+    /// Template matching with the images in the templates directory
+    Templates,
+    /// Compares the segmented characters to the templates by pixel density (without template matching)
+    Classifier,
+    /// Tesseract, restricted to the characters of the IGT
+    #[cfg(feature = "tesseract")]
+    Tesseract,
+}
 
-        Ok(Self { indices, templates })
+/// Windows scanned when detecting the ROI: twice as wide as the default ROI so that the whole IGT
+/// fits into at least one of them
+const ROI_DETECTION_WINDOW: opencv::core::Size = opencv::core::Size {
+    width: 2 * DEFAULT_ROI.width,
+    height: DEFAULT_ROI.height,
+};
+
+/// Title of the annotated preview window
+const DEBUG_WINDOW: &str = "Webcam OCR";
+
+/// How long to wait for a frame before checking for a shutdown request again
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How often the templates directory is checked for changes.
+const TEMPLATES_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the splits files are checked for changes by other programs.
+const SPLITS_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often frames are compared to the title screens with --games-dir.
+const GAME_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Default region of interest (ROI) for a 1080p capture of the guidebook.
+const DEFAULT_ROI: Rect = Rect {
+    x: 1260,
+    y: 45,
+    width: 620,
+    height: 50,
+};
+
+fn parse_roi(s: &str) -> Result<Rect> {
+    let parts = s
+        .split(',')
+        .map(|p| p.trim().parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow!("Invalid ROI '{}': {}", s, e))?;
+
+    match parts[..] {
+        [x, y, width, height] if width > 0 && height > 0 => Ok(Rect::new(x, y, width, height)),
+        _ => Err(anyhow!(
+            "Invalid ROI '{}': expected X,Y,WIDTH,HEIGHT with positive size",
+            s
+        )),
     }
+}
 
-    pub fn get(&self, character: Character) -> Option<&Template> {
-        self.indices
-            .get(&character)
-            .map(|&idx| &self.templates[idx])
+fn parse_counter(s: &str) -> Result<Counter> {
+    let (name, roi) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Invalid counter '{}': expected NAME=X,Y,WIDTH,HEIGHT", s))?;
+    if name.trim().is_empty() {
+        return Err(anyhow!("Invalid counter '{}': missing name", s));
     }
+    Ok(Counter::new(name.trim().to_string(), parse_roi(roi)?))
 }
 
-#[derive(Clone)]
-struct TemplateMatch {
-    x: i32,
-    y: i32,
-    bounding_box: Size_<i32>,
-    character: char,
-    confidence: f32,
+/// Opens the frame source selected on the command line.
+fn open_source(args: &Args) -> Result<Box<dyn FrameSource>> {
+    let source: Box<dyn FrameSource> = if let Some(path) = &args.video {
+        let mut video = VideoCaptureSource::open_file(path)?;
+        if let Some(start) = args.start {
+            video.seek(start)?;
+        }
+        Box::new(video)
+    } else if let Some(monitor) = args.screen {
+        Box::new(ScreenSource::open_monitor(monitor)?)
+    } else if let Some(title) = &args.window {
+        Box::new(ScreenSource::open_window(title)?)
+    } else if let Some(pipeline) = &args.gst_pipeline {
+        Box::new(VideoCaptureSource::open_gstreamer(pipeline)?)
+    } else if let Some(url) = &args.stream {
+        let url = url.clone();
+        Box::new(Reconnecting::new(
+            move || Ok(Box::new(VideoCaptureSource::open_stream(&url)?) as Box<dyn FrameSource>),
+            Duration::from_secs(args.stall_timeout),
+        )?)
+    } else {
+        // USB capture cards tend to drop out, reopen them just like streams
+        let camera = args.camera;
+        let properties = args.camera_properties.clone();
+        Box::new(Reconnecting::new(
+            move || {
+                Ok(
+                    Box::new(VideoCaptureSource::open_camera(camera, &properties)?)
+                        as Box<dyn FrameSource>,
+                )
+            },
+            Duration::from_secs(args.stall_timeout),
+        )?)
+    };
+
+    Ok(source)
 }
 
-fn find_occurances_of_template(
-    image: &Mat,
-    template: &Template,
-    matches: &mut Vec<TemplateMatch>,
-) -> Result<()> {
-    let result_cols = image.cols() - template.size.width + 1;
-    let result_rows = image.rows() - template.size.height + 1;
-
-    let mut result = Mat::new_rows_cols_with_default(
-        result_rows,
-        result_cols,
-        opencv::core::CV_32FC1,
-        opencv::core::Scalar::all(0.0),
-    )?;
-
-    imgproc::match_template(
-        &image,
-        &template.template,
-        &mut result,
-        imgproc::TM_CCOEFF_NORMED,
-        &opencv::core::no_array(),
-    )?;
-
-    let mut max_val = 0.0;
-    for y in 0..result.rows() {
-        for x in 0..result.cols() {
-            let val = *result.at_2d::<f32>(y, x)?;
-            if val >= max_val {
-                max_val = val;
+/// Prints the capture devices found among the first `max_index` indices.
+fn list_cameras(max_index: i32) -> Result<()> {
+    let cameras = capture::probe_cameras(max_index);
+    if cameras.is_empty() {
+        println!("No capture devices found");
+        return Ok(());
+    }
+
+    for camera in cameras {
+        let resolutions = camera
+            .resolutions
+            .iter()
+            .map(|(width, height)| format!("{}x{}", width, height))
+            .collect::<Vec<_>>();
+        println!(
+            "--camera {}: {} ({}), {}",
+            camera.index,
+            camera.name.as_deref().unwrap_or("unknown device"),
+            camera.backend,
+            if resolutions.is_empty() {
+                "no common resolution".to_string()
+            } else {
+                resolutions.join(", ")
             }
-            if val >= template.threshold {
-                matches.push(TemplateMatch {
-                    x,
-                    y,
-                    bounding_box: template.size,
-                    character: template.character,
-                    confidence: val,
-                });
+        );
+    }
+    Ok(())
+}
+
+/// Runs a `runs` subcommand: lists, deletes, archives, prunes or edits the runs of a splits file.
+fn manage_runs(command: &RunsCommand) -> Result<()> {
+    match command {
+        RunsCommand::List { file } => {
+            for line in Splits::load_from_file(file)?.format_runs() {
+                println!("{}", line);
             }
         }
+        RunsCommand::Delete { file, id } => {
+            let mut splits = Splits::load_from_file(file)?;
+            splits.delete_run(*id)?;
+            println!("Deleted run {} from {}", id, file.display());
+        }
+        RunsCommand::Archive { file, keep_last } => {
+            let mut splits = Splits::load_from_file(file)?;
+            let archive_path = splits
+                .archive_path()
+                .ok_or_else(|| anyhow!("Invalid splits file {}", file.display()))?;
+            let archived = splits.archive_runs(*keep_last, &archive_path)?;
+            println!("Archived {} runs to {}", archived, archive_path.display());
+        }
+        RunsCommand::Prune { file, keep_last } => {
+            let mut splits = Splits::load_from_file(file)?;
+            let pruned = splits.prune_runs(*keep_last)?;
+            println!("Deleted {} runs from {}", pruned, file.display());
+        }
+        RunsCommand::Edit {
+            file,
+            id,
+            category,
+            tags,
+            notes,
+        } => {
+            let mut splits = Splits::load_from_file(file)?;
+            let tags = (!tags.is_empty()).then(|| tags.clone());
+            splits.set_run_metadata(*id, category.clone(), tags, notes.clone())?;
+            println!("Updated run {} in {}", id, file.display());
+        }
+        RunsCommand::Correct {
+            file,
+            time,
+            run,
+            split,
+        } => {
+            let mut splits = Splits::load_from_file(file)?;
+            let run = match run {
+                Some(run) => *run,
+                None => {
+                    splits
+                        .active_run()
+                        .ok_or_else(|| anyhow!("No active run in {}, pass --run", file.display()))?
+                        .id
+                }
+            };
+            let previous = splits.correct_split_time(run, split.as_deref(), *time)?;
+            println!(
+                "Corrected run {} from {} to {}",
+                run,
+                Splits::format_time(Some(previous)),
+                Splits::format_time(Some(*time))
+            );
+        }
+        RunsCommand::Golds { file, reset } => {
+            let mut splits = Splits::load_from_file(file)?;
+            for line in splits.recompute_best_segments(*reset)? {
+                println!("{}", line);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints the inconsistencies of the splits file `file` and, with `fix`, repairs them after
+/// backing up the file.
+fn check_splits_file(file: &Path, fix: bool) -> Result<()> {
+    let mut splits = Splits::load_from_file(file)?;
+    let corrected = !splits.matches_file()?;
+    let inconsistencies = splits.check();
+    if !corrected && inconsistencies.is_empty() {
+        println!("{}: no problems found", file.display());
+        return Ok(());
+    }
+
+    println!("{}:", file.display());
+    if corrected {
+        println!(
+            "  [fixable] Loading corrected the file (e.g. the order of the runs or the PB split times) or upgraded its version"
+        );
+    }
+    for inconsistency in &inconsistencies {
+        let kind = if inconsistency.is_fixable() {
+            "fixable"
+        } else {
+            "fix by hand"
+        };
+        println!("  [{}] {}", kind, inconsistency);
+    }
+    if !fix {
+        if corrected || inconsistencies.iter().any(|i| i.is_fixable()) {
+            println!("Run with --fix to repair the fixable problems");
+        }
+        return Ok(());
     }
-    //println!("max val: {}", max_val);
 
+    let backup = splits
+        .backup_path()
+        .ok_or_else(|| anyhow!("Invalid splits file {}", file.display()))?;
+    fs::copy(file, &backup).map_err(|e| {
+        anyhow!(
+            "Failed to back up {} to {}: {}",
+            file.display(),
+            backup.display(),
+            e
+        )
+    })?;
+    splits.repair()?;
+    splits.save_to_file()?;
+    println!(
+        "Repaired {} ({} problems left), the previous file is at {}",
+        file.display(),
+        splits.check().len(),
+        backup.display()
+    );
     Ok(())
 }
 
-fn extract_igt(
-    image: &Mat,
-    templates: &Templates,
-    matches: &mut Vec<TemplateMatch>,
-) -> Result<InGameTime> {
-    // Use '%' as an indicator whether we are in the guidebook and terminate early if not
-    let percent = templates.get(Character::Percent).unwrap();
-    find_occurances_of_template(image, &percent, matches)?;
+fn export(command: &ExportCommand) -> Result<()> {
+    match command {
+        ExportCommand::Report {
+            file,
+            format,
+            output,
+            title,
+        } => {
+            let splits = Splits::load_from_file(file)?;
+            let title = title.clone().unwrap_or_else(|| {
+                let name = file.file_name().unwrap_or_default().to_string_lossy();
+                let name = name.strip_suffix(".gz").unwrap_or(&name);
+                name.strip_suffix(".json").unwrap_or(name).to_string()
+            });
+            let report = splits::render_report(&splits, &title, *format);
+            match output {
+                Some(output) => {
+                    fs::write(output, report).map_err(|e| {
+                        anyhow!("Failed to write report {}: {}", output.display(), e)
+                    })?;
+                    println!("Wrote the report to {}", output.display());
+                }
+                None => print!("{}", report),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints the splits files in the data directory with their number of runs and PB.
+fn list_splits_files() -> Result<()> {
+    let dir = data_dir::data_dir()?;
+    let names = if dir.is_dir() {
+        data_dir::list_splits(&dir)?
+    } else {
+        Vec::new()
+    };
+    if names.is_empty() {
+        println!("No splits files in {}", dir.display());
+        return Ok(());
+    }
 
-    if matches.is_empty() {
-        return Err(anyhow!("No IGT found"));
+    println!("Splits files in {}:", dir.display());
+    for name in names {
+        match data_dir::splits_path(&dir, &name).and_then(|path| Splits::load_from_file(&path)) {
+            Ok(splits) => println!(
+                "  {}  {} runs, PB {}",
+                name,
+                splits.runs().len(),
+                Splits::format_time(splits.personal_best().and_then(|pb| pb.final_time))
+            ),
+            Err(e) => println!("  {}  (invalid: {})", name, e),
+        }
     }
+    Ok(())
+}
 
-    // Find occurances of all characters
-    for template in &templates.templates {
-        if template.character == '%' {
-            continue;
+/// Installs a Ctrl-C / SIGTERM handler requesting a graceful shutdown. A second signal exits
+/// immediately.
+fn install_shutdown_handler() -> Result<Arc<AtomicBool>> {
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let requested = shutdown.clone();
+    ctrlc::set_handler(move || {
+        if requested.swap(true, Ordering::SeqCst) {
+            std::process::exit(130);
         }
+    })?;
+    Ok(shutdown)
+}
 
-        find_occurances_of_template(image, &template, matches)?;
+/// Opens or closes the annotated preview window.
+fn show_debug_window(show: bool) -> Result<()> {
+    if show {
+        highgui::named_window(DEBUG_WINDOW, highgui::WINDOW_NORMAL)?;
+    } else {
+        highgui::destroy_window(DEBUG_WINDOW)?;
     }
+    Ok(())
+}
 
-    // Sort by x-coordinate
-    matches.sort_by(|a, b| a.x.cmp(&b.x));
+/// Switches the split views of all trackers to the next comparison and prints them.
+fn cycle_comparison(trackers: &mut [Tracker]) {
+    let multiple = trackers.len() > 1;
+    for tracker in trackers {
+        if multiple {
+            println!("[{}]", tracker.label());
+        }
+        for line in tracker.cycle_comparison() {
+            println!("{}", line);
+        }
+    }
+}
 
-    // Simple 1D NMS on x-axis
-    let mut filtered: Vec<TemplateMatch> = Vec::new();
-    for m in matches.drain(..) {
-        let mut replaced = false;
-        for other in &mut filtered {
-            let m_start = m.x;
-            let m_end = m.x + m.bounding_box.width;
-            let o_start = other.x;
-            let o_end = other.x + other.bounding_box.width;
+/// Switches the split views of all trackers to racing against the golds (or back) and prints them.
+fn toggle_golds(trackers: &mut [Tracker]) {
+    let multiple = trackers.len() > 1;
+    for tracker in trackers {
+        if multiple {
+            println!("[{}]", tracker.label());
+        }
+        for line in tracker.toggle_golds() {
+            println!("{}", line);
+        }
+    }
+}
 
-            let overlap = (m_end.min(o_end) - m_start.max(o_start)).max(0);
-            let min_width = m.bounding_box.width.min(other.bounding_box.width);
+/// Removes the split time recorded last by the active run of the tracker labeled `label`, or of
+/// all trackers, and prints what was removed.
+fn undo_last_split(trackers: &mut [Tracker], label: Option<&str>) {
+    let multiple = trackers.len() > 1;
+    let mut found = false;
+    for tracker in trackers
+        .iter_mut()
+        .filter(|tracker| label.is_none_or(|label| tracker.label() == label.trim()))
+    {
+        found = true;
+        let result = tracker.undo();
+        if multiple {
+            print!("[{}] ", tracker.label());
+        }
+        match result {
+            Ok(removed) => println!("{}", removed),
+            Err(e) => println!("Nothing to undo: {}", e),
+        }
+    }
+    if !found {
+        println!("No splits labeled '{}'", label.unwrap_or_default());
+    }
+}
 
-            if overlap as f32 > 0.5 * min_width as f32 {
-                if m.confidence > other.confidence {
-                    *other = m.clone();
-                }
-                replaced = true;
-                break;
-            }
+/// Reloads the splits file of the tracker labeled `label`, or of all trackers, after it was changed
+/// by another program, or with `merge` adds the runs in the file to the splits.
+fn resolve_external_edit(trackers: &mut [Tracker], label: Option<&str>, merge: bool) {
+    let mut found = false;
+    for tracker in trackers
+        .iter_mut()
+        .filter(|tracker| label.is_none_or(|label| tracker.label() == label.trim()))
+    {
+        found = true;
+        match tracker.resolve_external_edit(merge) {
+            Ok(result) => println!("[{}] {}", tracker.label(), result),
+            Err(e) => println!(
+                "[{}] Failed to load the splits file: {}",
+                tracker.label(),
+                e
+            ),
         }
+    }
+    if !found {
+        println!("No splits labeled '{}'", label.unwrap_or_default());
+    }
+}
 
-        if !replaced {
-            filtered.push(m);
+/// Pulls the changes made to the splits file at `path` on other machines. Failures are printed,
+/// tracking goes on with the local file.
+#[cfg(feature = "sync")]
+fn pull_splits_file(remote: &splits::Remote, path: &Path) {
+    match remote.pull(path) {
+        Ok(splits::PullOutcome::NoRemoteCopy) => println!(
+            "No remote copy of {} yet, it is pushed after the first finished run",
+            path.display()
+        ),
+        Ok(splits::PullOutcome::UpToDate) => {}
+        Ok(splits::PullOutcome::Pulled) => {
+            println!(
+                "Pulled the changes to {} from the remote copy",
+                path.display()
+            )
         }
+        Ok(splits::PullOutcome::Merged { added }) => println!(
+            "Merged {} runs from the remote copy into {}",
+            added,
+            path.display()
+        ),
+        Ok(splits::PullOutcome::Conflict { copy, reason }) => println!(
+            "{} and its remote copy both changed since the last sync and can't be merged ({}). The remote copy is in {}, finished runs aren't pushed until one of them is replaced with the other",
+            path.display(),
+            reason,
+            copy.display()
+        ),
+        Err(e) => println!(
+            "Failed to pull the changes to {}, using the local file: {}",
+            path.display(),
+            e
+        ),
     }
+}
 
-    // Sort again to ensure left-to-right order
-    filtered.sort_by(|a, b| a.x.cmp(&b.x));
+/// Reads frames until the IGT is found and returns the ROI around it.
+fn wait_for_roi(
+    capture: &CaptureThread,
+    templates: &Templates,
+    preprocessing: &Preprocessing,
+) -> Result<Rect> {
+    println!("Open the guidebook to detect the IGT region...");
 
-    let mut result = String::new();
-    for (i, m) in filtered.iter().enumerate() {
-        if i > 0 {
-            let prev = &filtered[i - 1];
-            let gap = m.x - (prev.x + prev.bounding_box.width);
-            if gap as f32 > 20.0 {
-                result.push(' ');
-            }
+    loop {
+        let Some(CapturedFrame { frame, .. }) = capture.recv() else {
+            return Err(anyhow!("Capture stopped before the IGT was found"));
+        };
+
+        if let Some(roi) = ocr::detect_roi(&frame, templates, preprocessing, ROI_DETECTION_WINDOW)?
+        {
+            println!(
+                "Detected IGT region: {},{},{},{}",
+                roi.x, roi.y, roi.width, roi.height
+            );
+            return Ok(roi);
         }
-        result.push(m.character);
     }
+}
 
-    // Return filtered matches to caller
-    *matches = filtered;
+/// Reads frames until one shows something other than black and returns its size and the area
+/// between the black bars.
+fn wait_for_content_area(capture: &CaptureThread) -> Result<(opencv::core::Size, Rect)> {
+    println!("Waiting for the game picture to detect black bars...");
 
-    Ok(InGameTime::parse(&result)?)
+    loop {
+        let Some(CapturedFrame { frame, .. }) = capture.recv() else {
+            return Err(anyhow!("Capture stopped before the game picture was shown"));
+        };
+
+        if let Some(content) = letterbox::detect_content_area(&frame)? {
+            println!(
+                "Game picture: {},{},{},{} of {}x{}",
+                content.x,
+                content.y,
+                content.width,
+                content.height,
+                frame.cols(),
+                frame.rows()
+            );
+            return Ok((frame.size()?, content));
+        }
+    }
 }
 
-#[derive(Parser, Debug)]
-pub struct Args {
-    /// Path to the splits JSON file
-    #[arg(value_name = "SPLITS_FILE")]
-    pub splits_file: PathBuf,
+/// Creates the recognizer replacing template matching, if another engine than templates is selected.
+fn create_engine(engine: OcrEngine, templates: &Templates) -> Result<Option<Box<dyn Recognizer>>> {
+    Ok(match engine {
+        OcrEngine::Templates => None,
+        OcrEngine::Classifier => Some(Box::new(GlyphClassifier::from_templates(templates)?)),
+        #[cfg(feature = "tesseract")]
+        OcrEngine::Tesseract => Some(Box::new(ocr::tesseract::TesseractRecognizer::new()?)),
+    })
 }
 
 fn main() -> Result<()> {
-    let args = Args::parse();
-
-    let debug = false;
-
-    let mut video = videoio::VideoCapture::new(2, videoio::CAP_ANY)?;
-    /*let mut video =
-    videoio::VideoCapture::from_file_def("C:\\Users\\domin\\Videos\\2025-07-16 20-00-51.mkv")?;*/
-    if !videoio::VideoCapture::is_opened(&video)? {
-        panic!("Unable to open video!");
+    let mut args = Args::parse();
+
+    match &args.command {
+        Some(Command::TrainTemplates {
+            input,
+            output,
+            roi,
+            preprocess,
+            deinterlace,
+            color_key,
+        }) => {
+            let preprocessing = preprocess
+                .clone()
+                .with_deinterlace(*deinterlace)
+                .with_color_key(*color_key);
+            return training::train_templates(
+                input,
+                output,
+                roi.unwrap_or(DEFAULT_ROI),
+                &preprocessing,
+            );
+        }
+        Some(Command::ReadImage { file, options }) => {
+            return screenshot::read_image(file, options);
+        }
+        Some(Command::Regression { dir, options }) => {
+            return regression::run(dir, options);
+        }
+        Some(Command::Bench {
+            video,
+            frames,
+            options,
+        }) => {
+            return bench::run(video, *frames, options);
+        }
+        Some(Command::Calibrate {
+            camera,
+            video,
+            frames,
+            options,
+        }) => {
+            return calibration::run(*camera, video.as_deref(), *frames, options);
+        }
+        Some(Command::Stats { files }) => {
+            for file in files {
+                let splits = Splits::load_from_file(file)?;
+                println!("{}: {}", file.display(), splits.format_stats());
+                for line in splits.format_run_statistics() {
+                    println!("  {}", line);
+                }
+                println!();
+                for line in splits.format_segment_statistics() {
+                    println!("  {}", line);
+                }
+                let pb_history = splits.format_pb_history();
+                if !pb_history.is_empty() {
+                    println!();
+                    println!("  PB history:");
+                    for line in pb_history {
+                        println!("    {}", line);
+                    }
+                }
+            }
+            return Ok(());
+        }
+        Some(Command::Runs { command }) => {
+            return manage_runs(command);
+        }
+        Some(Command::Check { file, fix }) => {
+            return check_splits_file(file, *fix);
+        }
+        Some(Command::Merge { a, b, output }) => {
+            let mut splits = Splits::load_from_file(a)?.with_path(output.clone());
+            let added = splits.merge(Splits::load_from_file(b)?)?;
+            splits.save_to_file()?;
+            println!(
+                "Added {} runs from {} to {} runs in {}",
+                added,
+                b.display(),
+                splits.runs().len() - added,
+                output.display()
+            );
+            return Ok(());
+        }
+        Some(Command::New {
+            file,
+            splits: new_splits,
+            from,
+        }) => {
+            let splits = splits::create_splits_file(file.clone(), new_splits, from.as_deref())?;
+            for split in splits.splits() {
+                println!("  {:>4}%  {}", split.percent, split.name);
+            }
+            println!(
+                "Created {} with {} splits",
+                file.display(),
+                splits.splits().len()
+            );
+            return Ok(());
+        }
+        Some(Command::Import {
+            file,
+            format,
+            percents,
+            output,
+        }) => {
+            let splits = splits::import_splits(*format, file, output.clone(), percents)?;
+            splits.save_to_file()?;
+            println!(
+                "Imported {} splits and {} runs to {}",
+                splits.splits().len(),
+                splits.runs().len(),
+                output.display()
+            );
+            return Ok(());
+        }
+        Some(Command::Export { command }) => {
+            return export(command);
+        }
+        Some(Command::List) => {
+            return list_splits_files();
+        }
+        Some(Command::ListCameras { max_index }) => {
+            return list_cameras(*max_index);
+        }
+        None => {}
     }
 
-    // Set resolution to 1920x1080
-    video.set(opencv::videoio::CAP_PROP_FRAME_WIDTH, 1920.0)?;
-    video.set(opencv::videoio::CAP_PROP_FRAME_HEIGHT, 1080.0)?;
+    if !args.splits_names.is_empty() {
+        let dir = data_dir::data_dir()?;
+        for name in &args.splits_names {
+            args.splits_files.push(data_dir::splits_path(&dir, name)?);
+        }
+    }
 
-    // Optional: read back to verify
-    let width = video.get(opencv::videoio::CAP_PROP_FRAME_WIDTH)?;
-    let height = video.get(opencv::videoio::CAP_PROP_FRAME_HEIGHT)?;
-    println!("Resolution set to: {}x{}", width, height);
+    let mut debug = args.debug;
+
+    let correction = if args.crop.is_some() || args.perspective.is_some() {
+        Some(FrameCorrection::new(args.crop, args.perspective)?)
+    } else {
+        None
+    };
+
+    let source_args = args.clone();
+    let capture = CaptureThread::spawn(
+        move || open_source(&source_args),
+        CaptureOptions {
+            frame_step: if args.video.is_some() {
+                args.frame_step
+            } else {
+                1
+            },
+            end: args.end,
+            drop_when_full: args.video.is_none(),
+            correction,
+        },
+    );
 
     if debug {
-        highgui::named_window("Webcam OCR", highgui::WINDOW_NORMAL)?;
+        show_debug_window(true)?;
     }
 
-    // Define the region of interest (ROI)
-    let roi_rect = Rect::new(1260, 45, 620, 50); // x, y, width, height
+    let mut preprocessing = args
+        .preprocess
+        .clone()
+        .with_deinterlace(args.deinterlace)
+        .with_color_key(args.color_key);
 
     // Load template images
-    let templates = Templates::load()?;
+    let templates_dir = args
+        .templates_dir
+        .clone()
+        .unwrap_or_else(Templates::default_dir);
+    let mut templates = Templates::load(&templates_dir, args.template_set.as_deref())?;
+    if args.gpu && !templates.enable_gpu()? {
+        println!("OpenCL is not available, falling back to the CPU");
+    }
 
-    let mut splits = Splits::load_from_file(&args.splits_file)?;
-    let mut display = SplitsDisplay::new();
-    //splits.print_splits();
+    // With --games-dir, there is a single runner whose splits file is selected later on
+    let runners = if args.games_dir.is_some() {
+        1
+    } else {
+        args.splits_files.len()
+    };
+
+    // Define the regions of interest (ROI), one per splits file
+    let rois = match args.rois.len() {
+        0 if args.auto_roi && runners == 1 => {
+            match wait_for_roi(&capture, &templates, &preprocessing) {
+                Ok(roi) => vec![roi],
+                Err(e) => {
+                    // Report the capture error instead, if there is one
+                    capture.join()?;
+                    return Err(e);
+                }
+            }
+        }
+        0 if args.auto_roi => {
+            return Err(anyhow!(
+                "--auto-roi only supports a single splits file, use --roi for each runner instead"
+            ));
+        }
+        0 if runners == 1 => vec![DEFAULT_ROI],
+        n if n == runners => args.rois.clone(),
+        n => {
+            return Err(anyhow!(
+                "Got {} ROIs for {} splits files, expected one ROI per splits file",
+                n,
+                runners
+            ));
+        }
+    };
+
+    // Move the ROIs, given for a full-frame game picture, into the area between the black bars
+    let content_area = if args.detect_letterbox {
+        match wait_for_content_area(&capture) {
+            Ok(content_area) => Some(content_area),
+            Err(e) => {
+                capture.join()?;
+                return Err(e);
+            }
+        }
+    } else {
+        None
+    };
+    let place = |roi: Rect| match content_area {
+        Some((frame_size, content)) => letterbox::map_roi(roi, frame_size, content),
+        None => roi,
+    };
+    let rois: Vec<Rect> = rois.into_iter().map(place).collect();
+
+    if !args.fallback_rois.is_empty() && runners > 1 {
+        return Err(anyhow!("--fallback-roi only supports a single splits file"));
+    }
+    if !args.counters.is_empty() && runners > 1 {
+        return Err(anyhow!("--counter only supports a single splits file"));
+    }
+    if args.livesplit_server.is_some() && runners > 1 {
+        return Err(anyhow!(
+            "--livesplit-server only supports a single splits file"
+        ));
+    }
+    if args.websocket.is_some() && runners > 1 {
+        return Err(anyhow!("--websocket only supports a single splits file"));
+    }
+    if args.http_api.is_some() && runners > 1 {
+        return Err(anyhow!("--http-api only supports a single splits file"));
+    }
+    if args.event_socket.is_some() && runners > 1 {
+        return Err(anyhow!("--event-socket only supports a single splits file"));
+    }
+    // Started once, the trackers of all games answer through them
+    let livesplit_server = args
+        .livesplit_server
+        .map(|address| LiveSplitServer::start(address, args.comparison))
+        .transpose()?;
+    if let Some(server) = &livesplit_server {
+        println!("LiveSplit Server listening on {}", server.address());
+    }
+    let websocket = args
+        .websocket
+        .map(|address| WebSocketBroadcast::start(address, args.comparison))
+        .transpose()?;
+    if let Some(websocket) = &websocket {
+        println!("WebSocket server listening on ws://{}", websocket.address());
+    }
+    let http_api = args
+        .http_api
+        .map(|address| HttpApi::start(address, args.comparison))
+        .transpose()?;
+    if let Some(http_api) = &http_api {
+        println!("HTTP API listening on http://{}", http_api.address());
+    }
+    let event_sink = args
+        .event_socket
+        .clone()
+        .map(EventSink::start)
+        .transpose()?;
+    if let Some(event_sink) = &event_sink {
+        println!("Publishing events on {}", event_sink.path().display());
+    }
+    if args.level_roi.is_some() {
+        if runners > 1 {
+            return Err(anyhow!("--level-roi only supports a single splits file"));
+        }
+        if !templates.has_letters() {
+            return Err(anyhow!(
+                "--level-roi requires letter templates in {}",
+                templates.dir().join(ocr::LETTERS_DIR).display()
+            ));
+        }
+    }
+
+    let make_tracker = |path: &Path, roi: Rect| -> Result<Tracker> {
+        if args.restore_backup && Splits::restore_if_damaged(path)? {
+            println!(
+                "Restored the damaged splits file {} from its backup",
+                path.display()
+            );
+        }
+        #[cfg(feature = "sync")]
+        if let Some(remote) = &args.sync {
+            pull_splits_file(remote, path);
+        }
+        let mut tracker = Tracker::from_splits_file(path, roi)?
+            .with_vote_window(args.vote_window as usize)
+            .with_debounce(args.confirmations as usize)
+            .with_counters(
+                args.counters
+                    .iter()
+                    .map(|counter| counter.clone().with_roi(place(counter.roi())))
+                    .collect(),
+            )
+            .with_fallback_rois(args.fallback_rois.iter().copied().map(place).collect())
+            .with_comparison(args.comparison)
+            .with_save_interval(Duration::from_secs(args.save_interval));
+        if let Some(level_roi) = args.level_roi {
+            tracker = tracker.with_level_roi(place(level_roi));
+        }
+        if args.lock_slots {
+            tracker = tracker.with_slot_lock();
+        }
+        if args.run_summary {
+            tracker = tracker.with_run_summary();
+        }
+        if args.real_time {
+            tracker = tracker.with_real_time();
+        }
+        if let Some(category) = &args.category {
+            tracker = tracker.with_category(category.clone());
+        }
+        if let Some(dir) = &args.run_log {
+            tracker = tracker.with_observer(Box::new(RunLog::new(dir.clone())?));
+        }
+        if let Some(keep) = args.archive_after {
+            let archived = tracker.archive_runs(keep)?;
+            if archived > 0 {
+                println!("Archived {} runs of {}", archived, path.display());
+            }
+        }
+        if let Some(minutes) = args.watchdog {
+            tracker = tracker.with_watchdog(Duration::from_secs(minutes * 60));
+        }
+        if let Some(server) = &livesplit_server {
+            tracker = tracker.with_observer(Box::new(server.clone()));
+        }
+        if let Some(websocket) = &websocket {
+            tracker = tracker.with_observer(Box::new(websocket.clone()));
+        }
+        if let Some(http_api) = &http_api {
+            tracker = tracker.with_observer(Box::new(http_api.clone()));
+        }
+        if let Some(event_sink) = &event_sink {
+            tracker = tracker.with_observer(Box::new(event_sink.clone()));
+        }
+        #[cfg(feature = "sync")]
+        if let Some(remote) = &args.sync {
+            tracker = tracker.with_observer(Box::new(splits::RemoteSync::new(remote.clone())));
+        }
+        Ok(tracker)
+    };
+
+    let game_detector = args
+        .games_dir
+        .as_deref()
+        .map(GameDetector::load)
+        .transpose()?;
+    let game_roi = rois[0];
+    let mut game_checked_at = Instant::now();
+    if game_detector.is_some() {
+        println!("Open the title screen to select the splits file...");
+    }
+
+    let mut trackers = args
+        .splits_files
+        .iter()
+        .zip(rois)
+        .map(|(path, roi)| make_tracker(path, roi))
+        .collect::<Result<Vec<_>>>()?;
+    let mut retimings: Vec<Retiming> = trackers.iter().map(|_| Retiming::new()).collect();
+    let mut diagnostics = args
+        .diagnostics_dir
+        .clone()
+        .map(Diagnostics::new)
+        .transpose()?;
+    let mut confidence_stats = ConfidenceStats::new();
+    let mut confidence_stats_printed_at = Instant::now();
+    let mut health_stats = HealthStats::new();
+    let mut health_stats_printed_at = Instant::now();
+    let mut templates_checked_at = Instant::now();
+    let mut splits_checked_at = Instant::now();
+    let mut tuning: Option<Tuning> = None;
+    let mut load_tracker = LoadTracker::new();
+    let mut observation_log = ObservationLog::new();
+    if let Some(path) = &args.observation_log {
+        observation_log = observation_log.with_file(path)?;
+    }
+    let mut recorder = args
+        .record
+        .as_deref()
+        .map(|path| Recorder::new(path, args.record_fps));
+
+    // Recognizer replacing template matching, if another engine was selected
+    let mut engine = create_engine(args.ocr_engine, &templates)?;
 
     let mut resized = false;
-    let mut last_igt = InGameTime::default();
-    let mut run_finished = false;
+    let mut frame_index: u64 = 0;
+    let mut sampler = Sampler::new(args.sample_rate);
+    if let Some(idle_rate) = args.idle_rate {
+        sampler = sampler.with_idle_rate(idle_rate, Duration::from_secs(args.idle_after));
+    }
+
+    let console = Console::spawn();
+    let shutdown = install_shutdown_handler()?;
 
     println!();
     println!();
     println!();
     loop {
-        let mut frame = Mat::default();
-        video.read(&mut frame)?;
-        if frame.empty() {
-            continue;
-        }
-
-        //let now = Instant::now();
-        let roi_view = Mat::roi(&frame, roi_rect)?;
-        let mut roi = Mat::default();
-        opencv::core::copy_to(&roi_view, &mut roi, &opencv::core::no_array())?;
-
-        // Convert ROI to grayscale
-        let mut gray = Mat::default();
-        imgproc::cvt_color(
-            &roi,
-            &mut gray,
-            imgproc::COLOR_BGR2GRAY,
-            0,
-            opencv::core::AlgorithmHint::ALGO_HINT_DEFAULT,
-        )?;
-
-        let mut binarized_roi = Mat::default();
-        opencv::imgproc::threshold(
-            &gray,              // input
-            &mut binarized_roi, // output
-            0.0,                // threshold value (0 = auto for Otsu)
-            255.0,              // max value
-            imgproc::THRESH_OTSU,
-        )?;
-
-        let mut matches: Vec<TemplateMatch> = vec![];
-        if let Ok(igt) = extract_igt(&binarized_roi, &templates, &mut matches) {
-            //let elapsed = now.elapsed();
-            //println!("Found <{}> in {} ms", igt, elapsed.as_millis());
-
-            if igt != last_igt {
-                if debug {
-                    println!("IGT: {}", igt);
+        if shutdown.load(Ordering::SeqCst) {
+            println!("Shutting down...");
+            for tracker in &mut trackers {
+                tracker.interrupt()?;
+            }
+            break;
+        }
+
+        // Checked before waiting for a frame, as a frozen capture might not deliver any
+        for tracker in &mut trackers {
+            if let Some(warning) = tracker.check_watchdog() {
+                println!(
+                    "\x07{}",
+                    format!("WARNING: [{}] {}", tracker.label(), warning)
+                        .red()
+                        .bold()
+                );
+            }
+        }
+
+        let CapturedFrame {
+            mut frame,
+            position: video_time,
+        } = match capture.recv_timeout(SHUTDOWN_POLL_INTERVAL) {
+            Ok(captured) => captured,
+            Err(RecvTimeoutError::Timeout) => continue,
+            // End of video file (or capture failure, reported below)
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        let frame_started_at = Instant::now();
+
+        if let Some(active) = &mut recorder
+            && let Err(e) = active.write(&frame)
+        {
+            println!("Stopped recording to {}: {}", active.path().display(), e);
+            recorder = None;
+        }
+
+        while let Some(command) = console.try_recv() {
+            match command.as_str() {
+                "d" | "debug" => {
+                    debug = !debug;
+                    show_debug_window(debug)?;
+                    resized = false;
+                }
+                "c" | "compare" => cycle_comparison(&mut trackers),
+                "g" | "golds" => toggle_golds(&mut trackers),
+                "u" | "undo" => undo_last_split(&mut trackers, None),
+                "reload" => resolve_external_edit(&mut trackers, None, false),
+                "merge" => resolve_external_edit(&mut trackers, None, true),
+                _ => match command.split_once(' ') {
+                    Some(("u" | "undo", label)) => undo_last_split(&mut trackers, Some(label)),
+                    Some(("reload", label)) => {
+                        resolve_external_edit(&mut trackers, Some(label), false)
+                    }
+                    Some(("merge", label)) => {
+                        resolve_external_edit(&mut trackers, Some(label), true)
+                    }
+                    _ => println!(
+                        "Unknown command '{}' (d: toggle the debug view, c: cycle the comparison, g: race against the golds, u [LABEL]: undo the last split, reload/merge [LABEL]: load the splits file changed by another program or add its runs)",
+                        command
+                    ),
+                },
+            }
+        }
+
+        if splits_checked_at.elapsed() >= SPLITS_CHECK_INTERVAL {
+            for tracker in &mut trackers {
+                if let Some(message) = tracker.check_external_edit() {
+                    println!("[{}] {}", tracker.label(), message);
                 }
+            }
+            splits_checked_at = Instant::now();
+        }
 
-                if splits.active_run().is_none() {
-                    println!("IGT:");
-                    for split in splits.splits().iter().take(3) {
-                        Splits::print_split(splits.compute_name_width(), split);
+        if templates_checked_at.elapsed() >= TEMPLATES_CHECK_INTERVAL {
+            match templates.reload_if_changed() {
+                Ok(true) => {
+                    println!("Reloaded templates from {}", templates.dir().display());
+                    if let Some(tuning) = &mut tuning {
+                        tuning.sync(&templates)?;
+                    }
+                    if args.ocr_engine == OcrEngine::Classifier {
+                        engine = Some(Box::new(GlyphClassifier::from_templates(&templates)?));
                     }
+                }
+                Ok(false) => {}
+                // E.g. a file that is still being written, try again on the next check
+                Err(e) => println!("Failed to reload templates: {}", e),
+            }
+            templates_checked_at = Instant::now();
+        }
 
-                    let bpt = splits.best_possible_time();
-                    println!();
-                    let name_width = splits.compute_name_width();
-                    let name_fmt = Splits::pad_str("BPT:", name_width);
-                    let time_fmt = Splits::format_time(bpt);
-                    println!("{} {:>8} {:>8}", name_fmt, "      ", time_fmt);
-
-                    // FIXME: this doesn't work if the IGT/percentage is <= first split
-                    // Not sure whether there's a solution without integrating with LiveSplit / providing explicit GUI controls
-                    splits.initialize_active_run(&igt);
-                    run_finished = true;
+        // The trackbars are shown along with the debug view
+        if debug && tuning.is_none() {
+            tuning = Some(Tuning::open(&templates, &preprocessing)?);
+        } else if !debug {
+            tuning = None;
+        }
+        if let Some(tuning) = &mut tuning {
+            tuning.apply(&mut templates, &mut preprocessing)?;
+        }
+
+        if let Some(detector) = &game_detector
+            && game_checked_at.elapsed() >= GAME_CHECK_INTERVAL
+        {
+            // The trackers are labelled after the splits file, i.e. the game
+            if let Some((game, splits_file)) = detector.detect(&frame)?
+                && trackers
+                    .first()
+                    .is_none_or(|tracker| tracker.label() != game)
+            {
+                println!("Detected {}, using {}", game, splits_file.display());
+                trackers = vec![make_tracker(splits_file, game_roi)?];
+                retimings = vec![Retiming::new()];
+            }
+            game_checked_at = Instant::now();
+        }
+
+        let mut overlays: Vec<RoiOverlay> = Vec::new();
+        let mut updated = false;
+        if args.detect_loads {
+            let loading = loading::is_loading_frame(&frame)?;
+            match load_tracker.update(video_time, loading) {
+                Some(LoadEvent::Started { at }) => {
+                    println!("[{}] Loading...", retime::format_video_time(at));
+                }
+                Some(LoadEvent::Ended { at, duration }) => {
+                    println!(
+                        "[{}] Loading finished after {:.3}s",
+                        retime::format_video_time(at),
+                        duration.as_secs_f64()
+                    );
+                }
+                None => {}
+            }
+        }
+
+        // No OCR (and therefore no split updates) during loads
+        let sample = !load_tracker.is_loading() && sampler.should_sample(video_time);
+        for (tracker, retiming) in trackers.iter_mut().zip(&mut retimings).filter(|_| sample) {
+            let gpu = templates.gpu();
+            let candidates = tracker.roi_candidates();
+            let mut roi_rect = candidates[0];
+
+            //let now = Instant::now();
+            let mut binarized_roi = binarize_roi(&frame, roi_rect, &preprocessing, gpu)?;
+
+            let mut matches: Vec<TemplateMatch> = vec![];
+            // The last accepted IGT decides between digits that match equally well
+            let prior = *tracker.last_igt();
+            let result = match (&mut engine, tracker.slot_lock_mut()) {
+                (Some(engine), _) => engine.recognize(&binarized_roi, &mut matches),
+                (None, Some(slot_lock)) => {
+                    slot_lock.extract_igt(&binarized_roi, &templates, Some(&prior), &mut matches)
+                }
+                (None, None) => {
+                    ocr::extract_igt(&binarized_roi, &templates, Some(&prior), &mut matches)
+                }
+            };
+            let mut result = tracker.check_max_percent(result);
+
+            let recognizer: &mut dyn Recognizer = match &mut engine {
+                Some(engine) => engine.as_mut(),
+                None => &mut templates,
+            };
+
+            // Try the fallback ROIs until one of them shows the IGT, then lock onto that one
+            for &candidate in &candidates[1..] {
+                if result.is_ok() {
+                    break;
+                }
+
+                let binarized = binarize_roi(&frame, candidate, &preprocessing, gpu)?;
+                let mut candidate_matches = vec![];
+                if let Ok(igt) = tracker
+                    .check_max_percent(recognizer.recognize(&binarized, &mut candidate_matches))
+                {
+                    roi_rect = candidate;
+                    binarized_roi = binarized;
+                    matches = candidate_matches;
+                    result = Ok(igt);
+                }
+            }
+            if result.is_ok() && candidates.len() > 1 {
+                tracker.lock_roi(roi_rect);
+                println!(
+                    "{}: Using ROI {},{},{},{}",
+                    tracker.label(),
+                    roi_rect.x,
+                    roi_rect.y,
+                    roi_rect.width,
+                    roi_rect.height
+                );
+            }
+            if let Some(diagnostics) = &mut diagnostics {
+                let reason = match &result {
+                    // No matches means the IGT simply isn't on screen
+                    Err(e) if !matches.is_empty() => Some(format!("parse failed: {}", e)),
+                    Ok(igt) if !tracker.is_plausible(igt) => {
+                        Some(format!("implausible reading: {}", igt))
+                    }
+                    _ => None,
+                };
+                if let Some(reason) = reason {
+                    diagnostics.dump(tracker.label(), &binarized_roi, &matches, &reason)?;
+                }
+            }
+
+            health_stats.record_ocr(result.is_ok());
+            if result.is_ok() {
+                confidence_stats.record(&matches);
+            }
+
+            // Summary for the debug view, before `accept` and `update` change the tracker
+            let overlay_reading = match &result {
+                Ok(igt) if !tracker.is_plausible(igt) => format!("{} (implausible)", igt),
+                Ok(igt) => igt.to_string(),
+                Err(_) => matches.iter().map(|m| m.character).collect(),
+            };
+
+            let reading = result.as_ref().ok().copied();
+            let accepted = tracker.accept(reading);
+            let decision = match (&result, accepted) {
+                (_, Some(igt)) => Decision::Accepted(igt),
+                (Ok(_), None) => Decision::Pending,
+                (Err(_), None) if matches.is_empty() => {
+                    Decision::Rejected("no IGT found".to_string())
                 }
+                (Err(e), None) => Decision::Rejected(e.to_string()),
+            };
+
+            if let Some(igt) = accepted {
+                //let elapsed = now.elapsed();
+                //println!("Found <{}> in {} ms", igt, elapsed.as_millis());
 
-                splits.update_with_igt(&igt);
+                observation_log.record(tracker.label(), frame_index, video_time, &igt)?;
 
-                if let Some(active_run) = splits.active_run()
-                    && active_run.end_time.is_none()
+                if args.retime
+                    && let Some(change) = retiming.record(frame_index, video_time, &igt)
                 {
-                    run_finished = false;
+                    println!(
+                        "[{}] {}: {}",
+                        retime::format_video_time(change.video_time),
+                        tracker.label(),
+                        change.igt
+                    );
                 }
 
-                if !run_finished {
-                    let lines = display.render_split_view(&splits, &igt, 3);
-                    if !lines.is_empty() {
-                        println!("IGT:");
+                if let Some(lines) = tracker.update(&igt) {
+                    if debug {
+                        println!("{}: IGT: {}", tracker.label(), igt);
+                    }
+
+                    if !lines.is_empty() && !args.retime {
+                        if args.splits_files.len() > 1 {
+                            println!("[{}]", tracker.label());
+                        }
                         for line in lines {
                             println!("{}", line);
                         }
                     }
+                    updated = true;
                 }
+            }
 
-                if let Some(active_run) = splits.active_run()
-                    && active_run.end_time.is_some()
+            overlays.push(RoiOverlay {
+                roi: roi_rect,
+                matches,
+                reading: overlay_reading,
+                decision,
+            });
+
+            for index in 0..tracker.counters().len() {
+                let counter_rect = tracker.counters()[index].roi();
+                let binarized_counter =
+                    binarize_roi(&frame, counter_rect, &preprocessing, templates.gpu())?;
+                let mut counter_matches: Vec<TemplateMatch> = vec![];
+                let reading =
+                    ocr::extract_number(&binarized_counter, &templates, &mut counter_matches).ok();
+                if let Some(lines) = tracker.update_counter(index, reading)
+                    && !args.retime
                 {
-                    run_finished = true;
+                    for line in lines {
+                        println!("{}", line);
+                    }
                 }
+            }
 
-                last_igt = igt;
+            if let Some(level_rect) = tracker.level_roi() {
+                let binarized_level =
+                    binarize_roi(&frame, level_rect, &preprocessing, templates.gpu())?;
+                let mut level_matches: Vec<TemplateMatch> = vec![];
+                let reading =
+                    ocr::extract_text(&binarized_level, &templates, &mut level_matches).ok();
+                if let Some(lines) = tracker.update_level(reading)
+                    && !args.retime
+                {
+                    for line in lines {
+                        println!("{}", line);
+                    }
+                }
             }
         }
 
-        if debug {
-            for pt in matches {
-                let top_left = opencv::core::Point::new(roi_rect.x + pt.x, roi_rect.y + pt.y);
-                opencv::imgproc::rectangle(
-                    &mut frame,
-                    opencv::core::Rect::new(
-                        top_left.x,
-                        top_left.y,
-                        pt.bounding_box.width,
-                        pt.bounding_box.height,
-                    ),
-                    opencv::core::Scalar::new(255.0, 0.0, 255.0, 0.0),
-                    2,
-                    imgproc::LINE_8,
-                    0,
-                )?;
+        if sample {
+            // Recognizers only report matches if they found the '%'
+            let igt_visible = overlays.iter().any(|overlay| !overlay.matches.is_empty());
+            sampler.report(video_time, igt_visible);
+        }
+
+        frame_index += args.frame_step as u64;
+
+        if let Some(interval) = args.confidence_stats
+            && confidence_stats_printed_at.elapsed() >= Duration::from_secs(interval)
+        {
+            println!("Match confidences:");
+            for line in confidence_stats.render() {
+                println!("{}", line);
+            }
+            confidence_stats_printed_at = Instant::now();
+        }
+
+        if updated && trackers.len() > 1 && !args.retime {
+            for line in tracker::render_race_view(&trackers) {
+                println!("{}", line);
             }
+        }
 
-            // Draw ROI rectangle on original frame
-            opencv::imgproc::rectangle(
-                &mut frame,
-                roi_rect,
-                opencv::core::Scalar::new(0.0, 255.0, 0.0, 0.0),
-                2,
-                imgproc::LINE_8,
-                0,
-            )?;
+        health_stats.record_frame(frame_started_at.elapsed());
+        if let Some(interval) = args.stats_interval
+            && health_stats_printed_at.elapsed() >= Duration::from_secs(interval)
+        {
+            for line in health_stats.render(capture.counts()) {
+                println!("{}", line);
+            }
+            health_stats_printed_at = Instant::now();
+        }
+
+        if debug {
+            overlay::draw_overlays(&mut frame, &overlays)?;
 
             let mut display_frame = Mat::default();
             opencv::imgproc::resize(
@@ -435,18 +1846,109 @@ fn main() -> Result<()> {
             if !resized {
                 println!("Frame: {} x {}", display_frame.cols(), display_frame.rows());
                 let _ = highgui::resize_window(
-                    "Webcam OCR",
+                    DEBUG_WINDOW,
                     display_frame.cols(),
                     display_frame.rows(),
                 )?;
                 resized = true;
             }
 
-            highgui::imshow("Webcam OCR", &display_frame)?;
-            if highgui::wait_key(1)? == 27 {
-                break; // ESC to quit
+            highgui::imshow(DEBUG_WINDOW, &display_frame)?;
+            match highgui::wait_key(1)? {
+                // ESC to quit, shutting down like on Ctrl-C
+                27 => shutdown.store(true, Ordering::SeqCst),
+                key if key == 'd' as i32 => {
+                    debug = false;
+                    show_debug_window(false)?;
+                }
+                key if key == 'c' as i32 => cycle_comparison(&mut trackers),
+                key if key == 'g' as i32 => toggle_golds(&mut trackers),
+                key if key == 'u' as i32 => undo_last_split(&mut trackers, None),
+                key if key == 's' as i32 => {
+                    if let Some(tuning) = &tuning {
+                        tuning.save(&templates, &preprocessing)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let capture_counts = capture.counts();
+    capture.join()?;
+
+    if args.stats_interval.is_some() {
+        println!();
+        for line in health_stats.render(capture_counts) {
+            println!("{}", line);
+        }
+    }
+
+    if let Some(path) = &args.observation_log {
+        println!();
+        println!(
+            "Logged {} IGT readings to {}",
+            observation_log.observations().len(),
+            path.display()
+        );
+    }
+
+    if let Some(recorder) = &recorder {
+        println!();
+        println!(
+            "Recorded {} frames to {}",
+            recorder.frames(),
+            recorder.path().display()
+        );
+    }
+
+    if args.detect_loads {
+        println!();
+        println!(
+            "Loads: {}, total load time: {:.3}s",
+            load_tracker.loads(),
+            load_tracker.total().as_secs_f64()
+        );
+    }
+
+    if args.confidence_stats.is_some() {
+        println!();
+        println!("Match confidences:");
+        for line in confidence_stats.render() {
+            println!("{}", line);
+        }
+    }
+
+    if args.retime {
+        for (tracker, retiming) in trackers.iter().zip(&retimings) {
+            println!();
+            println!("Retimed splits [{}]:", tracker.label());
+            for line in retiming.render_summary(tracker.splits()) {
+                println!("{}", line);
             }
         }
+
+        if let Some(path) = &args.retime_report {
+            let runs: Vec<_> = trackers
+                .iter()
+                .zip(&retimings)
+                .map(|(tracker, retiming)| (tracker.label(), tracker.splits(), retiming))
+                .collect();
+            retime::write_report(path, &runs)?;
+            println!();
+            println!("Wrote verification report to {}", path.display());
+        }
+
+        if let Some(path) = &args.chapters
+            && let (Some(tracker), Some(retiming)) = (trackers.first(), retimings.first())
+        {
+            fs::write(
+                path,
+                retiming.render_chapters(tracker.splits(), args.chapter_format),
+            )
+            .map_err(|e| anyhow!("Failed to write chapters {}: {}", path.display(), e))?;
+            println!("Wrote chapters to {}", path.display());
+        }
     }
 
     Ok(())
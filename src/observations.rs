@@ -0,0 +1,137 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::in_game_time::InGameTime;
+
+/// When and where an accepted IGT reading was first seen.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Observation {
+    pub runner: String,
+    pub igt: String,
+    pub percent: u32,
+    pub frame_index: u64,
+    /// Position in the capture (video time for files)
+    pub position_ms: u128,
+    /// Monotonic time since tracking started, unaffected by clock changes
+    pub monotonic_ms: u128,
+    pub wall_clock: DateTime<Utc>,
+}
+
+/// Log of the accepted IGT readings with their frame index and timestamps, for settling retiming
+/// disputes. Only readings that differ from the runner's previous one are logged, i.e. the first
+/// frame showing each IGT. Optionally appended to a file as JSON lines.
+pub struct ObservationLog {
+    started_at: Instant,
+    observations: Vec<Observation>,
+    file: Option<File>,
+}
+
+impl ObservationLog {
+    pub fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            observations: Vec::new(),
+            file: None,
+        }
+    }
+
+    /// Also appends every observation to the file at `path`.
+    pub fn with_file(mut self, path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| {
+                anyhow::anyhow!("Failed to open observation log {}: {}", path.display(), e)
+            })?;
+        self.file = Some(file);
+        Ok(self)
+    }
+
+    /// Logs `igt` if it differs from the last reading logged for `runner`.
+    pub fn record(
+        &mut self,
+        runner: &str,
+        frame_index: u64,
+        position: Duration,
+        igt: &InGameTime,
+    ) -> Result<()> {
+        let text = igt.to_string();
+        if self
+            .observations
+            .iter()
+            .rev()
+            .find(|o| o.runner == runner)
+            .is_some_and(|o| o.igt == text)
+        {
+            return Ok(());
+        }
+
+        let observation = Observation {
+            runner: runner.to_string(),
+            igt: text,
+            percent: igt.percent,
+            frame_index,
+            position_ms: position.as_millis(),
+            monotonic_ms: self.started_at.elapsed().as_millis(),
+            wall_clock: Utc::now(),
+        };
+        if let Some(file) = &mut self.file {
+            serde_json::to_writer(&mut *file, &observation)?;
+            writeln!(file)?;
+        }
+        self.observations.push(observation);
+        Ok(())
+    }
+
+    pub fn observations(&self) -> &[Observation] {
+        &self.observations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn igt(percent: u32, secs: u64) -> InGameTime {
+        InGameTime::from_secs(percent, secs)
+    }
+
+    #[test]
+    fn logs_first_frame_of_each_reading_per_runner() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("observations.jsonl");
+        let mut log = ObservationLog::new().with_file(&path).unwrap();
+
+        log.record("A", 0, Duration::ZERO, &igt(1, 60)).unwrap();
+        log.record("B", 0, Duration::ZERO, &igt(1, 60)).unwrap();
+        log.record("A", 1, Duration::from_millis(16), &igt(1, 60))
+            .unwrap();
+        log.record("A", 60, Duration::from_millis(1000), &igt(1, 61))
+            .unwrap();
+
+        let observations = log.observations();
+        assert_eq!(observations.len(), 3);
+        assert_eq!(observations[2].runner, "A");
+        assert_eq!(observations[2].frame_index, 60);
+        assert_eq!(observations[2].position_ms, 1000);
+        assert_eq!(observations[2].percent, 1);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().count(), 3);
+        assert!(
+            contents
+                .lines()
+                .last()
+                .unwrap()
+                .contains("\"igt\":\"1% 0:01:01\"")
+        );
+    }
+}
@@ -0,0 +1,1220 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use opencv::core::Rect;
+use opencv::core::Size;
+use opencv::core::Size_;
+use opencv::core::{AccessFlag, UMat, UMatUsageFlags};
+use opencv::imgproc;
+use opencv::prelude::*;
+
+use anyhow::{Result, anyhow};
+use rayon::prelude::*;
+
+use crate::in_game_time::InGameTime;
+use crate::preprocess::{self, Preprocessing};
+
+pub mod classifier;
+#[cfg(feature = "tesseract")]
+pub mod tesseract;
+
+/// Reads the IGT from a binarized ROI.
+pub trait Recognizer {
+    /// Returns the IGT shown in `image` and fills `matches` with the recognized characters. An
+    /// empty `matches` means that the IGT isn't visible at all.
+    fn recognize(&mut self, image: &Mat, matches: &mut Vec<TemplateMatch>) -> Result<InGameTime>;
+}
+
+impl Recognizer for Templates {
+    fn recognize(&mut self, image: &Mat, matches: &mut Vec<TemplateMatch>) -> Result<InGameTime> {
+        extract_igt(image, self, None, matches)
+    }
+}
+
+/// Scale factor applied to the template images when loading them, i.e. the template files are
+/// stored at 1 / TEMPLATE_SCALE times the size of the characters in the ROI
+pub const TEMPLATE_SCALE: f64 = 0.75;
+
+/// Horizontal gap (in pixels) between two characters that separates words
+const WORD_GAP: i32 = 20;
+
+/// Scale factor of the downscaled images used for the coarse pre-check
+const COARSE_SCALE: f64 = 0.5;
+
+/// How much lower than the template threshold the coarse pre-check accepts (downscaling blurs)
+const COARSE_THRESHOLD_MARGIN: f32 = 0.15;
+
+/// Matches of different digits at the same position whose confidences differ by at most this much
+/// are ambiguous, and are decided by the previous IGT instead
+const AMBIGUITY_MARGIN: f32 = 0.05;
+
+/// At most this many ambiguous characters are decided by the previous IGT (trying all combinations)
+const MAX_AMBIGUOUS_CHARACTERS: usize = 4;
+
+/// How far (in pixels) a character may move horizontally from its slot and still be found there
+const SLOT_MARGIN: i32 = 4;
+
+/// Number of reads of the locked slots after which the whole ROI is scanned again, e.g. to notice
+/// an additional digit
+const SLOT_RESCAN_INTERVAL: u32 = 30;
+
+pub struct Template {
+    template: Mat,
+    coarse_template: Mat,
+    size: Size_<i32>,
+    threshold: f32,
+    character: char,
+}
+
+impl Template {
+    pub fn load_from_file(path: &Path, threshold: f32, character: char) -> Result<Self> {
+        let template = opencv::imgcodecs::imread(
+            &path.to_string_lossy(),
+            opencv::imgcodecs::IMREAD_GRAYSCALE,
+        )?;
+        if template.empty() {
+            return Err(anyhow!("Failed to load template {}", path.display()));
+        }
+
+        let mut binarized_template = Mat::default();
+        opencv::imgproc::threshold(
+            &template,
+            &mut binarized_template,
+            0.0,
+            255.0,
+            imgproc::THRESH_OTSU,
+        )?;
+
+        // TODO: store in proper size
+        let mut template_scaled = Mat::default();
+        opencv::imgproc::resize(
+            &binarized_template,
+            &mut template_scaled,
+            opencv::core::Size {
+                width: (binarized_template.cols() as f64 * TEMPLATE_SCALE) as i32,
+                height: (binarized_template.rows() as f64 * TEMPLATE_SCALE) as i32,
+            },
+            0.0,
+            0.0,
+            imgproc::INTER_LINEAR,
+        )?;
+
+        let template_size = template_scaled.size()?;
+
+        let mut coarse_template = Mat::default();
+        opencv::imgproc::resize(
+            &template_scaled,
+            &mut coarse_template,
+            opencv::core::Size::default(),
+            COARSE_SCALE,
+            COARSE_SCALE,
+            imgproc::INTER_AREA,
+        )?;
+
+        Ok(Self {
+            template: template_scaled,
+            coarse_template,
+            size: template_size,
+            threshold,
+            character,
+        })
+    }
+
+    pub fn character(&self) -> char {
+        self.character
+    }
+
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+}
+
+#[derive(Clone, Copy, Hash, Eq, PartialEq)]
+pub enum Character {
+    Percent,
+    Colon,
+    Zero,
+    One,
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Eight,
+    Nine,
+}
+
+/// Optional file in the templates directory overriding the default thresholds, as a JSON object
+/// mapping characters to thresholds, e.g. `{ "4": 0.82, "%": 0.78 }`
+pub const THRESHOLDS_FILE: &str = "thresholds.json";
+
+/// Optional subdirectory of the templates directory with letter templates (`a.png` to `z.png`), used
+/// to read the level name banner
+pub const LETTERS_DIR: &str = "letters";
+
+/// Default threshold of the letter templates
+const LETTER_THRESHOLD: f32 = 0.80;
+
+/// File name, default threshold and displayed character of each template
+const TEMPLATE_DEFINITIONS: [(Character, &str, f32, char); 12] = [
+    (Character::Percent, "percent.png", 0.80, '%'),
+    (Character::Colon, "colon.png", 0.75, ':'),
+    (Character::Zero, "zero.png", 0.80, '0'),
+    (Character::One, "one.png", 0.83, '1'),
+    (Character::Two, "two.png", 0.83, '2'),
+    (Character::Three, "three.png", 0.83, '3'),
+    (Character::Four, "four.png", 0.85, '4'),
+    (Character::Five, "five.png", 0.85, '5'),
+    (Character::Six, "six.png", 0.83, '6'),
+    (Character::Seven, "seven.png", 0.85, '7'),
+    (Character::Eight, "eight.png", 0.80, '8'),
+    (Character::Nine, "nine.png", 0.80, '9'),
+];
+
+/// Returns the file name of the template for `character`, if there is one.
+pub fn template_file_name(character: char) -> Option<&'static str> {
+    TEMPLATE_DEFINITIONS
+        .iter()
+        .find(|&&(_, _, _, c)| c == character)
+        .map(|&(_, filename, _, _)| filename)
+}
+
+pub struct Templates {
+    dir: PathBuf,
+    modified_at: Option<SystemTime>,
+    indices: HashMap<Character, usize>,
+    templates: Vec<Template>,
+    /// Upper case letters, empty if there is no letters directory
+    letters: Vec<Template>,
+    gpu: bool,
+}
+
+impl Templates {
+    /// Default templates directory: `templates/` in the working directory if it exists, otherwise
+    /// next to the executable.
+    pub fn default_dir() -> PathBuf {
+        let cwd_dir = PathBuf::from("templates");
+        if cwd_dir.is_dir() {
+            return cwd_dir;
+        }
+
+        std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(|parent| parent.join("templates")))
+            .filter(|dir| dir.is_dir())
+            .unwrap_or(cwd_dir)
+    }
+
+    /// Loads the templates in `dir`, or in its subdirectory `set` if given (e.g. for a different
+    /// display language or game version).
+    pub fn load(dir: &Path, set: Option<&str>) -> Result<Self> {
+        let Some(set) = set else {
+            return Self::load_set(dir);
+        };
+
+        let set_dir = dir.join(set);
+        if !set_dir.is_dir() {
+            let mut sets: Vec<String> = fs::read_dir(dir)?
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .collect();
+            sets.sort();
+            return Err(anyhow!(
+                "Unknown template set '{}' in {} (available: {})",
+                set,
+                dir.display(),
+                if sets.is_empty() {
+                    "none".to_string()
+                } else {
+                    sets.join(", ")
+                }
+            ));
+        }
+        Self::load_set(&set_dir)
+    }
+
+    fn load_set(dir: &Path) -> Result<Self> {
+        // Determine this before loading, so that changes made while loading trigger another reload
+        let modified_at = latest_modification(dir)?;
+
+        let thresholds = load_thresholds(dir)?;
+
+        let mut templates = vec![];
+        let mut indices = HashMap::new();
+
+        for &(id, filename, default_threshold, character) in &TEMPLATE_DEFINITIONS {
+            let threshold = thresholds
+                .get(&character)
+                .copied()
+                .unwrap_or(default_threshold);
+            let template = Template::load_from_file(&dir.join(filename), threshold, character)?;
+            indices.insert(id, templates.len());
+            templates.push(template);
+        }
+
+        let letters = load_letters(&dir.join(LETTERS_DIR), &thresholds)?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            modified_at,
+            indices,
+            templates,
+            letters,
+            gpu: false,
+        })
+    }
+
+    /// Reloads the templates if any file in the templates directory changed since they were loaded.
+    ///
+    /// Returns whether the templates were reloaded. On error, the current templates are kept.
+    pub fn reload_if_changed(&mut self) -> Result<bool> {
+        if latest_modification(&self.dir)? <= self.modified_at {
+            return Ok(false);
+        }
+
+        let mut reloaded = Templates::load_set(&self.dir)?;
+        reloaded.gpu = self.gpu;
+        *self = reloaded;
+        Ok(true)
+    }
+
+    /// Matches templates on `UMat`s so that OpenCV's OpenCL path is used.
+    /// Returns `false` if OpenCL isn't available.
+    pub fn enable_gpu(&mut self) -> Result<bool> {
+        if !opencv::core::have_opencl()? {
+            return Ok(false);
+        }
+
+        opencv::core::set_use_opencl(true)?;
+        self.gpu = true;
+        Ok(true)
+    }
+
+    /// Directory the templates were loaded from.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Whether matching (and preprocessing) should run on the GPU.
+    pub fn gpu(&self) -> bool {
+        self.gpu
+    }
+
+    /// The templates of the IGT's characters (without the letters).
+    pub fn iter(&self) -> impl Iterator<Item = &Template> {
+        self.templates.iter()
+    }
+
+    /// Changes the threshold of the template for `character` until the templates are reloaded.
+    pub fn set_threshold(&mut self, character: char, threshold: f32) {
+        if let Some(template) = self
+            .templates
+            .iter_mut()
+            .find(|template| template.character == character)
+        {
+            template.threshold = threshold;
+        }
+    }
+
+    pub fn get(&self, character: Character) -> Option<&Template> {
+        self.indices
+            .get(&character)
+            .map(|&idx| &self.templates[idx])
+    }
+
+    /// Whether letter templates were loaded, i.e. whether `extract_text` can read anything.
+    pub fn has_letters(&self) -> bool {
+        !self.letters.is_empty()
+    }
+}
+
+/// Loads the letter templates in `dir` if it exists. Missing letters are skipped, since a game
+/// usually doesn't show all of them in its level names.
+fn load_letters(dir: &Path, thresholds: &HashMap<char, f32>) -> Result<Vec<Template>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut letters = Vec::new();
+    for character in 'A'..='Z' {
+        let path = dir.join(format!("{}.png", character.to_ascii_lowercase()));
+        if path.exists() {
+            let threshold = thresholds
+                .get(&character)
+                .copied()
+                .unwrap_or(LETTER_THRESHOLD);
+            letters.push(Template::load_from_file(&path, threshold, character)?);
+        }
+    }
+    Ok(letters)
+}
+
+/// Loads the thresholds file in the templates directory `dir`, if there is one.
+pub fn load_thresholds(dir: &Path) -> Result<HashMap<char, f32>> {
+    let path = dir.join(THRESHOLDS_FILE);
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+
+    let json = fs::read_to_string(&path)?;
+    parse_thresholds(&json).map_err(|e| anyhow!("Invalid {}: {}", path.display(), e))
+}
+
+/// Writes `thresholds` (rounded to two decimals) into the thresholds file in the templates directory
+/// `dir`, keeping the entries of other characters. Returns the path of the file.
+pub fn save_thresholds(dir: &Path, thresholds: &BTreeMap<char, f32>) -> Result<PathBuf> {
+    let mut merged: BTreeMap<char, f64> = load_thresholds(dir)?
+        .into_iter()
+        .map(|(character, threshold)| (character, threshold as f64))
+        .collect();
+    for (&character, &threshold) in thresholds {
+        merged.insert(character, (threshold as f64 * 100.0).round() / 100.0);
+    }
+
+    let path = dir.join(THRESHOLDS_FILE);
+    let file = fs::File::create(&path)
+        .map_err(|e| anyhow!("Failed to create {}: {}", path.display(), e))?;
+    serde_json::to_writer_pretty(file, &merged)?;
+    Ok(path)
+}
+
+/// Parses the contents of a thresholds file, rejecting unknown characters and thresholds outside of
+/// `0.0..=1.0`.
+fn parse_thresholds(json: &str) -> Result<HashMap<char, f32>> {
+    let thresholds: HashMap<char, f32> = serde_json::from_str(json)?;
+    for (&character, &threshold) in &thresholds {
+        if template_file_name(character).is_none() && !character.is_ascii_uppercase() {
+            return Err(anyhow!("No template for character '{}'", character));
+        }
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(anyhow!(
+                "Threshold {} for '{}' must be between 0 and 1",
+                threshold,
+                character
+            ));
+        }
+    }
+    Ok(thresholds)
+}
+
+/// Latest modification time of the files in `dir` (including the directory itself, which changes
+/// when files are added or removed).
+fn latest_modification(dir: &Path) -> Result<Option<SystemTime>> {
+    let letters_dir = dir.join(LETTERS_DIR);
+    let letters_modified = if letters_dir.is_dir() {
+        latest_modification_of(&letters_dir)?
+    } else {
+        None
+    };
+    Ok(latest_modification_of(dir)?.max(letters_modified))
+}
+
+fn latest_modification_of(dir: &Path) -> Result<Option<SystemTime>> {
+    let mut latest = fs::metadata(dir)
+        .map_err(|e| {
+            anyhow!(
+                "Failed to read templates directory {}: {}",
+                dir.display(),
+                e
+            )
+        })?
+        .modified()
+        .ok();
+    for entry in fs::read_dir(dir)? {
+        let modified = entry?.metadata()?.modified().ok();
+        latest = latest.max(modified);
+    }
+    Ok(latest)
+}
+
+#[derive(Clone)]
+pub struct TemplateMatch {
+    pub x: i32,
+    pub y: i32,
+    pub bounding_box: Size_<i32>,
+    pub character: char,
+    pub confidence: f32,
+    /// Threshold of the template that produced this match
+    pub threshold: f32,
+}
+
+pub fn find_occurances_of_template(
+    image: &Mat,
+    template: &Template,
+    matches: &mut Vec<TemplateMatch>,
+) -> Result<()> {
+    let result_cols = image.cols() - template.size.width + 1;
+    let result_rows = image.rows() - template.size.height + 1;
+
+    let mut result = Mat::new_rows_cols_with_default(
+        result_rows,
+        result_cols,
+        opencv::core::CV_32FC1,
+        opencv::core::Scalar::all(0.0),
+    )?;
+
+    imgproc::match_template(
+        &image,
+        &template.template,
+        &mut result,
+        imgproc::TM_CCOEFF_NORMED,
+        &opencv::core::no_array(),
+    )?;
+
+    extract_peaks(&result, template, matches)
+}
+
+/// Highest confidence of `template` anywhere in `image` (regardless of its threshold), or `None` if
+/// `image` is smaller than the template.
+pub fn best_confidence(image: &Mat, template: &Template) -> Result<Option<f32>> {
+    if image.cols() < template.size.width || image.rows() < template.size.height {
+        return Ok(None);
+    }
+
+    let mut result = Mat::default();
+    imgproc::match_template(
+        image,
+        &template.template,
+        &mut result,
+        imgproc::TM_CCOEFF_NORMED,
+        &opencv::core::no_array(),
+    )?;
+
+    let mut max_val = 0.0;
+    opencv::core::min_max_loc(
+        &result,
+        None,
+        Some(&mut max_val),
+        None,
+        None,
+        &opencv::core::no_array(),
+    )?;
+    Ok(Some(max_val as f32))
+}
+
+/// Same as `find_occurances_of_template`, but matches on an image uploaded to the GPU.
+fn find_occurances_of_template_gpu(
+    image: &UMat,
+    template: &Template,
+    matches: &mut Vec<TemplateMatch>,
+) -> Result<()> {
+    let gpu_template = template
+        .template
+        .get_umat(AccessFlag::ACCESS_READ, UMatUsageFlags::USAGE_DEFAULT)?;
+
+    let mut gpu_result = UMat::new(UMatUsageFlags::USAGE_DEFAULT);
+    imgproc::match_template(
+        image,
+        &gpu_template,
+        &mut gpu_result,
+        imgproc::TM_CCOEFF_NORMED,
+        &opencv::core::no_array(),
+    )?;
+
+    // Peak extraction is cheap, download the result
+    let mut result = Mat::default();
+    gpu_result.copy_to(&mut result)?;
+
+    extract_peaks(&result, template, matches)
+}
+
+/// Extracts the matches of `template` from the result matrix of `match_template`.
+fn extract_peaks(
+    result: &Mat,
+    template: &Template,
+    matches: &mut Vec<TemplateMatch>,
+) -> Result<()> {
+    // Find regions above the threshold...
+    let mut above_threshold = Mat::default();
+    imgproc::threshold(
+        &result,
+        &mut above_threshold,
+        template.threshold as f64,
+        255.0,
+        imgproc::THRESH_BINARY,
+    )?;
+    let mut mask = Mat::default();
+    above_threshold.convert_to(&mut mask, opencv::core::CV_8U, 1.0, 0.0)?;
+
+    let mut labels = Mat::default();
+    let mut stats = Mat::default();
+    let mut centroids = Mat::default();
+    let count = imgproc::connected_components_with_stats(
+        &mask,
+        &mut labels,
+        &mut stats,
+        &mut centroids,
+        8,
+        opencv::core::CV_32S,
+    )?;
+
+    // ...and report the peak of each region as a match (label 0 is the background)
+    for label in 1..count {
+        let region = Rect::new(
+            *stats.at_2d::<i32>(label, imgproc::CC_STAT_LEFT)?,
+            *stats.at_2d::<i32>(label, imgproc::CC_STAT_TOP)?,
+            *stats.at_2d::<i32>(label, imgproc::CC_STAT_WIDTH)?,
+            *stats.at_2d::<i32>(label, imgproc::CC_STAT_HEIGHT)?,
+        );
+        let region_result = Mat::roi(result, region)?;
+
+        let mut max_val = 0.0;
+        let mut max_loc = opencv::core::Point::default();
+        opencv::core::min_max_loc(
+            &region_result,
+            None,
+            Some(&mut max_val),
+            None,
+            Some(&mut max_loc),
+            &opencv::core::no_array(),
+        )?;
+
+        matches.push(TemplateMatch {
+            x: region.x + max_loc.x,
+            y: region.y + max_loc.y,
+            bounding_box: template.size,
+            character: template.character,
+            confidence: max_val as f32,
+            threshold: template.threshold,
+        });
+    }
+
+    Ok(())
+}
+
+/// Cheap check whether `template` might be present in `image`, by matching downscaled versions of
+/// both. Used to skip the full matching on frames that don't show the IGT at all.
+fn coarse_precheck(image: &Mat, template: &Template) -> Result<bool> {
+    let mut coarse_image = Mat::default();
+    imgproc::resize(
+        image,
+        &mut coarse_image,
+        opencv::core::Size::default(),
+        COARSE_SCALE,
+        COARSE_SCALE,
+        imgproc::INTER_AREA,
+    )?;
+
+    if coarse_image.cols() < template.coarse_template.cols()
+        || coarse_image.rows() < template.coarse_template.rows()
+    {
+        return Ok(false);
+    }
+
+    let mut result = Mat::default();
+    imgproc::match_template(
+        &coarse_image,
+        &template.coarse_template,
+        &mut result,
+        imgproc::TM_CCOEFF_NORMED,
+        &opencv::core::no_array(),
+    )?;
+
+    let mut max_val = 0.0;
+    opencv::core::min_max_loc(
+        &result,
+        None,
+        Some(&mut max_val),
+        None,
+        None,
+        &opencv::core::no_array(),
+    )?;
+
+    Ok(max_val as f32 >= template.threshold - COARSE_THRESHOLD_MARGIN)
+}
+
+/// Reads the IGT from a binarized ROI. Digits that can't be told apart by their match confidence
+/// (e.g. 8 and 0) are decided by `prior`, the previously accepted IGT, if given.
+pub fn extract_igt(
+    image: &Mat,
+    templates: &Templates,
+    prior: Option<&InGameTime>,
+    matches: &mut Vec<TemplateMatch>,
+) -> Result<InGameTime> {
+    // Use '%' as an indicator whether we are in the guidebook and terminate early if not
+    let percent = templates.get(Character::Percent).unwrap();
+    if !coarse_precheck(image, percent)? {
+        return Err(anyhow!("No IGT found"));
+    }
+
+    if templates.gpu {
+        let gpu_image = image.get_umat(AccessFlag::ACCESS_READ, UMatUsageFlags::USAGE_DEFAULT)?;
+        find_occurances_of_template_gpu(&gpu_image, percent, matches)?;
+    } else {
+        find_occurances_of_template(image, percent, matches)?;
+    }
+    if matches.is_empty() {
+        return Err(anyhow!("No IGT found"));
+    }
+
+    find_all_characters(
+        image,
+        templates,
+        |c| c != '%' && !c.is_ascii_alphabetic(),
+        matches,
+    )?;
+    let groups = group_overlapping_matches(std::mem::take(matches));
+    choose_reading(&groups, prior, matches)
+}
+
+/// Picks one match per group (see `group_overlapping_matches`) into `matches` and parses them.
+///
+/// Usually that's the most confident match. If other digits match about as well, the combination
+/// that continues `prior` most closely (without running backwards) is picked instead.
+fn choose_reading(
+    groups: &[Vec<TemplateMatch>],
+    prior: Option<&InGameTime>,
+    matches: &mut Vec<TemplateMatch>,
+) -> Result<InGameTime> {
+    matches.extend(groups.iter().map(|group| group[0].clone()));
+    let best = InGameTime::parse(&join_matches(matches));
+
+    // The candidates of each character, the most confident first
+    let candidates: Vec<Vec<&TemplateMatch>> = groups
+        .iter()
+        .map(|group| {
+            let best = &group[0];
+            if !best.character.is_ascii_digit() {
+                return vec![best];
+            }
+            group
+                .iter()
+                .filter(|m| {
+                    m.character.is_ascii_digit()
+                        && m.confidence >= best.confidence - AMBIGUITY_MARGIN
+                })
+                .collect()
+        })
+        .collect();
+    let ambiguous = candidates.iter().filter(|c| c.len() > 1).count();
+    let Some(prior) = prior.filter(|_| (1..=MAX_AMBIGUOUS_CHARACTERS).contains(&ambiguous)) else {
+        return Ok(best?);
+    };
+
+    // Try every combination of the candidates, keeping the closest reading after `prior`
+    let mut chosen: Option<(InGameTime, Vec<TemplateMatch>)> = None;
+    let mut combination = vec![0; candidates.len()];
+    loop {
+        let reading: Vec<TemplateMatch> = combination
+            .iter()
+            .zip(&candidates)
+            .map(|(&choice, character)| character[choice].clone())
+            .collect();
+        if let Ok(igt) = InGameTime::parse(&join_matches(&reading))
+            && igt.percent >= prior.percent
+            && igt.duration >= prior.duration
+            && chosen.as_ref().is_none_or(|(closest, _)| igt < *closest)
+        {
+            chosen = Some((igt, reading));
+        }
+
+        // Next combination, counting with one digit per character
+        let Some(i) = (0..candidates.len()).find(|&i| combination[i] + 1 < candidates[i].len())
+        else {
+            break;
+        };
+        combination[i] += 1;
+        combination[..i].fill(0);
+    }
+
+    match chosen {
+        Some((igt, reading)) => {
+            *matches = reading;
+            Ok(igt)
+        }
+        None => Ok(best?),
+    }
+}
+
+/// Horizontal position of a character of the IGT.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Slot {
+    x: i32,
+    width: i32,
+}
+
+/// Remembers where the characters of the IGT were found, so that later frames only need to be
+/// matched around those positions instead of across the whole ROI.
+#[derive(Default)]
+pub struct SlotLock {
+    slots: Vec<Slot>,
+    reads_since_scan: u32,
+}
+
+impl SlotLock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads the IGT like `extract_igt`, but only matches around the locked character slots. The
+    /// whole image is scanned (and the slots are locked again) every `SLOT_RESCAN_INTERVAL` reads,
+    /// and whenever the slots don't show a valid IGT.
+    pub fn extract_igt(
+        &mut self,
+        image: &Mat,
+        templates: &Templates,
+        prior: Option<&InGameTime>,
+        matches: &mut Vec<TemplateMatch>,
+    ) -> Result<InGameTime> {
+        if !self.slots.is_empty() && self.reads_since_scan < SLOT_RESCAN_INTERVAL {
+            self.reads_since_scan += 1;
+            if let Ok(igt) = extract_igt_in_slots(image, templates, &self.slots, prior, matches) {
+                return Ok(igt);
+            }
+            matches.clear();
+        }
+
+        self.reset();
+        let igt = extract_igt(image, templates, prior, matches)?;
+        self.slots = matches
+            .iter()
+            .map(|m| Slot {
+                x: m.x,
+                width: m.bounding_box.width,
+            })
+            .collect();
+        Ok(igt)
+    }
+
+    /// Forgets the slots, e.g. after switching to another ROI.
+    pub fn reset(&mut self) {
+        self.slots.clear();
+        self.reads_since_scan = 0;
+    }
+}
+
+/// Matches the IGT templates only within `SLOT_MARGIN` of each slot. The candidates of each slot
+/// are chosen from like in `extract_igt`.
+fn extract_igt_in_slots(
+    image: &Mat,
+    templates: &Templates,
+    slots: &[Slot],
+    prior: Option<&InGameTime>,
+    matches: &mut Vec<TemplateMatch>,
+) -> Result<InGameTime> {
+    let slot_matches = slots
+        .par_iter()
+        .map(|slot| -> Result<Vec<TemplateMatch>> {
+            let window = slot_window(*slot, image.cols());
+            let window_image = Mat::roi(image, Rect::new(window.0, 0, window.1, image.rows()))?;
+
+            let mut candidates = Vec::new();
+            for template in &templates.templates {
+                if template.size.width <= window.1 && template.size.height <= image.rows() {
+                    find_occurances_of_template(&window_image, template, &mut candidates)?;
+                }
+            }
+
+            Ok(candidates
+                .into_iter()
+                .map(|m| TemplateMatch {
+                    x: m.x + window.0,
+                    ..m
+                })
+                .collect())
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let groups = group_overlapping_matches(slot_matches.into_iter().flatten().collect());
+    choose_reading(&groups, prior, matches)
+}
+
+/// Start and width of the columns searched for the character in `slot`.
+fn slot_window(slot: Slot, image_width: i32) -> (i32, i32) {
+    let start = (slot.x - SLOT_MARGIN).max(0);
+    let end = (slot.x + slot.width + SLOT_MARGIN).min(image_width);
+    (start, end - start)
+}
+
+/// Reads a line of upper case text (e.g. the level name banner) using the letter templates.
+pub fn extract_text(
+    image: &Mat,
+    templates: &Templates,
+    matches: &mut Vec<TemplateMatch>,
+) -> Result<String> {
+    if !templates.has_letters() {
+        return Err(anyhow!("No letter templates loaded"));
+    }
+
+    find_all_characters(image, templates, |c| c.is_ascii_alphabetic(), matches)?;
+    suppress_overlapping_matches(matches);
+
+    let text = join_matches(matches);
+    if text.is_empty() {
+        return Err(anyhow!("No text found"));
+    }
+    Ok(text)
+}
+
+/// Concatenates the characters of the (sorted) matches, separating words by a space.
+fn join_matches(matches: &[TemplateMatch]) -> String {
+    let mut result = String::new();
+    for (i, m) in matches.iter().enumerate() {
+        if i > 0 {
+            let prev = &matches[i - 1];
+            let gap = m.x - (prev.x + prev.bounding_box.width);
+            if gap > WORD_GAP {
+                result.push(' ');
+            }
+        }
+        result.push(m.character);
+    }
+    result
+}
+
+/// Reads a plain number (e.g. a collectible counter) using the digit templates.
+pub fn extract_number(
+    image: &Mat,
+    templates: &Templates,
+    matches: &mut Vec<TemplateMatch>,
+) -> Result<u32> {
+    find_all_characters(image, templates, |c| c.is_ascii_digit(), matches)?;
+    suppress_overlapping_matches(matches);
+
+    let digits: String = matches.iter().map(|m| m.character).collect();
+    if digits.is_empty() {
+        return Err(anyhow!("No number found"));
+    }
+    Ok(digits.parse()?)
+}
+
+/// Adds the occurances of all templates whose character passes `filter` to `matches`.
+fn find_all_characters(
+    image: &Mat,
+    templates: &Templates,
+    filter: impl Fn(char) -> bool + Sync,
+    matches: &mut Vec<TemplateMatch>,
+) -> Result<()> {
+    if templates.gpu {
+        // Upload once, then match all templates sequentially (OpenCL parallelizes internally)
+        let gpu_image = image.get_umat(AccessFlag::ACCESS_READ, UMatUsageFlags::USAGE_DEFAULT)?;
+        for template in templates.templates.iter().chain(&templates.letters) {
+            if filter(template.character) {
+                find_occurances_of_template_gpu(&gpu_image, template, matches)?;
+            }
+        }
+    } else {
+        // Find occurances of all characters, matching each template on its own thread
+        let character_matches = templates
+            .templates
+            .par_iter()
+            .chain(templates.letters.par_iter())
+            .filter(|template| filter(template.character))
+            .map(|template| {
+                let mut template_matches = Vec::new();
+                find_occurances_of_template(image, template, &mut template_matches)?;
+                Ok(template_matches)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        matches.extend(character_matches.into_iter().flatten());
+    }
+    Ok(())
+}
+
+/// Keeps only the best of horizontally overlapping matches, sorted from left to right.
+fn suppress_overlapping_matches(matches: &mut Vec<TemplateMatch>) {
+    let groups = group_overlapping_matches(std::mem::take(matches));
+    *matches = groups
+        .into_iter()
+        .map(|mut group| group.swap_remove(0))
+        .collect();
+}
+
+/// Groups horizontally overlapping matches (i.e. candidates for the same character), sorted from
+/// left to right. Each group is sorted by confidence, best first.
+fn group_overlapping_matches(mut matches: Vec<TemplateMatch>) -> Vec<Vec<TemplateMatch>> {
+    // Sort by x-coordinate
+    matches.sort_by(|a, b| a.x.cmp(&b.x));
+
+    // Simple 1D NMS on x-axis, comparing to the best match of each group
+    let mut groups: Vec<Vec<TemplateMatch>> = Vec::new();
+    for m in matches {
+        let group = groups.iter_mut().find(|group| {
+            let best = &group[0];
+            let m_start = m.x;
+            let m_end = m.x + m.bounding_box.width;
+            let o_start = best.x;
+            let o_end = best.x + best.bounding_box.width;
+
+            let overlap = (m_end.min(o_end) - m_start.max(o_start)).max(0);
+            let min_width = m.bounding_box.width.min(best.bounding_box.width);
+            overlap as f32 > 0.5 * min_width as f32
+        });
+
+        match group {
+            Some(group) => {
+                if m.confidence > group[0].confidence {
+                    group.insert(0, m);
+                } else {
+                    group.push(m);
+                }
+            }
+            None => groups.push(vec![m]),
+        }
+    }
+
+    for group in &mut groups {
+        group.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    }
+    // Sort again to ensure left-to-right order of the best matches
+    groups.sort_by_key(|group| group[0].x);
+    groups
+}
+
+/// Converts the ROI to grayscale and applies the `preprocessing` chain (by default binarizing it).
+pub fn binarize_roi(
+    frame: &Mat,
+    roi_rect: Rect,
+    preprocessing: &Preprocessing,
+    gpu: bool,
+) -> Result<Mat> {
+    let roi_view = Mat::roi(frame, roi_rect)?;
+
+    let mut roi = Mat::default();
+    opencv::core::copy_to(&roi_view, &mut roi, &opencv::core::no_array())?;
+
+    if let Some(mode) = preprocessing.deinterlace() {
+        roi = preprocess::deinterlace(&roi, mode, roi_rect.y)?;
+    }
+
+    if gpu {
+        let gpu_roi = roi.get_umat(AccessFlag::ACCESS_READ, UMatUsageFlags::USAGE_DEFAULT)?;
+
+        let new_umat = || UMat::new(UMatUsageFlags::USAGE_DEFAULT);
+        let gray = preprocessing.to_gray(&gpu_roi, new_umat)?;
+        let binarized = preprocessing.apply(gray, new_umat)?;
+
+        let mut binarized_roi = Mat::default();
+        binarized.copy_to(&mut binarized_roi)?;
+        return Ok(binarized_roi);
+    }
+
+    // Convert ROI to grayscale (or the color key's mask)
+    let gray = preprocessing.to_gray(&roi, Mat::default)?;
+    preprocessing.apply(gray, Mat::default)
+}
+
+/// Returns the start positions of windows of `size` covering `0..len` with the given `step`. The last
+/// window is aligned to the end so that the whole range is covered.
+fn window_positions(len: i32, size: i32, step: i32) -> Vec<i32> {
+    if size > len {
+        return Vec::new();
+    }
+
+    let mut positions: Vec<i32> = (0..=len - size).step_by(step.max(1) as usize).collect();
+    if positions.last() != Some(&(len - size)) {
+        positions.push(len - size);
+    }
+    positions
+}
+
+/// Scans the whole frame for the IGT and returns a ROI enclosing it (with some margin), if found.
+///
+/// The frame is scanned in overlapping windows of the given size. Each window is first checked for
+/// the '%' template only, the full `extract_igt` (which also requires the ':' of the time) only runs
+/// on windows containing one.
+pub fn detect_roi(
+    frame: &Mat,
+    templates: &Templates,
+    preprocessing: &Preprocessing,
+    window: Size,
+) -> Result<Option<Rect>> {
+    let window = Size::new(
+        window.width.min(frame.cols()),
+        window.height.min(frame.rows()),
+    );
+    let percent = templates.get(Character::Percent).unwrap();
+
+    for y in window_positions(frame.rows(), window.height, window.height / 2) {
+        for x in window_positions(frame.cols(), window.width, window.width / 4) {
+            let window_rect = Rect::new(x, y, window.width, window.height);
+            let binarized = binarize_roi(frame, window_rect, preprocessing, templates.gpu)?;
+
+            let mut matches = Vec::new();
+            find_occurances_of_template(&binarized, percent, &mut matches)?;
+            if matches.is_empty() {
+                continue;
+            }
+
+            matches.clear();
+            if extract_igt(&binarized, templates, None, &mut matches).is_ok() {
+                return Ok(Some(enclosing_roi(window_rect, &matches, frame.size()?)));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Computes a ROI around the given matches (relative to `window`), leaving room for the IGT to grow.
+fn enclosing_roi(window: Rect, matches: &[TemplateMatch], frame_size: Size) -> Rect {
+    let min_x = matches.iter().map(|m| m.x).min().unwrap_or(0);
+    let max_x = matches
+        .iter()
+        .map(|m| m.x + m.bounding_box.width)
+        .max()
+        .unwrap_or(window.width);
+    let min_y = matches.iter().map(|m| m.y).min().unwrap_or(0);
+    let max_y = matches
+        .iter()
+        .map(|m| m.y + m.bounding_box.height)
+        .max()
+        .unwrap_or(window.height);
+
+    let char_width = matches
+        .iter()
+        .map(|m| m.bounding_box.width)
+        .max()
+        .unwrap_or(0);
+    let margin_x = 2 * char_width;
+    let margin_y = (max_y - min_y) / 2;
+
+    let left = (window.x + min_x - margin_x).max(0);
+    let top = (window.y + min_y - margin_y).max(0);
+    let right = (window.x + max_x + margin_x).min(frame_size.width);
+    let bottom = (window.y + max_y + margin_y).min(frame_size.height);
+
+    Rect::new(left, top, right - left, bottom - top)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_thresholds_accepts_known_characters() {
+        let thresholds = parse_thresholds(r#"{ "4": 0.82, "%": 0.78 }"#).unwrap();
+        assert_eq!(thresholds.len(), 2);
+        assert_eq!(thresholds[&'4'], 0.82);
+        assert_eq!(thresholds[&'%'], 0.78);
+    }
+
+    #[test]
+    fn parse_thresholds_accepts_letters() {
+        let thresholds = parse_thresholds(r#"{ "A": 0.75 }"#).unwrap();
+        assert_eq!(thresholds[&'A'], 0.75);
+    }
+
+    #[test]
+    fn parse_thresholds_rejects_invalid_entries() {
+        assert!(parse_thresholds(r#"{ "x": 0.8 }"#).is_err());
+        assert!(parse_thresholds(r#"{ "4": 1.2 }"#).is_err());
+        assert!(parse_thresholds(r#"{ "4": "high" }"#).is_err());
+    }
+
+    /// Groups with one candidate per character of `text`, 10 pixels wide, leaving a word gap
+    /// after '%'. Characters given as `[a|b]` get two candidates, `b` slightly less confident.
+    fn groups_for(text: &str) -> Vec<Vec<TemplateMatch>> {
+        let candidate = |x: i32, character: char, confidence: f32| TemplateMatch {
+            x,
+            y: 0,
+            bounding_box: Size::new(10, 10),
+            character,
+            confidence,
+            threshold: 0.8,
+        };
+
+        let mut groups = Vec::new();
+        let mut x = 0;
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            let group = if c == '[' {
+                let first = chars.next().unwrap();
+                chars.next(); // '|'
+                let second = chars.next().unwrap();
+                chars.next(); // ']'
+                vec![candidate(x, first, 0.90), candidate(x, second, 0.88)]
+            } else {
+                vec![candidate(x, c, 0.95)]
+            };
+            groups.push(group);
+            x += if c == '%' { 40 } else { 12 };
+        }
+        groups
+    }
+
+    fn igt(percent: u32, secs: u64) -> InGameTime {
+        InGameTime::from_secs(percent, secs)
+    }
+
+    #[test]
+    fn choose_reading_prefers_most_confident_without_prior() {
+        let mut matches = Vec::new();
+        let reading = choose_reading(&groups_for("1%0:01:1[8|0]"), None, &mut matches).unwrap();
+        assert_eq!(reading, igt(1, 78));
+        assert_eq!(matches.len(), 9);
+    }
+
+    #[test]
+    fn choose_reading_continues_prior() {
+        let prior = igt(1, 69);
+        let mut matches = Vec::new();
+        let reading =
+            choose_reading(&groups_for("1%0:01:1[8|0]"), Some(&prior), &mut matches).unwrap();
+        assert_eq!(reading, igt(1, 70));
+        assert_eq!(matches[8].character, '0');
+
+        // Nothing continues the prior (e.g. a new run), so the most confident reading wins
+        let prior = igt(1, 90);
+        let mut matches = Vec::new();
+        let reading =
+            choose_reading(&groups_for("1%0:01:1[8|0]"), Some(&prior), &mut matches).unwrap();
+        assert_eq!(reading, igt(1, 78));
+    }
+
+    #[test]
+    fn group_overlapping_matches_keeps_candidates() {
+        let groups =
+            group_overlapping_matches(groups_for("[8|0]1").into_iter().flatten().collect());
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].len(), 2);
+        assert_eq!(groups[0][0].character, '8');
+        assert_eq!(groups[1][0].character, '1');
+    }
+
+    #[test]
+    fn slot_window_adds_margin_within_image() {
+        assert_eq!(slot_window(Slot { x: 10, width: 20 }, 100), (6, 28));
+        assert_eq!(slot_window(Slot { x: 2, width: 20 }, 100), (0, 26));
+        assert_eq!(slot_window(Slot { x: 85, width: 20 }, 100), (81, 19));
+    }
+
+    #[test]
+    fn window_positions_cover_whole_range() {
+        assert_eq!(window_positions(10, 4, 4), vec![0, 4, 6]);
+        assert_eq!(window_positions(8, 4, 4), vec![0, 4]);
+        assert_eq!(window_positions(4, 4, 2), vec![0]);
+        assert!(window_positions(3, 4, 2).is_empty());
+    }
+
+    #[test]
+    fn enclosing_roi_adds_margin_and_clamps_to_frame() {
+        let matches = vec![
+            TemplateMatch {
+                x: 10,
+                y: 5,
+                bounding_box: Size::new(20, 30),
+                character: '%',
+                confidence: 1.0,
+                threshold: 0.8,
+            },
+            TemplateMatch {
+                x: 100,
+                y: 6,
+                bounding_box: Size::new(10, 30),
+                character: ':',
+                confidence: 1.0,
+                threshold: 0.8,
+            },
+        ];
+
+        let roi = enclosing_roi(Rect::new(0, 100, 200, 50), &matches, Size::new(1920, 1080));
+
+        // x: 10 - 2 * 20 clamps to 0, right edge: 110 + 40
+        // y: 105 - 15 .. 136 + 15
+        assert_eq!(roi, Rect::new(0, 90, 150, 61));
+    }
+}
@@ -0,0 +1,303 @@
+use anyhow::{Result, anyhow};
+use opencv::core::Size;
+use opencv::prelude::*;
+
+use super::{Recognizer, TemplateMatch, Templates, WORD_GAP};
+use crate::in_game_time::InGameTime;
+use crate::training::column_runs;
+
+/// Number of zones per glyph, horizontally and vertically
+const ZONE_COLUMNS: usize = 4;
+const ZONE_ROWS: usize = 6;
+
+/// Weight of the aspect ratio relative to a single zone (tells ':' and '1' apart)
+const ASPECT_WEIGHT: f32 = 2.0;
+
+/// Glyphs further away than this from every prototype are not recognized
+const MAX_DISTANCE: f32 = 2.5;
+
+/// Borrowed 8-bit grayscale image.
+pub struct GrayImage<'a> {
+    data: &'a [u8],
+    width: usize,
+    height: usize,
+}
+
+impl<'a> GrayImage<'a> {
+    pub fn new(data: &'a [u8], width: usize, height: usize) -> Result<Self> {
+        if data.len() != width * height {
+            return Err(anyhow!(
+                "Expected {} bytes for a {}x{} image, got {}",
+                width * height,
+                width,
+                height,
+                data.len()
+            ));
+        }
+        Ok(Self {
+            data,
+            width,
+            height,
+        })
+    }
+
+    /// Wraps a continuous single channel `Mat`.
+    pub fn from_mat(mat: &'a Mat) -> Result<Self> {
+        if mat.typ() != opencv::core::CV_8UC1 || !mat.is_continuous() {
+            return Err(anyhow!("Expected a continuous 8-bit grayscale image"));
+        }
+        Self::new(mat.data_bytes()?, mat.cols() as usize, mat.rows() as usize)
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> u8 {
+        self.data[y * self.width + x]
+    }
+
+    /// Foreground mask, where the background is whatever color dominates the border.
+    fn foreground(&self) -> Vec<bool> {
+        let border: Vec<u8> = (0..self.width)
+            .flat_map(|x| [self.pixel(x, 0), self.pixel(x, self.height - 1)])
+            .chain((0..self.height).flat_map(|y| [self.pixel(0, y), self.pixel(self.width - 1, y)]))
+            .collect();
+        let bright_background = border.iter().filter(|&&p| p > 127).count() * 2 > border.len();
+
+        self.data
+            .iter()
+            .map(|&p| (p > 127) != bright_background)
+            .collect()
+    }
+}
+
+/// Bounding box of a glyph, in pixels.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct GlyphBox {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+/// Recognizes characters by comparing pixel-zoning features of the segmented glyphs to prototypes
+/// computed from the templates. Needs neither template matching nor any other OpenCV algorithm.
+pub struct GlyphClassifier {
+    prototypes: Vec<(char, Vec<f32>)>,
+}
+
+impl GlyphClassifier {
+    pub fn from_templates(templates: &Templates) -> Result<Self> {
+        let mut prototypes = Vec::new();
+        for template in &templates.templates {
+            let mat = template.template.try_clone()?;
+            let image = GrayImage::from_mat(&mat)?;
+            let foreground = image.foreground();
+            let glyph = bounding_box(&foreground, image.width, image.height, 0, image.width)
+                .ok_or_else(|| anyhow!("Template '{}' is empty", template.character))?;
+            prototypes.push((
+                template.character,
+                features(&foreground, image.width, glyph),
+            ));
+        }
+        Ok(Self { prototypes })
+    }
+
+    /// Returns the closest prototype to `features` and its distance.
+    fn classify(&self, features: &[f32]) -> Option<(char, f32)> {
+        self.prototypes
+            .iter()
+            .map(|(character, prototype)| (*character, distance(features, prototype)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .filter(|&(_, distance)| distance <= MAX_DISTANCE)
+    }
+
+    /// Segments and classifies the glyphs of `image`, from left to right.
+    fn read(&self, image: &GrayImage) -> Vec<TemplateMatch> {
+        let foreground = image.foreground();
+        let filled_columns: Vec<bool> = (0..image.width)
+            .map(|x| (0..image.height).any(|y| foreground[y * image.width + x]))
+            .collect();
+
+        column_runs(&filled_columns)
+            .into_iter()
+            .filter_map(|(start, end)| {
+                let glyph = bounding_box(
+                    &foreground,
+                    image.width,
+                    image.height,
+                    start as usize,
+                    end as usize,
+                )?;
+                let (character, distance) =
+                    self.classify(&features(&foreground, image.width, glyph))?;
+                Some(TemplateMatch {
+                    x: glyph.x as i32,
+                    y: glyph.y as i32,
+                    bounding_box: Size::new(glyph.width as i32, glyph.height as i32),
+                    character,
+                    confidence: 1.0 - distance / MAX_DISTANCE,
+                    threshold: 0.0,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Recognizer for GlyphClassifier {
+    fn recognize(&mut self, image: &Mat, matches: &mut Vec<TemplateMatch>) -> Result<InGameTime> {
+        let glyphs = self.read(&GrayImage::from_mat(image)?);
+        if !glyphs.iter().any(|m| m.character == '%') {
+            return Err(anyhow!("No IGT found"));
+        }
+
+        let mut text = String::new();
+        for (i, m) in glyphs.iter().enumerate() {
+            if i > 0 {
+                let prev = &glyphs[i - 1];
+                if m.x - (prev.x + prev.bounding_box.width) > WORD_GAP {
+                    text.push(' ');
+                }
+            }
+            text.push(m.character);
+        }
+        *matches = glyphs;
+
+        InGameTime::parse(&text)
+    }
+}
+
+/// Bounding box of the foreground pixels in the columns `start..end`.
+fn bounding_box(
+    foreground: &[bool],
+    width: usize,
+    height: usize,
+    start: usize,
+    end: usize,
+) -> Option<GlyphBox> {
+    let rows: Vec<usize> = (0..height)
+        .filter(|&y| (start..end).any(|x| foreground[y * width + x]))
+        .collect();
+    let columns: Vec<usize> = (start..end)
+        .filter(|&x| (0..height).any(|y| foreground[y * width + x]))
+        .collect();
+
+    Some(GlyphBox {
+        x: *columns.first()?,
+        y: *rows.first()?,
+        width: columns.last()? - columns.first()? + 1,
+        height: rows.last()? - rows.first()? + 1,
+    })
+}
+
+/// Foreground ratio of each zone of the glyph, followed by the (weighted) aspect ratio.
+fn features(foreground: &[bool], width: usize, glyph: GlyphBox) -> Vec<f32> {
+    let mut features = Vec::with_capacity(ZONE_COLUMNS * ZONE_ROWS + 1);
+    for zone_y in 0..ZONE_ROWS {
+        for zone_x in 0..ZONE_COLUMNS {
+            // Zones cover at least one pixel, even for glyphs smaller than the grid
+            let x0 = glyph.x + zone_x * glyph.width / ZONE_COLUMNS;
+            let x1 = (glyph.x + (zone_x + 1) * glyph.width / ZONE_COLUMNS).max(x0 + 1);
+            let y0 = glyph.y + zone_y * glyph.height / ZONE_ROWS;
+            let y1 = (glyph.y + (zone_y + 1) * glyph.height / ZONE_ROWS).max(y0 + 1);
+
+            let filled = (y0..y1)
+                .flat_map(|y| (x0..x1).map(move |x| (x, y)))
+                .filter(|&(x, y)| foreground[y * width + x])
+                .count();
+            features.push(filled as f32 / ((x1 - x0) * (y1 - y0)) as f32);
+        }
+    }
+    features.push(ASPECT_WEIGHT * glyph.width as f32 / glyph.height as f32);
+    features
+}
+
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (a - b) * (a - b))
+        .sum::<f32>()
+        .sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds an image from rows of '#' (foreground, dark) and '.' (background, bright).
+    fn image(rows: &[&str]) -> Vec<u8> {
+        rows.iter()
+            .flat_map(|row| row.chars().map(|c| if c == '#' { 0 } else { 255 }))
+            .collect()
+    }
+
+    const BAR: [&str; 8] = [
+        "......", ".##...", ".##...", ".##...", ".##...", ".##...", ".##...", "......",
+    ];
+
+    const DOTS: [&str; 8] = [
+        "......", "......", ".##...", ".##...", "......", ".##...", ".##...", "......",
+    ];
+
+    fn glyph_features(rows: &[&str]) -> Vec<f32> {
+        let data = image(rows);
+        let image = GrayImage::new(&data, rows[0].len(), rows.len()).unwrap();
+        let foreground = image.foreground();
+        let glyph = bounding_box(&foreground, image.width, image.height, 0, image.width).unwrap();
+        features(&foreground, image.width, glyph)
+    }
+
+    #[test]
+    fn foreground_is_opposite_of_border() {
+        let data = image(&["...", ".#.", "..."]);
+        let foreground = GrayImage::new(&data, 3, 3).unwrap().foreground();
+        assert_eq!(foreground.iter().filter(|&&f| f).count(), 1);
+        assert!(foreground[4]);
+    }
+
+    #[test]
+    fn bounding_box_is_tight() {
+        let data = image(&BAR);
+        let foreground = GrayImage::new(&data, 6, 8).unwrap().foreground();
+        assert_eq!(
+            bounding_box(&foreground, 6, 8, 0, 6),
+            Some(GlyphBox {
+                x: 1,
+                y: 1,
+                width: 2,
+                height: 6
+            })
+        );
+        assert_eq!(bounding_box(&foreground, 6, 8, 3, 6), None);
+    }
+
+    #[test]
+    fn classifies_closest_prototype() {
+        let classifier = GlyphClassifier {
+            prototypes: vec![('1', glyph_features(&BAR)), (':', glyph_features(&DOTS))],
+        };
+
+        let colon = glyph_features(&[
+            "......", ".##...", ".##...", "......", "......", ".##...", ".##...", "......",
+        ]);
+        assert_eq!(classifier.classify(&colon).map(|(c, _)| c), Some(':'));
+        assert_eq!(classifier.classify(&glyph_features(&BAR)), Some(('1', 0.0)));
+    }
+
+    #[test]
+    fn reads_glyphs_left_to_right() {
+        let classifier = GlyphClassifier {
+            prototypes: vec![('1', glyph_features(&BAR)), (':', glyph_features(&DOTS))],
+        };
+
+        let rows: Vec<String> = BAR
+            .iter()
+            .zip(DOTS)
+            .map(|(a, b)| format!("{}{}", a, b))
+            .collect();
+        let rows: Vec<&str> = rows.iter().map(|r| r.as_str()).collect();
+        let data = image(&rows);
+        let glyphs = classifier.read(&GrayImage::new(&data, 12, 8).unwrap());
+
+        let text: String = glyphs.iter().map(|m| m.character).collect();
+        assert_eq!(text, "1:");
+        assert_eq!(glyphs[1].x, 7);
+    }
+}
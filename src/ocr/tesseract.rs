@@ -0,0 +1,83 @@
+use anyhow::{Result, anyhow};
+use opencv::core::Size;
+use opencv::prelude::*;
+use tesseract::{PageSegMode, Tesseract};
+
+use super::{Recognizer, TemplateMatch};
+use crate::in_game_time::InGameTime;
+
+/// Characters that can appear in the IGT
+const CHARSET: &str = "0123456789%:";
+
+/// Recognizes the IGT with tesseract instead of template matching.
+pub struct TesseractRecognizer {
+    /// `None` if a previous recognition failed (the API is consumed on errors), re-initialized lazily
+    api: Option<Tesseract>,
+}
+
+impl TesseractRecognizer {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            api: Some(Self::init()?),
+        })
+    }
+
+    fn init() -> Result<Tesseract> {
+        // Uses the tessdata found via TESSDATA_PREFIX
+        let mut api =
+            Tesseract::new(None, Some("eng"))?.set_variable("tessedit_char_whitelist", CHARSET)?;
+        api.set_page_seg_mode(PageSegMode::PsmSingleLine);
+        Ok(api)
+    }
+}
+
+impl Recognizer for TesseractRecognizer {
+    /// Tesseract doesn't report the positions of the characters here, so every match spans the
+    /// whole image and carries tesseract's mean confidence.
+    fn recognize(&mut self, image: &Mat, matches: &mut Vec<TemplateMatch>) -> Result<InGameTime> {
+        let continuous;
+        let image = if image.is_continuous() {
+            image
+        } else {
+            continuous = image.try_clone()?;
+            &continuous
+        };
+
+        let api = match self.api.take() {
+            Some(api) => api,
+            None => Self::init()?,
+        };
+        let mut api = api
+            .set_frame(
+                image.data_bytes()?,
+                image.cols(),
+                image.rows(),
+                1,
+                image.cols(),
+            )?
+            .recognize()?;
+        let text = api.get_text()?;
+        let confidence = api.mean_text_conf() as f32 / 100.0;
+        self.api = Some(api);
+
+        let text = text.trim();
+        if !text.contains('%') {
+            return Err(anyhow!("No IGT found"));
+        }
+
+        matches.extend(
+            text.chars()
+                .filter(|c| !c.is_whitespace())
+                .map(|character| TemplateMatch {
+                    x: 0,
+                    y: 0,
+                    bounding_box: Size::new(image.cols(), image.rows()),
+                    character,
+                    confidence,
+                    threshold: 0.0,
+                }),
+        );
+
+        InGameTime::parse(text)
+    }
+}
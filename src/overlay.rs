@@ -0,0 +1,137 @@
+use anyhow::Result;
+use opencv::core::{Mat, Point, Rect, Scalar};
+use opencv::imgproc;
+use opencv::prelude::*;
+
+use crate::in_game_time::InGameTime;
+use crate::ocr::TemplateMatch;
+
+/// What happened to the reading of a ROI.
+pub enum Decision {
+    /// The IGT was accepted and fed into the splits
+    Accepted(InGameTime),
+    /// The reading is valid, but still has to win the vote / be confirmed
+    Pending,
+    Rejected(String),
+}
+
+impl Decision {
+    pub fn describe(&self) -> String {
+        match self {
+            Decision::Accepted(igt) => format!("accepted {}", igt),
+            Decision::Pending => "pending confirmation".to_string(),
+            Decision::Rejected(reason) => format!("rejected: {}", reason),
+        }
+    }
+
+    fn color(&self) -> Scalar {
+        match self {
+            Decision::Accepted(_) => Scalar::new(0.0, 255.0, 0.0, 0.0),
+            Decision::Pending => Scalar::new(0.0, 255.0, 255.0, 0.0),
+            Decision::Rejected(_) => Scalar::new(0.0, 0.0, 255.0, 0.0),
+        }
+    }
+}
+
+/// Everything drawn onto the debug view for one ROI of a frame.
+pub struct RoiOverlay {
+    pub roi: Rect,
+    pub matches: Vec<TemplateMatch>,
+    /// The parsed IGT, or the raw characters if it couldn't be parsed
+    pub reading: String,
+    pub decision: Decision,
+}
+
+/// Draws the ROIs with their character matches (and confidences), the reading and the decision.
+pub fn draw_overlays(frame: &mut Mat, overlays: &[RoiOverlay]) -> Result<()> {
+    for overlay in overlays {
+        let roi = overlay.roi;
+        for m in &overlay.matches {
+            let top_left = Point::new(roi.x + m.x, roi.y + m.y);
+            imgproc::rectangle(
+                frame,
+                Rect::new(
+                    top_left.x,
+                    top_left.y,
+                    m.bounding_box.width,
+                    m.bounding_box.height,
+                ),
+                Scalar::new(255.0, 0.0, 255.0, 0.0),
+                2,
+                imgproc::LINE_8,
+                0,
+            )?;
+            draw_text(
+                frame,
+                &format!("{:.2}", m.confidence),
+                Point::new(top_left.x, top_left.y - 4),
+                0.4,
+                Scalar::new(255.0, 0.0, 255.0, 0.0),
+            )?;
+        }
+
+        // Draw ROI rectangle on original frame
+        imgproc::rectangle(
+            frame,
+            roi,
+            Scalar::new(0.0, 255.0, 0.0, 0.0),
+            2,
+            imgproc::LINE_8,
+            0,
+        )?;
+
+        // Below the ROI, or above it if there's no room
+        let below = roi.y + roi.height + 30;
+        let y = if below < frame.rows() {
+            below
+        } else {
+            roi.y - 10
+        };
+        draw_text(
+            frame,
+            &format!("'{}' {}", overlay.reading, overlay.decision.describe()),
+            Point::new(roi.x, y),
+            0.8,
+            overlay.decision.color(),
+        )?;
+    }
+    Ok(())
+}
+
+fn draw_text(frame: &mut Mat, text: &str, origin: Point, scale: f64, color: Scalar) -> Result<()> {
+    imgproc::put_text(
+        frame,
+        text,
+        origin,
+        imgproc::FONT_HERSHEY_SIMPLEX,
+        scale,
+        color,
+        2,
+        imgproc::LINE_AA,
+        false,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn describes_decisions() {
+        let igt = InGameTime {
+            percent: 117,
+            duration: Duration::from_secs(3 * 3600 + 3 * 60 + 23),
+        };
+        assert_eq!(
+            Decision::Accepted(igt).describe(),
+            format!("accepted {}", igt)
+        );
+        assert_eq!(Decision::Pending.describe(), "pending confirmation");
+        assert_eq!(
+            Decision::Rejected("no IGT found".to_string()).describe(),
+            "rejected: no IGT found"
+        );
+    }
+}
@@ -0,0 +1,452 @@
+use std::fmt;
+
+use anyhow::{Result, anyhow};
+use clap::ValueEnum;
+use opencv::core::{Point, Scalar, Size, ToInputArray, ToOutputArray};
+use opencv::imgproc;
+use opencv::prelude::*;
+
+/// Tile grid used by CLAHE
+const CLAHE_TILES: i32 = 8;
+
+/// Largest blur kernel, far more than blurring the characters of the IGT could use
+const MAX_BLUR_SIZE: i32 = 31;
+
+/// A single preprocessing step, applied to the grayscale ROI.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// Contrast limited adaptive histogram equalization
+    Clahe { clip_limit: f64 },
+    /// Gaussian blur with a `size` x `size` kernel
+    Blur { size: i32 },
+    /// Binarization with a fixed threshold
+    Threshold { value: f64 },
+    /// Binarization with a threshold determined by Otsu's method
+    Otsu,
+    /// Swaps black and white
+    Invert,
+}
+
+/// How to remove the combing of interlaced captures.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum Deinterlace {
+    /// Keep the even lines and interpolate the odd ones
+    Even,
+    /// Keep the odd lines and interpolate the even ones
+    Odd,
+    /// Average every line with the next one
+    Blend,
+}
+
+/// Range of HSV colors (in OpenCV's ranges, i.e. hue 0-179, saturation and value 0-255) that make up
+/// the text. If the low hue is above the high hue, the range wraps around (e.g. for red).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorKey {
+    low: [f64; 3],
+    high: [f64; 3],
+}
+
+/// The chain of steps turning the grayscale ROI into the image the IGT is read from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preprocessing {
+    /// Applied to the color ROI, before the conversion to grayscale
+    deinterlace: Option<Deinterlace>,
+    /// Replaces the grayscale conversion by a mask of the pixels within the color range
+    color_key: Option<ColorKey>,
+    steps: Vec<Step>,
+}
+
+impl Default for Preprocessing {
+    fn default() -> Self {
+        Self {
+            deinterlace: None,
+            color_key: None,
+            steps: vec![Step::Otsu],
+        }
+    }
+}
+
+impl Preprocessing {
+    pub fn with_deinterlace(mut self, deinterlace: Option<Deinterlace>) -> Self {
+        self.deinterlace = deinterlace;
+        self
+    }
+
+    pub fn deinterlace(&self) -> Option<Deinterlace> {
+        self.deinterlace
+    }
+
+    pub fn with_color_key(mut self, color_key: Option<ColorKey>) -> Self {
+        self.color_key = color_key;
+        self
+    }
+
+    /// Threshold of the fixed binarization step, `None` if there is none (e.g. Otsu's method).
+    pub fn binarization_threshold(&self) -> Option<f64> {
+        self.steps.iter().find_map(|step| match *step {
+            Step::Threshold { value } => Some(value),
+            _ => None,
+        })
+    }
+
+    /// Replaces the binarization step (fixed threshold or Otsu's method) by a fixed threshold of
+    /// `value`, or by Otsu's method if `None`. Appended if there is no binarization step yet.
+    pub fn set_binarization_threshold(&mut self, value: Option<f64>) {
+        let step = match value {
+            Some(value) => Step::Threshold { value },
+            None => Step::Otsu,
+        };
+        match self
+            .steps
+            .iter_mut()
+            .find(|step| matches!(step, Step::Threshold { .. } | Step::Otsu))
+        {
+            Some(existing) => *existing = step,
+            None => self.steps.push(step),
+        }
+    }
+
+    /// Converts the (BGR) ROI to the single channel image the steps are applied to: grayscale, or
+    /// the mask of the color key.
+    pub fn to_gray<T>(&self, roi: &T, new: impl Fn() -> T) -> Result<T>
+    where
+        T: ToInputArray + ToOutputArray,
+    {
+        let Some(key) = &self.color_key else {
+            let mut gray = new();
+            imgproc::cvt_color(
+                roi,
+                &mut gray,
+                imgproc::COLOR_BGR2GRAY,
+                0,
+                opencv::core::AlgorithmHint::ALGO_HINT_DEFAULT,
+            )?;
+            return Ok(gray);
+        };
+
+        let mut hsv = new();
+        imgproc::cvt_color(
+            roi,
+            &mut hsv,
+            imgproc::COLOR_BGR2HSV,
+            0,
+            opencv::core::AlgorithmHint::ALGO_HINT_DEFAULT,
+        )?;
+
+        let [low_h, low_s, low_v] = key.low;
+        let [high_h, high_s, high_v] = key.high;
+        let mut mask = new();
+        if low_h <= high_h {
+            opencv::core::in_range(
+                &hsv,
+                &Scalar::new(low_h, low_s, low_v, 0.0),
+                &Scalar::new(high_h, high_s, high_v, 0.0),
+                &mut mask,
+            )?;
+        } else {
+            let mut upper_hues = new();
+            opencv::core::in_range(
+                &hsv,
+                &Scalar::new(low_h, low_s, low_v, 0.0),
+                &Scalar::new(179.0, high_s, high_v, 0.0),
+                &mut upper_hues,
+            )?;
+            let mut lower_hues = new();
+            opencv::core::in_range(
+                &hsv,
+                &Scalar::new(0.0, low_s, low_v, 0.0),
+                &Scalar::new(high_h, high_s, high_v, 0.0),
+                &mut lower_hues,
+            )?;
+            opencv::core::bitwise_or(
+                &upper_hues,
+                &lower_hues,
+                &mut mask,
+                &opencv::core::no_array(),
+            )?;
+        }
+        Ok(mask)
+    }
+
+    /// Applies all steps to `image`, on the CPU (`Mat`) or the GPU (`UMat`). `new` creates an empty
+    /// image of the same kind for the output of each step.
+    pub fn apply<T>(&self, image: T, new: impl Fn() -> T) -> Result<T>
+    where
+        T: ToInputArray + ToOutputArray,
+    {
+        let mut image = image;
+        for step in &self.steps {
+            let mut output = new();
+            match *step {
+                Step::Clahe { clip_limit } => {
+                    let mut clahe =
+                        imgproc::create_clahe(clip_limit, Size::new(CLAHE_TILES, CLAHE_TILES))?;
+                    clahe.apply(&image, &mut output)?;
+                }
+                Step::Blur { size } => imgproc::gaussian_blur(
+                    &image,
+                    &mut output,
+                    Size::new(size, size),
+                    0.0,
+                    0.0,
+                    opencv::core::BORDER_DEFAULT,
+                    opencv::core::AlgorithmHint::ALGO_HINT_DEFAULT,
+                )?,
+                Step::Threshold { value } => {
+                    imgproc::threshold(&image, &mut output, value, 255.0, imgproc::THRESH_BINARY)?;
+                }
+                Step::Otsu => {
+                    imgproc::threshold(&image, &mut output, 0.0, 255.0, imgproc::THRESH_OTSU)?;
+                }
+                Step::Invert => {
+                    opencv::core::bitwise_not(&image, &mut output, &opencv::core::no_array())?
+                }
+            }
+            image = output;
+        }
+        Ok(image)
+    }
+}
+
+/// Formats the steps the way `parse_preprocessing` reads them, e.g. for a `--preprocess` argument.
+impl fmt::Display for Preprocessing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let steps: Vec<String> = self
+            .steps
+            .iter()
+            .map(|step| match *step {
+                Step::Clahe { clip_limit } => format!("clahe={}", clip_limit),
+                Step::Blur { size } => format!("blur={}", size),
+                Step::Threshold { value } => format!("threshold={}", value),
+                Step::Otsu => "otsu".to_string(),
+                Step::Invert => "invert".to_string(),
+            })
+            .collect();
+        write!(f, "{}", steps.join(","))
+    }
+}
+
+/// Parses a comma separated list of steps, e.g. `clahe=2.0,blur=3,otsu`. Available steps:
+/// `clahe[=CLIP_LIMIT]`, `blur[=SIZE]`, `threshold=VALUE`, `otsu` and `invert`.
+pub fn parse_preprocessing(s: &str) -> Result<Preprocessing> {
+    let steps = s
+        .split(',')
+        .map(|step| {
+            let step = step.trim();
+            let (name, value) = match step.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim())),
+                None => (step, None),
+            };
+            let number = |default: Option<f64>| -> Result<f64> {
+                match value {
+                    Some(value) => value
+                        .parse()
+                        .map_err(|e| anyhow!("Invalid value for '{}': {}", name, e)),
+                    None => default.ok_or_else(|| anyhow!("'{}' requires a value", name)),
+                }
+            };
+
+            match name {
+                "clahe" => {
+                    let clip_limit = number(Some(2.0))?;
+                    if !clip_limit.is_finite() || clip_limit <= 0.0 {
+                        return Err(anyhow!("CLAHE clip limit must be positive"));
+                    }
+                    Ok(Step::Clahe { clip_limit })
+                }
+                "blur" => {
+                    let size = match value {
+                        Some(value) => value
+                            .parse::<i32>()
+                            .map_err(|e| anyhow!("Invalid value for '{}': {}", name, e))?,
+                        None => 3,
+                    };
+                    if !(1..=MAX_BLUR_SIZE).contains(&size) || size % 2 == 0 {
+                        return Err(anyhow!(
+                            "Blur size must be an odd number between 1 and {}",
+                            MAX_BLUR_SIZE
+                        ));
+                    }
+                    Ok(Step::Blur { size })
+                }
+                "threshold" => {
+                    let value = number(None)?;
+                    if !(0.0..=255.0).contains(&value) {
+                        return Err(anyhow!("Threshold must be between 0 and 255"));
+                    }
+                    Ok(Step::Threshold { value })
+                }
+                "otsu" | "invert" if value.is_some() => {
+                    Err(anyhow!("'{}' doesn't take a value", name))
+                }
+                "otsu" => Ok(Step::Otsu),
+                "invert" => Ok(Step::Invert),
+                _ => Err(anyhow!("Unknown preprocessing step '{}'", step)),
+            }
+        })
+        .collect::<Result<Vec<_>>>()
+        .map_err(|e| anyhow!("Invalid preprocessing '{}': {}", s, e))?;
+
+    Ok(Preprocessing {
+        deinterlace: None,
+        color_key: None,
+        steps,
+    })
+}
+
+/// Parses a color key given as `H,S,V-H,S,V` (low and high end of the range).
+pub fn parse_color_key(s: &str) -> Result<ColorKey> {
+    let parse_hsv = |hsv: &str| -> Result<[f64; 3]> {
+        let values = hsv
+            .split(',')
+            .map(|v| v.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| anyhow!("Invalid color key '{}': {}", s, e))?;
+        match values[..] {
+            [hue, saturation, value]
+                if (0.0..=179.0).contains(&hue)
+                    && (0.0..=255.0).contains(&saturation)
+                    && (0.0..=255.0).contains(&value) =>
+            {
+                Ok([hue, saturation, value])
+            }
+            _ => Err(anyhow!(
+                "Invalid color key '{}': expected H,S,V with hue 0-179, saturation and value 0-255",
+                s
+            )),
+        }
+    };
+
+    let (low, high) = s
+        .split_once('-')
+        .ok_or_else(|| anyhow!("Invalid color key '{}': expected H,S,V-H,S,V", s))?;
+    let key = ColorKey {
+        low: parse_hsv(low)?,
+        high: parse_hsv(high)?,
+    };
+    if key.low[1] > key.high[1] || key.low[2] > key.high[2] {
+        return Err(anyhow!(
+            "Invalid color key '{}': saturation and value ranges must not be reversed",
+            s
+        ));
+    }
+    Ok(key)
+}
+
+/// Removes the combing of an interlaced `image`, see `Deinterlace`. `top` is the row of the frame
+/// that `image` (e.g. the ROI) starts at, the fields are made up of the even / odd rows of the frame.
+pub fn deinterlace(image: &Mat, mode: Deinterlace, top: i32) -> Result<Mat> {
+    if image.rows() < 2 {
+        return Ok(image.try_clone()?);
+    }
+
+    let mut output = Mat::default();
+    match mode {
+        Deinterlace::Blend => {
+            imgproc::blur(
+                image,
+                &mut output,
+                Size::new(1, 2),
+                Point::new(-1, -1),
+                opencv::core::BORDER_DEFAULT,
+            )?;
+        }
+        Deinterlace::Even | Deinterlace::Odd => {
+            let parity = if mode == Deinterlace::Even { 0 } else { 1 };
+            let first = (parity + top).rem_euclid(2);
+            let mut field = Mat::default();
+            for y in (first..image.rows()).step_by(2) {
+                field.push_back(&image.row(y)?)?;
+            }
+            imgproc::resize(
+                &field,
+                &mut output,
+                image.size()?,
+                0.0,
+                0.0,
+                imgproc::INTER_LINEAR,
+            )?;
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_chain_in_order() {
+        assert_eq!(
+            parse_preprocessing("clahe, blur=5,threshold=140,invert").unwrap(),
+            Preprocessing {
+                deinterlace: None,
+                color_key: None,
+                steps: vec![
+                    Step::Clahe { clip_limit: 2.0 },
+                    Step::Blur { size: 5 },
+                    Step::Threshold { value: 140.0 },
+                    Step::Invert,
+                ]
+            }
+        );
+        assert_eq!(
+            parse_preprocessing("otsu").unwrap(),
+            Preprocessing::default()
+        );
+    }
+
+    #[test]
+    fn replaces_binarization_step() {
+        let mut preprocessing = parse_preprocessing("clahe,otsu,invert").unwrap();
+        assert_eq!(preprocessing.binarization_threshold(), None);
+
+        preprocessing.set_binarization_threshold(Some(140.0));
+        assert_eq!(preprocessing.binarization_threshold(), Some(140.0));
+        assert_eq!(preprocessing.to_string(), "clahe=2,threshold=140,invert");
+
+        preprocessing.set_binarization_threshold(None);
+        assert_eq!(preprocessing.to_string(), "clahe=2,otsu,invert");
+        assert_eq!(
+            parse_preprocessing(&preprocessing.to_string()).unwrap(),
+            preprocessing
+        );
+    }
+
+    #[test]
+    fn parses_color_key() {
+        assert_eq!(
+            parse_color_key("20,100,150-35,255,255").unwrap(),
+            ColorKey {
+                low: [20.0, 100.0, 150.0],
+                high: [35.0, 255.0, 255.0],
+            }
+        );
+        // Wrapping hue range
+        assert!(parse_color_key("170,50,50-10,255,255").is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_color_keys() {
+        assert!(parse_color_key("20,100,150").is_err());
+        assert!(parse_color_key("20,100-35,255,255").is_err());
+        assert!(parse_color_key("200,100,150-35,255,255").is_err());
+        assert!(parse_color_key("20,200,150-35,100,255").is_err());
+        assert!(parse_color_key("yellow-35,255,255").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_steps() {
+        assert!(parse_preprocessing("sharpen").is_err());
+        assert!(parse_preprocessing("blur=4").is_err());
+        assert!(parse_preprocessing("blur=2.5").is_err());
+        assert!(parse_preprocessing("blur=1e10").is_err());
+        assert!(parse_preprocessing("blur=33").is_err());
+        assert!(parse_preprocessing("threshold").is_err());
+        assert!(parse_preprocessing("threshold=300").is_err());
+        assert!(parse_preprocessing("clahe=0").is_err());
+        assert!(parse_preprocessing("clahe=NaN").is_err());
+        assert!(parse_preprocessing("otsu=1").is_err());
+        assert!(parse_preprocessing("").is_err());
+    }
+}
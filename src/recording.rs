@@ -0,0 +1,99 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use opencv::core::{Mat, Size};
+use opencv::prelude::*;
+use opencv::videoio;
+
+/// Writes the frames the splitter processes to a video file, so that the recording shows exactly
+/// what the OCR saw. The file is opened on the first frame, once its size is known.
+pub struct Recorder {
+    path: PathBuf,
+    fps: f64,
+    writer: Option<videoio::VideoWriter>,
+    frames: u64,
+}
+
+impl Recorder {
+    pub fn new(path: &Path, fps: f64) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            fps,
+            writer: None,
+            frames: 0,
+        }
+    }
+
+    pub fn write(&mut self, frame: &Mat) -> Result<()> {
+        if frame.empty() {
+            return Ok(());
+        }
+
+        if self.writer.is_none() {
+            self.writer = Some(open_writer(&self.path, self.fps, frame.size()?)?);
+        }
+        if let Some(writer) = &mut self.writer {
+            writer.write(frame)?;
+            self.frames += 1;
+        }
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+}
+
+fn open_writer(path: &Path, fps: f64, size: Size) -> Result<videoio::VideoWriter> {
+    let [a, b, c, d] = fourcc_for(path)
+        .ok_or_else(|| anyhow!("Unsupported recording format {}", path.display()))?;
+    let writer = videoio::VideoWriter::new(
+        &path.to_string_lossy(),
+        videoio::VideoWriter::fourcc(a, b, c, d)?,
+        fps,
+        size,
+        true,
+    )?;
+    if !writer.is_opened()? {
+        return Err(anyhow!("Unable to open recording {}", path.display()));
+    }
+    Ok(writer)
+}
+
+/// Checks that the recording format is supported, so that this fails before splitting starts.
+pub fn parse_recording_path(s: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(s);
+    match fourcc_for(&path) {
+        Some(_) => Ok(path),
+        None => Err(anyhow!(
+            "Unsupported recording format '{}': use .mp4, .mkv or .avi",
+            s
+        )),
+    }
+}
+
+/// Codec for the container given by the file extension.
+fn fourcc_for(path: &Path) -> Option<[char; 4]> {
+    match path.extension()?.to_str()? {
+        "mp4" | "mkv" => Some(['m', 'p', '4', 'v']),
+        "avi" => Some(['M', 'J', 'P', 'G']),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_codec_by_extension() {
+        assert_eq!(fourcc_for(Path::new("run.mp4")), Some(['m', 'p', '4', 'v']));
+        assert_eq!(fourcc_for(Path::new("run.avi")), Some(['M', 'J', 'P', 'G']));
+        assert_eq!(fourcc_for(Path::new("run.gif")), None);
+        assert_eq!(fourcc_for(Path::new("run")), None);
+    }
+}
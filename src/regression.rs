@@ -0,0 +1,189 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+
+use crate::screenshot::{ScreenshotOptions, ScreenshotReader, load_image};
+
+/// Number of correct readings of a character out of all its occurrences.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+struct CharacterAccuracy {
+    correct: u32,
+    total: u32,
+}
+
+/// Accuracy of the OCR on a set of labeled screenshots, per character and per image.
+#[derive(Default)]
+struct Report {
+    characters: BTreeMap<char, CharacterAccuracy>,
+    images: u32,
+    correct_images: u32,
+}
+
+impl Report {
+    /// Compares the characters read from an image to the expected ones, position by position.
+    /// If the number of characters differs, all expected characters count as misread.
+    fn record(&mut self, expected: &str, recognized: &str) -> bool {
+        let aligned = expected.chars().count() == recognized.chars().count();
+        let mut recognized_chars = recognized.chars();
+        for c in expected.chars() {
+            let accuracy = self.characters.entry(c).or_default();
+            accuracy.total += 1;
+            if aligned && recognized_chars.next() == Some(c) {
+                accuracy.correct += 1;
+            }
+        }
+
+        let correct = expected == recognized;
+        self.images += 1;
+        if correct {
+            self.correct_images += 1;
+        }
+        correct
+    }
+
+    fn render(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "Images: {}/{} read correctly ({:.1}%)",
+            self.correct_images,
+            self.images,
+            percentage(self.correct_images, self.images)
+        )];
+        for (c, accuracy) in &self.characters {
+            lines.push(format!(
+                "  '{}': {}/{} ({:.1}%)",
+                c,
+                accuracy.correct,
+                accuracy.total,
+                percentage(accuracy.correct, accuracy.total)
+            ));
+        }
+        lines
+    }
+}
+
+fn percentage(part: u32, total: u32) -> f64 {
+    if total > 0 {
+        100.0 * part as f64 / total as f64
+    } else {
+        0.0
+    }
+}
+
+/// Parses the IGT encoded in a screenshot's file name, as `<percent>_<H>-<MM>-<SS>` optionally
+/// followed by `_<anything>` (e.g. `117_3-03-23_glare.png`), into the characters shown on screen
+/// (e.g. `117%3:03:23`).
+fn parse_expected(file_stem: &str) -> Result<String> {
+    let mut parts = file_stem.split('_');
+    let (Some(percent), Some(time)) = (parts.next(), parts.next()) else {
+        return Err(anyhow!(
+            "File name '{}' isn't <percent>_<H>-<MM>-<SS>",
+            file_stem
+        ));
+    };
+
+    let percent: u32 = percent
+        .parse()
+        .map_err(|e| anyhow!("Invalid percent in '{}': {}", file_stem, e))?;
+    let time: Vec<&str> = time.split('-').collect();
+    match time[..] {
+        [h, mm, ss]
+            if [h, mm, ss]
+                .iter()
+                .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+                && mm.len() == 2
+                && ss.len() == 2 =>
+        {
+            Ok(format!("{}%{}:{}:{}", percent, h, mm, ss))
+        }
+        _ => Err(anyhow!("Invalid time in '{}': must be H-MM-SS", file_stem)),
+    }
+}
+
+/// Reads every labeled screenshot in `dir` and prints whether it was read correctly, followed by
+/// the accuracy per character and per image.
+pub fn run(dir: &Path, options: &ScreenshotOptions) -> Result<()> {
+    let mut paths = fs::read_dir(dir)
+        .map_err(|e| anyhow!("Failed to read directory {}: {}", dir.display(), e))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.retain(|path| {
+        path.extension()
+            .is_some_and(|ext| ext == "png" || ext == "jpg")
+    });
+    paths.sort();
+
+    let mut reader = ScreenshotReader::new(options)?;
+    let mut report = Report::default();
+    for path in &paths {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let expected = match parse_expected(&stem) {
+            Ok(expected) => expected,
+            Err(e) => {
+                println!("{}: skipped ({})", name, e);
+                continue;
+            }
+        };
+
+        let reading = reader.read(&load_image(path)?)?;
+        let recognized: String = reading.matches.iter().map(|m| m.character).collect();
+        // The ':' in front of the percentage isn't part of the IGT
+        let recognized = recognized.strip_prefix(':').unwrap_or(&recognized);
+
+        if report.record(&expected, recognized) {
+            println!("{}: OK", name);
+        } else {
+            println!("{}: expected '{}', read '{}'", name, expected, recognized);
+        }
+    }
+
+    if report.images == 0 {
+        return Err(anyhow!("No labeled screenshots in {}", dir.display()));
+    }
+    for line in report.render() {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_expected_igt_from_file_name() {
+        assert_eq!(parse_expected("117_3-03-23").unwrap(), "117%3:03:23");
+        assert_eq!(parse_expected("5_0-00-07_glare").unwrap(), "5%0:00:07");
+        assert!(parse_expected("117").is_err());
+        assert!(parse_expected("117_3-3-23").is_err());
+        assert!(parse_expected("screenshot_3-03-23").is_err());
+    }
+
+    #[test]
+    fn scores_characters_by_position() {
+        let mut report = Report::default();
+        assert!(report.record("1%0:00:01", "1%0:00:01"));
+        assert!(!report.record("1%0:00:08", "1%0:00:03"));
+        // A missing character misaligns the reading, so nothing counts as correct
+        assert!(!report.record("2%0:00:01", "2%0:0:01"));
+
+        assert_eq!(report.correct_images, 1);
+        assert_eq!(
+            report.characters[&'8'],
+            CharacterAccuracy {
+                correct: 0,
+                total: 1
+            }
+        );
+        assert_eq!(
+            report.characters[&'%'],
+            CharacterAccuracy {
+                correct: 2,
+                total: 3
+            }
+        );
+        assert_eq!(report.render()[0], "Images: 1/3 read correctly (33.3%)");
+    }
+}
@@ -0,0 +1,481 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::anyhow;
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::in_game_time::{InGameTime, format_hms};
+use crate::splits::Splits;
+
+/// Format of the chapter markers generated from a retimed run.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ChapterFormat {
+    /// Timestamp and title per line, to paste into a YouTube video description
+    Youtube,
+    /// FFmpeg metadata file, to add with `ffmpeg -i video -i chapters -map_metadata 1 -codec copy ...`
+    Ffmpeg,
+}
+
+/// An IGT reading that differs from the previous one, together with its position in the video.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IgtChange {
+    pub frame_index: u64,
+    pub video_time: Duration,
+    pub igt: InGameTime,
+}
+
+/// Collects all IGT changes while processing a recorded video.
+#[derive(Default)]
+pub struct Retiming {
+    changes: Vec<IgtChange>,
+}
+
+impl Retiming {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records `igt` if it differs from the last recorded reading. Returns the change if recorded.
+    pub fn record(
+        &mut self,
+        frame_index: u64,
+        video_time: Duration,
+        igt: &InGameTime,
+    ) -> Option<&IgtChange> {
+        if self.changes.last().is_some_and(|c| c.igt == *igt) {
+            return None;
+        }
+
+        self.changes.push(IgtChange {
+            frame_index,
+            video_time,
+            igt: *igt,
+        });
+        self.changes.last()
+    }
+
+    pub fn changes(&self) -> &[IgtChange] {
+        &self.changes
+    }
+
+    /// Returns the first observed change for every split, in split order (`None` if never observed).
+    pub fn first_changes_per_split<'a>(
+        &'a self,
+        splits: &'a Splits,
+    ) -> Vec<(&'a str, Option<&'a IgtChange>)> {
+        let indices: Vec<Option<usize>> = self
+            .changes
+            .iter()
+            .map(|c| splits.split_index_at(&c.igt))
+            .collect();
+        splits
+            .splits()
+            .iter()
+            .enumerate()
+            .map(|(index, split)| {
+                let change = self
+                    .changes
+                    .iter()
+                    .zip(&indices)
+                    .find(|(_, split_index)| **split_index == Some(index))
+                    .map(|(change, _)| change);
+                (split.name.as_str(), change)
+            })
+            .collect()
+    }
+
+    /// Renders one chapter per observed split, from the previous observed split (or the start of the
+    /// video) up to the video timestamp at which the split was first seen.
+    pub fn render_chapters(&self, splits: &Splits, format: ChapterFormat) -> String {
+        let mut chapters = Vec::new();
+        let mut start = Duration::ZERO;
+        for (name, change) in self.first_changes_per_split(splits) {
+            if let Some(change) = change {
+                chapters.push((name, start, change.video_time));
+                start = change.video_time;
+            }
+        }
+
+        match format {
+            ChapterFormat::Youtube => chapters
+                .iter()
+                .map(|(name, start, _)| format!("{} {}\n", format_hms(*start), name))
+                .collect(),
+            ChapterFormat::Ffmpeg => {
+                let mut metadata = ";FFMETADATA1\n".to_string();
+                for (name, start, end) in chapters {
+                    metadata.push_str(&format!(
+                        "\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+                        start.as_millis(),
+                        end.as_millis(),
+                        escape_ffmetadata(name)
+                    ));
+                }
+                metadata
+            }
+        }
+    }
+
+    /// Renders the retimed splits, i.e. the IGT and video timestamp at which each split was first seen.
+    pub fn render_summary(&self, splits: &Splits) -> Vec<String> {
+        let name_width = splits.compute_name_width();
+        self.first_changes_per_split(splits)
+            .into_iter()
+            .map(|(name, change)| {
+                let name_fmt =
+                    Splits::pad_str(&Splits::truncate_name(name, name_width), name_width);
+                match change {
+                    Some(change) => format!(
+                        "{} {:>8} @ {}",
+                        name_fmt,
+                        Splits::format_time(Some(change.igt.duration)),
+                        format_video_time(change.video_time)
+                    ),
+                    None => format!("{} {:>8}", name_fmt, Splits::format_time(None)),
+                }
+            })
+            .collect()
+    }
+}
+
+/// The first observation of a percentage (of each split at it, if there are several), for verifying
+/// a run against its video.
+#[derive(Serialize)]
+struct Milestone<'a> {
+    percent: u32,
+    /// Name of the split completed at this percentage, if any
+    split: Option<&'a str>,
+    igt: String,
+    video_time: String,
+    frame_index: u64,
+}
+
+#[derive(Serialize)]
+struct RunReport<'a> {
+    runner: &'a str,
+    milestones: Vec<Milestone<'a>>,
+}
+
+impl<'a> RunReport<'a> {
+    fn new(runner: &'a str, splits: &'a Splits, retiming: &'a Retiming) -> Self {
+        let mut milestones: Vec<Milestone> = Vec::new();
+        let mut observed: Vec<(u32, Option<usize>)> = Vec::new();
+        for change in retiming.changes() {
+            let index = splits.split_index_at(&change.igt);
+            if observed.contains(&(change.igt.percent, index)) {
+                continue;
+            }
+            observed.push((change.igt.percent, index));
+            milestones.push(Milestone {
+                percent: change.igt.percent,
+                split: index.map(|index| splits.splits()[index].name.as_str()),
+                igt: format_hms(change.igt.duration),
+                video_time: format_video_time(change.video_time),
+                frame_index: change.frame_index,
+            });
+        }
+        Self { runner, milestones }
+    }
+
+    fn render_markdown(&self) -> String {
+        let mut markdown = format!(
+            "## {}\n\n| Percent | Split | IGT | Video time | Frame |\n|---:|---|---:|---:|---:|\n",
+            self.runner
+        );
+        for m in &self.milestones {
+            markdown.push_str(&format!(
+                "| {}% | {} | {} | {} | {} |\n",
+                m.percent,
+                m.split.unwrap_or(""),
+                m.igt,
+                m.video_time,
+                m.frame_index
+            ));
+        }
+        markdown
+    }
+}
+
+/// Checks that the report can be written in the format given by the file extension, so that this
+/// fails before processing the video.
+pub fn parse_report_path(s: &str) -> anyhow::Result<PathBuf> {
+    let path = PathBuf::from(s);
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json" | "md") => Ok(path),
+        _ => Err(anyhow!(
+            "Unsupported report format '{}': use .json or .md",
+            s
+        )),
+    }
+}
+
+/// Writes the video timestamp at which each percentage was first observed, per runner, to `path`
+/// as JSON (`.json`) or Markdown (`.md`), for moderators verifying the run against the video.
+pub fn write_report(path: &Path, runs: &[(&str, &Splits, &Retiming)]) -> anyhow::Result<()> {
+    let reports: Vec<RunReport> = runs
+        .iter()
+        .map(|(runner, splits, retiming)| RunReport::new(runner, splits, retiming))
+        .collect();
+
+    let contents = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::to_string_pretty(&reports)?,
+        Some("md") => reports
+            .iter()
+            .map(RunReport::render_markdown)
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => {
+            return Err(anyhow!(
+                "Unsupported report format {}: use .json or .md",
+                path.display()
+            ));
+        }
+    };
+    fs::write(path, contents)
+        .map_err(|e| anyhow!("Failed to write report {}: {}", path.display(), e))
+}
+
+/// Escapes the characters with a special meaning in FFmpeg metadata files.
+fn escape_ffmetadata(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Formats a video position as H:MM:SS.mmm
+pub fn format_video_time(time: Duration) -> String {
+    let secs = time.as_secs();
+    format!(
+        "{:01}:{:02}:{:02}.{:03}",
+        secs / 3600,
+        (secs % 3600) / 60,
+        secs % 60,
+        time.subsec_millis()
+    )
+}
+
+/// Parses a video position given as seconds, M:SS or H:MM:SS (each optionally with fractional seconds)
+pub fn parse_video_time(s: &str) -> anyhow::Result<Duration> {
+    let parts: Vec<&str> = s.trim().split(':').collect();
+    if parts.is_empty() || parts.len() > 3 {
+        anyhow::bail!("Invalid timestamp '{}': expected [[H:]MM:]SS[.mmm]", s);
+    }
+
+    let (seconds_str, rest) = parts.split_last().unwrap();
+    let seconds: f64 = seconds_str
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Invalid seconds '{}': {}", seconds_str, e))?;
+    if !seconds.is_finite() || seconds < 0.0 || (!rest.is_empty() && seconds >= 60.0) {
+        anyhow::bail!("Invalid seconds '{}' in timestamp '{}'", seconds_str, s);
+    }
+
+    let mut total = 0u64;
+    for (i, part) in rest.iter().enumerate() {
+        let value: u64 = part
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Invalid timestamp '{}': {}", s, e))?;
+        // Minutes are the last element before the seconds and must be < 60 if hours are given
+        if i > 0 && value >= 60 {
+            anyhow::bail!("Invalid minutes '{}' in timestamp '{}'", part, s);
+        }
+        total = total
+            .checked_mul(60)
+            .and_then(|total| total.checked_add(value))
+            .ok_or_else(|| anyhow::anyhow!("Timestamp '{}' is too large", s))?;
+    }
+
+    total
+        .checked_mul(60)
+        .map(Duration::from_secs)
+        .zip(Duration::try_from_secs_f64(seconds).ok())
+        .and_then(|(minutes, seconds)| minutes.checked_add(seconds))
+        .ok_or_else(|| anyhow::anyhow!("Timestamp '{}' is too large", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn igt(percent: u32, secs: u64) -> InGameTime {
+        InGameTime::from_secs(percent, secs)
+    }
+
+    #[test]
+    fn record_ignores_unchanged_igt() {
+        let mut retiming = Retiming::new();
+
+        assert!(retiming.record(0, Duration::ZERO, &igt(10, 60)).is_some());
+        assert!(
+            retiming
+                .record(1, Duration::from_millis(33), &igt(10, 60))
+                .is_none()
+        );
+        assert!(
+            retiming
+                .record(30, Duration::from_secs(1), &igt(10, 61))
+                .is_some()
+        );
+
+        assert_eq!(retiming.changes().len(), 2);
+        assert_eq!(retiming.changes()[1].frame_index, 30);
+    }
+
+    #[test]
+    fn report_lists_first_observation_of_each_percent() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("splits.json");
+        fs::write(
+            &path,
+            r#"{
+            "version": 1,
+            "splits": {
+                "splits": [
+                    { "name": "Sunny Villa", "percent": 2, "duration": null }
+                ]
+            }
+        }"#,
+        )
+        .unwrap();
+        let splits = Splits::load_from_file(&path).expect("splits should be valid");
+        let mut retiming = Retiming::new();
+        retiming.record(0, Duration::ZERO, &igt(1, 60));
+        retiming.record(30, Duration::from_secs(1), &igt(1, 61));
+        retiming.record(90, Duration::from_secs(3), &igt(2, 63));
+
+        let report = RunReport::new("Runner 1", &splits, &retiming);
+        assert_eq!(
+            report.render_markdown(),
+            "## Runner 1\n\n\
+             | Percent | Split | IGT | Video time | Frame |\n\
+             |---:|---|---:|---:|---:|\n\
+             | 1% |  | 0:01:00 | 0:00:00.000 | 0 |\n\
+             | 2% | Sunny Villa | 0:01:03 | 0:00:03.000 | 90 |\n"
+        );
+    }
+
+    #[test]
+    fn chapters_span_from_split_to_split() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("splits.json");
+        fs::write(
+            &path,
+            r#"{
+            "version": 1,
+            "splits": {
+                "splits": [
+                    { "name": "Sunny Villa", "percent": 2, "duration": null },
+                    { "name": "Cloud Spires", "percent": 4, "duration": null },
+                    { "name": "Molten; Crater", "percent": 6, "duration": null }
+                ]
+            }
+        }"#,
+        )
+        .unwrap();
+        let splits = Splits::load_from_file(&path).expect("splits should be valid");
+        let mut retiming = Retiming::new();
+        retiming.record(0, Duration::from_secs(5), &igt(1, 60));
+        retiming.record(90, Duration::from_secs(65), &igt(2, 120));
+        retiming.record(900, Duration::from_millis(3_725_500), &igt(6, 3780));
+
+        assert_eq!(
+            retiming.render_chapters(&splits, ChapterFormat::Youtube),
+            "0:00:00 Sunny Villa\n0:01:05 Molten; Crater\n"
+        );
+        assert_eq!(
+            retiming.render_chapters(&splits, ChapterFormat::Ffmpeg),
+            ";FFMETADATA1\n\
+             \n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=65000\ntitle=Sunny Villa\n\
+             \n[CHAPTER]\nTIMEBASE=1/1000\nSTART=65000\nEND=3725500\ntitle=Molten\\; Crater\n"
+        );
+    }
+
+    #[test]
+    fn splits_at_the_same_percent_are_told_apart_by_min_time() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("splits.json");
+        fs::write(
+            &path,
+            r#"{
+            "version": 3,
+            "splits": {
+                "personal_best": null,
+                "runs": [],
+                "splits": [
+                    { "name": "Sunny Villa", "percent": 2, "time": null, "history": [] },
+                    { "name": "Sunny Villa Skateboarding", "percent": 2, "min_time": "0:02:00", "time": null, "history": [] },
+                    { "name": "Cloud Spires", "percent": 4, "time": null, "history": [] }
+                ]
+            }
+        }"#,
+        )
+        .unwrap();
+        let splits = Splits::load_from_file(&path).expect("splits should be valid");
+        let mut retiming = Retiming::new();
+        retiming.record(0, Duration::from_secs(5), &igt(1, 50));
+        retiming.record(30, Duration::from_secs(15), &igt(2, 60));
+        retiming.record(60, Duration::from_secs(16), &igt(2, 61));
+        retiming.record(90, Duration::from_secs(85), &igt(2, 130));
+        retiming.record(120, Duration::from_secs(155), &igt(4, 200));
+
+        assert_eq!(
+            retiming.render_chapters(&splits, ChapterFormat::Youtube),
+            "0:00:00 Sunny Villa\n0:00:15 Sunny Villa Skateboarding\n0:01:25 Cloud Spires\n"
+        );
+        let report = RunReport::new("Runner 1", &splits, &retiming);
+        let rows: Vec<(u32, Option<&str>, &str)> = report
+            .milestones
+            .iter()
+            .map(|m| (m.percent, m.split, m.igt.as_str()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (1, None, "0:00:50"),
+                (2, Some("Sunny Villa"), "0:01:00"),
+                (2, Some("Sunny Villa Skateboarding"), "0:02:10"),
+                (4, Some("Cloud Spires"), "0:03:20"),
+            ]
+        );
+    }
+
+    #[test]
+    fn format_video_time_includes_millis() {
+        assert_eq!(
+            format_video_time(Duration::from_millis(3_723_045)),
+            "1:02:03.045"
+        );
+    }
+
+    #[test]
+    fn parse_video_time_accepts_all_formats() {
+        assert_eq!(parse_video_time("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(
+            parse_video_time("1:30.5").unwrap(),
+            Duration::from_millis(90_500)
+        );
+        assert_eq!(
+            parse_video_time("1:02:03").unwrap(),
+            Duration::from_secs(3723)
+        );
+    }
+
+    #[test]
+    fn parse_video_time_rejects_invalid_input() {
+        assert!(parse_video_time("").is_err());
+        assert!(parse_video_time("abc").is_err());
+        assert!(parse_video_time("1:60").is_err());
+        assert!(parse_video_time("1:60:00").is_err());
+        assert!(parse_video_time("1:2:3:4").is_err());
+        assert!(parse_video_time("-5").is_err());
+        assert!(parse_video_time("1e30").is_err());
+        assert!(parse_video_time("999999999999999999:00:00").is_err());
+    }
+}
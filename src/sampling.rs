@@ -0,0 +1,165 @@
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+
+/// Slower cadence used while the IGT isn't visible (i.e. the player isn't in the guidebook).
+struct IdleCadence {
+    interval: Duration,
+    after: Duration,
+}
+
+/// Decides which frames get OCR'd, based on their position in the source.
+pub struct Sampler {
+    interval: Duration,
+    idle: Option<IdleCadence>,
+    next_sample_at: Duration,
+    last_visible_at: Option<Duration>,
+}
+
+impl Sampler {
+    /// Samples at most `rate` frames per second (every frame if `None`).
+    pub fn new(rate: Option<f64>) -> Self {
+        Self {
+            interval: rate.map_or(Duration::ZERO, |rate| Duration::from_secs_f64(1.0 / rate)),
+            idle: None,
+            next_sample_at: Duration::ZERO,
+            last_visible_at: None,
+        }
+    }
+
+    /// Backs off to `idle_rate` frames per second once the IGT hasn't been visible for `idle_after`.
+    pub fn with_idle_rate(mut self, idle_rate: f64, idle_after: Duration) -> Self {
+        self.idle = Some(IdleCadence {
+            interval: Duration::from_secs_f64(1.0 / idle_rate),
+            after: idle_after,
+        });
+        self
+    }
+
+    /// Whether the sampler is currently in the slow idle cadence.
+    pub fn is_idle(&self, position: Duration) -> bool {
+        match &self.idle {
+            Some(idle) => self
+                .last_visible_at
+                .is_none_or(|seen| position.saturating_sub(seen) > idle.after),
+            None => false,
+        }
+    }
+
+    /// Reports whether the IGT was visible in the sampled frame at `position`.
+    pub fn report(&mut self, position: Duration, igt_visible: bool) {
+        if !igt_visible {
+            return;
+        }
+
+        if self.is_idle(position) {
+            // Switch back to the regular cadence right away
+            self.next_sample_at = position + self.interval;
+        }
+        self.last_visible_at = Some(position);
+    }
+
+    /// Returns whether the frame at `position` should be sampled.
+    pub fn should_sample(&mut self, position: Duration) -> bool {
+        if position < self.next_sample_at {
+            return false;
+        }
+
+        let interval = match &self.idle {
+            Some(idle) if self.is_idle(position) => idle.interval,
+            _ => self.interval,
+        };
+
+        // Keep a steady cadence, but don't try to catch up if we fell behind (e.g. after a seek)
+        self.next_sample_at += interval;
+        if self.next_sample_at <= position {
+            self.next_sample_at = position + interval;
+        }
+        true
+    }
+}
+
+/// Parses a sample rate in frames per second, which must be positive and not so small that the
+/// interval between samples doesn't fit in a `Duration`.
+pub fn parse_sample_rate(s: &str) -> Result<f64> {
+    let rate: f64 = s
+        .parse()
+        .map_err(|e| anyhow!("Invalid sample rate '{}': {}", s, e))?;
+    if !rate.is_finite() || rate <= 0.0 {
+        return Err(anyhow!("Sample rate must be positive, got '{}'", s));
+    }
+    if Duration::try_from_secs_f64(1.0 / rate).is_err() {
+        return Err(anyhow!("Sample rate '{}' is too small", s));
+    }
+    Ok(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn samples_every_frame_without_rate() {
+        let mut sampler = Sampler::new(None);
+        assert!(sampler.should_sample(ms(0)));
+        assert!(sampler.should_sample(ms(0)));
+        assert!(sampler.should_sample(ms(16)));
+    }
+
+    #[test]
+    fn limits_samples_to_rate() {
+        let mut sampler = Sampler::new(Some(5.0));
+
+        // 60 fps source for one second
+        let sampled = (0..60)
+            .filter(|i| sampler.should_sample(ms(i * 1000 / 60)))
+            .count();
+        assert_eq!(sampled, 5);
+    }
+
+    #[test]
+    fn does_not_catch_up_after_jump() {
+        let mut sampler = Sampler::new(Some(1.0));
+        assert!(sampler.should_sample(ms(0)));
+        assert!(sampler.should_sample(ms(10_000)));
+        assert!(!sampler.should_sample(ms(10_500)));
+        assert!(sampler.should_sample(ms(11_000)));
+    }
+
+    #[test]
+    fn backs_off_while_igt_is_not_visible() {
+        let mut sampler = Sampler::new(None).with_idle_rate(2.0, Duration::from_secs(5));
+
+        // Idle from the start
+        assert!(sampler.is_idle(ms(0)));
+        assert!(sampler.should_sample(ms(0)));
+        assert!(!sampler.should_sample(ms(100)));
+        assert!(sampler.should_sample(ms(500)));
+
+        // IGT shows up: sample every frame
+        sampler.report(ms(500), true);
+        assert!(!sampler.is_idle(ms(516)));
+        assert!(sampler.should_sample(ms(516)));
+        assert!(sampler.should_sample(ms(533)));
+
+        // IGT disappears: back off after the timeout
+        sampler.report(ms(533), false);
+        assert!(!sampler.is_idle(ms(5_000)));
+        assert!(sampler.is_idle(ms(5_600)));
+        assert!(sampler.should_sample(ms(5_600)));
+        assert!(!sampler.should_sample(ms(5_700)));
+    }
+
+    #[test]
+    fn parse_sample_rate_rejects_non_positive() {
+        assert_eq!(parse_sample_rate("2.5").unwrap(), 2.5);
+        assert!(parse_sample_rate("0").is_err());
+        assert!(parse_sample_rate("-1").is_err());
+        assert!(parse_sample_rate("fast").is_err());
+        assert!(parse_sample_rate("1e-20").is_err());
+    }
+}
@@ -0,0 +1,164 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use opencv::core::{Mat, Rect};
+use opencv::prelude::*;
+
+use crate::in_game_time::InGameTime;
+use crate::ocr::{self, Recognizer, TemplateMatch, Templates, binarize_roi};
+use crate::preprocess::{self, ColorKey, Deinterlace, Preprocessing};
+use crate::{DEFAULT_ROI, OcrEngine, ROI_DETECTION_WINDOW, create_engine, parse_roi};
+
+/// OCR settings of the subcommands reading screenshots or videos outside of tracking.
+#[derive(clap::Args, Debug, Clone)]
+pub struct ScreenshotOptions {
+    /// Region of the images containing the IGT, as X,Y,WIDTH,HEIGHT [default: detected automatically]
+    #[arg(long, value_name = "X,Y,W,H", value_parser = parse_roi)]
+    pub roi: Option<Rect>,
+
+    /// Preprocessing of the ROI (see the top-level --preprocess)
+    #[arg(long, value_name = "STEPS", value_parser = preprocess::parse_preprocessing, default_value = "otsu")]
+    pub preprocess: Preprocessing,
+
+    /// Deinterlace the images before preprocessing
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub deinterlace: Option<Deinterlace>,
+
+    /// Color key applied to the images instead of converting them to grayscale
+    #[arg(long, value_name = "H,S,V-H,S,V", value_parser = preprocess::parse_color_key)]
+    pub color_key: Option<ColorKey>,
+
+    /// Directory with the character templates [default: ./templates, or next to the executable]
+    #[arg(long, value_name = "DIR")]
+    pub templates_dir: Option<PathBuf>,
+
+    /// Use the templates in this subdirectory of the templates directory
+    #[arg(long, value_name = "NAME")]
+    pub template_set: Option<String>,
+
+    /// OCR engine used to read the IGT
+    #[arg(long, value_enum, default_value_t = OcrEngine::Templates)]
+    pub ocr_engine: OcrEngine,
+}
+
+/// Result of reading the IGT from a screenshot.
+pub struct ScreenshotReading {
+    pub roi: Rect,
+    pub matches: Vec<TemplateMatch>,
+    pub result: Result<InGameTime>,
+}
+
+/// Runs the same ROI / preprocessing / OCR pipeline as tracking on single screenshots.
+pub struct ScreenshotReader {
+    roi: Option<Rect>,
+    preprocessing: Preprocessing,
+    templates: Templates,
+    engine: Option<Box<dyn Recognizer>>,
+}
+
+impl ScreenshotReader {
+    pub fn new(options: &ScreenshotOptions) -> Result<Self> {
+        let templates_dir = options
+            .templates_dir
+            .clone()
+            .unwrap_or_else(Templates::default_dir);
+        let templates = Templates::load(&templates_dir, options.template_set.as_deref())?;
+        let engine = create_engine(options.ocr_engine, &templates)?;
+
+        Ok(Self {
+            roi: options.roi,
+            preprocessing: options
+                .preprocess
+                .clone()
+                .with_deinterlace(options.deinterlace)
+                .with_color_key(options.color_key),
+            templates,
+            engine,
+        })
+    }
+
+    /// Returns the configured ROI, or else the one detected in `image` (or the default one).
+    pub fn roi(&self, image: &Mat) -> Result<Rect> {
+        Ok(match self.roi {
+            Some(roi) => roi,
+            None => ocr::detect_roi(
+                image,
+                &self.templates,
+                &self.preprocessing,
+                ROI_DETECTION_WINDOW,
+            )?
+            .unwrap_or(DEFAULT_ROI),
+        })
+    }
+
+    pub fn templates(&self) -> &Templates {
+        &self.templates
+    }
+
+    pub fn binarize(&self, image: &Mat, roi: Rect) -> Result<Mat> {
+        binarize_roi(image, roi, &self.preprocessing, false)
+    }
+
+    pub fn recognize(
+        &mut self,
+        binarized_roi: &Mat,
+        matches: &mut Vec<TemplateMatch>,
+    ) -> Result<InGameTime> {
+        let recognizer: &mut dyn Recognizer = match &mut self.engine {
+            Some(engine) => engine.as_mut(),
+            None => &mut self.templates,
+        };
+        recognizer.recognize(binarized_roi, matches)
+    }
+
+    /// Reads the IGT from `image`, in the configured ROI or else the detected (or default) one.
+    pub fn read(&mut self, image: &Mat) -> Result<ScreenshotReading> {
+        let roi = self.roi(image)?;
+        let binarized_roi = self.binarize(image, roi)?;
+
+        let mut matches = Vec::new();
+        let result = self.recognize(&binarized_roi, &mut matches);
+        Ok(ScreenshotReading {
+            roi,
+            matches,
+            result,
+        })
+    }
+}
+
+pub fn load_image(path: &Path) -> Result<Mat> {
+    let image =
+        opencv::imgcodecs::imread(&path.to_string_lossy(), opencv::imgcodecs::IMREAD_COLOR)?;
+    if image.empty() {
+        return Err(anyhow!("Failed to load image {}", path.display()));
+    }
+    Ok(image)
+}
+
+/// Reads the IGT from the screenshot at `path` and prints the ROI, the matches and the result.
+pub fn read_image(path: &Path, options: &ScreenshotOptions) -> Result<()> {
+    let mut reader = ScreenshotReader::new(options)?;
+    let reading = reader.read(&load_image(path)?)?;
+
+    let roi = reading.roi;
+    println!("ROI: {},{},{},{}", roi.x, roi.y, roi.width, roi.height);
+    println!("Matches:");
+    for m in &reading.matches {
+        println!(
+            "  '{}' at {},{} ({}x{}): confidence {:.3}, threshold {:.3}",
+            m.character,
+            m.x,
+            m.y,
+            m.bounding_box.width,
+            m.bounding_box.height,
+            m.confidence,
+            m.threshold
+        );
+    }
+
+    let igt = reading
+        .result
+        .map_err(|e| anyhow!("Failed to read IGT: {}", e))?;
+    println!("IGT: {}", igt);
+    Ok(())
+}
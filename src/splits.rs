@@ -1,7 +1,31 @@
+mod background_save;
+mod event_sink;
+mod events;
 mod file_persistency;
+mod http_api;
+mod import;
+mod livesplit_server;
+mod report;
+mod run_log;
 mod splits;
+#[cfg(feature = "sync")]
+mod sync;
+mod websocket;
+mod wizard;
 
-pub use splits::Splits;
+pub use background_save::{BackgroundSave, DEFAULT_SAVE_INTERVAL};
+pub use event_sink::EventSink;
+pub use events::{TimerEvent, TimerObserver};
+pub use http_api::HttpApi;
+pub use import::{ImportFormat, import_splits};
+pub use livesplit_server::LiveSplitServer;
+pub use report::{ReportFormat, render_report};
+pub use run_log::RunLog;
+pub use splits::{Comparison, Splits};
+#[cfg(feature = "sync")]
+pub use sync::{PullOutcome, Remote, RemoteSync, parse_remote};
+pub use websocket::WebSocketBroadcast;
+pub use wizard::{create_splits_file, parse_split};
 
 use colored::{Color, Colorize};
 use std::time::Duration;
@@ -9,21 +33,73 @@ use uuid::Uuid;
 
 use crate::in_game_time::InGameTime;
 
+/// Color of the deltas of best segments (golds)
+const GOLD: Color = Color::TrueColor {
+    r: 255,
+    g: 227,
+    b: 0,
+};
+
 pub struct SplitsDisplay {
+    comparison: Comparison,
+    /// Comparison to return to when leaving the race against the golds
+    comparison_before_golds: Option<Comparison>,
+    real_time: bool,
     last_run_id: Option<Uuid>,
-    pb_snapshot: Vec<Option<Duration>>,
+    comparison_snapshot: Vec<Option<Duration>>,
     best_segs_snapshot: Vec<Option<Duration>>,
 }
 
 impl SplitsDisplay {
     pub fn new() -> Self {
         Self {
+            comparison: Comparison::default(),
+            comparison_before_golds: None,
+            real_time: false,
             last_run_id: None,
-            pb_snapshot: Vec::new(),
+            comparison_snapshot: Vec::new(),
             best_segs_snapshot: Vec::new(),
         }
     }
 
+    /// Computes the deltas against `comparison` instead of the PB
+    pub fn with_comparison(mut self, comparison: Comparison) -> Self {
+        self.comparison = comparison;
+        self
+    }
+
+    /// Adds a column with the wall clock time of the run (RTA) next to the IGT
+    pub fn with_real_time(mut self) -> Self {
+        self.real_time = true;
+        self
+    }
+
+    pub fn comparison(&self) -> Comparison {
+        self.comparison
+    }
+
+    /// Switches to `comparison` during a run, taking the comparison times from `splits` as they are.
+    pub fn set_comparison(&mut self, splits: &Splits, comparison: Comparison) {
+        self.comparison = comparison;
+        if self.last_run_id.is_some() {
+            self.comparison_snapshot = splits.comparison_times(comparison);
+        }
+    }
+
+    /// Switches to racing against the golds (the best segments comparison) and back to the previous
+    /// comparison. Returns the comparison switched to.
+    pub fn toggle_golds(&mut self, splits: &Splits) -> Comparison {
+        let comparison = match self.comparison {
+            Comparison::BestSegments => self.comparison_before_golds.take().unwrap_or_default(),
+            previous => {
+                self.comparison_before_golds = Some(previous);
+                Comparison::BestSegments
+            }
+        };
+        self.set_comparison(splits, comparison);
+        comparison
+    }
+
     /// Render a split view of given `window_size` lines centered around the current split
     pub fn render_split_view(
         &mut self,
@@ -31,12 +107,12 @@ impl SplitsDisplay {
         current_igt: &InGameTime,
         window_size: usize,
     ) -> Vec<String> {
-        // --- 1. Detect run start & snapshot PBs and best segments ---
+        // --- 1. Detect run start & snapshot the comparison and best segments ---
         // TODO: I believe this doesn't work with the first split => initialize also when creating SplitsDisplay?
         if let Some(active_run) = splits.active_run() {
             if Some(active_run.id) != self.last_run_id {
                 self.last_run_id = Some(active_run.id);
-                self.pb_snapshot = splits.splits().iter().map(|s| s.time).collect();
+                self.comparison_snapshot = splits.comparison_times(self.comparison);
                 self.best_segs_snapshot = splits.splits().iter().map(|s| s.best_segment).collect();
             }
         }
@@ -47,11 +123,8 @@ impl SplitsDisplay {
             return Vec::new();
         }
 
-        // Find the split index corresponding to the current IGT percent
-        let current_index = all_splits
-            .iter()
-            .position(|s| s.percent == current_igt.percent);
-        let current_index = match current_index {
+        // Find the split index corresponding to the current IGT
+        let current_index = match splits.find_split_index(current_igt) {
             Some(idx) => idx,
             None => return Vec::new(),
         };
@@ -69,10 +142,15 @@ impl SplitsDisplay {
         // --- 4. Format rows ---
         let name_width = splits.compute_name_width();
         let mut lines = Vec::new();
+        let segments = self
+            .last_run_id
+            .map(|run_id| splits.segment_times(run_id))
+            .unwrap_or_default();
+        let comparison_segments = Splits::segments(&self.comparison_snapshot);
 
         for idx in start..end {
             let split = &all_splits[idx];
-            let pb_time = self.pb_snapshot.get(idx).copied().unwrap_or(None);
+            let comparison_time = self.comparison_snapshot.get(idx).copied().unwrap_or(None);
 
             let (time, delta) = if idx < current_index {
                 // Past split
@@ -81,21 +159,21 @@ impl SplitsDisplay {
                     .iter()
                     .find(|h| Some(h.run_id) == self.last_run_id)
                     .map(|h| h.duration);
-                let delta = match (run_time, pb_time) {
-                    (Some(rt), Some(pb)) => Some(rt.as_secs() as i64 - pb.as_secs() as i64),
+                let delta = match (run_time, comparison_time) {
+                    (Some(rt), Some(ct)) => Some(rt.as_secs() as i64 - ct.as_secs() as i64),
                     _ => None,
                 };
                 (run_time, delta)
             } else if idx == current_index {
                 // Current split
-                let delta = match pb_time {
-                    Some(pb) => Some(current_igt.duration.as_secs() as i64 - pb.as_secs() as i64),
+                let delta = match comparison_time {
+                    Some(ct) => Some(current_igt.duration.as_secs() as i64 - ct.as_secs() as i64),
                     None => None,
                 };
                 (Some(current_igt.duration), delta)
             } else {
                 // Future split
-                (pb_time, None)
+                (comparison_time, None)
             };
 
             // Check for golds
@@ -114,44 +192,115 @@ impl SplitsDisplay {
                 Splits::pad_str(&truncated, name_width)
             };
 
-            // Format time
-            let time_fmt = Splits::format_time(time);
+            // Format time, a skipped split has none
+            let skipped = idx < current_index
+                && self
+                    .last_run_id
+                    .is_some_and(|run_id| splits.is_skipped(run_id, idx));
+            let time_fmt = if skipped {
+                String::from("-")
+            } else {
+                Splits::format_time(time)
+            };
 
             // Format delta
             let delta_fmt = match delta {
-                Some(d) if gold => {
-                    if d >= 0 {
-                        format!("+{:02}:{:02}", d / 60, d % 60).color(Color::TrueColor {
-                            r: 255,
-                            g: 227,
-                            b: 0,
-                        })
-                    } else {
-                        format!("-{:02}:{:02}", (-d) / 60, (-d) % 60).color(Color::TrueColor {
-                            r: 255,
-                            g: 227,
-                            b: 0,
-                        })
-                    }
-                }
-                Some(d) if d >= 0 => format!("+{:02}:{:02}", d / 60, d % 60).red(),
-                Some(d) if d < 0 => format!("-{:02}:{:02}", (-d) / 60, (-d) % 60).green(),
+                Some(d) if gold => format_delta(d).color(GOLD),
+                Some(d) if d >= 0 => format_delta(d).red(),
+                Some(d) if d < 0 => format_delta(d).green(),
+                // A gold without a PB to compare to (e.g. the first run)
+                None if gold => String::from("  gold").color(GOLD),
                 _ => String::from("      ").white(),
             };
 
-            lines.push(format!("{} {:>8} {:>8}", name_fmt, delta_fmt, time_fmt));
+            // Format segment time, colored by the comparison's segment. Only known up to the
+            // current split, whose segment is still running.
+            let segment = segments
+                .get(idx)
+                .copied()
+                .flatten()
+                .filter(|_| idx <= current_index);
+            let comparison_segment = comparison_segments.get(idx).copied().flatten();
+            let segment_fmt = match (segment, comparison_segment) {
+                (Some(s), _) if gold => Splits::format_time(Some(s)).color(GOLD),
+                (Some(s), Some(c)) if s <= c => Splits::format_time(Some(s)).green(),
+                (Some(s), Some(_)) => Splits::format_time(Some(s)).red(),
+                (Some(s), None) => Splits::format_time(Some(s)).white(),
+                (None, _) => String::new().white(),
+            };
+
+            let mut line = format!(
+                "{} {:>8} {:>8} {:>8}",
+                name_fmt, delta_fmt, time_fmt, segment_fmt
+            );
+            if self.real_time {
+                // Wall clock time of the run, as recorded or up to now for the current split
+                let real_time = self.last_run_id.and_then(|run_id| {
+                    if idx < current_index {
+                        split
+                            .history
+                            .iter()
+                            .find(|h| h.run_id == run_id)
+                            .and_then(|h| h.real_time)
+                    } else if idx == current_index {
+                        splits.real_time_of(run_id)
+                    } else {
+                        None
+                    }
+                });
+                let real_time_fmt = match real_time {
+                    Some(real_time) => format!("RTA {}", Splits::format_time(Some(real_time))),
+                    None => String::new(),
+                };
+                line.push_str(&format!(" {:>11}", real_time_fmt));
+            }
+            lines.push(line);
         }
 
         // --- 5. Append BPT ---
-        let bpt = splits.best_possible_time();
         // Blank line to separate splits from BPT
         lines.push(String::new());
+        lines.push(splits.format_best_possible_time(name_width));
 
-        let name_width = splits.compute_name_width();
-        let name_fmt = Splits::pad_str("BPT:", name_width);
-        let time_fmt = Splits::format_time(bpt);
-        lines.push(format!("{} {:>8} {:>8}", name_fmt, "      ", time_fmt));
+        // Racing against the golds: pace compared to the sum of best segments up to the current
+        // split, and the sum of best (the golds' final time)
+        if self.comparison == Comparison::BestSegments {
+            let pace = self
+                .comparison_snapshot
+                .get(current_index)
+                .copied()
+                .flatten()
+                .map(|sob| current_igt.duration.as_secs() as i64 - sob.as_secs() as i64);
+            let pace_fmt = match pace {
+                Some(d) if d > 0 => format_delta(d).red(),
+                Some(d) => format_delta(d).green(),
+                None => String::from("      ").white(),
+            };
+            lines.push(format!(
+                "{} {:>8} {:>8}",
+                Splits::pad_str("SoB pace:", name_width),
+                pace_fmt,
+                Splits::format_time(splits.sum_of_bests())
+            ));
+        }
+
+        // --- 6. Append the notes of the upcoming split ---
+        if let Some(next) = all_splits.get(current_index + 1)
+            && let Some(notes) = &next.notes
+        {
+            lines.push(String::new());
+            lines.push(format!("Next: {}", next.name).bold().to_string());
+            lines.extend(notes.lines().map(|line| format!("  {}", line)));
+        }
 
         lines
     }
 }
+
+/// Formats a delta in seconds as +mm:ss or -mm:ss
+fn format_delta(delta: i64) -> String {
+    match delta {
+        d if d >= 0 => format!("+{:02}:{:02}", d / 60, d % 60),
+        d => format!("-{:02}:{:02}", (-d) / 60, (-d) % 60),
+    }
+}
@@ -0,0 +1,295 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::splits::file_persistency;
+use crate::splits::{Splits, TimerEvent, TimerObserver};
+
+/// Default interval of the saves of intermediate changes, see `BackgroundSave::new`
+pub const DEFAULT_SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+enum SaveRequest {
+    Save {
+        path: PathBuf,
+        json: String,
+        /// Written right away instead of after the interval
+        now: bool,
+    },
+    /// Write the pending saves now and report back when done
+    Flush(Sender<()>),
+    /// Drop the pending save of the file
+    Discard(PathBuf),
+}
+
+/// Saves the splits to their file whenever a run changed, on a writer thread so that the capture
+/// loop doesn't wait for the disk. Pending saves are written on `flush` and when dropped, and
+/// dropped when the splits are reloaded.
+pub struct BackgroundSave {
+    sender: Option<Sender<SaveRequest>>,
+    writer: Option<JoinHandle<()>>,
+    /// Run and index of the split recorded last
+    last_split: Option<(Uuid, usize)>,
+}
+
+impl BackgroundSave {
+    /// New splits, started, reset and finished runs and PBs are saved right away. Other changes,
+    /// e.g. the time of the current split going up while the guidebook stays open, are saved at
+    /// most every `interval`, coalescing the changes in between.
+    pub fn new(interval: Duration) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let writer = thread::spawn(move || write_saves(receiver, interval));
+        Self {
+            sender: Some(sender),
+            writer: Some(writer),
+            last_split: None,
+        }
+    }
+
+    /// Whether `event` is saved right away rather than after the interval
+    fn saves_now(&mut self, event: &TimerEvent) -> bool {
+        match event {
+            TimerEvent::SplitRecorded { run_id, index, .. } => {
+                self.last_split.replace((*run_id, *index)) != Some((*run_id, *index))
+            }
+            // Recorded along with its split
+            TimerEvent::GoldAchieved { .. } => false,
+            TimerEvent::RunStarted { .. }
+            | TimerEvent::RunFinished { .. }
+            | TimerEvent::RunReset { .. }
+            | TimerEvent::PbSet { .. } => true,
+        }
+    }
+
+    fn send(&self, request: SaveRequest) -> bool {
+        self.sender
+            .as_ref()
+            .is_some_and(|sender| sender.send(request).is_ok())
+    }
+}
+
+impl TimerObserver for BackgroundSave {
+    fn on_event(&mut self, splits: &Splits, event: &TimerEvent) {
+        let now = self.saves_now(event);
+        // Serialized here, the splits can't be shared with the writer
+        let request = splits
+            .path()
+            .ok_or_else(|| anyhow::anyhow!("No file path to save to"))
+            .and_then(|path| {
+                Ok(SaveRequest::Save {
+                    path: path.clone(),
+                    json: file_persistency::to_json(splits)?,
+                    now,
+                })
+            });
+        match request {
+            Ok(request) => {
+                if !self.send(request) {
+                    println!("Failed to save the splits: the writer stopped");
+                }
+            }
+            Err(e) => println!("Failed to save the splits: {}", e),
+        }
+    }
+
+    fn flush(&mut self) {
+        let (done, wait) = mpsc::channel();
+        if self.send(SaveRequest::Flush(done)) {
+            let _ = wait.recv();
+        }
+    }
+
+    fn on_reload(&mut self, splits: &Splits) {
+        self.last_split = None;
+        if let Some(path) = splits.path() {
+            self.send(SaveRequest::Discard(path.clone()));
+        }
+    }
+}
+
+impl Drop for BackgroundSave {
+    fn drop(&mut self) {
+        // The writer writes the pending saves once the channel is closed
+        self.sender = None;
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+/// The latest save per file, due `interval` after the first one since the last write
+struct PendingSaves {
+    saves: Vec<(PathBuf, String)>,
+    deadline: Instant,
+    interval: Duration,
+}
+
+impl PendingSaves {
+    fn new(interval: Duration) -> Self {
+        Self {
+            saves: Vec::new(),
+            deadline: Instant::now(),
+            interval,
+        }
+    }
+
+    /// Replaces the pending save of the file, made at `at`
+    fn add(&mut self, path: PathBuf, json: String, at: Instant) {
+        if self.saves.is_empty() {
+            self.deadline = at + self.interval;
+        }
+        match self.saves.iter_mut().find(|(p, _)| *p == path) {
+            Some(save) => save.1 = json,
+            None => self.saves.push((path, json)),
+        }
+    }
+
+    fn discard(&mut self, path: &Path) {
+        self.saves.retain(|(p, _)| p != path);
+    }
+
+    /// Time left at `at` until the pending saves are due, `None` if there are none
+    fn time_left(&self, at: Instant) -> Option<Duration> {
+        (!self.saves.is_empty()).then(|| self.deadline.saturating_duration_since(at))
+    }
+
+    fn take(&mut self) -> Vec<(PathBuf, String)> {
+        std::mem::take(&mut self.saves)
+    }
+}
+
+/// Writes the latest save per file `interval` after the first pending one, right away if a save
+/// asks for it, or when flushed.
+fn write_saves(receiver: Receiver<SaveRequest>, interval: Duration) {
+    let mut pending = PendingSaves::new(interval);
+    loop {
+        let request = match pending.time_left(Instant::now()) {
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            Some(time_left) => receiver.recv_timeout(time_left),
+        };
+        match request {
+            Ok(SaveRequest::Save { path, json, now }) => {
+                pending.add(path, json, Instant::now());
+                if now {
+                    write_pending(&mut pending);
+                }
+            }
+            Ok(SaveRequest::Flush(done)) => {
+                write_pending(&mut pending);
+                let _ = done.send(());
+            }
+            Ok(SaveRequest::Discard(path)) => pending.discard(&path),
+            Err(RecvTimeoutError::Timeout) => write_pending(&mut pending),
+            Err(RecvTimeoutError::Disconnected) => {
+                write_pending(&mut pending);
+                return;
+            }
+        }
+    }
+}
+
+fn write_pending(pending: &mut PendingSaves) {
+    for (path, json) in pending.take() {
+        if let Err(e) = file_persistency::write_to_file(&json, &path) {
+            println!("Failed to save the splits: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    use crate::in_game_time::InGameTime;
+
+    use crate::splits::splits::Split;
+
+    fn make_splits(path: PathBuf) -> Splits {
+        Splits::create(
+            path,
+            vec![Split {
+                name: "A".into(),
+                percent: 10,
+                min_time: None,
+                time: None,
+                best_segment: None,
+                notes: None,
+                history: Vec::new(),
+            }],
+        )
+        .unwrap()
+    }
+
+    fn wait_for(path: &Path) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !path.exists() {
+            if Instant::now() > deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        true
+    }
+
+    #[test]
+    fn new_splits_are_saved_right_away_and_updates_after_the_interval() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("splits.json");
+        let splits = make_splits(path.clone());
+        let run_id = Uuid::new_v4();
+        let recorded = TimerEvent::SplitRecorded {
+            run_id,
+            index: 0,
+            time: InGameTime::from_secs(10, 60),
+        };
+
+        let mut save = BackgroundSave::new(Duration::from_secs(60));
+        save.on_event(&splits, &recorded);
+        assert!(wait_for(&path));
+
+        // The time of the same split went up, left pending (see the test of `PendingSaves`)
+        std::fs::remove_file(&path).unwrap();
+        assert!(!save.saves_now(&recorded));
+        save.on_event(&splits, &recorded);
+        save.flush();
+        assert_eq!(Splits::load_from_file(&path).unwrap(), splits);
+
+        std::fs::remove_file(&path).unwrap();
+        save.on_event(&splits, &recorded);
+        drop(save);
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn pending_saves_are_due_an_interval_after_the_first_one() {
+        let interval = Duration::from_secs(60);
+        let (a, b) = (PathBuf::from("a.json"), PathBuf::from("b.json"));
+        let start = Instant::now();
+        let mut pending = PendingSaves::new(interval);
+        assert_eq!(pending.time_left(start), None);
+
+        pending.add(a.clone(), "1".into(), start);
+        assert_eq!(pending.time_left(start), Some(interval));
+        // Coalesced, without pushing the deadline back
+        let later = start + Duration::from_secs(30);
+        pending.add(a.clone(), "2".into(), later);
+        pending.add(b.clone(), "3".into(), later);
+        assert_eq!(pending.time_left(later), Some(Duration::from_secs(30)));
+        assert_eq!(pending.time_left(start + interval), Some(Duration::ZERO));
+        assert_eq!(
+            pending.take(),
+            vec![(a.clone(), "2".into()), (b.clone(), "3".into())]
+        );
+        assert_eq!(pending.time_left(start + interval), None);
+
+        // The next save starts a new interval
+        let after = start + interval * 2;
+        pending.add(a.clone(), "4".into(), after);
+        assert_eq!(pending.time_left(after), Some(interval));
+        pending.discard(&a);
+        assert_eq!(pending.time_left(after), None);
+    }
+}
@@ -0,0 +1,224 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+
+use anyhow::{Result, anyhow};
+use serde_json::json;
+
+use crate::splits::{Splits, TimerEvent, TimerObserver};
+
+type Clients = Arc<Mutex<Vec<Sender<String>>>>;
+
+struct Sink {
+    path: PathBuf,
+    clients: Clients,
+}
+
+impl Drop for Sink {
+    fn drop(&mut self) {
+        // Named pipes go away with their last handle
+        if cfg!(unix) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Publishes the timer events as JSON lines (see `TimerEvent::to_json`, with the `run_id` added) to
+/// every client of a Unix socket, or of a named pipe on Windows (e.g. `\\.\pipe\srt-igt-splits`),
+/// for local scripts reacting to splits without networking, e.g. `socat - UNIX-CONNECT:PATH`.
+/// Clones publish to the same clients, e.g. for the tracker of another game.
+#[derive(Clone)]
+pub struct EventSink {
+    sink: Arc<Sink>,
+}
+
+impl EventSink {
+    /// Accepts clients at `path` on a thread of its own
+    pub fn start(path: PathBuf) -> Result<Self> {
+        let clients = Clients::default();
+        listen(&path, clients.clone())
+            .map_err(|e| anyhow!("Failed to listen on {} for events: {}", path.display(), e))?;
+        Ok(Self {
+            sink: Arc::new(Sink { path, clients }),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.sink.path
+    }
+}
+
+/// Writes the lines published to the new client `stream` on a thread of its own, so that a client
+/// that doesn't read can't hold up the others
+fn add_client(clients: &Clients, mut stream: impl Write + Send + 'static) {
+    let (sender, receiver) = mpsc::channel::<String>();
+    clients
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(sender);
+    thread::spawn(move || {
+        for line in receiver {
+            if stream
+                .write_all(line.as_bytes())
+                .and_then(|_| stream.flush())
+                .is_err()
+            {
+                // Disconnected, dropped at the next event
+                return;
+            }
+        }
+    });
+}
+
+#[cfg(unix)]
+fn listen(path: &Path, clients: Clients) -> std::io::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixListener;
+
+    // Left behind by a session that didn't shut down cleanly, other files are kept
+    if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            add_client(&clients, stream);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(windows)]
+fn listen(path: &Path, clients: Clients) -> std::io::Result<()> {
+    use std::fs::File;
+    use std::io;
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::io::{AsRawHandle, FromRawHandle};
+    use std::ptr;
+
+    use windows_sys::Win32::Foundation::{ERROR_PIPE_CONNECTED, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::Storage::FileSystem::PIPE_ACCESS_OUTBOUND;
+    use windows_sys::Win32::System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE,
+        PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+    };
+
+    let name: Vec<u16> = path.as_os_str().encode_wide().chain([0]).collect();
+    // One instance per client, the next one is created once a client connected
+    let create = move || -> io::Result<File> {
+        // SAFETY: `name` is a NUL-terminated wide string that outlives the call
+        let handle = unsafe {
+            CreateNamedPipeW(
+                name.as_ptr(),
+                PIPE_ACCESS_OUTBOUND,
+                PIPE_TYPE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                4096,
+                0,
+                0,
+                ptr::null(),
+            )
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the handle was just created and is owned by the file from now on
+        Ok(unsafe { File::from_raw_handle(handle) })
+    };
+
+    // Created here, an invalid pipe name is reported right away
+    let mut pipe = create()?;
+    thread::spawn(move || {
+        loop {
+            // SAFETY: `pipe` is an open named pipe, waited for synchronously
+            let connected = unsafe { ConnectNamedPipe(pipe.as_raw_handle(), ptr::null_mut()) }
+                != 0
+                // Connected between creating the pipe and waiting for a client
+                || io::Error::last_os_error().raw_os_error() == Some(ERROR_PIPE_CONNECTED as i32);
+            let next = match create() {
+                Ok(next) => next,
+                Err(e) => {
+                    println!("Failed to wait for event clients: {}", e);
+                    return;
+                }
+            };
+            let client = std::mem::replace(&mut pipe, next);
+            if connected {
+                add_client(&clients, client);
+            }
+        }
+    });
+    Ok(())
+}
+
+impl TimerObserver for EventSink {
+    fn on_event(&mut self, splits: &Splits, event: &TimerEvent) {
+        let mut entry = event.to_json(splits);
+        entry["run_id"] = json!(event.run_id());
+        let line = format!("{}\n", entry);
+        self.sink
+            .clients
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|client| client.send(line.clone()).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use tempfile::tempdir;
+
+    use crate::in_game_time::InGameTime;
+    use crate::splits::splits::Split;
+
+    #[cfg(unix)]
+    #[test]
+    fn events_are_published_as_json_lines() {
+        use std::io::{BufRead, BufReader};
+        use std::os::unix::net::UnixStream;
+
+        let dir = tempdir().unwrap();
+        let mut splits = Splits::create(
+            dir.path().join("splits.json"),
+            vec![Split {
+                name: "Sunny Villa".into(),
+                percent: 10,
+                min_time: None,
+                time: None,
+                best_segment: None,
+                notes: None,
+                history: Vec::new(),
+            }],
+        )
+        .unwrap();
+        let path = dir.path().join("events.sock");
+        let mut sink = EventSink::start(path.clone()).unwrap();
+        let client = UnixStream::connect(&path).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while sink.sink.clients.lock().unwrap().is_empty() {
+            assert!(Instant::now() < deadline, "the client wasn't accepted");
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let events = splits.update_with_igt(&InGameTime::from_secs(10, 60));
+        for event in &events {
+            sink.on_event(&splits, event);
+        }
+        let mut lines = BufReader::new(client).lines();
+        let started: serde_json::Value =
+            serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(started["event"], "run_started");
+        assert_eq!(started["run_id"], json!(splits.runs()[0].id));
+        let split: serde_json::Value =
+            serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(split["split"], "Sunny Villa");
+
+        drop(sink);
+        assert!(!path.exists());
+    }
+}
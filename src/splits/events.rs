@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::in_game_time::InGameTime;
+use crate::splits::Splits;
+
+/// What happened to the runs when an IGT (or a trigger) was fed into the splits, see
+/// `Splits::update_with_igt`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimerEvent {
+    /// A new run started
+    RunStarted { run_id: Uuid },
+    /// The split at `index` was recorded at `time`, or its time was updated
+    SplitRecorded {
+        run_id: Uuid,
+        index: usize,
+        time: InGameTime,
+    },
+    /// The segment ending in the split at `index` is a new best segment (gold)
+    GoldAchieved {
+        run_id: Uuid,
+        index: usize,
+        segment: Duration,
+    },
+    /// The run reached the final split
+    RunFinished { run_id: Uuid, final_time: Duration },
+    /// The unfinished run was abandoned because the IGT went back, e.g. the game was reset
+    RunReset { run_id: Uuid },
+    /// The finished run is the new personal best
+    PbSet { run_id: Uuid, final_time: Duration },
+}
+
+/// An IGT reading as JSON, e.g. for logs and integrations
+pub fn igt_json(igt: &InGameTime) -> Value {
+    json!({
+        "igt": igt.to_string(),
+        "percent": igt.percent,
+        "igt_ms": igt.duration.as_millis() as u64,
+    })
+}
+
+impl TimerEvent {
+    pub fn run_id(&self) -> Uuid {
+        match self {
+            TimerEvent::RunStarted { run_id }
+            | TimerEvent::SplitRecorded { run_id, .. }
+            | TimerEvent::GoldAchieved { run_id, .. }
+            | TimerEvent::RunFinished { run_id, .. }
+            | TimerEvent::RunReset { run_id }
+            | TimerEvent::PbSet { run_id, .. } => *run_id,
+        }
+    }
+
+    /// The event as JSON without its run, e.g. `{"event": "split_recorded", "split": "Sunny
+    /// Villa", ...}`, with the split names of `splits`.
+    pub fn to_json(&self, splits: &Splits) -> Value {
+        let split_name = |index: usize| splits.splits().get(index).map(|split| split.name.as_str());
+        match self {
+            TimerEvent::RunStarted { .. } => json!({ "event": "run_started" }),
+            TimerEvent::SplitRecorded { index, time, .. } => {
+                let mut entry = igt_json(time);
+                entry["event"] = json!("split_recorded");
+                entry["split"] = json!(split_name(*index));
+                entry
+            }
+            TimerEvent::GoldAchieved { index, segment, .. } => json!({
+                "event": "gold_achieved",
+                "split": split_name(*index),
+                "segment_ms": segment.as_millis() as u64,
+            }),
+            TimerEvent::RunFinished { final_time, .. } => {
+                json!({ "event": "run_finished", "final_time_ms": final_time.as_millis() as u64 })
+            }
+            TimerEvent::RunReset { .. } => json!({ "event": "run_reset" }),
+            TimerEvent::PbSet { final_time, .. } => {
+                json!({ "event": "pb_set", "final_time_ms": final_time.as_millis() as u64 })
+            }
+        }
+    }
+}
+
+/// Subscribes to the events of the splits, e.g. to save them or to forward them to an integration.
+/// Called after the splits were updated, so `splits` already reflects the event.
+pub trait TimerObserver {
+    fn on_event(&mut self, splits: &Splits, event: &TimerEvent);
+
+    /// Called for every accepted IGT reading once the splits were updated with it, before the
+    /// events it caused.
+    fn on_observation(&mut self, _splits: &Splits, _igt: &InGameTime) {}
+
+    /// Finishes handling the events so far, e.g. before the splits are saved directly.
+    fn flush(&mut self) {}
+
+    /// Called when the splits were replaced with the ones in their file (see `Splits::reload`).
+    /// Events so far refer to the previous splits. Also called with the splits the observer starts
+    /// out with (see `Tracker::with_observer`).
+    fn on_reload(&mut self, _splits: &Splits) {}
+}
@@ -1,21 +1,32 @@
 use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::time::Duration;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
 
 use anyhow::{Result, bail};
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use flate2::{Compression, Crc, read::GzDecoder, write::GzEncoder};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use serde_with::{DisplayFromStr, serde_as};
 use tempfile::NamedTempFile;
 use uuid::Uuid;
 
-use crate::splits::{Splits, splits::HistoricalSplit, splits::RunSummary, splits::Split};
+use crate::in_game_time::InGameTime;
+use crate::in_game_time::format_hms;
+use crate::splits::{
+    Splits, splits::ActiveRun, splits::ArchivedTimes, splits::CounterTrigger,
+    splits::HistoricalSplit, splits::LevelTrigger, splits::PersonalBestRecord, splits::RunSummary,
+    splits::Split,
+};
 
 /// Current version of splits file. Increment on breaking change and create migration.
 const SPLITS_FILE_VERSION_V1: u32 = 1;
 const SPLITS_FILE_VERSION_V2: u32 = 2;
+/// V3 adds the category, tags and notes of runs to the V2 layout, which reads both.
+const SPLITS_FILE_VERSION_V3: u32 = 3;
 
 /// Used for version detection. Any JSON containing a top-level "version" field will deserialize properly into this struct.
 #[derive(Debug, Deserialize)]
@@ -24,7 +35,44 @@ struct DetectVersion {
 }
 
 fn detect_splits_version(json: &str) -> Result<DetectVersion> {
-    serde_json::from_str(json).map_err(|e| anyhow::anyhow!("Failed to parse splits version: {}", e))
+    parse_json(json)
+}
+
+/// Deserializes `json`, naming the field that failed (e.g. `splits.runs[2].id`) and how to fix it
+/// along with the line and column.
+fn parse_json<T: DeserializeOwned>(json: &str) -> Result<T> {
+    let deserializer = &mut serde_json::Deserializer::from_str(json);
+    serde_path_to_error::deserialize(deserializer).map_err(|e| {
+        let path = e.path().to_string();
+        let error = e.into_inner();
+        match (error.is_data(), hint(&path)) {
+            // Missing fields at the top level
+            (true, _) if path == "." => anyhow::anyhow!("{}", error),
+            (true, Some(hint)) => anyhow::anyhow!("Invalid `{}`: {} ({})", path, error, hint),
+            (true, None) => anyhow::anyhow!("Invalid `{}`: {}", path, error),
+            // Syntax errors, truncated files
+            (false, _) => anyhow::anyhow!("Invalid JSON: {}", error),
+        }
+    })
+}
+
+/// How to write the field at `path`, for the fields that are easy to get wrong when editing by hand
+fn hint(path: &str) -> Option<&'static str> {
+    let field = path.rsplit('.').next()?;
+    let field = field.split('[').next()?;
+    match field {
+        "version" => Some("expected the version of the splits file format, e.g. 3"),
+        "percent" | "max_percent" | "latest_percent" => {
+            Some("percentages are whole numbers from 0, e.g. 117")
+        }
+        "id" | "run_id" => Some("IDs are UUIDs, e.g. 67e55044-10b1-426f-9247-bb680e5fe0c8"),
+        "start_time" | "end_time" | "date" => {
+            Some("dates are RFC 3339 timestamps, e.g. 2025-08-15T12:00:00Z")
+        }
+        "time" | "final_time" | "duration" | "best_segment" | "min_time" | "real_time"
+        | "total" | "latest_time" => Some("times are H:MM:SS, e.g. 1:02:03"),
+        _ => None,
+    }
 }
 
 // Wrapper around std::time::Duration that adds serialization / deserialization into a human-readable format.
@@ -33,11 +81,7 @@ struct HmsDuration(pub Duration);
 
 impl fmt::Display for HmsDuration {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let secs = self.0.as_secs();
-        let h = secs / 3600;
-        let m = (secs % 3600) / 60;
-        let s = secs % 60;
-        write!(f, "{:01}:{:02}:{:02}", h, m, s)
+        write!(f, "{}", format_hms(self.0))
     }
 }
 
@@ -101,24 +145,53 @@ struct SplitV1 {
 struct SplitsFileV2 {
     pub version: u32,
     pub splits: SplitsV2,
+    /// CRC32 of `splits`, see `checksum`. Files without one (older or edited by hand) aren't checked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 struct SplitsV2 {
     #[serde(default)]
     pub segmented: bool,
+    /// Splits are matched in the order of the route rather than by percent
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ordered: bool,
     pub personal_best: Option<RunSummaryV2>,
     pub runs: Vec<RunSummaryV2>,
     pub splits: Vec<SplitV2>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub triggers: Vec<CounterTriggerV2>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub level_triggers: Vec<LevelTriggerV2>,
+    /// Highest percentage of the category, e.g. 117 for 117%
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_percent: Option<u32>,
+    /// Number and sum of the split times moved to the archive file, per split
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub archived: Vec<ArchivedTimesV2>,
+    /// Unfinished run to resume after a restart
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_run: Option<ActiveRunV2>,
+    /// Former personal bests, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub previous_pbs: Vec<PersonalBestRecordV2>,
 }
 
 impl From<SplitsV1> for SplitsV2 {
     fn from(v1: SplitsV1) -> Self {
         let mut splits = SplitsV2 {
             segmented: false,
+            ordered: false,
             personal_best: None,
             runs: Vec::new(),
             splits: v1.splits.into_iter().map(|split| split.into()).collect(),
+            triggers: Vec::new(),
+            level_triggers: Vec::new(),
+            max_percent: None,
+            archived: Vec::new(),
+            active_run: None,
+            previous_pbs: Vec::new(),
         };
 
         // Recover personal best from splits
@@ -128,6 +201,7 @@ impl From<SplitsV1> for SplitsV2 {
                 split.history.push(HistoricalSplitV2 {
                     run_id: id,
                     duration: pb_time,
+                    real_time: None,
                 });
             }
         }
@@ -139,6 +213,9 @@ impl From<SplitsV1> for SplitsV2 {
             start_time: Utc::now(),
             end_time: None,
             final_time: splits.splits.last().map(|s| s.time).flatten(),
+            category: None,
+            tags: Vec::new(),
+            notes: None,
         });
         splits
     }
@@ -149,8 +226,19 @@ impl From<SplitsV1> for SplitsV2 {
 struct SplitV2 {
     pub name: String,
     pub percent: u32,
+    /// Lowest IGT at which the split can be reached, telling apart splits at the same percent
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_time: Option<HmsDuration>,
     #[serde_as(as = "Option<DisplayFromStr>")]
     pub time: Option<HmsDuration>,
+    /// Fastest segment ending in this split (gold), kept even once its run is no longer in `history`
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub best_segment: Option<HmsDuration>,
+    /// Route notes shown while heading for this split
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
     pub history: Vec<HistoricalSplitV2>,
 }
 
@@ -159,7 +247,10 @@ impl From<SplitV1> for SplitV2 {
         SplitV2 {
             name: v1.name,
             percent: v1.percent,
+            min_time: None,
             time: v1.duration,
+            best_segment: None,
+            notes: None,
             history: Vec::new(),
         }
     }
@@ -170,7 +261,10 @@ impl From<&Split> for SplitV2 {
         SplitV2 {
             name: s.name.clone(),
             percent: s.percent,
+            min_time: s.min_time.map(HmsDuration),
             time: s.time.map(HmsDuration),
+            best_segment: s.best_segment.map(HmsDuration),
+            notes: s.notes.clone(),
             history: s.history.iter().map(|h| h.into()).collect(),
         }
     }
@@ -181,8 +275,10 @@ impl From<&SplitV2> for Split {
         Split {
             name: sv2.name.clone(),
             percent: sv2.percent,
+            min_time: sv2.min_time.map(|h| h.0),
             time: sv2.time.map(|h| h.0),
-            best_segment: None,
+            best_segment: sv2.best_segment.map(|h| h.0),
+            notes: sv2.notes.clone(),
             history: sv2.history.iter().map(|h| h.into()).collect(),
         }
     }
@@ -196,6 +292,12 @@ struct RunSummaryV2 {
     pub end_time: Option<DateTime<Utc>>,
     #[serde_as(as = "Option<DisplayFromStr>")]
     pub final_time: Option<HmsDuration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
 }
 
 impl From<&RunSummary> for RunSummaryV2 {
@@ -205,6 +307,9 @@ impl From<&RunSummary> for RunSummaryV2 {
             start_time: run.start_time,
             end_time: run.end_time,
             final_time: run.final_time.map(HmsDuration),
+            category: run.category.clone(),
+            tags: run.tags.clone(),
+            notes: run.notes.clone(),
         }
     }
 }
@@ -216,6 +321,158 @@ impl From<&RunSummaryV2> for RunSummary {
             start_time: run_v2.start_time,
             end_time: run_v2.end_time,
             final_time: run_v2.final_time.map(|h| h.0),
+            category: run_v2.category.clone(),
+            tags: run_v2.tags.clone(),
+            notes: run_v2.notes.clone(),
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct ActiveRunV2 {
+    pub id: Uuid,
+    pub start_time: DateTime<Utc>,
+    pub latest_percent: u32,
+    #[serde_as(as = "DisplayFromStr")]
+    pub latest_time: HmsDuration,
+}
+
+impl From<&ActiveRun> for ActiveRunV2 {
+    fn from(run: &ActiveRun) -> Self {
+        ActiveRunV2 {
+            id: run.id,
+            start_time: run.start_time,
+            latest_percent: run.latest_split.percent,
+            latest_time: HmsDuration(run.latest_split.duration),
+        }
+    }
+}
+
+impl From<&ActiveRunV2> for ActiveRun {
+    fn from(run_v2: &ActiveRunV2) -> Self {
+        ActiveRun {
+            id: run_v2.id,
+            start_time: run_v2.start_time,
+            end_time: None,
+            latest_split: InGameTime {
+                percent: run_v2.latest_percent,
+                duration: run_v2.latest_time.0,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct CounterTriggerV2 {
+    pub percent: u32,
+    pub counter: String,
+    pub value: u32,
+}
+
+impl From<&CounterTrigger> for CounterTriggerV2 {
+    fn from(t: &CounterTrigger) -> Self {
+        CounterTriggerV2 {
+            percent: t.percent,
+            counter: t.counter.clone(),
+            value: t.value,
+        }
+    }
+}
+
+impl From<&CounterTriggerV2> for CounterTrigger {
+    fn from(tv2: &CounterTriggerV2) -> Self {
+        CounterTrigger {
+            percent: tv2.percent,
+            counter: tv2.counter.clone(),
+            value: tv2.value,
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct PersonalBestRecordV2 {
+    pub id: Uuid,
+    pub date: DateTime<Utc>,
+    #[serde_as(as = "DisplayFromStr")]
+    pub final_time: HmsDuration,
+}
+
+impl From<&PersonalBestRecord> for PersonalBestRecordV2 {
+    fn from(pb: &PersonalBestRecord) -> Self {
+        PersonalBestRecordV2 {
+            id: pb.id,
+            date: pb.date,
+            final_time: HmsDuration(pb.final_time),
+        }
+    }
+}
+
+impl From<&PersonalBestRecordV2> for PersonalBestRecord {
+    fn from(pb_v2: &PersonalBestRecordV2) -> Self {
+        PersonalBestRecord {
+            id: pb_v2.id,
+            date: pb_v2.date,
+            final_time: pb_v2.final_time.0,
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct ArchivedTimesV2 {
+    pub percent: u32,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_time: Option<HmsDuration>,
+    pub count: u32,
+    #[serde_as(as = "DisplayFromStr")]
+    pub total: HmsDuration,
+}
+
+impl From<&ArchivedTimes> for ArchivedTimesV2 {
+    fn from(t: &ArchivedTimes) -> Self {
+        ArchivedTimesV2 {
+            percent: t.percent,
+            min_time: t.min_time.map(HmsDuration),
+            count: t.count,
+            total: HmsDuration(t.total),
+        }
+    }
+}
+
+impl From<&ArchivedTimesV2> for ArchivedTimes {
+    fn from(tv2: &ArchivedTimesV2) -> Self {
+        ArchivedTimes {
+            percent: tv2.percent,
+            min_time: tv2.min_time.map(|h| h.0),
+            count: tv2.count,
+            total: tv2.total.0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct LevelTriggerV2 {
+    pub percent: u32,
+    pub level: String,
+}
+
+impl From<&LevelTrigger> for LevelTriggerV2 {
+    fn from(t: &LevelTrigger) -> Self {
+        LevelTriggerV2 {
+            percent: t.percent,
+            level: t.level.clone(),
+        }
+    }
+}
+
+impl From<&LevelTriggerV2> for LevelTrigger {
+    fn from(tv2: &LevelTriggerV2) -> Self {
+        LevelTrigger {
+            percent: tv2.percent,
+            level: tv2.level.clone(),
         }
     }
 }
@@ -226,6 +483,10 @@ struct HistoricalSplitV2 {
     pub run_id: Uuid,
     #[serde_as(as = "DisplayFromStr")]
     pub duration: HmsDuration,
+    /// Wall clock time since the start of the run
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub real_time: Option<HmsDuration>,
 }
 
 impl From<&HistoricalSplit> for HistoricalSplitV2 {
@@ -233,6 +494,7 @@ impl From<&HistoricalSplit> for HistoricalSplitV2 {
         HistoricalSplitV2 {
             run_id: h.run_id,
             duration: HmsDuration(h.duration),
+            real_time: h.real_time.map(HmsDuration),
         }
     }
 }
@@ -242,6 +504,7 @@ impl From<&HistoricalSplitV2> for HistoricalSplit {
         HistoricalSplit {
             run_id: hv2.run_id,
             duration: hv2.duration.0,
+            real_time: hv2.real_time.map(|h| h.0),
         }
     }
 }
@@ -251,6 +514,7 @@ impl From<SplitsFileV1> for SplitsFileV2 {
         SplitsFileV2 {
             version: SPLITS_FILE_VERSION_V2,
             splits: v1.splits.into(),
+            checksum: None,
         }
     }
 }
@@ -258,13 +522,21 @@ impl From<SplitsFileV1> for SplitsFileV2 {
 impl From<&Splits> for SplitsFileV2 {
     fn from(splits: &Splits) -> Self {
         SplitsFileV2 {
-            version: SPLITS_FILE_VERSION_V2,
+            version: SPLITS_FILE_VERSION_V3,
             splits: SplitsV2 {
                 segmented: splits.segmented(),
+                ordered: splits.ordered(),
                 personal_best: splits.personal_best().map(|pb| pb.into()),
                 runs: splits.runs().iter().map(|run| run.into()).collect(),
                 splits: splits.splits().iter().map(|split| split.into()).collect(),
+                triggers: splits.triggers().iter().map(|t| t.into()).collect(),
+                level_triggers: splits.level_triggers().iter().map(|t| t.into()).collect(),
+                max_percent: splits.max_percent(),
+                archived: splits.archived().iter().map(|t| t.into()).collect(),
+                active_run: splits.resumable_run().map(|run| run.into()),
+                previous_pbs: splits.previous_pbs().iter().map(|pb| pb.into()).collect(),
             },
+            checksum: None,
         }
     }
 }
@@ -278,50 +550,272 @@ fn from_v2(file_v2: SplitsFileV2, path: &Path) -> anyhow::Result<Splits> {
         .iter()
         .map(|split| split.into())
         .collect();
-    Ok(Splits::create_segmented_with_history(
+    let triggers = file_v2.splits.triggers.iter().map(|t| t.into()).collect();
+    let level_triggers = file_v2
+        .splits
+        .level_triggers
+        .iter()
+        .map(|t| t.into())
+        .collect();
+    let archived = file_v2.splits.archived.iter().map(|t| t.into()).collect();
+    let previous_pbs = file_v2
+        .splits
+        .previous_pbs
+        .iter()
+        .map(|pb| pb.into())
+        .collect();
+    let create = match file_v2.splits.ordered {
+        true => Splits::create_ordered_with_history,
+        false => Splits::create_segmented_with_history,
+    };
+    create(
         path.to_path_buf(),
         file_v2.splits.segmented,
         personal_best,
         runs,
         splits,
-    )?)
+    )?
+    .with_triggers(triggers)?
+    .with_level_triggers(level_triggers)?
+    .with_max_percent(file_v2.splits.max_percent)?
+    .with_archived(archived)?
+    .with_previous_pbs(previous_pbs)
+    .with_resumed_run(file_v2.splits.active_run.map(|run| (&run).into()))
+}
+
+/// Modification time of the splits files as this process last loaded or wrote them, to tell apart
+/// changes made by other programs. Locked while writing, so that a write and its modification
+/// time are seen together.
+static KNOWN_MODIFICATIONS: Mutex<Vec<(PathBuf, SystemTime)>> = Mutex::new(Vec::new());
+
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn remember_modification(known: &mut Vec<(PathBuf, SystemTime)>, path: &Path) {
+    let Some(modified) = modification_time(path) else {
+        return;
+    };
+    match known.iter_mut().find(|(p, _)| p == path) {
+        Some(entry) => entry.1 = modified,
+        None => known.push((path.to_path_buf(), modified)),
+    }
+}
+
+/// Whether the file at `path` changed since this process last loaded or wrote it, e.g. because it
+/// was edited by hand.
+pub fn modified_externally(path: &Path) -> bool {
+    let known = KNOWN_MODIFICATIONS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    known
+        .iter()
+        .find(|(p, _)| p == path)
+        .is_some_and(|&(_, modified)| modification_time(path) != Some(modified))
+}
+
+/// Backup of the splits file at `path`, holding the version before the last save
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    PathBuf::from(backup)
+}
+
+/// Keeps the file at `path` as its backup before it is replaced.
+fn back_up(path: &Path) -> std::io::Result<()> {
+    let backup = backup_path(path);
+    match fs::remove_file(&backup) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    // A hard link keeps the previous file once it is replaced, without copying it
+    fs::hard_link(path, &backup).or_else(|_| fs::copy(path, &backup).map(|_| ()))
+}
+
+/// Replaces the splits file at `path` with its backup if it is damaged (see `DamagedFile`), moving
+/// the damaged file to `<path>.damaged`. Returns whether it was restored.
+pub fn restore_if_damaged(path: &Path) -> Result<bool> {
+    let error = match load_from_file(path) {
+        Ok(_) => return Ok(false),
+        Err(e) if e.is::<DamagedFile>() => e,
+        Err(e) => return Err(e),
+    };
+    let backup = backup_path(path);
+    if let Err(e) = load_from_file(&backup) {
+        bail!("{}, which can't be loaded either: {}", error, e);
+    }
+    let mut damaged = path.as_os_str().to_owned();
+    damaged.push(".damaged");
+    fs::rename(path, &damaged)?;
+    fs::copy(&backup, path)?;
+    Ok(true)
+}
+
+/// The splits file is damaged, e.g. truncated or partly overwritten, rather than well-formed with
+/// invalid contents.
+#[derive(Debug)]
+pub struct DamagedFile {
+    path: PathBuf,
+    reason: String,
+}
+
+impl fmt::Display for DamagedFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Splits file {} is damaged: {}. The version before the last save is in {}",
+            self.path.display(),
+            self.reason,
+            backup_path(&self.path).display()
+        )
+    }
+}
+
+impl std::error::Error for DamagedFile {}
+
+/// Checksum of the `splits` object of a splits file, over its compact JSON so that it doesn't
+/// depend on the formatting of the file.
+fn checksum(splits: &serde_json::Value) -> String {
+    let mut crc = Crc::new();
+    crc.update(splits.to_string().as_bytes());
+    format!("{:08x}", crc.sum())
+}
+
+/// Checks that `contents` are complete JSON, and whether its checksum (if any) matches.
+fn verify_integrity(contents: &str) -> std::result::Result<bool, String> {
+    let file: serde_json::Value =
+        serde_json::from_str(contents).map_err(|e| format!("it isn't complete JSON ({})", e))?;
+    Ok(file.get("checksum").is_none_or(|expected| {
+        let actual = checksum(file.get("splits").unwrap_or(&serde_json::Value::Null));
+        expected.as_str() == Some(actual.as_str())
+    }))
 }
 
 pub fn load_from_file(path: &Path) -> Result<Splits> {
-    let contents = fs::read_to_string(path)
+    // Before reading, so that changes made while reading are noticed
+    remember_modification(
+        &mut KNOWN_MODIFICATIONS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner),
+        path,
+    );
+    let bytes = fs::read(path)
         .map_err(|e| anyhow::anyhow!("Failed to read file {}: {}", path.display(), e))?;
-
-    let version_info = detect_splits_version(&contents)?;
+    let damaged = |reason: String| DamagedFile {
+        path: path.to_path_buf(),
+        reason,
+    };
+    let contents = decode_contents(bytes).map_err(|e| damaged(e.to_string()))?;
+    // A file that doesn't match its checksum but is valid was edited by another program
+    let intact = verify_integrity(&contents).map_err(damaged)?;
+
+    let invalid = |e: anyhow::Error| {
+        if intact {
+            anyhow::anyhow!("Invalid splits file {}: {}", path.display(), e)
+        } else {
+            damaged(format!("its contents don't match the checksum ({})", e)).into()
+        }
+    };
+    let version_info = detect_splits_version(&contents).map_err(invalid)?;
 
     match version_info.version {
         SPLITS_FILE_VERSION_V1 => {
-            let file_v1: SplitsFileV1 = serde_json::from_str(&contents)?;
-            from_v2(file_v1.into(), path)
+            let file_v1: SplitsFileV1 = parse_json(&contents).map_err(invalid)?;
+            from_v2(file_v1.into(), path).map_err(invalid)
         }
-        SPLITS_FILE_VERSION_V2 => {
-            let file_v2: SplitsFileV2 = serde_json::from_str(&contents)?;
-            from_v2(file_v2, path)
+        SPLITS_FILE_VERSION_V2 | SPLITS_FILE_VERSION_V3 => {
+            let file_v2: SplitsFileV2 = parse_json(&contents).map_err(invalid)?;
+            from_v2(file_v2, path).map_err(invalid)
         }
-        v => bail!("Unsupported version: {}", v),
+        v => bail!(
+            "Unsupported version {} of splits file {}, expected at most {}",
+            v,
+            path.display(),
+            SPLITS_FILE_VERSION_V3
+        ),
     }
 }
 
 pub fn save_to_file(splits: &Splits, path: &Path) -> Result<()> {
+    write_to_file(&to_json(splits)?, path)
+}
+
+/// Serializes the splits the way `save_to_file` writes them.
+pub fn to_json(splits: &Splits) -> Result<String> {
     // Convert Splits → SplitsFileV2
-    let file_v2 = SplitsFileV2::from(splits);
+    let mut file_v2 = SplitsFileV2::from(splits);
+    file_v2.checksum = Some(checksum(&serde_json::to_value(&file_v2.splits)?));
+
+    // Serialize to pretty JSON
+    Ok(serde_json::to_string_pretty(&file_v2)?)
+}
+
+/// Whether the file at `path` holds exactly `splits`, as `save_to_file` would write them
+pub fn matches_file(splits: &Splits, path: &Path) -> Result<bool> {
+    let saved: serde_json::Value = serde_json::from_str(&read_contents(path)?)?;
+    let current: serde_json::Value = serde_json::from_str(&to_json(splits)?)?;
+    Ok(saved == current)
+}
+
+/// Gzip magic bytes, compressed files are read whatever their name
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Whether splits saved to `path` are gzip-compressed, i.e. it ends in `.gz` (`spyro.json.gz`).
+pub fn is_compressed(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "gz")
+}
+
+pub fn read_contents(path: &Path) -> Result<String> {
+    decode_contents(fs::read(path)?)
+}
+
+/// Contents of a splits file, decompressed if gzip-compressed
+pub fn decode_contents(bytes: Vec<u8>) -> Result<String> {
+    if bytes.starts_with(&GZIP_MAGIC) {
+        let mut contents = String::new();
+        GzDecoder::new(bytes.as_slice()).read_to_string(&mut contents)?;
+        Ok(contents)
+    } else {
+        Ok(String::from_utf8(bytes)?)
+    }
+}
+
+/// Contents of the file at `path` holding `json`, gzip-compressed if `is_compressed(path)`.
+pub fn encode_contents(json: &str, path: &Path) -> Result<Vec<u8>> {
+    if is_compressed(path) {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(json.as_bytes())?;
+        Ok(encoder.finish()?)
+    } else {
+        Ok(json.as_bytes().to_vec())
+    }
+}
 
+/// Atomically replaces the file at `path` with `json`, gzip-compressed if `is_compressed(path)`.
+pub fn write_to_file(json: &str, path: &Path) -> Result<()> {
     // Create temp file in same directory
-    let temp_file = NamedTempFile::new_in(
+    let mut temp_file = NamedTempFile::new_in(
         path.parent()
             .ok_or_else(|| anyhow::anyhow!("Invalid path: no parent directory"))?,
     )?;
 
-    // Serialize to pretty JSON
-    serde_json::to_writer_pretty(&temp_file, &file_v2)?;
+    temp_file.write_all(&encode_contents(json, path)?)?;
     temp_file.as_file().sync_all()?;
 
     // Persist atomically
+    let mut known = KNOWN_MODIFICATIONS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    // Only the version this process loaded or wrote, a file changed by another program might be
+    // damaged and replace a good backup. Saving goes on without a backup.
+    let unchanged = known
+        .iter()
+        .any(|(p, modified)| p == path && modification_time(path) == Some(*modified));
+    if unchanged {
+        let _ = back_up(path);
+    }
     temp_file.persist(path)?;
+    remember_modification(&mut known, path);
 
     Ok(())
 }
@@ -395,6 +889,9 @@ mod tests {
             start_time: Utc::now() - Duration::from_secs(100),
             end_time: Some(Utc::now()),
             final_time: Some(Duration::from_secs(50)),
+            category: None,
+            tags: Vec::new(),
+            notes: None,
         };
 
         let v2: RunSummaryV2 = (&run).into();
@@ -408,6 +905,7 @@ mod tests {
         let hist = HistoricalSplit {
             run_id: Uuid::new_v4(),
             duration: Duration::from_secs(123),
+            real_time: None,
         };
 
         let v2: HistoricalSplitV2 = (&hist).into();
@@ -421,11 +919,14 @@ mod tests {
         let split = Split {
             name: "Test".to_string(),
             percent: 75,
+            min_time: None,
             time: Some(Duration::from_secs(200)),
             best_segment: None,
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id: Uuid::new_v4(),
                 duration: Duration::from_secs(150),
+                real_time: None,
             }],
         };
 
@@ -444,16 +945,21 @@ mod tests {
             start_time: Utc::now() - Duration::from_secs(1234),
             end_time: Some(Utc::now()),
             final_time: Some(Duration::from_secs(567)),
+            category: None,
+            tags: Vec::new(),
+            notes: None,
         };
 
         let history = vec![
             HistoricalSplit {
                 run_id: Uuid::new_v4(),
                 duration: Duration::from_secs(678),
+                real_time: None,
             },
             HistoricalSplit {
                 run_id,
                 duration: Duration::from_secs(567),
+                real_time: None,
             },
         ];
 
@@ -464,8 +970,10 @@ mod tests {
             vec![Split {
                 name: "Test Split".to_string(),
                 percent: 50,
+                min_time: None,
                 time: Some(Duration::from_secs(567)),
                 best_segment: None,
+                notes: None,
                 history,
             }],
         )
@@ -615,6 +1123,46 @@ mod tests {
         assert!(parsed.is_ok(), "Empty splits list should be valid");
     }
 
+    #[test]
+    fn load_errors_name_the_invalid_field_and_its_position() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        let load_error = |json: &str| {
+            std::fs::write(temp_file.path(), json).unwrap();
+            load_from_file(temp_file.path()).unwrap_err().to_string()
+        };
+
+        let error = load_error(
+            r#"{
+            "version": 3,
+            "splits": {
+                "splits": [
+                    { "name": "Buzz", "percent": -18 }
+                ]
+            }
+        }"#,
+        );
+        assert!(error.contains("`splits.splits[0].percent`"), "{}", error);
+        assert!(error.contains("line 5"), "{}", error);
+        assert!(error.contains("whole numbers"), "{}", error);
+
+        let error = load_error(
+            r#"{
+            "version": 3,
+            "splits": {
+                "splits": [],
+                "runs": [
+                    { "id": "not-a-uuid", "start_time": "2025-08-14T15:00:00Z" }
+                ]
+            }
+        }"#,
+        );
+        assert!(error.contains("`splits.runs[0].id`"), "{}", error);
+        assert!(error.contains("UUID"), "{}", error);
+
+        let error = load_error(r#"{ "splits": {} }"#);
+        assert!(error.contains("missing field `version`"), "{}", error);
+    }
+
     #[test]
     fn load_from_file_with_valid_v2_file() -> Result<()> {
         use std::fs::write;
@@ -746,7 +1294,83 @@ mod tests {
     }
 
     #[test]
-    fn save_to_file_writes_valid_v2_splits() -> anyhow::Result<()> {
+    fn changes_by_other_programs_are_noticed() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("splits.json");
+
+        let mut splits = Splits::create(
+            file_path.clone(),
+            vec![Split {
+                name: "Start".to_string(),
+                percent: 25,
+                min_time: None,
+                time: None,
+                best_segment: None,
+                notes: None,
+                history: Vec::new(),
+            }],
+        )
+        .expect("splits should be valid");
+        splits.save_to_file()?;
+        assert!(!splits.modified_externally());
+
+        let edited = fs::read_to_string(&file_path)?.replace("Start", "Sunny Beach");
+        fs::write(&file_path, edited)?;
+        // The modification time might not have changed within the same tick
+        fs::File::options()
+            .write(true)
+            .open(&file_path)?
+            .set_modified(SystemTime::now() + Duration::from_secs(10))?;
+        assert!(splits.modified_externally());
+
+        splits.reload()?;
+        assert!(!splits.modified_externally());
+        assert_eq!(splits.splits()[0].name, "Sunny Beach");
+
+        Ok(())
+    }
+
+    #[test]
+    fn damaged_files_are_told_apart_and_restored_from_the_backup() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("splits.json");
+        let split = |name: &str| Split {
+            name: name.to_string(),
+            percent: 25,
+            min_time: None,
+            time: None,
+            best_segment: None,
+            notes: None,
+            history: Vec::new(),
+        };
+        Splits::create(file_path.clone(), vec![split("Start")])?.save_to_file()?;
+        let saved = fs::read_to_string(&file_path)?;
+        assert!(saved.contains("\"checksum\""));
+        Splits::create(file_path.clone(), vec![split("Sunny Beach")])?.save_to_file()?;
+        assert_eq!(fs::read_to_string(backup_path(&file_path))?, saved);
+
+        // Edited by another program, but still valid
+        let edited = fs::read_to_string(&file_path)?.replace("Sunny Beach", "Cloud Spires");
+        fs::write(&file_path, &edited)?;
+        assert_eq!(load_from_file(&file_path)?.splits()[0].name, "Cloud Spires");
+
+        // Altered so that it is invalid
+        fs::write(&file_path, edited.replace("25", "-25"))?;
+        assert!(load_from_file(&file_path).unwrap_err().is::<DamagedFile>());
+        // Truncated
+        fs::write(&file_path, &edited[..edited.len() / 2])?;
+        assert!(load_from_file(&file_path).unwrap_err().is::<DamagedFile>());
+
+        assert!(restore_if_damaged(&file_path)?);
+        assert_eq!(load_from_file(&file_path)?.splits()[0].name, "Start");
+        assert!(dir.path().join("splits.json.damaged").exists());
+        assert!(!restore_if_damaged(&file_path)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn save_to_file_writes_valid_v3_splits() -> anyhow::Result<()> {
         let dir = tempdir()?;
         let file_path = dir.path().join("splits.json");
 
@@ -756,15 +1380,19 @@ mod tests {
                 Split {
                     name: "Start".to_string(),
                     percent: 25,
+                    min_time: None,
                     time: Some(Duration::from_secs(5)),
                     best_segment: None,
+                    notes: None,
                     history: Vec::new(),
                 },
                 Split {
                     name: "End".to_string(),
                     percent: 100,
+                    min_time: None,
                     time: Some(Duration::from_secs(5 * 60)),
                     best_segment: None,
+                    notes: None,
                     history: Vec::new(),
                 },
             ],
@@ -775,7 +1403,7 @@ mod tests {
 
         // Check that file exists and contains expected JSON
         let contents = fs::read_to_string(&file_path)?;
-        assert!(contents.contains("\"version\": 2"));
+        assert!(contents.contains("\"version\": 3"));
         assert!(contents.contains("\"Start\""));
         assert!(contents.contains("\"End\""));
 
@@ -793,6 +1421,9 @@ mod tests {
             start_time: chrono::Utc::now(),
             end_time: Some(chrono::Utc::now() + chrono::Duration::seconds(2400)),
             final_time: Some(Duration::from_secs(1750)),
+            category: Some("No Sparx".to_string()),
+            tags: vec!["marathon".to_string()],
+            notes: Some("Lag in the last level".to_string()),
         };
         let original_splits = Splits::create_segmented_with_history(
             file_path.clone(),
@@ -803,26 +1434,45 @@ mod tests {
                 Split {
                     name: "Split 1".to_string(),
                     percent: 25,
+                    min_time: None,
                     time: Some(Duration::from_secs(600)),
                     best_segment: None,
+                    notes: None,
                     history: vec![HistoricalSplit {
                         run_id,
                         duration: Duration::from_secs(590),
+                        real_time: Some(Duration::from_secs(655)),
                     }],
                 },
                 Split {
                     name: "Split 2".to_string(),
                     percent: 75,
+                    min_time: None,
                     time: Some(Duration::from_secs(1800)),
                     best_segment: None,
+                    notes: Some("Supercharge before the ramp".to_string()),
                     history: vec![HistoricalSplit {
                         run_id,
                         duration: Duration::from_secs(1750),
+                        real_time: None,
                     }],
                 },
             ],
         )
-        .expect("splits should be valid");
+        .expect("splits should be valid")
+        .with_triggers(vec![CounterTrigger {
+            percent: 75,
+            counter: "gems".to_string(),
+            value: 100,
+        }])
+        .expect("trigger should be valid")
+        .with_level_triggers(vec![LevelTrigger {
+            percent: 25,
+            level: "Sunny Villa".to_string(),
+        }])
+        .expect("trigger should be valid")
+        .with_max_percent(Some(120))
+        .expect("maximum should be valid");
 
         // Save to file
         original_splits.save_to_file()?;
@@ -842,14 +1492,104 @@ mod tests {
             assert_eq!(orig.name, loaded.name);
             assert_eq!(orig.percent, loaded.percent);
             assert_eq!(orig.time, loaded.time);
-            assert_eq!(orig.history.len(), loaded.history.len());
+            assert_eq!(orig.best_segment, loaded.best_segment);
+            assert_eq!(orig.notes, loaded.notes);
+            assert_eq!(orig.history, loaded.history);
         }
 
+        assert_eq!(loaded_splits.triggers(), original_splits.triggers());
+        assert_eq!(
+            loaded_splits.level_triggers(),
+            original_splits.level_triggers()
+        );
+        assert_eq!(loaded_splits.max_percent(), Some(120));
+
         assert_eq!(loaded_splits.runs().len(), original_splits.runs().len());
         assert_eq!(
             loaded_splits.personal_best().unwrap().id,
             original_splits.personal_best().unwrap().id
         );
+        assert_eq!(loaded_splits.runs(), original_splits.runs());
+
+        Ok(())
+    }
+
+    #[test]
+    fn compressed_splits_files_round_trip() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("splits.json.gz");
+        let splits = Splits::create(
+            path.clone(),
+            vec![Split {
+                name: "Buzz".to_string(),
+                percent: 18,
+                min_time: None,
+                time: Some(Duration::from_secs(25 * 60 + 43)),
+                best_segment: None,
+                notes: None,
+                history: Vec::new(),
+            }],
+        )?;
+
+        splits.save_to_file()?;
+        assert!(fs::read(&path)?.starts_with(&GZIP_MAGIC));
+        assert_eq!(load_from_file(&path)?, splits);
+
+        // Detected by the contents, not the name
+        let renamed = dir.path().join("splits.json");
+        fs::copy(&path, &renamed)?;
+        assert_eq!(load_from_file(&renamed)?.splits(), splits.splits());
+
+        Ok(())
+    }
+
+    #[test]
+    fn interrupted_run_is_resumed_after_loading() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file_path = dir.path().join("splits.json");
+
+        let split = |name: &str, percent| Split {
+            name: name.to_string(),
+            percent,
+            min_time: None,
+            time: None,
+            best_segment: None,
+            notes: None,
+            history: Vec::new(),
+        };
+        let mut splits = Splits::create(
+            file_path.clone(),
+            vec![split("A", 10), split("B", 20), split("C", 30)],
+        )?;
+        splits.update_with_igt(&InGameTime {
+            percent: 10,
+            duration: Duration::from_secs(60),
+        });
+        let run_id = splits.active_run().unwrap().id;
+        splits.interrupt_active_run()?;
+
+        let mut loaded = load_from_file(&file_path)?;
+        let active_run = loaded.active_run().expect("run should be resumed");
+        assert_eq!(active_run.id, run_id);
+        assert_eq!(active_run.end_time, None);
+        assert_eq!(active_run.latest_split.percent, 10);
+        assert_eq!(loaded.runs()[0].end_time, None);
+
+        // The next split continues the same run
+        loaded.update_with_igt(&InGameTime {
+            percent: 20,
+            duration: Duration::from_secs(130),
+        });
+        assert_eq!(loaded.runs().len(), 1);
+        assert_eq!(loaded.splits()[1].history[0].run_id, run_id);
+
+        // Finished runs aren't resumed
+        loaded.update_with_igt(&InGameTime {
+            percent: 30,
+            duration: Duration::from_secs(200),
+        });
+        loaded.save_to_file()?;
+        assert!(load_from_file(&file_path)?.active_run().is_none());
 
         Ok(())
     }
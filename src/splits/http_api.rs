@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::in_game_time::InGameTime;
+use crate::splits::websocket::state_json;
+use crate::splits::{Comparison, Splits, TimerEvent, TimerObserver};
+
+/// Answers of the endpoints, taken whenever the splits changed
+#[derive(Default)]
+struct Responses {
+    state: Value,
+    splits: Value,
+    runs: Value,
+    pb: Value,
+}
+
+fn ms(time: Option<Duration>) -> Option<u64> {
+    time.map(|time| time.as_millis() as u64)
+}
+
+/// The splits with the PB's split times and the best segments
+fn splits_json(splits: &Splits) -> Value {
+    let splits: Vec<Value> = splits
+        .splits()
+        .iter()
+        .map(|split| {
+            json!({
+                "name": split.name,
+                "percent": split.percent,
+                "min_time_ms": ms(split.min_time),
+                "pb_time_ms": ms(split.time),
+                "best_segment_ms": ms(split.best_segment),
+                "notes": split.notes,
+            })
+        })
+        .collect();
+    json!(splits)
+}
+
+/// Every run with its split times, oldest first
+fn runs_json(splits: &Splits) -> Value {
+    // Looked up per run, the history is per split
+    let mut times: HashMap<Uuid, Vec<Option<u64>>> = HashMap::new();
+    for (index, split) in splits.splits().iter().enumerate() {
+        for hs in &split.history {
+            times
+                .entry(hs.run_id)
+                .or_insert_with(|| vec![None; splits.splits().len()])[index] =
+                ms(Some(hs.duration));
+        }
+    }
+    let runs: Vec<Value> = splits
+        .runs()
+        .iter()
+        .map(|run| {
+            json!({
+                "id": run.id,
+                "start_time": run.start_time,
+                "end_time": run.end_time,
+                "final_time_ms": ms(run.final_time),
+                "category": run.category,
+                "tags": run.tags,
+                "notes": run.notes,
+                "split_times_ms": times
+                    .get(&run.id)
+                    .cloned()
+                    .unwrap_or_else(|| vec![None; splits.splits().len()]),
+            })
+        })
+        .collect();
+    json!(runs)
+}
+
+/// The PB with its split and segment times, and the former PBs
+fn pb_json(splits: &Splits) -> Value {
+    let times: Vec<Option<Duration>> = splits.splits().iter().map(|s| s.time).collect();
+    let pb_splits: Vec<Value> = splits
+        .splits()
+        .iter()
+        .zip(Splits::segments(&times))
+        .map(|(split, segment)| {
+            json!({
+                "name": split.name,
+                "time_ms": ms(split.time),
+                "segment_ms": ms(segment),
+            })
+        })
+        .collect();
+    let previous: Vec<Value> = splits
+        .previous_pbs()
+        .iter()
+        .map(|pb| {
+            json!({
+                "run_id": pb.id,
+                "date": pb.date,
+                "final_time_ms": ms(Some(pb.final_time)),
+            })
+        })
+        .collect();
+    json!({
+        "run_id": splits.personal_best().map(|pb| pb.id),
+        "start_time": splits.personal_best().map(|pb| pb.start_time),
+        "final_time_ms": ms(splits.personal_best().and_then(|pb| pb.final_time)),
+        "splits": pb_splits,
+        "sum_of_best_ms": ms(splits.sum_of_bests()),
+        "previous": previous,
+    })
+}
+
+/// Serves the state of the splits as JSON over HTTP for scripts and stream tools polling it:
+/// `/state` (the active run, as pushed by `WebSocketBroadcast`), `/splits`, `/runs` and `/pb`.
+/// Clones answer from the same state, e.g. for the tracker of another game.
+#[derive(Clone)]
+pub struct HttpApi {
+    responses: Arc<Mutex<Responses>>,
+    address: SocketAddr,
+    /// What the deltas of the state are against
+    comparison: Comparison,
+    /// IGT of the state taken last
+    last_igt: Option<InGameTime>,
+}
+
+impl HttpApi {
+    /// Listens on `address` (e.g. 127.0.0.1:16836) for requests, each connection served on a
+    /// thread of its own.
+    pub fn start(address: SocketAddr, comparison: Comparison) -> Result<Self> {
+        let listener = TcpListener::bind(address).map_err(|e| {
+            anyhow!(
+                "Failed to listen on {} for HTTP API requests: {}",
+                address,
+                e
+            )
+        })?;
+        let address = listener.local_addr()?;
+        let responses = Arc::new(Mutex::new(Responses::default()));
+        let shared = responses.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let responses = shared.clone();
+                thread::spawn(move || {
+                    let _ = serve(stream, &responses);
+                });
+            }
+        });
+        Ok(Self {
+            responses,
+            address,
+            comparison,
+            last_igt: None,
+        })
+    }
+
+    /// Address the server listens on, e.g. with port 0 the port picked
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    fn responses(&self) -> MutexGuard<'_, Responses> {
+        self.responses
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn update_state(&mut self, splits: &Splits) {
+        self.last_igt = splits.active_run().map(|run| run.latest_split);
+        self.responses().state = state_json(splits, self.comparison);
+    }
+
+    fn update(&mut self, splits: &Splits) {
+        self.update_state(splits);
+        let mut responses = self.responses();
+        responses.splits = splits_json(splits);
+        responses.runs = runs_json(splits);
+        responses.pb = pb_json(splits);
+    }
+}
+
+/// Status and body of the answer to a request for `target` (the path with an optional query)
+fn respond(responses: &Responses, method: &str, target: &str) -> (&'static str, Value) {
+    if method != "GET" && method != "HEAD" {
+        return (
+            "405 Method Not Allowed",
+            json!({ "error": "Only GET requests are supported" }),
+        );
+    }
+    let path = target.split('?').next().unwrap_or_default();
+    match path.trim_end_matches('/') {
+        "/state" => ("200 OK", responses.state.clone()),
+        "/splits" => ("200 OK", responses.splits.clone()),
+        "/runs" => ("200 OK", responses.runs.clone()),
+        "/pb" => ("200 OK", responses.pb.clone()),
+        _ => (
+            "404 Not Found",
+            json!({
+                "error": format!("No endpoint {}", path),
+                "endpoints": ["/state", "/splits", "/runs", "/pb"],
+            }),
+        ),
+    }
+}
+
+/// Answers the request of the connection through `stream`, then closes it
+fn serve(mut stream: TcpStream, responses: &Mutex<Responses>) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers don't matter
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or_default(),
+    );
+    let (status, body) = respond(
+        &responses.lock().unwrap_or_else(PoisonError::into_inner),
+        method,
+        target,
+    );
+    let body = serde_json::to_string_pretty(&body)?;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    )?;
+    if method != "HEAD" {
+        stream.write_all(body.as_bytes())?;
+    }
+    Ok(())
+}
+
+impl TimerObserver for HttpApi {
+    fn on_event(&mut self, splits: &Splits, _event: &TimerEvent) {
+        self.update(splits);
+    }
+
+    fn on_observation(&mut self, splits: &Splits, igt: &InGameTime) {
+        if self.last_igt != Some(*igt) {
+            self.update_state(splits);
+        }
+    }
+
+    fn on_reload(&mut self, splits: &Splits) {
+        self.update(splits);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use tempfile::tempdir;
+
+    use crate::splits::splits::Split;
+
+    fn get(address: SocketAddr, target: &str) -> (String, Value) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.lines().next().unwrap().to_string();
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    #[test]
+    fn endpoints_are_served_before_the_first_event() {
+        let dir = tempdir().unwrap();
+        let mut splits = Splits::create(
+            dir.path().join("splits.json"),
+            vec![Split {
+                name: "Sunny Villa".into(),
+                percent: 10,
+                min_time: None,
+                time: None,
+                best_segment: None,
+                notes: None,
+                history: Vec::new(),
+            }],
+        )
+        .unwrap();
+        splits.update_with_igt(&InGameTime::from_secs(10, 60));
+
+        let mut api =
+            HttpApi::start("127.0.0.1:0".parse().unwrap(), Comparison::PersonalBest).unwrap();
+        // Like a tracker does when the observer is attached
+        api.on_reload(&splits);
+
+        let (status, pb) = get(api.address(), "/pb");
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(pb["final_time_ms"], 60_000);
+        let (_, layout) = get(api.address(), "/splits");
+        assert_eq!(layout[0]["name"], "Sunny Villa");
+        let (_, state) = get(api.address(), "/state");
+        assert_eq!(state["phase"], "ended");
+    }
+
+    #[test]
+    fn endpoints_serve_state_splits_runs_and_pb() {
+        let dir = tempdir().unwrap();
+        let mut splits = Splits::create(
+            dir.path().join("splits.json"),
+            ["Sunny Villa", "Cloud Spires"]
+                .iter()
+                .zip([10, 20])
+                .map(|(name, percent)| Split {
+                    name: name.to_string(),
+                    percent,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: Vec::new(),
+                })
+                .collect(),
+        )
+        .unwrap();
+        let mut api =
+            HttpApi::start("127.0.0.1:0".parse().unwrap(), Comparison::PersonalBest).unwrap();
+        for (percent, secs) in [(10, 60), (20, 150), (10, 55)] {
+            let igt = InGameTime::from_secs(percent, secs);
+            let events = splits.update_with_igt(&igt);
+            api.on_observation(&splits, &igt);
+            for event in &events {
+                api.on_event(&splits, event);
+            }
+        }
+
+        let (status, state) = get(api.address(), "/state");
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(state["phase"], "running");
+        assert_eq!(state["splits"][0]["delta_ms"], -5_000);
+
+        let (_, layout) = get(api.address(), "/splits");
+        assert_eq!(layout[1]["name"], "Cloud Spires");
+        assert_eq!(layout[1]["pb_time_ms"], 150_000);
+        assert_eq!(layout[0]["best_segment_ms"], 55_000);
+
+        let (_, runs) = get(api.address(), "/runs");
+        assert_eq!(runs.as_array().unwrap().len(), 2);
+        assert_eq!(runs[0]["final_time_ms"], 150_000);
+        assert_eq!(runs[1]["split_times_ms"], json!([55_000, null]));
+
+        let (_, pb) = get(api.address(), "/pb/");
+        assert_eq!(pb["final_time_ms"], 150_000);
+        assert_eq!(pb["splits"][1]["segment_ms"], 90_000);
+
+        let (status, error) = get(api.address(), "/nope");
+        assert_eq!(status, "HTTP/1.1 404 Not Found");
+        assert_eq!(error["endpoints"][0], "/state");
+    }
+}
@@ -0,0 +1,504 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Result, anyhow, bail};
+use chrono::Utc;
+use clap::ValueEnum;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::splits::{Splits, splits::HistoricalSplit, splits::RunSummary, splits::Split};
+
+/// Splits file format of another timer
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ImportFormat {
+    Splitterino,
+    Urn,
+    Flitter,
+}
+
+impl ImportFormat {
+    pub fn importer(self) -> Box<dyn Importer> {
+        match self {
+            ImportFormat::Splitterino => Box::new(SplitterinoImporter),
+            ImportFormat::Urn => Box::new(UrnImporter),
+            ImportFormat::Flitter => Box::new(FlitterImporter),
+        }
+    }
+}
+
+/// Splits and runs read from the file of another timer, before they are assigned percentages
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportedSplits {
+    pub names: Vec<String>,
+    pub best_segments: Vec<Option<Duration>>,
+    /// Cumulative split times of every run (one per split), oldest run first
+    pub runs: Vec<Vec<Option<Duration>>>,
+}
+
+/// Reads the splits file of another timer.
+pub trait Importer {
+    fn parse(&self, json: &str) -> Result<ImportedSplits>;
+}
+
+/// Imports the splits file `file` of another timer as splits saved to `path`. The percentage of each
+/// split is taken from `percents` (one per split) or, if empty, from the split names (e.g.
+/// "Sunny Beach 12%"). Runs get the import time as start time, the fastest finished run becomes
+/// the PB.
+pub fn import_splits(
+    format: ImportFormat,
+    file: &Path,
+    path: PathBuf,
+    percents: &[u32],
+) -> Result<Splits> {
+    let json = fs::read_to_string(file)
+        .map_err(|e| anyhow!("Failed to read file {}: {}", file.display(), e))?;
+    let imported = format
+        .importer()
+        .parse(&json)
+        .map_err(|e| anyhow!("Failed to import {:?} splits: {}", format, e))?;
+    to_splits(imported, path, percents)
+}
+
+fn to_splits(imported: ImportedSplits, path: PathBuf, percents: &[u32]) -> Result<Splits> {
+    if imported.names.is_empty() {
+        bail!("No splits to import");
+    }
+    let percents: Vec<u32> = if percents.is_empty() {
+        imported
+            .names
+            .iter()
+            .map(|name| {
+                percent_in_name(name).ok_or_else(|| {
+                    anyhow!("No percentage in split name '{}', pass --percents", name)
+                })
+            })
+            .collect::<Result<_>>()?
+    } else if percents.len() == imported.names.len() {
+        percents.to_vec()
+    } else {
+        bail!(
+            "Expected {} percentages (one per split), got {}",
+            imported.names.len(),
+            percents.len()
+        );
+    };
+
+    // Keep the order of the runs, the files don't tell when they were started
+    let imported_at = Utc::now();
+    let count = imported.runs.len() as i64;
+    let runs: Vec<(RunSummary, Vec<Option<Duration>>)> = imported
+        .runs
+        .into_iter()
+        .filter(|times| times.iter().any(Option::is_some))
+        .enumerate()
+        .map(|(i, times)| {
+            let run = RunSummary {
+                id: Uuid::new_v4(),
+                start_time: imported_at - chrono::Duration::seconds(count - i as i64),
+                end_time: None,
+                final_time: times.last().copied().flatten(),
+                category: None,
+                tags: Vec::new(),
+                notes: None,
+            };
+            (run, times)
+        })
+        .collect();
+    let personal_best = runs
+        .iter()
+        .map(|(run, _)| run)
+        .filter(|run| run.final_time.is_some())
+        .min_by_key(|run| run.final_time)
+        .cloned();
+
+    let splits = imported
+        .names
+        .into_iter()
+        .zip(percents)
+        .enumerate()
+        .map(|(index, (name, percent))| Split {
+            name,
+            percent,
+            min_time: None,
+            time: None,
+            best_segment: imported.best_segments.get(index).copied().flatten(),
+            notes: None,
+            history: runs
+                .iter()
+                .filter_map(|(run, times)| {
+                    Some(HistoricalSplit {
+                        run_id: run.id,
+                        duration: times.get(index).copied().flatten()?,
+                        real_time: None,
+                    })
+                })
+                .collect(),
+        })
+        .collect();
+
+    Splits::create_with_history(
+        path,
+        personal_best,
+        runs.into_iter().map(|(run, _)| run).collect(),
+        splits,
+    )
+}
+
+/// Percentage in a split name, e.g. 12 for "Sunny Beach 12%"
+fn percent_in_name(name: &str) -> Option<u32> {
+    let (before, _) = name.split_once('%')?;
+    let digits = before.len() - before.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    before[before.len() - digits..].parse().ok()
+}
+
+/// Parses a time as shown by other timers, e.g. "1:02:03.45", "2:03.450" or "3.4". "-" and empty
+/// times are missing times.
+fn parse_timer_time(s: &str) -> Result<Option<Duration>> {
+    let s = s.trim();
+    if s.is_empty() || s == "-" {
+        return Ok(None);
+    }
+    let mut secs = 0.0;
+    for part in s.split(':') {
+        let value: f64 = part.parse().map_err(|_| anyhow!("Invalid time '{}'", s))?;
+        secs = secs * 60.0 + value;
+    }
+    // Also rejects negative times and ones too large for a duration, e.g. "1e30"
+    Duration::try_from_secs_f64(secs)
+        .map(Some)
+        .map_err(|_| anyhow!("Invalid time '{}'", s))
+}
+
+fn parse_optional_time(s: &Option<String>) -> Result<Option<Duration>> {
+    match s {
+        Some(s) => parse_timer_time(s),
+        None => Ok(None),
+    }
+}
+
+/// Cumulative times of a run from its segment times. Stops at the first missing segment.
+fn cumulative(segments: &[Option<Duration>]) -> Vec<Option<Duration>> {
+    segments
+        .iter()
+        .scan(Some(Duration::ZERO), |total, segment| {
+            *total = total.zip(*segment).map(|(total, segment)| total + segment);
+            Some(*total)
+        })
+        .collect()
+}
+
+/// Splitterino: segment times (in milliseconds, IGT and RTA) of the PB and the best segments
+struct SplitterinoImporter;
+
+#[derive(Deserialize)]
+struct SplitterinoFile {
+    splits: SplitterinoSplits,
+}
+
+#[derive(Deserialize)]
+struct SplitterinoSplits {
+    segments: Vec<SplitterinoSegment>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SplitterinoSegment {
+    name: String,
+    #[serde(default)]
+    personal_best: Option<SplitterinoTimes>,
+    #[serde(default)]
+    overall_best: Option<SplitterinoTimes>,
+}
+
+#[derive(Deserialize)]
+struct SplitterinoTimes {
+    #[serde(default)]
+    igt: Option<SplitterinoTime>,
+    #[serde(default)]
+    rta: Option<SplitterinoTime>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SplitterinoTime {
+    raw_time: u64,
+    #[serde(default)]
+    pause_time: u64,
+}
+
+impl SplitterinoTimes {
+    /// The IGT, or the RTA if the segment has no IGT
+    fn duration(&self) -> Option<Duration> {
+        let time = self.igt.as_ref().or(self.rta.as_ref())?;
+        Some(Duration::from_millis(
+            time.raw_time.saturating_sub(time.pause_time),
+        ))
+    }
+}
+
+impl Importer for SplitterinoImporter {
+    fn parse(&self, json: &str) -> Result<ImportedSplits> {
+        let file: SplitterinoFile = serde_json::from_str(json)?;
+        let segments = &file.splits.segments;
+        let pb: Vec<Option<Duration>> = segments
+            .iter()
+            .map(|s| {
+                s.personal_best
+                    .as_ref()
+                    .and_then(SplitterinoTimes::duration)
+            })
+            .collect();
+        Ok(ImportedSplits {
+            names: segments.iter().map(|s| s.name.clone()).collect(),
+            best_segments: segments
+                .iter()
+                .map(|s| s.overall_best.as_ref().and_then(SplitterinoTimes::duration))
+                .collect(),
+            runs: vec![cumulative(&pb)],
+        })
+    }
+}
+
+/// Urn: split times of the PB and the best segments
+struct UrnImporter;
+
+#[derive(Deserialize)]
+struct UrnFile {
+    splits: Vec<UrnSplit>,
+}
+
+#[derive(Deserialize)]
+struct UrnSplit {
+    title: String,
+    #[serde(default)]
+    time: Option<String>,
+    #[serde(default)]
+    best_segment: Option<String>,
+}
+
+impl Importer for UrnImporter {
+    fn parse(&self, json: &str) -> Result<ImportedSplits> {
+        let file: UrnFile = serde_json::from_str(json)?;
+        Ok(ImportedSplits {
+            names: file.splits.iter().map(|s| s.title.clone()).collect(),
+            best_segments: file
+                .splits
+                .iter()
+                .map(|s| parse_optional_time(&s.best_segment))
+                .collect::<Result<_>>()?,
+            runs: vec![
+                file.splits
+                    .iter()
+                    .map(|s| parse_optional_time(&s.time))
+                    .collect::<Result<_>>()?,
+            ],
+        })
+    }
+}
+
+/// Flitter: split times of every attempt (and the PB), and the best segments
+struct FlitterImporter;
+
+#[derive(Deserialize)]
+struct FlitterFile {
+    split_names: Vec<String>,
+    #[serde(default)]
+    golds: Vec<FlitterGold>,
+    #[serde(default)]
+    personal_best: Option<FlitterRun>,
+    #[serde(default)]
+    history: Vec<FlitterRun>,
+}
+
+#[derive(Deserialize)]
+struct FlitterGold {
+    title: String,
+    duration: String,
+}
+
+#[derive(Deserialize)]
+struct FlitterRun {
+    #[serde(default)]
+    splits: Vec<FlitterSplit>,
+}
+
+#[derive(Deserialize)]
+struct FlitterSplit {
+    title: String,
+    #[serde(default)]
+    time: Option<String>,
+}
+
+impl FlitterRun {
+    /// Split times in the order of `names`, matched by title
+    fn times(&self, names: &[String]) -> Result<Vec<Option<Duration>>> {
+        names
+            .iter()
+            .map(|name| match self.splits.iter().find(|s| &s.title == name) {
+                Some(split) => parse_optional_time(&split.time),
+                None => Ok(None),
+            })
+            .collect()
+    }
+}
+
+impl Importer for FlitterImporter {
+    fn parse(&self, json: &str) -> Result<ImportedSplits> {
+        let file: FlitterFile = serde_json::from_str(json)?;
+        let names = file.split_names;
+        let best_segments = names
+            .iter()
+            .map(|name| match file.golds.iter().find(|g| &g.title == name) {
+                Some(gold) => parse_timer_time(&gold.duration),
+                None => Ok(None),
+            })
+            .collect::<Result<_>>()?;
+        let mut runs = file
+            .history
+            .iter()
+            .map(|run| run.times(&names))
+            .collect::<Result<Vec<_>>>()?;
+        // The PB is usually one of the attempts in the history
+        if let Some(pb) = &file.personal_best {
+            let pb = pb.times(&names)?;
+            if !runs.contains(&pb) {
+                runs.push(pb);
+            }
+        }
+        Ok(ImportedSplits {
+            names,
+            best_segments,
+            runs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Option<Duration> {
+        Some(Duration::from_secs(secs))
+    }
+
+    #[test]
+    fn parses_times_of_other_timers() {
+        assert_eq!(parse_timer_time("1:02:03").unwrap(), secs(3723));
+        assert_eq!(
+            parse_timer_time("2:03.5").unwrap(),
+            Some(Duration::from_millis(123_500))
+        );
+        assert_eq!(parse_timer_time("-").unwrap(), None);
+        assert!(parse_timer_time("1:xx").is_err());
+        assert!(parse_timer_time("1e30").is_err());
+        assert!(parse_timer_time("-5").is_err());
+    }
+
+    #[test]
+    fn finds_percent_in_split_name() {
+        assert_eq!(percent_in_name("Sunny Beach 12%"), Some(12));
+        assert_eq!(percent_in_name("117% Gnasty's Loot"), Some(117));
+        assert_eq!(percent_in_name("Sunny Beach"), None);
+    }
+
+    #[test]
+    fn imports_splitterino_pb_and_best_segments() {
+        let json = r#"{
+            "version": "0.1",
+            "splits": {
+                "segments": [
+                    {
+                        "id": "a",
+                        "name": "Sunny Beach 10%",
+                        "personalBest": { "igt": { "rawTime": 60000, "pauseTime": 0 } },
+                        "overallBest": { "igt": { "rawTime": 55000, "pauseTime": 0 } }
+                    },
+                    {
+                        "id": "b",
+                        "name": "Cloud Temples 20%",
+                        "personalBest": { "rta": { "rawTime": 50000, "pauseTime": 10000 } }
+                    }
+                ]
+            }
+        }"#;
+
+        let imported = SplitterinoImporter.parse(json).unwrap();
+        assert_eq!(imported.best_segments, vec![secs(55), None]);
+        assert_eq!(imported.runs, vec![vec![secs(60), secs(100)]]);
+
+        let splits = to_splits(imported, PathBuf::from("splits.json"), &[]).unwrap();
+        assert_eq!(splits.splits()[1].percent, 20);
+        assert_eq!(splits.personal_best().unwrap().final_time, secs(100));
+        assert_eq!(splits.splits()[0].best_segment, secs(55));
+        assert_eq!(splits.splits()[1].best_segment, secs(40));
+    }
+
+    #[test]
+    fn imports_urn_pb_with_given_percents() {
+        let json = r#"{
+            "title": "Spyro 3 117%",
+            "splits": [
+                { "title": "Sunny Beach", "time": "1:00.00", "best_time": "1:00.00", "best_segment": "58.50" },
+                { "title": "Molten Crater", "time": "2:30.00", "best_time": "2:20.00", "best_segment": "1:20.00" }
+            ]
+        }"#;
+
+        let imported = UrnImporter.parse(json).unwrap();
+        assert!(to_splits(UrnImporter.parse(json).unwrap(), PathBuf::new(), &[]).is_err());
+        assert!(to_splits(UrnImporter.parse(json).unwrap(), PathBuf::new(), &[5]).is_err());
+
+        let splits = to_splits(imported, PathBuf::from("splits.json"), &[5, 10]).unwrap();
+        assert_eq!(splits.runs().len(), 1);
+        assert_eq!(splits.splits()[0].time, secs(60));
+        assert_eq!(splits.splits()[1].time, secs(150));
+        assert_eq!(
+            splits.splits()[0].best_segment,
+            Some(Duration::from_millis(58_500))
+        );
+    }
+
+    #[test]
+    fn imports_flitter_history() {
+        let json = r#"{
+            "title": "Spyro 3",
+            "split_names": ["Sunny Beach 10%", "Cloud Temples 20%"],
+            "golds": [
+                { "title": "Sunny Beach 10%", "duration": "50.000" },
+                { "title": "Cloud Temples 20%", "duration": "45.000" }
+            ],
+            "personal_best": {
+                "splits": [
+                    { "title": "Sunny Beach 10%", "time": "55.000" },
+                    { "title": "Cloud Temples 20%", "time": "1:40.000" }
+                ]
+            },
+            "history": [
+                {
+                    "splits": [
+                        { "title": "Sunny Beach 10%", "time": "1:00.000" },
+                        { "title": "Cloud Temples 20%", "time": "2:00.000" }
+                    ]
+                },
+                {
+                    "splits": [
+                        { "title": "Sunny Beach 10%", "time": "55.000" },
+                        { "title": "Cloud Temples 20%", "time": "1:40.000" }
+                    ]
+                },
+                { "splits": [{ "title": "Sunny Beach 10%", "time": "50.000" }] }
+            ]
+        }"#;
+
+        let imported = FlitterImporter.parse(json).unwrap();
+        assert_eq!(imported.runs.len(), 3);
+
+        let splits = to_splits(imported, PathBuf::from("splits.json"), &[]).unwrap();
+        assert_eq!(splits.runs().len(), 3);
+        assert_eq!(splits.runs()[2].final_time, None);
+        assert_eq!(splits.personal_best().unwrap().id, splits.runs()[1].id);
+        assert_eq!(splits.splits()[0].history.len(), 3);
+        assert_eq!(splits.splits()[1].best_segment, secs(45));
+    }
+}
@@ -0,0 +1,425 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+
+use crate::in_game_time::InGameTime;
+use crate::splits::{Comparison, Splits, TimerEvent, TimerObserver};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum Phase {
+    #[default]
+    NotRunning,
+    Running,
+    Ended,
+}
+
+/// State of the splits the commands are answered from, taken whenever the splits changed
+#[derive(Default)]
+struct Snapshot {
+    phase: Phase,
+    split_names: Vec<String>,
+    /// Split times of the active run
+    times: Vec<Option<Duration>>,
+    /// Index of the split the runner is going for (the number of splits once finished), `None`
+    /// without a run
+    split_index: Option<usize>,
+    /// Latest IGT of the active run
+    current_time: Option<Duration>,
+    start_time: Option<DateTime<Utc>>,
+    end_time: Option<DateTime<Utc>>,
+    /// Split times of every comparison
+    comparisons: Vec<(Comparison, Vec<Option<Duration>>)>,
+    best_possible_time: Option<Duration>,
+    attempts: usize,
+    completed: usize,
+}
+
+impl Snapshot {
+    fn of(splits: &Splits) -> Self {
+        let run = splits.active_run().and_then(|active_run| {
+            splits
+                .runs()
+                .iter()
+                .find(|run| run.id == active_run.id)
+                .map(|run| (active_run, run))
+        });
+        let phase = match run {
+            Some((_, run)) if run.final_time.is_some() => Phase::Ended,
+            // Interrupted, i.e. ended without finishing
+            Some((active_run, _)) if active_run.end_time.is_some() => Phase::NotRunning,
+            Some(_) => Phase::Running,
+            None => Phase::NotRunning,
+        };
+        let times = match run {
+            Some((active_run, _)) if phase != Phase::NotRunning => splits
+                .splits()
+                .iter()
+                .map(|split| {
+                    split
+                        .history
+                        .iter()
+                        .find(|hs| hs.run_id == active_run.id)
+                        .map(|hs| hs.duration)
+                })
+                .collect(),
+            _ => vec![None; splits.splits().len()],
+        };
+        Self {
+            phase,
+            split_names: splits.splits().iter().map(|s| s.name.clone()).collect(),
+            split_index: (phase != Phase::NotRunning)
+                .then(|| times.iter().rposition(Option::is_some).map_or(0, |i| i + 1)),
+            times,
+            current_time: run
+                .filter(|_| phase != Phase::NotRunning)
+                .map(|(active_run, run)| {
+                    run.final_time.unwrap_or(active_run.latest_split.duration)
+                }),
+            start_time: run.map(|(_, run)| run.start_time),
+            end_time: run.and_then(|(_, run)| run.end_time),
+            comparisons: Comparison::value_variants()
+                .iter()
+                .map(|&comparison| (comparison, splits.comparison_times(comparison)))
+                .collect(),
+            best_possible_time: splits.best_possible_time(),
+            attempts: splits.runs().len(),
+            completed: splits
+                .runs()
+                .iter()
+                .filter(|run| run.final_time.is_some())
+                .count(),
+        }
+    }
+
+    fn comparison(&self, comparison: Comparison) -> &[Option<Duration>] {
+        self.comparisons
+            .iter()
+            .find(|(c, _)| *c == comparison)
+            .map_or(&[], |(_, times)| times.as_slice())
+    }
+
+    /// Difference to `comparison` at the latest split that has a time in both, in milliseconds
+    fn last_delta(&self, comparison: Comparison) -> Option<i128> {
+        let compared = self.comparison(comparison);
+        (0..self.split_index?).rev().find_map(|index| {
+            let time = self.times.get(index).copied().flatten()?;
+            let compared = compared.get(index).copied().flatten()?;
+            Some(time.as_millis() as i128 - compared.as_millis() as i128)
+        })
+    }
+
+    /// Split the runner is going for
+    fn current_split(&self) -> Option<usize> {
+        self.split_index
+            .filter(|&index| self.phase == Phase::Running && index < self.split_names.len())
+    }
+
+    fn real_time(&self) -> Option<Duration> {
+        let end = match self.phase {
+            Phase::NotRunning => return None,
+            Phase::Running => Utc::now(),
+            Phase::Ended => self.end_time?,
+        };
+        (end - self.start_time?).to_std().ok()
+    }
+}
+
+/// Comparison named in a command, as LiveSplit names them or as passed to `--comparison`
+fn parse_comparison(name: &str) -> Option<Comparison> {
+    match name {
+        "Personal Best" => Some(Comparison::PersonalBest),
+        "Best Segments" => Some(Comparison::BestSegments),
+        "Average Segments" => Some(Comparison::Average),
+        "Median Segments" => Some(Comparison::Median),
+        "Latest Run" => Some(Comparison::Latest),
+        name => Comparison::from_str(name, true).ok(),
+    }
+}
+
+/// Formats a time the way LiveSplit does, e.g. 1:02:03.00 or 2:03.00, "-" without one.
+fn format_time(time: Option<Duration>) -> String {
+    let Some(time) = time else {
+        return "-".to_string();
+    };
+    let hundredths = time.as_millis() / 10;
+    let (hours, minutes, seconds) = (
+        hundredths / 360_000,
+        hundredths / 6000 % 60,
+        hundredths / 100 % 60,
+    );
+    if hours > 0 {
+        format!(
+            "{}:{:02}:{:02}.{:02}",
+            hours,
+            minutes,
+            seconds,
+            hundredths % 100
+        )
+    } else {
+        format!("{}:{:02}.{:02}", minutes, seconds, hundredths % 100)
+    }
+}
+
+/// Formats a delta in milliseconds the way LiveSplit does, e.g. +1:05.00 or −3.00
+fn format_delta(delta: Option<i128>) -> String {
+    let Some(delta) = delta else {
+        return "-".to_string();
+    };
+    let hundredths = delta.unsigned_abs() / 10;
+    // LiveSplit's minus sign
+    let sign = if delta < 0 { "\u{2212}" } else { "+" };
+    let (hours, minutes, seconds) = (
+        hundredths / 360_000,
+        hundredths / 6000 % 60,
+        hundredths / 100 % 60,
+    );
+    let fraction = hundredths % 100;
+    if hours > 0 {
+        format!(
+            "{}{}:{:02}:{:02}.{:02}",
+            sign, hours, minutes, seconds, fraction
+        )
+    } else if minutes > 0 {
+        format!("{}{}:{:02}.{:02}", sign, minutes, seconds, fraction)
+    } else {
+        format!("{}{}.{:02}", sign, seconds, fraction)
+    }
+}
+
+/// Answers a command of the LiveSplit Server protocol, `None` for commands without an answer. The
+/// commands controlling the timer (split, reset, ...) are ignored: the timer follows the IGT.
+fn respond(snapshot: &Snapshot, default_comparison: Comparison, line: &str) -> Option<String> {
+    let (command, argument) = line
+        .trim()
+        .split_once(' ')
+        .map_or((line.trim(), ""), |(command, argument)| {
+            (command, argument.trim())
+        });
+    let comparison = parse_comparison(argument).unwrap_or(default_comparison);
+    let compared = snapshot.comparison(comparison);
+    let final_comparison = compared.last().copied().flatten();
+
+    let response = match command {
+        "ping" => "pong".to_string(),
+        "getcurrenttime" | "getcurrentgametime" => {
+            format_time(Some(snapshot.current_time.unwrap_or_default()))
+        }
+        "getcurrentrealtime" => format_time(Some(snapshot.real_time().unwrap_or_default())),
+        "getcurrenttimerphase" => format!("{:?}", snapshot.phase),
+        "getsplitindex" => snapshot
+            .split_index
+            .map_or("-1".to_string(), |index| index.to_string()),
+        "getcurrentsplitname" => snapshot
+            .current_split()
+            .map_or("-".to_string(), |index| snapshot.split_names[index].clone()),
+        "getprevioussplitname" => snapshot
+            .split_index
+            .and_then(|index| index.checked_sub(1))
+            .and_then(|index| snapshot.split_names.get(index))
+            .cloned()
+            .unwrap_or_else(|| "-".to_string()),
+        "getlastsplittime" => format_time(
+            snapshot
+                .split_index
+                .and_then(|index| index.checked_sub(1))
+                .and_then(|index| snapshot.times.get(index).copied().flatten()),
+        ),
+        "getcomparisonsplittime" => format_time(
+            snapshot
+                .current_split()
+                .and_then(|index| compared.get(index).copied().flatten()),
+        ),
+        "getdelta" => format_delta(snapshot.last_delta(comparison)),
+        "getfinaltime" => format_time(match snapshot.phase {
+            Phase::Ended => snapshot.current_time,
+            _ => final_comparison,
+        }),
+        "getpredictedtime" => format_time(match snapshot.phase {
+            Phase::Ended => snapshot.current_time,
+            Phase::Running => final_comparison.map(|final_time| {
+                let delta = snapshot.last_delta(comparison).unwrap_or(0);
+                let predicted = final_time.as_millis() as i128 + delta;
+                Duration::from_millis(predicted.max(0) as u64)
+            }),
+            Phase::NotRunning => final_comparison,
+        }),
+        "getbestpossibletime" => format_time(snapshot.best_possible_time),
+        "getattemptcount" => snapshot.attempts.to_string(),
+        "getcompletedcount" => snapshot.completed.to_string(),
+        _ => return None,
+    };
+    Some(response)
+}
+
+/// Answers the queries of tools speaking the LiveSplit Server protocol (e.g. browser overlays or
+/// stream deck plugins) about the current time, split and delta: one command per line over TCP,
+/// answered with one line. Deltas are against `comparison` unless a command names another. Clones
+/// answer from the same state, e.g. for the tracker of another game.
+#[derive(Clone)]
+pub struct LiveSplitServer {
+    snapshot: Arc<Mutex<Snapshot>>,
+    address: SocketAddr,
+}
+
+impl LiveSplitServer {
+    /// Listens on `address` (e.g. 127.0.0.1:16834) for connections, each served on a thread of
+    /// its own.
+    pub fn start(address: SocketAddr, comparison: Comparison) -> Result<Self> {
+        let listener = TcpListener::bind(address).map_err(|e| {
+            anyhow!(
+                "Failed to listen on {} for LiveSplit Server connections: {}",
+                address,
+                e
+            )
+        })?;
+        let address = listener.local_addr()?;
+        let snapshot = Arc::new(Mutex::new(Snapshot::default()));
+        let shared = snapshot.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let snapshot = shared.clone();
+                thread::spawn(move || serve(stream, &snapshot, comparison));
+            }
+        });
+        Ok(Self { snapshot, address })
+    }
+
+    /// Address the server listens on, e.g. with port 0 the port picked
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    fn update(&self, splits: &Splits) {
+        *self.snapshot.lock().unwrap_or_else(PoisonError::into_inner) = Snapshot::of(splits);
+    }
+}
+
+/// Answers the commands of one connection until it's closed
+fn serve(stream: TcpStream, snapshot: &Mutex<Snapshot>, comparison: Comparison) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        let response = respond(
+            &snapshot.lock().unwrap_or_else(PoisonError::into_inner),
+            comparison,
+            &line,
+        );
+        if let Some(response) = response
+            && write!(writer, "{}\r\n", response).is_err()
+        {
+            return;
+        }
+    }
+}
+
+impl TimerObserver for LiveSplitServer {
+    fn on_event(&mut self, splits: &Splits, _event: &TimerEvent) {
+        self.update(splits);
+    }
+
+    fn on_observation(&mut self, splits: &Splits, _igt: &InGameTime) {
+        self.update(splits);
+    }
+
+    fn on_reload(&mut self, splits: &Splits) {
+        self.update(splits);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    use crate::splits::splits::Split;
+
+    fn make_splits() -> (tempfile::TempDir, Splits) {
+        let dir = tempdir().unwrap();
+        let splits = Splits::create(
+            dir.path().join("splits.json"),
+            ["Sunny Villa", "Cloud Spires", "Molten Crater"]
+                .iter()
+                .zip([10, 20, 30])
+                .map(|(name, percent)| Split {
+                    name: name.to_string(),
+                    percent,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: Vec::new(),
+                })
+                .collect(),
+        )
+        .unwrap();
+        (dir, splits)
+    }
+
+    #[test]
+    fn commands_are_answered_from_the_splits() {
+        let (_dir, mut splits) = make_splits();
+        for (percent, secs) in [(10, 60), (20, 150), (30, 200), (0, 0), (10, 55), (20, 160)] {
+            splits.update_with_igt(&InGameTime::from_secs(percent, secs));
+        }
+        let snapshot = Snapshot::of(&splits);
+        let respond = |line: &str| respond(&snapshot, Comparison::PersonalBest, line);
+
+        assert_eq!(respond("ping").as_deref(), Some("pong"));
+        assert_eq!(respond("getcurrenttimerphase").as_deref(), Some("Running"));
+        assert_eq!(respond("getsplitindex").as_deref(), Some("2"));
+        assert_eq!(
+            respond("getcurrentsplitname").as_deref(),
+            Some("Molten Crater")
+        );
+        assert_eq!(
+            respond("getprevioussplitname").as_deref(),
+            Some("Cloud Spires")
+        );
+        assert_eq!(respond("getcurrenttime\r").as_deref(), Some("2:40.00"));
+        assert_eq!(respond("getlastsplittime").as_deref(), Some("2:40.00"));
+        assert_eq!(
+            respond("getcomparisonsplittime").as_deref(),
+            Some("3:20.00")
+        );
+        assert_eq!(respond("getdelta").as_deref(), Some("+10.00"));
+        assert_eq!(respond("getdelta Best Segments").as_deref(), Some("+15.00"));
+        assert_eq!(respond("getpredictedtime").as_deref(), Some("3:30.00"));
+        assert_eq!(respond("getfinaltime").as_deref(), Some("3:20.00"));
+        assert_eq!(respond("getattemptcount").as_deref(), Some("2"));
+        assert_eq!(respond("getcompletedcount").as_deref(), Some("1"));
+        assert_eq!(respond("split"), None);
+
+        assert_eq!(format_delta(Some(-3_500)), "\u{2212}3.50");
+        assert_eq!(format_time(Some(Duration::from_secs(3723))), "1:02:03.00");
+    }
+
+    #[test]
+    fn server_answers_over_tcp() {
+        let (_dir, mut splits) = make_splits();
+        let mut server =
+            LiveSplitServer::start("127.0.0.1:0".parse().unwrap(), Comparison::PersonalBest)
+                .unwrap();
+        let events = splits.update_with_igt(&InGameTime::from_secs(10, 60));
+        for event in &events {
+            server.on_event(&splits, event);
+        }
+
+        let mut stream = TcpStream::connect(server.address()).unwrap();
+        stream
+            .write_all(b"getsplitindex\r\nsplit\r\nping\r\n")
+            .unwrap();
+        let mut lines = BufReader::new(stream).lines();
+        assert_eq!(lines.next().unwrap().unwrap(), "1");
+        assert_eq!(lines.next().unwrap().unwrap(), "pong");
+    }
+}
@@ -0,0 +1,395 @@
+use std::time::Duration;
+
+use clap::ValueEnum;
+
+use crate::splits::Splits;
+
+/// Number of runs shown in the recent runs table, and of finished runs averaged for the trend
+const RECENT_RUNS: usize = 10;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Default)]
+pub enum ReportFormat {
+    /// Markdown tables, e.g. for pasting into Discord or a README
+    #[default]
+    Markdown,
+    /// Standalone HTML page
+    Html,
+}
+
+struct Table {
+    header: Vec<&'static str>,
+    /// Columns aligned to the right, i.e. the numbers
+    right_aligned: Vec<bool>,
+    rows: Vec<Vec<String>>,
+}
+
+struct Section {
+    title: &'static str,
+    lines: Vec<String>,
+    table: Option<Table>,
+}
+
+/// Renders a summary of the splits meant for sharing: the PB with its splits and segments, sum of
+/// best, golds, how many runs reached each split and the latest runs.
+pub fn render_report(splits: &Splits, title: &str, format: ReportFormat) -> String {
+    let sections = vec![
+        summary(splits),
+        pb_breakdown(splits),
+        golds(splits),
+        attempts(splits),
+        recent_runs(splits),
+    ];
+    match format {
+        ReportFormat::Markdown => render_markdown(title, &sections),
+        ReportFormat::Html => render_html(title, &sections),
+    }
+}
+
+fn time(duration: Option<Duration>) -> String {
+    Splits::format_time(duration).trim().to_string()
+}
+
+fn percentage(count: usize, total: usize) -> String {
+    match total {
+        0 => "-".to_string(),
+        _ => format!("{:.0}%", count as f64 * 100.0 / total as f64),
+    }
+}
+
+fn summary(splits: &Splits) -> Section {
+    let pb = splits.personal_best().and_then(|pb| pb.final_time);
+    let sob = splits.sum_of_bests();
+    let finished = splits
+        .runs()
+        .iter()
+        .filter(|r| r.final_time.is_some())
+        .count();
+    let mut lines = vec![
+        format!(
+            "PB: {}{}",
+            time(pb),
+            splits
+                .personal_best()
+                .map(|pb| format!(" ({})", pb.start_time.format("%Y-%m-%d")))
+                .unwrap_or_default()
+        ),
+        format!("Sum of best: {}", time(sob)),
+        format!(
+            "Possible timesave: {}",
+            time(pb.zip(sob).map(|(pb, sob)| pb.saturating_sub(sob)))
+        ),
+        format!(
+            "Attempts: {}, finished {} ({})",
+            splits.runs().len(),
+            finished,
+            percentage(finished, splits.runs().len())
+        ),
+    ];
+    if let Some(reset) = splits.average_reset_percent() {
+        lines.push(format!("Average reset at {:.0}%", reset));
+    }
+    Section {
+        title: "Summary",
+        lines,
+        table: None,
+    }
+}
+
+fn pb_breakdown(splits: &Splits) -> Section {
+    let times: Vec<Option<Duration>> = splits.splits().iter().map(|s| s.time).collect();
+    let rows = splits
+        .splits()
+        .iter()
+        .zip(Splits::segments(&times))
+        .map(|(split, segment)| {
+            let timesave = segment
+                .zip(split.best_segment)
+                .map(|(segment, best)| segment.saturating_sub(best));
+            vec![
+                split.name.clone(),
+                format!("{}%", split.percent),
+                time(split.time),
+                time(segment),
+                time(split.best_segment),
+                time(timesave),
+            ]
+        })
+        .collect();
+    Section {
+        title: "PB breakdown",
+        lines: Vec::new(),
+        table: Some(Table {
+            header: vec!["Split", "%", "Time", "Segment", "Best", "Timesave"],
+            right_aligned: vec![false, true, true, true, true, true],
+            rows,
+        }),
+    }
+}
+
+fn golds(splits: &Splits) -> Section {
+    let run_segments: Vec<_> = splits
+        .runs()
+        .iter()
+        .map(|run| (run, splits.segment_times(run.id)))
+        .collect();
+    let rows = splits
+        .splits()
+        .iter()
+        .enumerate()
+        .map(|(index, split)| {
+            // Golds of archived runs have no run left
+            let run = split.best_segment.and_then(|best| {
+                run_segments
+                    .iter()
+                    .find(|(_, segments)| segments[index] == Some(best))
+                    .map(|(run, _)| run.start_time.format("%Y-%m-%d").to_string())
+            });
+            vec![
+                split.name.clone(),
+                time(split.best_segment),
+                run.unwrap_or_else(|| "-".to_string()),
+            ]
+        })
+        .collect();
+    Section {
+        title: "Golds",
+        lines: Vec::new(),
+        table: Some(Table {
+            header: vec!["Split", "Best segment", "Achieved"],
+            right_aligned: vec![false, true, true],
+            rows,
+        }),
+    }
+}
+
+fn attempts(splits: &Splits) -> Section {
+    let total = splits.runs().len();
+    let rows = splits
+        .splits()
+        .iter()
+        .zip(splits.survival_counts())
+        .map(|(split, reached)| {
+            vec![
+                split.name.clone(),
+                reached.to_string(),
+                percentage(reached, total),
+            ]
+        })
+        .collect();
+    Section {
+        title: "Runs reaching each split",
+        lines: Vec::new(),
+        table: Some(Table {
+            header: vec!["Split", "Runs", "Rate"],
+            right_aligned: vec![false, true, true],
+            rows,
+        }),
+    }
+}
+
+fn recent_runs(splits: &Splits) -> Section {
+    let pb_id = splits.personal_best().map(|pb| pb.id);
+    let pb = splits.personal_best().and_then(|pb| pb.final_time);
+    let runs = splits.runs();
+    let active_run_id = splits
+        .active_run()
+        .filter(|r| r.end_time.is_none())
+        .map(|r| r.id);
+    let rows = runs
+        .iter()
+        .rev()
+        .take(RECENT_RUNS)
+        .map(|run| {
+            let result = match run.final_time {
+                Some(final_time) => time(Some(final_time)),
+                None if Some(run.id) == active_run_id => "In progress".to_string(),
+                None => match splits
+                    .segment_times(run.id)
+                    .iter()
+                    .rposition(Option::is_some)
+                {
+                    Some(index) => format!("Reset after {}", splits.splits()[index].name),
+                    None => "Reset".to_string(),
+                },
+            };
+            let delta = if Some(run.id) == pb_id {
+                Some("PB".to_string())
+            } else {
+                run.final_time
+                    .zip(pb)
+                    .map(|(final_time, pb)| match final_time.checked_sub(pb) {
+                        Some(slower) => format!("+{}", time(Some(slower))),
+                        None => format!("-{}", time(Some(pb - final_time))),
+                    })
+            };
+            vec![
+                run.start_time.format("%Y-%m-%d %H:%M").to_string(),
+                result,
+                delta.unwrap_or_default(),
+            ]
+        })
+        .collect();
+
+    let finished: Vec<Duration> = runs.iter().filter_map(|r| r.final_time).collect();
+    let average = |times: &[Duration]| {
+        (!times.is_empty()).then(|| times.iter().sum::<Duration>() / times.len() as u32)
+    };
+    let recent = &finished[finished.len().saturating_sub(RECENT_RUNS)..];
+    let mut lines = Vec::new();
+    if recent.len() < finished.len() {
+        lines.push(format!(
+            "Average of the last {} finished runs: {} (all {} finished runs: {})",
+            recent.len(),
+            time(average(recent)),
+            finished.len(),
+            time(average(&finished))
+        ));
+    } else if !finished.is_empty() {
+        lines.push(format!(
+            "Average of the {} finished runs: {}",
+            finished.len(),
+            time(average(&finished))
+        ));
+    }
+    Section {
+        title: "Recent runs",
+        lines,
+        table: Some(Table {
+            header: vec!["Started", "Result", "vs PB"],
+            right_aligned: vec![false, true, true],
+            rows,
+        }),
+    }
+}
+
+fn render_markdown(title: &str, sections: &[Section]) -> String {
+    let escape = |cell: &str| cell.replace('|', "\\|");
+    let mut markdown = format!("# {}\n", title);
+    for section in sections {
+        markdown.push_str(&format!("\n## {}\n\n", section.title));
+        for line in &section.lines {
+            markdown.push_str(&format!("- {}\n", line));
+        }
+        if let Some(table) = &section.table {
+            if !section.lines.is_empty() {
+                markdown.push('\n');
+            }
+            markdown.push_str(&format!("| {} |\n", table.header.join(" | ")));
+            let alignments: Vec<&str> = table
+                .right_aligned
+                .iter()
+                .map(|&right| if right { "---:" } else { "---" })
+                .collect();
+            markdown.push_str(&format!("|{}|\n", alignments.join("|")));
+            for row in &table.rows {
+                let cells: Vec<String> = row.iter().map(|cell| escape(cell)).collect();
+                markdown.push_str(&format!("| {} |\n", cells.join(" | ")));
+            }
+        }
+    }
+    markdown
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_html(title: &str, sections: &[Section]) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n\
+         <style>body {{ font-family: sans-serif; }} table {{ border-collapse: collapse; }} \
+         th, td {{ padding: 2px 8px; border-bottom: 1px solid #ccc; }} \
+         .right {{ text-align: right; }}</style>\n</head>\n<body>\n<h1>{0}</h1>\n",
+        escape_html(title)
+    );
+    for section in sections {
+        html.push_str(&format!("<h2>{}</h2>\n", escape_html(section.title)));
+        if !section.lines.is_empty() {
+            html.push_str("<ul>\n");
+            for line in &section.lines {
+                html.push_str(&format!("<li>{}</li>\n", escape_html(line)));
+            }
+            html.push_str("</ul>\n");
+        }
+        if let Some(table) = &section.table {
+            let cell = |tag: &str, text: &str, right: bool| {
+                let class = if right { " class=\"right\"" } else { "" };
+                format!("<{0}{1}>{2}</{0}>", tag, class, escape_html(text))
+            };
+            html.push_str("<table>\n<tr>");
+            for (header, &right) in table.header.iter().zip(&table.right_aligned) {
+                html.push_str(&cell("th", header, right));
+            }
+            html.push_str("</tr>\n");
+            for row in &table.rows {
+                html.push_str("<tr>");
+                for (text, &right) in row.iter().zip(&table.right_aligned) {
+                    html.push_str(&cell("td", text, right));
+                }
+                html.push_str("</tr>\n");
+            }
+            html.push_str("</table>\n");
+        }
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    use crate::in_game_time::InGameTime;
+    use crate::splits::splits::Split;
+
+    #[test]
+    fn report_shows_pb_golds_and_recent_runs() {
+        let dir = tempdir().unwrap();
+        let mut splits = Splits::create(
+            dir.path().join("splits.json"),
+            ["Sunny | Villa", "Cloud Spires"]
+                .iter()
+                .zip([10, 20])
+                .map(|(name, percent)| Split {
+                    name: name.to_string(),
+                    percent,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: Vec::new(),
+                })
+                .collect(),
+        )
+        .unwrap();
+        // A finished run, then a faster first segment in a run that's reset after it
+        for (percent, secs) in [(10, 60), (20, 150), (0, 0), (10, 50)] {
+            splits.update_with_igt(&InGameTime::from_secs(percent, secs));
+        }
+
+        let markdown = render_report(&splits, "Spyro 3", ReportFormat::Markdown);
+        assert!(markdown.starts_with("# Spyro 3\n"), "{}", markdown);
+        assert!(markdown.contains("- PB: 0:02:30"), "{}", markdown);
+        assert!(markdown.contains("- Sum of best: 0:02:20"), "{}", markdown);
+        assert!(
+            markdown.contains("- Attempts: 2, finished 1 (50%)"),
+            "{}",
+            markdown
+        );
+        assert!(
+            markdown.contains("| Sunny \\| Villa | 10% | 0:01:00 | 0:01:00 | 0:00:50 | 0:00:10 |"),
+            "{}",
+            markdown
+        );
+        assert!(markdown.contains("| In progress |  |"), "{}", markdown);
+        assert!(markdown.contains("| 0:02:30 | PB |"), "{}", markdown);
+
+        let html = render_report(&splits, "Spyro <3>", ReportFormat::Html);
+        assert!(html.contains("<h1>Spyro &lt;3&gt;</h1>"), "{}", html);
+        assert!(html.contains("<td>Cloud Spires</td>"), "{}", html);
+    }
+}
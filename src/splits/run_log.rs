@@ -0,0 +1,154 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use chrono::Utc;
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use crate::in_game_time::InGameTime;
+use crate::splits::events::igt_json;
+use crate::splits::{Splits, TimerEvent, TimerObserver};
+
+/// Appends the accepted IGT readings and the events of every run to a JSON lines file per run
+/// (`<run ID>.jsonl` in `dir`), e.g. for retiming a run or finding out why a split was missed.
+/// Entries are only ever appended, a resumed run continues its file.
+pub struct RunLog {
+    dir: PathBuf,
+    /// File of the run logged last
+    file: Option<(Uuid, File)>,
+    /// Run that finished last, readings after the final split aren't logged
+    finished: Option<Uuid>,
+}
+
+impl RunLog {
+    pub fn new(dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&dir).map_err(|e| {
+            anyhow::anyhow!("Failed to create run log folder {}: {}", dir.display(), e)
+        })?;
+        Ok(Self {
+            dir,
+            file: None,
+            finished: None,
+        })
+    }
+
+    pub fn path_of(&self, run_id: Uuid) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", run_id))
+    }
+
+    fn append(&mut self, run_id: Uuid, mut entry: Value) -> Result<()> {
+        if self.file.as_ref().is_none_or(|(id, _)| *id != run_id) {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.path_of(run_id))?;
+            self.file = Some((run_id, file));
+        }
+        let (_, file) = self.file.as_mut().expect("just opened");
+        entry["wall_clock"] = json!(Utc::now());
+        serde_json::to_writer(&mut *file, &entry)?;
+        writeln!(file)?;
+        Ok(())
+    }
+
+    fn report(result: Result<()>) {
+        if let Err(e) = result {
+            println!("Failed to write the run log: {}", e);
+        }
+    }
+}
+
+impl TimerObserver for RunLog {
+    fn on_event(&mut self, splits: &Splits, event: &TimerEvent) {
+        if let TimerEvent::RunFinished { run_id, .. } = event {
+            self.finished = Some(*run_id);
+        }
+        Self::report(self.append(event.run_id(), event.to_json(splits)));
+    }
+
+    fn on_observation(&mut self, splits: &Splits, igt: &InGameTime) {
+        // Readings before the first run started or after the final split belong to no run
+        let Some(run) = splits.active_run() else {
+            return;
+        };
+        if self.finished == Some(run.id) || !splits.runs().iter().any(|r| r.id == run.id) {
+            return;
+        }
+        let mut entry = igt_json(igt);
+        entry["event"] = json!("observation");
+        Self::report(self.append(run.id, entry));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    use crate::splits::splits::Split;
+
+    #[test]
+    fn logs_readings_and_events_per_run() {
+        let dir = tempdir().unwrap();
+        let mut log = RunLog::new(dir.path().join("runs")).unwrap();
+        let mut splits = Splits::create(
+            dir.path().join("splits.json"),
+            ["A", "B"]
+                .iter()
+                .zip([10, 20])
+                .map(|(name, percent)| Split {
+                    name: name.to_string(),
+                    percent,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: Vec::new(),
+                })
+                .collect(),
+        )
+        .unwrap();
+
+        let mut feed = |igt: InGameTime| {
+            let events = splits.update_with_igt(&igt);
+            log.on_observation(&splits, &igt);
+            for event in &events {
+                log.on_event(&splits, event);
+            }
+        };
+        feed(InGameTime::from_secs(10, 60));
+        feed(InGameTime::from_secs(10, 61));
+        feed(InGameTime::from_secs(20, 120));
+
+        let run_id = splits.runs()[0].id;
+        let contents = fs::read_to_string(log.path_of(run_id)).unwrap();
+        let entries: Vec<Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let kinds: Vec<&str> = entries
+            .iter()
+            .map(|entry| entry["event"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "observation",
+                "run_started",
+                "split_recorded",
+                "gold_achieved",
+                "observation",
+                "split_recorded",
+                "observation",
+                "split_recorded",
+                "gold_achieved",
+                "run_finished",
+                "pb_set",
+            ]
+        );
+        assert_eq!(entries[2]["split"], "A");
+        assert_eq!(entries[2]["igt_ms"], 60_000);
+    }
+}
@@ -1,17 +1,55 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
     time::Duration,
 };
 
 use anyhow::bail;
 use chrono::{DateTime, Utc};
+use clap::ValueEnum;
 use colored::Colorize;
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 use uuid::Uuid;
 
-use crate::in_game_time::InGameTime;
+use crate::in_game_time::{InGameTime, format_hms};
+use crate::splits::TimerEvent;
+
+/// What the times of a run are compared to
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Default)]
+pub enum Comparison {
+    /// Split times of the personal best
+    #[default]
+    PersonalBest,
+    /// Sum of the best segments up to each split
+    BestSegments,
+    /// Average of all previous times of each split
+    Average,
+    /// Median of all previous times of each split
+    Median,
+    /// Split times of the latest previous run
+    Latest,
+}
+
+impl Comparison {
+    /// The comparison after this one, wrapping around, to cycle through them at runtime.
+    pub fn next(self) -> Self {
+        let variants = Self::value_variants();
+        let index = variants.iter().position(|&c| c == self).unwrap_or(0);
+        variants[(index + 1) % variants.len()]
+    }
+
+    /// Name of the comparison as passed to `--comparison`
+    pub fn name(self) -> &'static str {
+        match self {
+            Comparison::PersonalBest => "personal-best",
+            Comparison::BestSegments => "best-segments",
+            Comparison::Average => "average",
+            Comparison::Median => "median",
+            Comparison::Latest => "latest",
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ActiveRun {
@@ -27,31 +65,171 @@ pub struct RunSummary {
     pub start_time: DateTime<Utc>,
     pub end_time: Option<DateTime<Utc>>,
     pub final_time: Option<Duration>, // if finished
+    /// Category run against the splits, e.g. when running several categories with the same layout
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+    pub notes: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct HistoricalSplit {
     pub run_id: Uuid,
     pub duration: Duration,
+    /// Wall clock time since the start of the run (RTA), unlike the IGT including loads and menus
+    pub real_time: Option<Duration>,
 }
 
 #[derive(Debug, PartialEq)]
 pub struct Split {
     pub name: String,
     pub percent: u32,
+    /// Lowest IGT at which the split can be reached. Tells apart splits at the same percent, e.g.
+    /// when the route reaches a percent twice or the counter sits at a value across two splits.
+    pub min_time: Option<Duration>,
     pub time: Option<Duration>,
     pub best_segment: Option<Duration>,
+    /// Route notes, shown while heading for this split
+    pub notes: Option<String>,
     pub history: Vec<HistoricalSplit>,
 }
 
+impl Split {
+    /// Whether this is the split at `percent` with the minimum IGT `min_time`
+    fn is_at(&self, percent: u32, min_time: Option<Duration>) -> bool {
+        self.percent == percent && self.min_time == min_time
+    }
+}
+
+/// Secondary trigger of the split at `percent`: it is also reached once the HUD counter named
+/// `counter` (e.g. gems) shows at least `value`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CounterTrigger {
+    pub percent: u32,
+    pub counter: String,
+    pub value: u32,
+}
+
+/// Secondary trigger of the split at `percent`: it is also reached once the level name banner shows
+/// `level`. Allows telling apart routes that reach the same percent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelTrigger {
+    pub percent: u32,
+    pub level: String,
+}
+
+/// Split times of the split at `percent` (and `min_time`) that were moved to the archive: how many
+/// and their sum. Keeps the averages of the history intact after archiving.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchivedTimes {
+    pub percent: u32,
+    pub min_time: Option<Duration>,
+    pub count: u32,
+    pub total: Duration,
+}
+
+/// A former personal best, replaced by a faster run
+#[derive(Debug, Clone, PartialEq)]
+pub struct PersonalBestRecord {
+    pub id: Uuid,
+    pub date: DateTime<Utc>,
+    pub final_time: Duration,
+}
+
+/// Spread of the segment times of one split over all runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentStatistics {
+    pub count: usize,
+    pub min: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+    pub std_dev: Duration,
+}
+
+/// Inconsistency in the splits found by `Splits::check`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inconsistency {
+    /// Split time of a run that isn't in the runs, e.g. left over from editing the file by hand
+    UnknownRun { split: String, run_id: Uuid },
+    /// The PB has another final time than its run in the runs
+    PbDiffersFromRun {
+        pb: Option<Duration>,
+        run: Option<Duration>,
+    },
+    /// The PB isn't the fastest finished run (or not finished at all)
+    PbNotFastest {
+        pb: Option<Duration>,
+        fastest: Option<Duration>,
+    },
+    /// A split time of a run is lower than the time of an earlier split of the same run
+    DecreasingTime {
+        run_id: Uuid,
+        split: String,
+        time: Duration,
+        previous: Duration,
+    },
+}
+
+impl Inconsistency {
+    /// Whether `Splits::repair` fixes it, the others need to be corrected by hand
+    pub fn is_fixable(&self) -> bool {
+        !matches!(self, Inconsistency::DecreasingTime { .. })
+    }
+}
+
+impl std::fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Inconsistency::UnknownRun { split, run_id } => write!(
+                f,
+                "\"{}\" has a time of run {}, which isn't in the runs",
+                split, run_id
+            ),
+            Inconsistency::PbDiffersFromRun { pb, run } => write!(
+                f,
+                "The PB ({}) has another final time than its run ({})",
+                Splits::format_time(*pb),
+                Splits::format_time(*run)
+            ),
+            Inconsistency::PbNotFastest { pb, fastest } => write!(
+                f,
+                "The PB ({}) isn't the fastest finished run ({})",
+                Splits::format_time(*pb),
+                Splits::format_time(*fastest)
+            ),
+            Inconsistency::DecreasingTime {
+                run_id,
+                split,
+                time,
+                previous,
+            } => write!(
+                f,
+                "Run {} reached \"{}\" at {}, before the time of an earlier split ({}), correct it with `runs correct`",
+                run_id,
+                split,
+                format_hms(*time),
+                format_hms(*previous)
+            ),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Splits {
     path: Option<PathBuf>,
     active_run: Option<ActiveRun>,
     segmented: bool,
+    /// Splits are matched in the declared order of the route rather than by percent
+    ordered: bool,
     personal_best: Option<RunSummary>,
     runs: Vec<RunSummary>,
     splits: Vec<Split>,
+    triggers: Vec<CounterTrigger>,
+    level_triggers: Vec<LevelTrigger>,
+    max_percent: Option<u32>,
+    archived: Vec<ArchivedTimes>,
+    previous_pbs: Vec<PersonalBestRecord>,
+    /// Category of the runs started from now on
+    run_category: Option<String>,
 }
 
 impl Splits {
@@ -61,9 +239,16 @@ impl Splits {
             path: None,
             active_run: None,
             segmented: false,
+            ordered: false,
             personal_best: None,
             runs: Vec::new(),
             splits: Vec::new(),
+            triggers: Vec::new(),
+            level_triggers: Vec::new(),
+            max_percent: None,
+            archived: Vec::new(),
+            previous_pbs: Vec::new(),
+            run_category: None,
         }
     }
 
@@ -73,9 +258,16 @@ impl Splits {
             path: Some(path),
             active_run: None,
             segmented: false,
+            ordered: false,
             personal_best: None,
             runs: Vec::new(),
             splits,
+            triggers: Vec::new(),
+            level_triggers: Vec::new(),
+            max_percent: None,
+            archived: Vec::new(),
+            previous_pbs: Vec::new(),
+            run_category: None,
         };
         splits.validate()?;
         Ok(splits)
@@ -103,14 +295,153 @@ impl Splits {
             path: Some(path),
             active_run: None,
             segmented: segmented,
+            ordered: false,
+            personal_best,
+            runs,
+            splits,
+            triggers: Vec::new(),
+            level_triggers: Vec::new(),
+            max_percent: None,
+            archived: Vec::new(),
+            previous_pbs: Vec::new(),
+            run_category: None,
+        };
+        splits.validate()?;
+        Ok(splits)
+    }
+
+    /// Creates splits that are matched in the declared order of the route rather than by percent,
+    /// for categories visiting percents out of numeric order. Currently only used for tests and
+    /// deserialization
+    pub fn create_ordered_with_history(
+        path: PathBuf,
+        segmented: bool,
+        personal_best: Option<RunSummary>,
+        runs: Vec<RunSummary>,
+        splits: Vec<Split>,
+    ) -> anyhow::Result<Self> {
+        let mut splits = Splits {
+            path: Some(path),
+            segmented,
+            ordered: true,
             personal_best,
             runs,
             splits,
+            ..Splits::new()
         };
         splits.validate()?;
         Ok(splits)
     }
 
+    /// Sets the counter triggers, each of which has to reference an existing split.
+    pub fn with_triggers(mut self, triggers: Vec<CounterTrigger>) -> anyhow::Result<Self> {
+        if let Some(trigger) = triggers
+            .iter()
+            .find(|t| !self.splits.iter().any(|s| s.percent == t.percent))
+        {
+            bail!(
+                "Trigger on counter '{}' references unknown split {}%",
+                trigger.counter,
+                trigger.percent
+            );
+        }
+        self.triggers = triggers;
+        Ok(self)
+    }
+
+    /// Sets the level triggers, each of which has to reference an existing split.
+    pub fn with_level_triggers(
+        mut self,
+        level_triggers: Vec<LevelTrigger>,
+    ) -> anyhow::Result<Self> {
+        if let Some(trigger) = level_triggers
+            .iter()
+            .find(|t| !self.splits.iter().any(|s| s.percent == t.percent))
+        {
+            bail!(
+                "Trigger on level '{}' references unknown split {}%",
+                trigger.level,
+                trigger.percent
+            );
+        }
+        self.level_triggers = level_triggers;
+        Ok(self)
+    }
+
+    /// Sets the highest percentage of the category (e.g. 100, 117 or 120), which no split may exceed.
+    /// Readings above it are misreads.
+    pub fn with_max_percent(mut self, max_percent: Option<u32>) -> anyhow::Result<Self> {
+        if let Some(max) = max_percent
+            && let Some(split) = self.splits.iter().find(|s| s.percent > max)
+        {
+            bail!(
+                "Split '{}' at {}% is above the category's maximum of {}%",
+                split.name,
+                split.percent,
+                max
+            );
+        }
+        self.max_percent = max_percent;
+        Ok(self)
+    }
+
+    /// Sets the totals of the archived split times, each of which has to reference an existing
+    /// split.
+    pub fn with_archived(mut self, archived: Vec<ArchivedTimes>) -> anyhow::Result<Self> {
+        if let Some(times) = archived
+            .iter()
+            .find(|t| !self.splits.iter().any(|s| s.is_at(t.percent, t.min_time)))
+        {
+            bail!("Archived times reference unknown split {}%", times.percent);
+        }
+        self.archived = archived;
+        Ok(self)
+    }
+
+    /// Sets the former personal bests, oldest first.
+    pub fn with_previous_pbs(mut self, previous_pbs: Vec<PersonalBestRecord>) -> Self {
+        self.previous_pbs = previous_pbs;
+        self
+    }
+
+    /// Sets the file the splits are saved to, e.g. to write them to a new file.
+    pub fn with_path(mut self, path: PathBuf) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    /// Resumes `active_run` (e.g. as saved before a restart), which has to be an unfinished run in
+    /// the history. The next IGT continues it unless it is lower than the run's latest split.
+    pub fn with_resumed_run(mut self, active_run: Option<ActiveRun>) -> anyhow::Result<Self> {
+        let Some(active_run) = active_run else {
+            return Ok(self);
+        };
+        let Some(run) = self.runs.iter_mut().find(|run| run.id == active_run.id) else {
+            bail!("Active run {} isn't part of the runs", active_run.id);
+        };
+        if run.final_time.is_some() {
+            bail!("Active run {} is already finished", active_run.id);
+        }
+
+        // No longer interrupted
+        run.end_time = None;
+        self.active_run = Some(ActiveRun {
+            end_time: None,
+            ..active_run
+        });
+        Ok(self)
+    }
+
+    /// The run to resume after a restart: the active run, if it was neither finished nor started
+    /// before the first reading (see `initialize_active_run`). Interrupted runs are resumable.
+    pub fn resumable_run(&self) -> Option<&ActiveRun> {
+        let active_run = self.active_run.as_ref()?;
+        self.runs
+            .iter()
+            .any(|run| run.id == active_run.id && run.final_time.is_none())
+            .then_some(active_run)
+    }
+
     pub fn path(&self) -> Option<&PathBuf> {
         self.path.as_ref()
     }
@@ -123,6 +454,10 @@ impl Splits {
         self.segmented
     }
 
+    pub fn ordered(&self) -> bool {
+        self.ordered
+    }
+
     // This is a hack, might be one more argument for proper LiveSplit integration
     pub fn initialize_active_run(&mut self, time: &InGameTime) {
         if self.active_run.is_none() {
@@ -153,11 +488,16 @@ impl Splits {
 
         if let Some(active_run) = self.active_run() {
             let elapsed = active_run.latest_split.duration;
+            let position = self.last_split_index(active_run.id);
             let future_best_segments = self
                 .splits
                 .iter()
-                .filter(|&s| s.percent > active_run.latest_split.percent)
-                .filter_map(|s| s.best_segment)
+                .enumerate()
+                .filter(|&(index, s)| match position {
+                    Some(position) => index > position,
+                    None => s.percent > active_run.latest_split.percent,
+                })
+                .filter_map(|(_, s)| s.best_segment)
                 .sum();
             Some(elapsed + future_best_segments)
         } else {
@@ -173,6 +513,26 @@ impl Splits {
         &self.splits
     }
 
+    pub fn triggers(&self) -> &Vec<CounterTrigger> {
+        &self.triggers
+    }
+
+    pub fn level_triggers(&self) -> &Vec<LevelTrigger> {
+        &self.level_triggers
+    }
+
+    pub fn max_percent(&self) -> Option<u32> {
+        self.max_percent
+    }
+
+    pub fn archived(&self) -> &Vec<ArchivedTimes> {
+        &self.archived
+    }
+
+    pub fn previous_pbs(&self) -> &Vec<PersonalBestRecord> {
+        &self.previous_pbs
+    }
+
     /// Loads splits from a file
     pub fn load_from_file(path: &Path) -> anyhow::Result<Self> {
         let mut splits = crate::splits::file_persistency::load_from_file(path)?;
@@ -180,6 +540,37 @@ impl Splits {
         Ok(splits)
     }
 
+    /// Whether the splits file changed since it was loaded or saved, e.g. because it was edited by
+    /// hand.
+    pub fn modified_externally(&self) -> bool {
+        self.path
+            .as_deref()
+            .is_some_and(crate::splits::file_persistency::modified_externally)
+    }
+
+    /// Whether the splits file holds exactly these splits, i.e. loading it didn't have to correct
+    /// (e.g. sort the runs or set the PB times from the PB run) or upgrade anything.
+    pub fn matches_file(&self) -> anyhow::Result<bool> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No file path to compare to"))?;
+        crate::splits::file_persistency::matches_file(self, path)
+    }
+
+    /// Replaces the splits with the ones in their file, e.g. after it was edited by hand. Keeps the
+    /// category of new runs.
+    pub fn reload(&mut self) -> anyhow::Result<()> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No file path to load from"))?;
+        let mut reloaded = Splits::load_from_file(path)?;
+        reloaded.run_category = self.run_category.take();
+        *self = reloaded;
+        Ok(())
+    }
+
     /// Save splits to file
     pub fn save_to_file(&self) -> anyhow::Result<()> {
         let path = self
@@ -191,13 +582,25 @@ impl Splits {
     }
 
     fn validate(&mut self) -> anyhow::Result<()> {
-        // Splits must be sorted by percent
-        self.splits.sort_by(|a, b| a.percent.cmp(&b.percent));
+        // Splits must be sorted by percent, splits at the same percent by their minimum IGT. Ordered
+        // splits keep the order of the route.
+        if !self.ordered {
+            self.splits.sort_by_key(|s| (s.percent, s.min_time));
+        }
 
         // Splits can't contain duplicate entries
-        // We just sorted above, so we can rely on that.
-        if self.splits.windows(2).any(|s| s[0].percent == s[1].percent) {
-            bail!("Splits contain duplicate entries (percentages)");
+        let mut seen = HashSet::new();
+        if let Some(duplicate) = self
+            .splits
+            .iter()
+            .find(|s| !seen.insert((s.percent, s.min_time)))
+        {
+            bail!(
+                "Splits contain duplicate entries: \"{}\" has the same percentage ({}%) and minimum IGT ({}) as another split, change one of them",
+                duplicate.name,
+                duplicate.percent,
+                duplicate.min_time.map_or("none".to_string(), format_hms)
+            );
         }
 
         // Runs should be sorted according to start time
@@ -207,7 +610,10 @@ impl Splits {
         let mut run_indices: HashMap<Uuid, usize> = HashMap::new();
         for (idx, run) in self.runs().iter().enumerate() {
             if !run_indices.insert(run.id, idx).is_none() {
-                bail!("Runs have duplicate IDs");
+                bail!(
+                    "Runs have duplicate IDs: {} is used by more than one run, give each run a new UUID",
+                    run.id
+                );
             }
         }
 
@@ -249,6 +655,7 @@ impl Splits {
                         None => final_split.history.push(HistoricalSplit {
                             run_id: run.id,
                             duration: final_time,
+                            real_time: None,
                         }),
                     }
                 }
@@ -276,37 +683,188 @@ impl Splits {
             }
         }
 
-        // Compute best segments
-        let best_segments: Vec<_> = self
-            .splits
-            .iter()
-            .map(|s| self.compute_best_segment_for(s.percent))
+        self.derive_best_segments();
+
+        Ok(())
+    }
+
+    /// Computes the best segments from the history, keeping stored ones that are faster (e.g. from
+    /// runs that are no longer in the history).
+    fn derive_best_segments(&mut self) {
+        let best_segments: Vec<_> = (0..self.splits.len())
+            .map(|index| self.compute_best_segment_for(index))
             .collect();
 
         for (split, best_segment) in self.splits.iter_mut().zip(best_segments) {
-            split.best_segment = best_segment;
+            split.best_segment = match (split.best_segment, best_segment) {
+                (Some(stored), Some(computed)) => Some(stored.min(computed)),
+                (stored, computed) => stored.or(computed),
+            };
         }
+    }
 
-        Ok(())
+    /// Derives the best segments from the split times of all runs in the history and saves the
+    /// splits, e.g. for splits recorded before best segments were tracked. With `reset`, stored best
+    /// segments that no run in the history matches (e.g. from deleted or archived runs) are dropped.
+    /// Returns a row per split with its best segment, and the previous one if it changed.
+    pub fn recompute_best_segments(&mut self, reset: bool) -> anyhow::Result<Vec<String>> {
+        let previous: Vec<_> = self.splits.iter().map(|s| s.best_segment).collect();
+        if reset {
+            for split in &mut self.splits {
+                split.best_segment = None;
+            }
+        }
+        self.derive_best_segments();
+        self.save_to_file()?;
+
+        let name_width = self.compute_name_width();
+        Ok(self
+            .splits
+            .iter()
+            .zip(previous)
+            .map(|(split, previous)| {
+                let row = format!(
+                    "{} {:>8}",
+                    Self::pad_str(&Self::truncate_name(&split.name, name_width), name_width),
+                    Self::format_time(split.best_segment)
+                );
+                if previous == split.best_segment {
+                    row
+                } else {
+                    format!("{}  (was {})", row, Self::format_time(previous))
+                }
+            })
+            .collect())
+    }
+
+    /// Returns the split matching the given percent and IGT, if found.
+    pub fn find_split(&self, time: &InGameTime) -> Option<&Split> {
+        self.find_split_index(time).map(|index| &self.splits[index])
+    }
+
+    /// Index of the split at the percent of `time`. Ordered splits are looked up from the split the
+    /// active run is at.
+    pub fn find_split_index(&self, time: &InGameTime) -> Option<usize> {
+        let position = match &self.active_run {
+            Some(active_run) if self.ordered => self.last_split_index(active_run.id),
+            _ => None,
+        };
+        self.find_split_index_from(time, position.unwrap_or(0))
+    }
+
+    /// Index of the split at the percent of `time`, from `start` on. Of several splits at that
+    /// percent whose minimum IGT `time` has reached, ordered splits take the next one in the route,
+    /// other ones the last.
+    fn find_split_index_from(&self, time: &InGameTime, start: usize) -> Option<usize> {
+        let splits = self.splits.get(start..)?;
+        let matches = |s: &Split| {
+            s.percent == time.percent && s.min_time.is_none_or(|min| time.duration >= min)
+        };
+        let index = match self.ordered {
+            true => splits.iter().position(matches),
+            false => splits.iter().rposition(matches),
+        };
+        index.map(|index| start + index)
     }
 
-    /// Returns the split matching the given percent, if found.
-    fn find_by_percent(&self, time: &InGameTime) -> Option<&Split> {
-        self.splits.iter().find(|s| s.percent == time.percent)
+    /// Index of the split at the percent of `time` like `find_split_index`, but regardless of where
+    /// the active run is, e.g. for the readings of a recorded run.
+    pub fn split_index_at(&self, time: &InGameTime) -> Option<usize> {
+        self.find_split_index_from(time, 0)
     }
 
-    fn find_by_percent_mut(&mut self, time: &InGameTime) -> Option<&mut Split> {
-        self.splits.iter_mut().find(|s| s.percent == time.percent)
+    /// Index of the split corresponding to the split at `index` of `other`, e.g. the splits of
+    /// another runner: the one at the same percent and minimum IGT, counting splits repeating both.
+    pub fn matching_split_index(&self, other: &Splits, index: usize) -> Option<usize> {
+        let split = other.splits.get(index)?;
+        let repetition = other.splits[..index]
+            .iter()
+            .filter(|s| s.is_at(split.percent, split.min_time))
+            .count();
+        self.splits
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.is_at(split.percent, split.min_time))
+            .nth(repetition)
+            .map(|(i, _)| i)
     }
 
-    fn find_split_index_by_percent(&self, time: &InGameTime) -> Option<usize> {
-        self.splits.iter().position(|s| s.percent == time.percent)
+    /// Whether `current` can't follow the latest split of `active_run`, i.e. the game was reset:
+    /// the percentage went down or, for ordered splits, no split from the one the run is at on
+    /// matches.
+    fn is_reset(&self, active_run: &ActiveRun, current: &InGameTime) -> bool {
+        let latest = &active_run.latest_split;
+        if !self.ordered {
+            return current.percent < latest.percent
+                || (current.percent == latest.percent
+                    && self.find_split_index(current) < self.find_split_index(latest));
+        }
+        match self.last_split_index(active_run.id) {
+            Some(position) => self.find_split_index_from(current, position).is_none(),
+            // Run started before the first reading (see `initialize_active_run`), the position in
+            // the route is unknown
+            None => current.duration < latest.duration,
+        }
     }
 
     fn is_final_split(&self, time: &InGameTime) -> bool {
+        !self.splits.is_empty() && self.find_split_index(time) == Some(self.splits.len() - 1)
+    }
+
+    /// Whether the run skipped the split at `index`: it has no time for it but a time for a later
+    /// split, e.g. because the guidebook wasn't opened at that milestone.
+    pub fn is_skipped(&self, run_id: Uuid, index: usize) -> bool {
+        let has_time = |split: &Split| split.history.iter().any(|hs| hs.run_id == run_id);
+        index < self.splits.len()
+            && !has_time(&self.splits[index])
+            && self.splits[index + 1..].iter().any(has_time)
+    }
+
+    /// Returns the segment time of every split in the run `run_id`, i.e. the time since the
+    /// previous split. `None` for splits the run has no time for and for segments spanning a
+    /// skipped split.
+    pub fn segment_times(&self, run_id: Uuid) -> Vec<Option<Duration>> {
         self.splits
-            .last()
-            .map_or(false, |s| s.percent == time.percent)
+            .iter()
+            .enumerate()
+            .map(|(index, split)| {
+                let duration = split
+                    .history
+                    .iter()
+                    .find(|hs| hs.run_id == run_id)?
+                    .duration;
+                if self.follows_skipped_split(run_id, index) {
+                    return None;
+                }
+                Some(self.compute_delta_at(run_id, index, duration))
+            })
+            .collect()
+    }
+
+    /// Turns cumulative split times (e.g. from `comparison_times`) into segment times. `None` where
+    /// the split or the one before has no time.
+    pub fn segments(times: &[Option<Duration>]) -> Vec<Option<Duration>> {
+        times
+            .iter()
+            .enumerate()
+            .map(|(index, &time)| {
+                let previous = match index {
+                    0 => Some(Duration::ZERO),
+                    _ => times[index - 1],
+                };
+                time?.checked_sub(previous?)
+            })
+            .collect()
+    }
+
+    /// Whether the segment of the split at `index` spans a split the run has no time for, so it
+    /// can't count as a best segment.
+    fn follows_skipped_split(&self, run_id: Uuid, index: usize) -> bool {
+        index > 0
+            && !self.splits[index - 1]
+                .history
+                .iter()
+                .any(|hs| hs.run_id == run_id)
     }
 
     /// Computes the delta time for the given split in the context of a run.
@@ -325,59 +883,114 @@ impl Splits {
     /// FIXME: we might eventually want to validate monotonicity of split
     /// times in `validate()` so that such cases are caught earlier.
     fn compute_delta_for(&self, run_id: Uuid, current: &InGameTime) -> Option<Duration> {
-        self.find_split_index_by_percent(current).map(|idx| {
-            self.splits[..idx]
-                .iter()
-                .rev()
-                .filter_map(|split| {
-                    split
-                        .history
-                        .iter()
-                        .find(|&hs| hs.run_id == run_id)
-                        .map(|hs| hs.duration)
-                })
-                .next()
-                .map(|prev| current.duration.checked_sub(prev))
-                .flatten()
-                .unwrap_or(current.duration)
-        })
+        self.find_split_index(current)
+            .map(|idx| self.compute_delta_at(run_id, idx, current.duration))
     }
 
-    fn compute_best_segment_for(&self, percent: u32) -> Option<Duration> {
-        self.splits
+    /// Delta time of the split at `index`, see `compute_delta_for`.
+    fn compute_delta_at(&self, run_id: Uuid, index: usize, duration: Duration) -> Duration {
+        self.splits[..index]
             .iter()
-            .find(|&s| s.percent == percent)
-            .and_then(|split| {
+            .rev()
+            .filter_map(|split| {
                 split
                     .history
                     .iter()
-                    .filter_map(|hs| {
-                        self.compute_delta_for(
-                            hs.run_id,
-                            &InGameTime {
-                                percent,
-                                duration: hs.duration,
-                            },
-                        )
-                    })
-                    .min()
+                    .find(|&hs| hs.run_id == run_id)
+                    .map(|hs| hs.duration)
             })
+            .next()
+            .and_then(|prev| duration.checked_sub(prev))
+            .unwrap_or(duration)
     }
 
-    fn compare(&self, current: &InGameTime) -> Option<(i64, &Split)> {
-        if let Some(split) = self.find_by_percent(current) {
-            match split.time {
-                Some(duration) => {
-                    let delta = current.duration.as_secs() as i64 - duration.as_secs() as i64;
-                    Some((delta, split))
-                }
-                None => None,
+    fn compute_best_segment_for(&self, index: usize) -> Option<Duration> {
+        self.splits[index]
+            .history
+            .iter()
+            .filter(|hs| !self.follows_skipped_split(hs.run_id, index))
+            .map(|hs| self.compute_delta_at(hs.run_id, index, hs.duration))
+            .min()
+    }
+
+    /// Returns the time of every split according to `comparison`, in the order of the splits. The
+    /// active run isn't part of the history compared to.
+    pub fn comparison_times(&self, comparison: Comparison) -> Vec<Option<Duration>> {
+        let active_run_id = self.active_run.as_ref().map(|r| r.id);
+        let previous = |split: &Split| -> Vec<Duration> {
+            split
+                .history
+                .iter()
+                .filter(|h| Some(h.run_id) != active_run_id)
+                .map(|h| h.duration)
+                .collect()
+        };
+
+        match comparison {
+            Comparison::PersonalBest => self.splits.iter().map(|s| s.time).collect(),
+            Comparison::BestSegments => self
+                .splits
+                .iter()
+                .scan(Some(Duration::ZERO), |sum, split| {
+                    *sum = sum.zip(split.best_segment).map(|(sum, best)| sum + best);
+                    Some(*sum)
+                })
+                .collect(),
+            Comparison::Average => self
+                .splits
+                .iter()
+                .map(|split| {
+                    let times = previous(split);
+                    let (count, total) = self
+                        .archived
+                        .iter()
+                        .find(|t| split.is_at(t.percent, t.min_time))
+                        .map_or((0, Duration::ZERO), |t| (t.count, t.total));
+                    let count = times.len() as u32 + count;
+                    (count > 0).then(|| (times.iter().sum::<Duration>() + total) / count)
+                })
+                .collect(),
+            Comparison::Median => self
+                .splits
+                .iter()
+                .map(|split| {
+                    let mut times = previous(split);
+                    times.sort();
+                    match times.len() {
+                        0 => None,
+                        n if n % 2 == 0 => Some((times[n / 2 - 1] + times[n / 2]) / 2),
+                        n => Some(times[n / 2]),
+                    }
+                })
+                .collect(),
+            Comparison::Latest => {
+                let latest = self
+                    .runs
+                    .iter()
+                    .rev()
+                    .find(|r| Some(r.id) != active_run_id)
+                    .map(|r| r.id);
+                self.splits
+                    .iter()
+                    .map(|split| {
+                        split
+                            .history
+                            .iter()
+                            .find(|h| Some(h.run_id) == latest)
+                            .map(|h| h.duration)
+                    })
+                    .collect()
             }
-        } else {
-            None
         }
     }
 
+    fn compare(&self, current: &InGameTime, comparison: Comparison) -> Option<(i64, &Split)> {
+        let idx = self.find_split_index(current)?;
+        let duration = self.comparison_times(comparison)[idx]?;
+        let delta = current.duration.as_secs() as i64 - duration.as_secs() as i64;
+        Some((delta, &self.splits[idx]))
+    }
+
     fn start_new_run_at(&mut self, current: &InGameTime, now: DateTime<Utc>) -> Uuid {
         let run_id = Uuid::new_v4();
         self.active_run = Some(ActiveRun {
@@ -389,7 +1002,12 @@ impl Splits {
         run_id
     }
 
-    fn finalize_run_at(&mut self, run_id: Uuid, current: &InGameTime, now: DateTime<Utc>) {
+    fn finalize_run_at(
+        &mut self,
+        run_id: Uuid,
+        current: &InGameTime,
+        now: DateTime<Utc>,
+    ) -> Vec<TimerEvent> {
         if let Some(active_run) = &mut self.active_run {
             active_run.end_time = Some(now);
         }
@@ -401,6 +1019,17 @@ impl Splits {
                 .and_then(|pb| pb.final_time)
                 .unwrap_or(Duration::MAX);
 
+        if is_pb
+            && let Some(pb) = &self.personal_best
+            && let Some(final_time) = pb.final_time
+        {
+            self.previous_pbs.push(PersonalBestRecord {
+                id: pb.id,
+                date: pb.end_time.unwrap_or(pb.start_time),
+                final_time,
+            });
+        }
+
         if let Some(run) = self.runs.iter_mut().find(|run| run.id == run_id) {
             run.end_time = Some(now);
             run.final_time = Some(current.duration);
@@ -410,6 +1039,10 @@ impl Splits {
             }
         }
 
+        let mut events = vec![TimerEvent::RunFinished {
+            run_id,
+            final_time: current.duration,
+        }];
         if is_pb {
             for split in &mut self.splits {
                 let pb = split.history.last();
@@ -421,18 +1054,31 @@ impl Splits {
                     split.time = None;
                 }
             }
+            events.push(TimerEvent::PbSet {
+                run_id,
+                final_time: current.duration,
+            });
         }
+        events
     }
 
-    fn record_split_time(&mut self, run_id: Uuid, current: &InGameTime) {
-        let delta = self.compute_delta_for(run_id, current);
+    /// Wall clock time since the start of `run_id`, if it is the active run
+    pub fn real_time_of(&self, run_id: Uuid) -> Option<Duration> {
+        let active_run = self.active_run.as_ref().filter(|run| run.id == run_id)?;
+        (Utc::now() - active_run.start_time).to_std().ok()
+    }
 
-        let idx = self
-            .splits
-            .iter()
-            .position(|s| s.percent == current.percent);
+    fn record_split_time(&mut self, run_id: Uuid, current: &InGameTime) -> Vec<TimerEvent> {
+        let idx = self.find_split_index(current);
+        let mut events = Vec::new();
 
         if let Some(idx) = idx {
+            let previous_best = self.splits[idx].best_segment;
+            // A segment spanning a skipped split can't be compared to the best segment
+            let delta = Some(self.compute_delta_at(run_id, idx, current.duration))
+                .filter(|_| !self.follows_skipped_split(run_id, idx));
+            let real_time = self.real_time_of(run_id);
+
             let current_split = &mut self.splits[idx];
 
             let existing = current_split
@@ -440,18 +1086,21 @@ impl Splits {
                 .last_mut()
                 .filter(|hs| hs.run_id == run_id);
 
+            let first_recorded = existing.is_none();
             match existing {
                 Some(entry) => {
                     entry.duration = current.duration;
+                    entry.real_time = real_time;
 
                     // Recompute best segment since we might have overwritten it when recording the previous time for this split
-                    let best_segment = self.compute_best_segment_for(current.percent);
+                    let best_segment = self.compute_best_segment_for(idx);
                     self.splits[idx].best_segment = best_segment;
                 }
                 None => {
                     current_split.history.push(HistoricalSplit {
                         run_id,
                         duration: current.duration,
+                        real_time,
                     });
 
                     if let Some(delta) = delta {
@@ -463,26 +1112,57 @@ impl Splits {
                     }
                 }
             }
+
+            events.push(TimerEvent::SplitRecorded {
+                run_id,
+                index: idx,
+                time: *current,
+            });
+            // Only when reaching the split, later readings of the same split only make it slower
+            if let Some(segment) = delta
+                && first_recorded
+                && previous_best.is_none_or(|best| segment < best)
+            {
+                events.push(TimerEvent::GoldAchieved {
+                    run_id,
+                    index: idx,
+                    segment,
+                });
+            }
         }
+        events
     }
 
-    pub fn update_with_igt(&mut self, current: &InGameTime) {
+    /// Feeds the IGT `current` into the runs: starts, continues, resets or finishes the active run.
+    /// Returns what happened, for the observers of the splits (see `TimerObserver`), which e.g.
+    /// save them.
+    pub fn update_with_igt(&mut self, current: &InGameTime) -> Vec<TimerEvent> {
         let now = Utc::now();
+        let mut events = Vec::new();
 
         // Check if current percent corresponds to a known split
-        if self.find_by_percent(current).is_none() {
+        if self.find_split_index_from(current, 0).is_none() {
             // Unknown percent -> no-op
-            return;
+            return events;
         }
+        let is_reset = self
+            .active_run
+            .as_ref()
+            .is_some_and(|active_run| self.is_reset(active_run, current));
 
         let run_id: Option<Uuid> = match &mut self.active_run {
             Some(active_run) => {
-                if current.percent < active_run.latest_split.percent {
+                if is_reset {
                     // IGT has regressed, treat it as reset
+                    if active_run.end_time.is_none() {
+                        events.push(TimerEvent::RunReset {
+                            run_id: active_run.id,
+                        });
+                    }
                     None
                 } else if active_run.end_time.is_some() {
                     // If the active run is already finished, ignore updates
-                    return;
+                    return events;
                 } else {
                     active_run.latest_split = *current;
                     Some(active_run.id)
@@ -501,72 +1181,968 @@ impl Splits {
                     start_time: now,
                     end_time: None,
                     final_time: None,
+                    category: self.run_category.clone(),
+                    tags: Vec::new(),
+                    notes: None,
                 });
+                events.push(TimerEvent::RunStarted { run_id });
                 run_id
             }
         };
 
-        self.record_split_time(run_id, current);
+        events.extend(self.record_split_time(run_id, current));
 
         if self.is_final_split(current) {
-            self.finalize_run_at(run_id, current, now);
+            events.extend(self.finalize_run_at(run_id, current, now));
         }
 
-        self.save_to_file();
+        events
     }
 
-    pub fn compare_and_print(&self, current: &InGameTime) {
-        // TODO: handle `None` case (print something like '-', check what LiveSplit does)
-        if let Some((delta, split)) = self.compare(current) {
-            let name_width = self.compute_name_width();
-            let display_name = Self::truncate_name(&split.name, name_width);
-            let colored_delta = if delta >= 0 {
-                let delta_str = format!("+{:02}:{:02}", delta / 60, delta % 60);
-                delta_str.red()
-            } else {
-                let delta_str = format!("-{:02}:{:02}", delta.abs() / 60, delta.abs() % 60);
-                delta_str.green()
-            };
-
-            let current_str = Self::format_time(Some(current.duration));
-            println!(
-                "{} {:>8} {:>8}",
-                Self::pad_str(&display_name, name_width),
-                colored_delta,
-                current_str
-            );
+    /// Ends the active run without finishing it (e.g. when shutting down) and saves the splits. The
+    /// run is kept as an interrupted run, i.e. with an end time but without a final time, and resumed
+    /// when the splits are loaded again.
+    pub fn interrupt_active_run(&mut self) -> anyhow::Result<()> {
+        let Some(active_run) = &mut self.active_run else {
+            return Ok(());
+        };
+        if active_run.end_time.is_some() {
+            return Ok(());
         }
-    }
-
-    /// Prints all the splits in order, without time comparison.
-    pub fn print_splits(&self) {
-        let name_width = self.compute_name_width();
 
-        for split in &self.splits {
-            Self::print_split(name_width, split);
+        let now = Utc::now();
+        active_run.end_time = Some(now);
+        let run_id = active_run.id;
+        if let Some(run) = self.runs.iter_mut().find(|run| run.id == run_id) {
+            run.end_time = Some(now);
         }
-    }
 
-    pub fn print_split(name_width: usize, split: &Split) {
-        let display_name = Self::truncate_name(&split.name, name_width);
-        let duration_str = Self::format_time(split.time);
-        println!(
-            "{} {:>8} {:>8}",
-            Self::pad_str(&display_name, name_width),
-            " ",
-            duration_str
-        );
+        self.save_to_file()
     }
 
-    pub fn compute_name_width(&self) -> usize {
-        const MAX_NAME_WIDTH: usize = 25;
+    /// Removes the split time the active run recorded last, e.g. after a misread slipped through,
+    /// and saves the splits. The run continues from the split before; a run without split times
+    /// left is discarded. Returns the removed split time. Finished runs can't be undone.
+    pub fn undo_last_split(&mut self) -> anyhow::Result<InGameTime> {
+        let Some(active_run) = &self.active_run else {
+            bail!("No active run");
+        };
+        if active_run.end_time.is_some() {
+            bail!("The run is already finished");
+        }
+        let run_id = active_run.id;
 
-        self.splits
+        // Times of the active run are always the last ones in the history of a split
+        let last_recorded = self
+            .splits
             .iter()
-            .map(|s| s.name.len())
-            .max()
-            .map(|len| len.min(MAX_NAME_WIDTH))
-            .unwrap_or(0)
+            .enumerate()
+            .rev()
+            .find_map(|(index, split)| {
+                split
+                    .history
+                    .last()
+                    .filter(|hs| hs.run_id == run_id)
+                    .map(|hs| {
+                        let time = InGameTime {
+                            percent: split.percent,
+                            duration: hs.duration,
+                        };
+                        (index, time)
+                    })
+            });
+        let Some((index, removed)) = last_recorded else {
+            bail!("The active run has no split times");
+        };
+
+        let removed_segment = Some(self.compute_delta_at(run_id, index, removed.duration))
+            .filter(|_| !self.follows_skipped_split(run_id, index));
+        self.splits[index].history.pop();
+
+        // Only a best segment set by the removed time is recomputed, other ones might not be part
+        // of the history
+        if removed_segment.is_some() && self.splits[index].best_segment == removed_segment {
+            self.splits[index].best_segment = self.compute_best_segment_for(index);
+        }
+
+        let previous = self.splits[..index].iter().rev().find_map(|split| {
+            split
+                .history
+                .iter()
+                .find(|hs| hs.run_id == run_id)
+                .map(|hs| InGameTime {
+                    percent: split.percent,
+                    duration: hs.duration,
+                })
+        });
+        match previous {
+            Some(previous) => {
+                if let Some(active_run) = &mut self.active_run {
+                    active_run.latest_split = previous;
+                }
+            }
+            None => {
+                self.active_run = None;
+                self.runs.retain(|run| run.id != run_id);
+            }
+        }
+
+        self.save_to_file()?;
+        Ok(removed)
+    }
+
+    /// Deletes the run `run_id` with its split times, e.g. a run started by a misread, and saves the
+    /// splits. If it was the PB, the fastest remaining finished run becomes the PB. Best segments of
+    /// the run are recomputed from the remaining history.
+    pub fn delete_run(&mut self, run_id: Uuid) -> anyhow::Result<()> {
+        if !self.runs.iter().any(|run| run.id == run_id) {
+            bail!("No run with ID {}", run_id);
+        }
+
+        let segments = self.segment_times(run_id);
+        for (split, segment) in self.splits.iter_mut().zip(segments) {
+            if segment.is_some() && split.best_segment == segment {
+                split.best_segment = None;
+            }
+        }
+        self.previous_pbs.retain(|pb| pb.id != run_id);
+        self.remove_runs(|id| id == run_id)?;
+        // A former PB that became the PB again
+        if let Some(pb) = &self.personal_best {
+            let pb_id = pb.id;
+            self.previous_pbs.retain(|previous| previous.id != pb_id);
+        }
+        self.save_to_file()
+    }
+
+    /// Deletes all runs but the latest `keep` ones and the PB, and saves the splits. Best segments
+    /// are kept. Returns the number of deleted runs.
+    pub fn prune_runs(&mut self, keep: usize) -> anyhow::Result<usize> {
+        let pruned = self.runs_beyond(keep);
+        self.remove_runs(|id| pruned.contains(&id))?;
+        self.save_to_file()?;
+        Ok(pruned.len())
+    }
+
+    /// Moves all runs but the latest `keep` ones and the PB, with their split times, to the splits
+    /// file at `archive_path` (merging them into it if it exists) and saves both files. Best
+    /// segments are kept and the archived split times still count for the `Average` comparison.
+    /// Returns the number of archived runs.
+    pub fn archive_runs(&mut self, keep: usize, archive_path: &Path) -> anyhow::Result<usize> {
+        let archived_ids = self.runs_beyond(keep);
+        if archived_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let runs: Vec<RunSummary> = self
+            .runs
+            .iter()
+            .filter(|run| archived_ids.contains(&run.id))
+            .cloned()
+            .collect();
+        let splits: Vec<Split> = self
+            .splits
+            .iter()
+            .map(|split| Split {
+                name: split.name.clone(),
+                percent: split.percent,
+                min_time: split.min_time,
+                time: None,
+                best_segment: None,
+                notes: None,
+                history: split
+                    .history
+                    .iter()
+                    .filter(|hs| archived_ids.contains(&hs.run_id))
+                    .cloned()
+                    .collect(),
+            })
+            .collect();
+
+        let archive = if archive_path.exists() {
+            let mut archive = Splits::load_from_file(archive_path)?;
+            archive.runs.extend(runs);
+            for split in splits {
+                match archive
+                    .splits
+                    .iter_mut()
+                    .find(|s| s.is_at(split.percent, split.min_time))
+                {
+                    Some(archived) => archived.history.extend(split.history),
+                    None => archive.splits.push(split),
+                }
+            }
+            archive.validate()?;
+            archive
+        } else {
+            Splits::create_with_history(archive_path.to_path_buf(), None, runs, splits)?
+        };
+        // Written first, so that a failure doesn't lose any runs
+        archive.save_to_file()?;
+
+        for split in &self.splits {
+            let archived: Vec<Duration> = split
+                .history
+                .iter()
+                .filter(|hs| archived_ids.contains(&hs.run_id))
+                .map(|hs| hs.duration)
+                .collect();
+            if archived.is_empty() {
+                continue;
+            }
+            let times = match self
+                .archived
+                .iter()
+                .position(|t| split.is_at(t.percent, t.min_time))
+            {
+                Some(index) => &mut self.archived[index],
+                None => {
+                    self.archived.push(ArchivedTimes {
+                        percent: split.percent,
+                        min_time: split.min_time,
+                        count: 0,
+                        total: Duration::ZERO,
+                    });
+                    self.archived.last_mut().expect("just pushed")
+                }
+            };
+            times.count += archived.len() as u32;
+            times.total += archived.iter().sum::<Duration>();
+        }
+
+        self.remove_runs(|id| archived_ids.contains(&id))?;
+        self.save_to_file()?;
+        Ok(archived_ids.len())
+    }
+
+    /// Archive file next to the splits file, e.g. `spyro.archive.json` for `spyro.json` and
+    /// `spyro.archive.json.gz` for `spyro.json.gz`
+    pub fn archive_path(&self) -> Option<PathBuf> {
+        let path = self.path.as_ref()?;
+        if crate::splits::file_persistency::is_compressed(path) {
+            let stem = Path::new(path.file_stem()?).file_stem()?.to_string_lossy();
+            return Some(path.with_file_name(format!("{}.archive.json.gz", stem)));
+        }
+        let stem = path.file_stem()?.to_string_lossy();
+        Some(path.with_file_name(format!("{}.archive.json", stem)))
+    }
+
+    /// Backup next to the splits file, e.g. `spyro.json.bak` for `spyro.json`, holding the version
+    /// before the last save
+    pub fn backup_path(&self) -> Option<PathBuf> {
+        self.path
+            .as_deref()
+            .map(crate::splits::file_persistency::backup_path)
+    }
+
+    /// Replaces the splits file at `path` with its backup if it is damaged, e.g. truncated, rather
+    /// than invalid. The damaged file is kept as `<path>.damaged`. Returns whether it was restored.
+    pub fn restore_if_damaged(path: &Path) -> anyhow::Result<bool> {
+        crate::splits::file_persistency::restore_if_damaged(path)
+    }
+
+    /// Adds the runs of `other` (e.g. splits recorded on another machine) with their split times,
+    /// skipping runs that are already in the history. The PB becomes the fastest finished run of
+    /// both, best segments the faster ones of both. The active run is kept. `other` must have the
+    /// same layout of splits; its archived split times are not merged. Returns the number of added
+    /// runs.
+    pub fn merge(&mut self, other: Splits) -> anyhow::Result<usize> {
+        if self.segmented != other.segmented || self.ordered != other.ordered {
+            bail!("Can't merge segmented or ordered splits with splits of another kind");
+        }
+        if let Some(split) = other.splits.iter().find(|split| {
+            !self
+                .splits
+                .iter()
+                .any(|s| s.is_at(split.percent, split.min_time))
+        }) {
+            bail!(
+                "No split {} at {}% to merge into",
+                split.name,
+                split.percent
+            );
+        }
+
+        let known: HashSet<Uuid> = self.runs.iter().map(|run| run.id).collect();
+        let added: HashSet<Uuid> = other
+            .runs
+            .iter()
+            .map(|run| run.id)
+            .filter(|id| !known.contains(id))
+            .collect();
+        self.runs
+            .extend(other.runs.into_iter().filter(|run| added.contains(&run.id)));
+        for split in other.splits {
+            let merged = self
+                .splits
+                .iter_mut()
+                .find(|s| s.is_at(split.percent, split.min_time))
+                .expect("checked above");
+            merged.history.extend(
+                split
+                    .history
+                    .into_iter()
+                    .filter(|hs| added.contains(&hs.run_id)),
+            );
+            merged.best_segment = match (merged.best_segment, split.best_segment) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+        }
+
+        self.personal_best = self
+            .runs
+            .iter()
+            .filter(|run| run.final_time.is_some())
+            .min_by_key(|run| run.final_time)
+            .cloned();
+        for record in other.previous_pbs {
+            if !self.previous_pbs.iter().any(|pb| pb.id == record.id) {
+                self.previous_pbs.push(record);
+            }
+        }
+        let pb_id = self.personal_best.as_ref().map(|pb| pb.id);
+        self.previous_pbs.retain(|pb| Some(pb.id) != pb_id);
+        self.previous_pbs.sort_by_key(|pb| pb.date);
+
+        self.validate()?;
+        Ok(added.len())
+    }
+
+    /// IDs of all runs but the latest `keep` ones and the PB
+    fn runs_beyond(&self, keep: usize) -> Vec<Uuid> {
+        let pb_id = self.personal_best.as_ref().map(|pb| pb.id);
+        self.runs
+            .iter()
+            .rev()
+            .skip(keep)
+            .map(|run| run.id)
+            .filter(|&id| Some(id) != pb_id)
+            .collect()
+    }
+
+    /// Removes the runs matching `remove` and their split times, picking a new PB if needed.
+    fn remove_runs(&mut self, remove: impl Fn(Uuid) -> bool) -> anyhow::Result<()> {
+        self.runs.retain(|run| !remove(run.id));
+        for split in &mut self.splits {
+            split.history.retain(|hs| !remove(hs.run_id));
+        }
+        if self.active_run.as_ref().is_some_and(|run| remove(run.id)) {
+            self.active_run = None;
+        }
+        if self.personal_best.as_ref().is_some_and(|pb| remove(pb.id)) {
+            self.personal_best = self
+                .runs
+                .iter()
+                .filter(|run| run.final_time.is_some())
+                .min_by_key(|run| run.final_time)
+                .cloned();
+        }
+        self.validate()
+    }
+
+    /// Sets the category of the runs started from now on, e.g. for the current session.
+    pub fn set_run_category(&mut self, category: Option<String>) {
+        self.run_category = category;
+    }
+
+    /// Sets the category, tags and notes of the run `run_id`, keeping the ones that aren't given
+    /// (`None`), and saves the splits.
+    pub fn set_run_metadata(
+        &mut self,
+        run_id: Uuid,
+        category: Option<String>,
+        tags: Option<Vec<String>>,
+        notes: Option<String>,
+    ) -> anyhow::Result<()> {
+        let Some(run) = self.runs.iter_mut().find(|run| run.id == run_id) else {
+            bail!("No run with ID {}", run_id);
+        };
+        if category.is_some() {
+            run.category = category;
+        }
+        if let Some(tags) = tags {
+            run.tags = tags;
+        }
+        if notes.is_some() {
+            run.notes = notes;
+        }
+        if let Some(pb) = &mut self.personal_best
+            && pb.id == run_id
+        {
+            *pb = run.clone();
+        }
+        self.save_to_file()
+    }
+
+    /// Corrects the time of the run `run_id` at the split named `split` (by default the last split
+    /// the run reached), e.g. a misread time, and saves the splits. The final time, PB and best
+    /// segments are updated accordingly. Returns the previous time.
+    pub fn correct_split_time(
+        &mut self,
+        run_id: Uuid,
+        split: Option<&str>,
+        duration: Duration,
+    ) -> anyhow::Result<Duration> {
+        if !self.runs.iter().any(|run| run.id == run_id) {
+            bail!("No run with ID {}", run_id);
+        }
+        let index = match split {
+            Some(name) => {
+                let mut matching = (0..self.splits.len()).filter(|&i| self.splits[i].name == name);
+                match (matching.next(), matching.next()) {
+                    (Some(index), None) => index,
+                    (None, _) => bail!("No split named {}", name),
+                    (Some(_), Some(_)) => bail!("Several splits are named {}", name),
+                }
+            }
+            None => self
+                .last_split_index(run_id)
+                .ok_or_else(|| anyhow::anyhow!("The run has no split times"))?,
+        };
+
+        let times: Vec<Option<Duration>> = self
+            .splits
+            .iter()
+            .map(|s| {
+                s.history
+                    .iter()
+                    .find(|hs| hs.run_id == run_id)
+                    .map(|hs| hs.duration)
+            })
+            .collect();
+        let Some(previous) = times[index] else {
+            bail!("The run has no time for {}", self.splits[index].name);
+        };
+        let earlier = times[..index].iter().rev().find_map(|&time| time);
+        let later = times[index + 1..].iter().find_map(|&time| time);
+        if earlier.is_some_and(|earlier| duration <= earlier)
+            || later.is_some_and(|later| duration >= later)
+        {
+            bail!(
+                "{} must be between the times of the previous and next splits of the run",
+                format_hms(duration)
+            );
+        }
+
+        // Best segments set by the wrong time are recomputed
+        let segments = self.segment_times(run_id);
+        for (split, segment) in self.splits.iter_mut().zip(segments) {
+            if segment.is_some() && split.best_segment == segment {
+                split.best_segment = None;
+            }
+        }
+        let last = self.splits.len() - 1;
+        let split = &mut self.splits[index];
+        if let Some(hs) = split.history.iter_mut().find(|hs| hs.run_id == run_id) {
+            hs.duration = duration;
+        }
+        let percent = split.percent;
+        if let Some(active_run) = &mut self.active_run
+            && active_run.id == run_id
+            && active_run.latest_split.duration == previous
+        {
+            active_run.latest_split = InGameTime::new(percent, duration);
+        }
+        if index == last
+            && let Some(run) = self.runs.iter_mut().find(|run| run.id == run_id)
+            && run.final_time.is_some()
+        {
+            run.final_time = Some(duration);
+            self.personal_best = self
+                .runs
+                .iter()
+                .filter(|run| run.final_time.is_some())
+                .min_by_key(|run| run.final_time)
+                .cloned();
+            let pb_id = self.personal_best.as_ref().map(|pb| pb.id);
+            self.previous_pbs.retain(|pb| Some(pb.id) != pb_id);
+        }
+
+        self.validate()?;
+        self.save_to_file()?;
+        Ok(previous)
+    }
+
+    /// Looks for inconsistencies that loading the splits doesn't correct: split times of unknown
+    /// runs, a PB that isn't the fastest finished run and runs whose split times go down.
+    pub fn check(&self) -> Vec<Inconsistency> {
+        let mut inconsistencies = Vec::new();
+        let active_run_id = self.active_run.as_ref().map(|r| r.id);
+        let known = |run_id: Uuid| {
+            Some(run_id) == active_run_id || self.runs.iter().any(|run| run.id == run_id)
+        };
+        for split in &self.splits {
+            let mut unknown: Vec<Uuid> = Vec::new();
+            for hs in &split.history {
+                if !known(hs.run_id) && !unknown.contains(&hs.run_id) {
+                    unknown.push(hs.run_id);
+                }
+            }
+            inconsistencies.extend(unknown.into_iter().map(|run_id| Inconsistency::UnknownRun {
+                split: split.name.clone(),
+                run_id,
+            }));
+        }
+
+        if let Some(pb) = &self.personal_best
+            && let Some(run) = self.runs.iter().find(|run| run.id == pb.id)
+            && run.final_time != pb.final_time
+        {
+            inconsistencies.push(Inconsistency::PbDiffersFromRun {
+                pb: pb.final_time,
+                run: run.final_time,
+            });
+        }
+        let pb = self.personal_best.as_ref().and_then(|pb| pb.final_time);
+        let fastest = self.runs.iter().filter_map(|run| run.final_time).min();
+        if fastest.is_some_and(|fastest| pb.is_none_or(|pb| fastest < pb)) {
+            inconsistencies.push(Inconsistency::PbNotFastest { pb, fastest });
+        }
+
+        for run in &self.runs {
+            let mut previous: Option<Duration> = None;
+            for split in &self.splits {
+                let Some(hs) = split.history.iter().find(|hs| hs.run_id == run.id) else {
+                    continue;
+                };
+                match previous {
+                    Some(previous) if hs.duration < previous => {
+                        inconsistencies.push(Inconsistency::DecreasingTime {
+                            run_id: run.id,
+                            split: split.name.clone(),
+                            time: hs.duration,
+                            previous,
+                        });
+                    }
+                    _ => previous = Some(hs.duration),
+                }
+            }
+        }
+        inconsistencies
+    }
+
+    /// Fixes the inconsistencies found by `check` that can be fixed without knowing the correct
+    /// times: drops the split times of unknown runs and makes the fastest finished run the PB, as
+    /// recorded in the runs. The splits aren't saved.
+    pub fn repair(&mut self) -> anyhow::Result<()> {
+        let active_run_id = self.active_run.as_ref().map(|r| r.id);
+        let run_ids: HashSet<Uuid> = self.runs.iter().map(|run| run.id).collect();
+        let known = |run_id: Uuid| Some(run_id) == active_run_id || run_ids.contains(&run_id);
+        // Best segments set by the unknown runs are derived again from the remaining runs
+        let unknown_golds: Vec<usize> = (0..self.splits.len())
+            .filter(|&index| {
+                self.splits[index].history.iter().any(|hs| {
+                    !known(hs.run_id)
+                        && self.splits[index].best_segment
+                            == Some(self.compute_delta_at(hs.run_id, index, hs.duration))
+                })
+            })
+            .collect();
+        for index in unknown_golds {
+            self.splits[index].best_segment = None;
+        }
+        for split in &mut self.splits {
+            split.history.retain(|hs| known(hs.run_id));
+        }
+
+        if let Some(pb) = &self.personal_best
+            && let Some(run) = self.runs.iter().find(|run| run.id == pb.id)
+        {
+            self.personal_best = Some(run.clone());
+        }
+        let pb = self.personal_best.as_ref().and_then(|pb| pb.final_time);
+        if let Some(fastest) = self
+            .runs
+            .iter()
+            .filter(|run| run.final_time.is_some())
+            .min_by_key(|run| run.final_time)
+            && pb.is_none_or(|pb| fastest.final_time.is_some_and(|time| time < pb))
+        {
+            self.personal_best = Some(fastest.clone());
+        }
+        self.validate()
+    }
+
+    /// Formats one line per run: ID, start time and final time, or the last split reached, followed
+    /// by the category, tags and notes of the run.
+    pub fn format_runs(&self) -> Vec<String> {
+        let pb_id = self.personal_best.as_ref().map(|pb| pb.id);
+        self.runs
+            .iter()
+            .map(|run| {
+                let result = match run.final_time {
+                    Some(final_time) => Self::format_time(Some(final_time)),
+                    None => match self.last_split_index(run.id) {
+                        Some(index) => format!("reset at {}%", self.splits[index].percent),
+                        None => String::from("no splits"),
+                    },
+                };
+                let mut line = format!(
+                    "{}  {}  {}{}",
+                    run.id,
+                    run.start_time
+                        .with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M"),
+                    result,
+                    if Some(run.id) == pb_id { "  (PB)" } else { "" }
+                );
+                if let Some(category) = &run.category {
+                    line.push_str(&format!("  [{}]", category));
+                }
+                if !run.tags.is_empty() {
+                    line.push_str(&format!("  #{}", run.tags.join(" #")));
+                }
+                if let Some(notes) = &run.notes {
+                    line.push_str(&format!("  \"{}\"", notes));
+                }
+                line
+            })
+            .collect()
+    }
+
+    /// Records the splits triggered by the counter `counter` showing `value`, at the IGT `duration`.
+    /// Returns what happened, like `update_with_igt`.
+    ///
+    /// Counter triggers are secondary to the percentage: they only record splits the active run
+    /// hasn't recorded yet, and never start or reset a run.
+    pub fn update_with_counter(
+        &mut self,
+        counter: &str,
+        value: u32,
+        duration: Duration,
+    ) -> Vec<TimerEvent> {
+        let percents = self
+            .triggers
+            .iter()
+            .filter(|t| t.counter == counter && value >= t.value)
+            .map(|t| t.percent)
+            .collect();
+        self.record_triggered_splits(percents, duration)
+    }
+
+    /// Records the splits triggered by entering the level `level` (as read from the level name
+    /// banner, compared case-insensitively), at the IGT `duration`. Returns what happened, like
+    /// `update_with_igt`.
+    ///
+    /// Like counter triggers, level triggers only record splits the active run hasn't recorded yet.
+    pub fn update_with_level(&mut self, level: &str, duration: Duration) -> Vec<TimerEvent> {
+        let level = normalize_level_name(level);
+        let percents = self
+            .level_triggers
+            .iter()
+            .filter(|t| normalize_level_name(&t.level) == level)
+            .map(|t| t.percent)
+            .collect();
+        self.record_triggered_splits(percents, duration)
+    }
+
+    /// Records the splits at `percents` that the active (unfinished) run hasn't recorded yet.
+    fn record_triggered_splits(
+        &mut self,
+        percents: Vec<u32>,
+        duration: Duration,
+    ) -> Vec<TimerEvent> {
+        let run_id = match &self.active_run {
+            Some(active_run) if active_run.end_time.is_none() => active_run.id,
+            _ => return Vec::new(),
+        };
+
+        let mut triggered: Vec<u32> = percents
+            .into_iter()
+            .filter(|&percent| {
+                self.find_split(&InGameTime { percent, duration })
+                    .is_some_and(|s| !s.history.iter().any(|hs| hs.run_id == run_id))
+            })
+            .collect();
+
+        triggered.sort();
+        triggered.dedup();
+
+        let now = Utc::now();
+        let mut events = Vec::new();
+        for &percent in &triggered {
+            let current = InGameTime { percent, duration };
+            events.extend(self.record_split_time(run_id, &current));
+
+            if self.is_final_split(&current) {
+                events.extend(self.finalize_run_at(run_id, &current, now));
+            }
+        }
+        events
+    }
+
+    pub fn compare_and_print(&self, current: &InGameTime, comparison: Comparison) {
+        if let Some(row) = self.format_comparison(current, comparison) {
+            println!("{}", row);
+        }
+    }
+
+    /// Formats the row of the split at the percent of `current`: name, delta to `comparison` and
+    /// current time. The delta is '-' if the comparison has no time for the split (e.g. no PB yet).
+    /// `None` if `current` isn't at a split.
+    pub fn format_comparison(
+        &self,
+        current: &InGameTime,
+        comparison: Comparison,
+    ) -> Option<String> {
+        let split = self.find_split(current)?;
+        let colored_delta = match self.compare(current, comparison) {
+            Some((delta, _)) if delta >= 0 => format!("+{:02}:{:02}", delta / 60, delta % 60).red(),
+            Some((delta, _)) => format!("-{:02}:{:02}", delta.abs() / 60, delta.abs() % 60).green(),
+            None => "-".normal(),
+        };
+
+        let name_width = self.compute_name_width();
+        let display_name = Self::truncate_name(&split.name, name_width);
+        let current_str = Self::format_time(Some(current.duration));
+        Some(format!(
+            "{} {:>8} {:>8}",
+            Self::pad_str(&display_name, name_width),
+            colored_delta,
+            current_str
+        ))
+    }
+
+    /// Prints all the splits in order, without time comparison.
+    pub fn print_splits(&self) {
+        let name_width = self.compute_name_width();
+
+        for split in &self.splits {
+            Self::print_split(name_width, split);
+        }
+    }
+
+    pub fn print_split(name_width: usize, split: &Split) {
+        println!("{}", Self::format_split(name_width, split));
+    }
+
+    /// Formats a split row (name and PB time) without time comparison.
+    pub fn format_split(name_width: usize, split: &Split) -> String {
+        let display_name = Self::truncate_name(&split.name, name_width);
+        let duration_str = Self::format_time(split.time);
+        format!(
+            "{} {:>8} {:>8}",
+            Self::pad_str(&display_name, name_width),
+            " ",
+            duration_str
+        )
+    }
+
+    /// Formats a summary line of the splits: PB, sum of best segments, the time that could still be
+    /// saved on the PB and the number of runs.
+    pub fn format_stats(&self) -> String {
+        let pb = self.personal_best.as_ref().and_then(|pb| pb.final_time);
+        let sob = self.sum_of_bests();
+        let timesave = match (pb, sob) {
+            (Some(pb), Some(sob)) => Self::format_time(Some(pb.saturating_sub(sob))),
+            _ => Self::format_time(None),
+        };
+        let finished = self.runs.iter().filter(|r| r.final_time.is_some()).count();
+        format!(
+            "PB {}  SoB {}  possible timesave {}  {} runs ({} finished)",
+            Self::format_time(pb),
+            Self::format_time(sob),
+            timesave,
+            self.runs.len(),
+            finished
+        )
+    }
+
+    /// Formats the completion rate of the runs, the average reset point and how many runs reached
+    /// each split.
+    pub fn format_run_statistics(&self) -> Vec<String> {
+        let total = self.runs.len();
+        let percentage = |count: usize| match total {
+            0 => 0.0,
+            _ => count as f64 * 100.0 / total as f64,
+        };
+
+        let finished = self.runs.iter().filter(|r| r.final_time.is_some()).count();
+        let mut lines = vec![format!(
+            "Completed {}/{} runs ({:.0}%)",
+            finished,
+            total,
+            percentage(finished)
+        )];
+        if let Some(reset) = self.average_reset_percent() {
+            lines.push(format!("Average reset at {:.0}%", reset));
+        }
+
+        let name_width = self.compute_name_width();
+        for (split, reached) in self.splits.iter().zip(self.survival_counts()) {
+            let name = Self::truncate_name(&split.name, name_width);
+            lines.push(format!(
+                "{} {:>5}/{} ({:.0}%)",
+                Self::pad_str(&name, name_width),
+                reached,
+                total,
+                percentage(reached)
+            ));
+        }
+        lines
+    }
+
+    /// Number of runs that reached each split, in the order of the splits. A run reached a split if
+    /// it has a time for that split or a later one.
+    pub fn survival_counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.splits.len()];
+        for run in &self.runs {
+            if let Some(last) = self.last_split_index(run.id) {
+                for count in &mut counts[..=last] {
+                    *count += 1;
+                }
+            }
+        }
+        counts
+    }
+
+    /// Average percentage of the last split reached by runs that were reset (i.e. neither finished
+    /// nor still active). `None` without such runs.
+    pub fn average_reset_percent(&self) -> Option<f64> {
+        let active_run_id = self
+            .active_run
+            .as_ref()
+            .filter(|r| r.end_time.is_none())
+            .map(|r| r.id);
+        let resets: Vec<u32> = self
+            .runs
+            .iter()
+            .filter(|r| r.final_time.is_none() && Some(r.id) != active_run_id)
+            .filter_map(|r| self.last_split_index(r.id))
+            .map(|index| self.splits[index].percent)
+            .collect();
+        (!resets.is_empty())
+            .then(|| resets.iter().map(|&p| p as f64).sum::<f64>() / resets.len() as f64)
+    }
+
+    /// Statistics of the segment times of every split over all runs, in the order of the splits.
+    /// `None` for splits without segment times.
+    pub fn segment_statistics(&self) -> Vec<Option<SegmentStatistics>> {
+        let mut segments: Vec<Vec<Duration>> = vec![Vec::new(); self.splits.len()];
+        for run in &self.runs {
+            for (times, segment) in segments.iter_mut().zip(self.segment_times(run.id)) {
+                times.extend(segment);
+            }
+        }
+
+        segments
+            .iter()
+            .map(|times| {
+                let count = times.len();
+                let min = *times.iter().min()?;
+                let max = *times.iter().max()?;
+                let mean = times.iter().map(Duration::as_secs_f64).sum::<f64>() / count as f64;
+                let variance = times
+                    .iter()
+                    .map(|t| (t.as_secs_f64() - mean).powi(2))
+                    .sum::<f64>()
+                    / count as f64;
+                Some(SegmentStatistics {
+                    count,
+                    min,
+                    max,
+                    mean: Duration::from_secs_f64(mean),
+                    std_dev: Duration::from_secs_f64(variance.sqrt()),
+                })
+            })
+            .collect()
+    }
+
+    /// Formats the spread of the segment times of every split, to tell consistent segments from
+    /// the ones that vary a lot.
+    pub fn format_segment_statistics(&self) -> Vec<String> {
+        let name_width = self.compute_name_width();
+        let mut lines = vec![format!(
+            "{} {:>5} {:>8} {:>8} {:>8} {:>8}",
+            Self::pad_str("Segment", name_width),
+            "runs",
+            "min",
+            "mean",
+            "max",
+            "std dev"
+        )];
+        for (split, statistics) in self.splits.iter().zip(self.segment_statistics()) {
+            let name = Self::pad_str(&Self::truncate_name(&split.name, name_width), name_width);
+            lines.push(match statistics {
+                Some(s) => format!(
+                    "{} {:>5} {:>8} {:>8} {:>8} {:>8}",
+                    name,
+                    s.count,
+                    Self::format_time(Some(s.min)),
+                    Self::format_time(Some(s.mean)),
+                    Self::format_time(Some(s.max)),
+                    Self::format_time(Some(s.std_dev))
+                ),
+                None => format!("{} {:>5}", name, 0),
+            });
+        }
+        lines
+    }
+
+    /// Index of the last split `run_id` has a time for
+    fn last_split_index(&self, run_id: Uuid) -> Option<usize> {
+        self.splits
+            .iter()
+            .rposition(|split| split.history.iter().any(|hs| hs.run_id == run_id))
+    }
+
+    /// Formats the PB progression, oldest first: date, final time and improvement of every PB.
+    pub fn format_pb_history(&self) -> Vec<String> {
+        let current = self.personal_best.as_ref().and_then(|pb| {
+            Some(PersonalBestRecord {
+                id: pb.id,
+                date: pb.end_time.unwrap_or(pb.start_time),
+                final_time: pb.final_time?,
+            })
+        });
+
+        let mut previous_time: Option<Duration> = None;
+        self.previous_pbs
+            .iter()
+            .chain(current.as_ref())
+            .map(|pb| {
+                let improvement = match previous_time {
+                    Some(previous) => {
+                        format!(
+                            "-{}",
+                            Self::format_time(Some(previous.saturating_sub(pb.final_time)))
+                        )
+                    }
+                    None => String::new(),
+                };
+                previous_time = Some(pb.final_time);
+                format!(
+                    "{}  {}  {}",
+                    pb.date.with_timezone(&chrono::Local).format("%Y-%m-%d"),
+                    Self::format_time(Some(pb.final_time)),
+                    improvement
+                )
+                .trim_end()
+                .to_string()
+            })
+            .collect()
+    }
+
+    /// Formats the best possible time row, shown below the splits while they update.
+    pub fn format_best_possible_time(&self, name_width: usize) -> String {
+        format!(
+            "{} {:>8} {:>8}",
+            Self::pad_str("BPT:", name_width),
+            " ",
+            Self::format_time(self.best_possible_time())
+        )
+    }
+
+    pub fn compute_name_width(&self) -> usize {
+        const MAX_NAME_WIDTH: usize = 25;
+
+        self.splits
+            .iter()
+            .map(|s| s.name.len())
+            .max()
+            .map(|len| len.min(MAX_NAME_WIDTH))
+            .unwrap_or(0)
     }
 
     /// Truncates a string to a max display width. If truncation is needed, adds "..".
@@ -607,13 +2183,7 @@ impl Splits {
 
     pub fn format_time(duration: Option<Duration>) -> String {
         match duration {
-            Some(duration) => {
-                let secs = duration.as_secs();
-                let hours = secs / 3600;
-                let minutes = (secs % 3600) / 60;
-                let seconds = secs % 60;
-                format!("{:01}:{:02}:{:02}", hours, minutes, seconds)
-            }
+            Some(duration) => format_hms(duration),
             None => {
                 format!("-:--:--")
             }
@@ -621,6 +2191,15 @@ impl Splits {
     }
 }
 
+/// Upper case with single spaces, since OCR doesn't preserve the case or exact spacing.
+fn normalize_level_name(level: &str) -> String {
+    level
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_uppercase()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -628,10 +2207,7 @@ mod tests {
     use tempfile::tempdir;
 
     fn make_ingame_time(percent: u32, hours: u64, minutes: u64, secs: u64) -> InGameTime {
-        InGameTime {
-            percent,
-            duration: Duration::from_secs(hours * 3600 + minutes * 60 + secs),
-        }
+        InGameTime::from_hms(percent, hours, minutes, secs)
     }
 
     #[test]
@@ -645,22 +2221,28 @@ mod tests {
         let split1 = Split {
             name: "C".to_string(),
             percent: 75,
+            min_time: None,
             time: None,
             best_segment: None,
+            notes: None,
             history: vec![],
         };
         let split2 = Split {
             name: "A".to_string(),
             percent: 25,
+            min_time: None,
             time: None,
             best_segment: None,
+            notes: None,
             history: vec![],
         };
         let split3 = Split {
             name: "B".to_string(),
             percent: 50,
+            min_time: None,
             time: None,
             best_segment: None,
+            notes: None,
             history: vec![],
         };
 
@@ -681,15 +2263,19 @@ mod tests {
             Split {
                 name: "First".to_string(),
                 percent: 50,
+                min_time: None,
                 time: None,
                 best_segment: None,
+                notes: None,
                 history: Vec::new(),
             },
             Split {
                 name: "Second".to_string(),
                 percent: 50,
+                min_time: None,
                 time: None,
                 best_segment: None,
+                notes: None,
                 history: Vec::new(),
             },
         ];
@@ -713,12 +2299,18 @@ mod tests {
                 start_time: Utc::now() - Duration::from_secs(60 * 60),
                 end_time: None,
                 final_time: None,
+                category: None,
+                tags: Vec::new(),
+                notes: None,
             },
             RunSummary {
                 id: duplicate_id, // duplicate
                 start_time: Utc::now(),
                 end_time: None,
                 final_time: None,
+                category: None,
+                tags: Vec::new(),
+                notes: None,
             },
         ];
 
@@ -737,6 +2329,9 @@ mod tests {
             start_time: Utc::now() - Duration::from_secs(60 * 60),
             end_time: Some(Utc::now()),
             final_time: Some(Duration::from_secs(45 * 60 + 15)),
+            category: None,
+            tags: Vec::new(),
+            notes: None,
         };
 
         // `runs` does not contain the PB run yet
@@ -773,18 +2368,27 @@ mod tests {
             start_time: Utc::now() - Duration::from_secs(300),
             end_time: Some(Utc::now() - Duration::from_secs(200)),
             final_time: Some(Duration::from_secs(105)),
+            category: None,
+            tags: Vec::new(),
+            notes: None,
         };
         let run_b = RunSummary {
             id: Uuid::new_v4(),
             start_time: Utc::now() - Duration::from_secs(200),
             end_time: Some(Utc::now() - Duration::from_secs(100)),
             final_time: Some(Duration::from_secs(100)),
+            category: None,
+            tags: Vec::new(),
+            notes: None,
         };
         let run_c = RunSummary {
             id: Uuid::new_v4(),
             start_time: Utc::now() - Duration::from_secs(100),
             end_time: Some(Utc::now()),
             final_time: Some(Duration::from_secs(95)),
+            category: None,
+            tags: Vec::new(),
+            notes: None,
         };
 
         let runs = vec![run_a.clone(), run_b.clone(), run_c.clone()];
@@ -793,20 +2397,25 @@ mod tests {
         let split = Split {
             name: "Split".to_string(),
             percent: 50,
+            min_time: None,
             time: None,
             best_segment: None,
+            notes: None,
             history: vec![
                 HistoricalSplit {
                     run_id: run_c.id,
                     duration: Duration::from_secs(95),
+                    real_time: None,
                 },
                 HistoricalSplit {
                     run_id: run_a.id,
                     duration: Duration::from_secs(105),
+                    real_time: None,
                 },
                 HistoricalSplit {
                     run_id: run_b.id,
                     duration: Duration::from_secs(100),
+                    real_time: None,
                 },
             ],
         };
@@ -832,6 +2441,9 @@ mod tests {
             start_time: Utc::now() - Duration::from_secs(100),
             end_time: Some(Utc::now()),
             final_time: Some(Duration::from_secs(100)),
+            category: None,
+            tags: Vec::new(),
+            notes: None,
         };
 
         // Split with multiple entries for the same run
@@ -839,28 +2451,35 @@ mod tests {
             Split {
                 name: "First Split".to_string(),
                 percent: 50,
+                min_time: None,
                 time: None,
                 best_segment: None,
+                notes: None,
                 history: vec![
                     HistoricalSplit {
                         run_id: run.id,
                         duration: Duration::from_secs(10),
+                        real_time: None,
                     },
                     HistoricalSplit {
                         run_id: run.id,
                         duration: Duration::from_secs(20),
+                        real_time: None,
                     },
                     HistoricalSplit {
                         run_id: run.id,
                         duration: Duration::from_secs(15),
+                        real_time: None,
                     },
                 ],
             },
             Split {
                 name: "Final Split".to_string(),
                 percent: 100,
+                min_time: None,
                 time: None,
                 best_segment: None,
+                notes: None,
                 history: vec![],
             },
         ];
@@ -887,17 +2506,23 @@ mod tests {
             start_time: Utc::now() - Duration::from_secs(120),
             end_time: Some(Utc::now()),
             final_time: Some(Duration::from_secs(120)),
+            category: None,
+            tags: Vec::new(),
+            notes: None,
         };
 
         // Early split that should remain untouched
         let early_split = Split {
             name: "Early".to_string(),
             percent: 50,
+            min_time: None,
             time: None,
             best_segment: None,
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id: run.id,
                 duration: Duration::from_secs(60),
+                real_time: None,
             }],
         };
 
@@ -905,11 +2530,14 @@ mod tests {
         let final_split = Split {
             name: "Final".to_string(),
             percent: 100,
+            min_time: None,
             time: None,
             best_segment: None,
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id: run.id,
                 duration: Duration::from_secs(90),
+                real_time: None,
             }],
         };
 
@@ -945,6 +2573,9 @@ mod tests {
             start_time: Utc::now(),
             end_time: Some(Utc::now() + Duration::from_secs(5)),
             final_time: Some(Duration::from_secs(5)),
+            category: None,
+            tags: Vec::new(),
+            notes: None,
         };
 
         // HistoricalSplit with an unknown run_id
@@ -953,16 +2584,20 @@ mod tests {
         let split = Split {
             name: "Split".to_string(),
             percent: 50,
+            min_time: None,
             time: None,
             best_segment: None,
+            notes: None,
             history: vec![
                 HistoricalSplit {
                     run_id: known_run.id,
                     duration: Duration::from_secs(5),
+                    real_time: None,
                 },
                 HistoricalSplit {
                     run_id: unknown_run_id,
                     duration: Duration::from_secs(99),
+                    real_time: None,
                 },
             ],
         };
@@ -989,14 +2624,19 @@ mod tests {
             start_time: Utc::now() - Duration::from_secs(60),
             end_time: Some(Utc::now()),
             final_time: Some(Duration::from_secs(45)),
+            category: None,
+            tags: Vec::new(),
+            notes: None,
         };
 
         // Final split is missing history
         let final_split = Split {
             name: "Final".to_string(),
             percent: 100,
+            min_time: None,
             time: None,
             best_segment: None,
+            notes: None,
             history: vec![],
         };
 
@@ -1021,12 +2661,18 @@ mod tests {
             start_time: Utc::now() - Duration::from_secs(60),
             end_time: None,
             final_time: None,
+            category: None,
+            tags: Vec::new(),
+            notes: None,
         };
         let run2 = RunSummary {
             id: Uuid::new_v4(),
             start_time: Utc::now(),
             end_time: None,
             final_time: None,
+            category: None,
+            tags: Vec::new(),
+            notes: None,
         };
 
         let splits = Splits::create_with_history(
@@ -1049,26 +2695,35 @@ mod tests {
             start_time: Utc::now() - Duration::from_secs(60),
             end_time: Some(Utc::now()),
             final_time: Some(Duration::from_secs(60)),
+            category: None,
+            tags: Vec::new(),
+            notes: None,
         };
 
         let split1 = Split {
             name: "50%".into(),
             percent: 50,
+            min_time: None,
             time: None,
             best_segment: None,
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id: pb_run.id,
                 duration: Duration::from_secs(30),
+                real_time: None,
             }],
         };
         let split2 = Split {
             name: "100%".into(),
             percent: 100,
+            min_time: None,
             time: None,
             best_segment: None,
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id: pb_run.id,
                 duration: Duration::from_secs(60),
+                real_time: None,
             }],
         };
 
@@ -1092,16 +2747,22 @@ mod tests {
             start_time: Utc::now(),
             end_time: None,
             final_time: None,
+            category: None,
+            tags: Vec::new(),
+            notes: None,
         };
 
         let split = Split {
             name: "Split 1".to_string(),
             percent: 50,
+            min_time: None,
             time: Some(Duration::from_secs(30)),
             best_segment: None,
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id: run.id,
                 duration: Duration::from_secs(30),
+                real_time: None,
             }],
         };
 
@@ -1125,16 +2786,20 @@ mod tests {
         let split_a = Split {
             name: "A".into(),
             percent: 10,
+            min_time: None,
             time: None,
             best_segment: None,
+            notes: None,
             history: vec![
                 HistoricalSplit {
                     run_id: run1,
                     duration: Duration::from_secs(60),
+                    real_time: None,
                 },
                 HistoricalSplit {
                     run_id: run2,
                     duration: Duration::from_secs(50),
+                    real_time: None,
                 },
             ],
         };
@@ -1142,64 +2807,269 @@ mod tests {
         let split_b = Split {
             name: "B".into(),
             percent: 20,
+            min_time: None,
             time: None,
             best_segment: None,
+            notes: None,
             history: vec![
                 HistoricalSplit {
                     run_id: run1,
                     duration: Duration::from_secs(145),
+                    real_time: None,
                 },
                 HistoricalSplit {
                     run_id: run2,
                     duration: Duration::from_secs(140),
+                    real_time: None,
                 },
             ],
         };
 
         let splits = Splits::create(PathBuf::from("dummy"), vec![split_a, split_b]).unwrap();
 
-        // After validation, best segments should be filled
+        // After validation, best segments should be filled
+        assert_eq!(splits.splits[0].best_segment, Some(Duration::from_secs(50)));
+        assert_eq!(splits.splits[1].best_segment, Some(Duration::from_secs(85))); // 1:25
+    }
+
+    #[test]
+    fn validate_keeps_stored_best_segments_that_are_faster() {
+        let run = Uuid::new_v4();
+        let split = |percent, best_segment, secs| Split {
+            name: format!("{}%", percent),
+            percent,
+            min_time: None,
+            time: None,
+            best_segment,
+            notes: None,
+            history: vec![HistoricalSplit {
+                run_id: run,
+                duration: Duration::from_secs(secs),
+                real_time: None,
+            }],
+        };
+
+        let splits = Splits::create(
+            PathBuf::from("dummy"),
+            vec![
+                // Gold from a run that is no longer in the history
+                split(10, Some(Duration::from_secs(40)), 50),
+                // Stored gold slower than the history (e.g. edited by hand)
+                split(20, Some(Duration::from_secs(120)), 140),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(splits.splits[0].best_segment, Some(Duration::from_secs(40)));
+        assert_eq!(splits.splits[1].best_segment, Some(Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn reset_best_segments_are_recomputed_from_history_only() {
+        let run = Uuid::new_v4();
+        let split = |percent, best_segment, secs| Split {
+            name: format!("{}%", percent),
+            percent,
+            min_time: None,
+            time: None,
+            best_segment,
+            notes: None,
+            history: vec![HistoricalSplit {
+                run_id: run,
+                duration: Duration::from_secs(secs),
+                real_time: None,
+            }],
+        };
+
+        let dir = tempdir().unwrap();
+        let mut splits = Splits::create(
+            dir.path().join("splits.json"),
+            vec![
+                split(10, Some(Duration::from_secs(40)), 50),
+                split(20, None, 140),
+            ],
+        )
+        .unwrap();
+
+        let rows = splits.recompute_best_segments(false).unwrap();
+        assert_eq!(rows, vec!["10%  0:00:40", "20%  0:01:30"]);
+
+        let rows = splits.recompute_best_segments(true).unwrap();
         assert_eq!(splits.splits[0].best_segment, Some(Duration::from_secs(50)));
-        assert_eq!(splits.splits[1].best_segment, Some(Duration::from_secs(85))); // 1:25
+        assert_eq!(rows[0], "10%  0:00:50  (was 0:00:40)");
+        assert_eq!(rows[1], "20%  0:01:30");
     }
 
     #[test]
-    fn find_by_percent_finds_correct_split() {
+    fn find_split_finds_correct_split() {
         let split1 = Split {
             name: "Alpha".to_string(),
             percent: 10,
+            min_time: None,
             time: Some(Duration::from_secs(60)),
             best_segment: None,
+            notes: None,
             history: vec![],
         };
         let split2 = Split {
             name: "Beta".to_string(),
             percent: 20,
+            min_time: None,
             time: Some(Duration::from_secs(2 * 60)),
             best_segment: None,
+            notes: None,
             history: vec![],
         };
         let splits = Splits::create(PathBuf::from("dummy_path"), vec![split1, split2])
             .expect("splits should be valid");
 
-        let result = splits.find_by_percent(&make_ingame_time(20, 0, 0, 0));
+        let result = splits.find_split(&make_ingame_time(20, 0, 0, 0));
         assert!(result.is_some());
         assert_eq!(result.unwrap().name, "Beta");
     }
 
     #[test]
-    fn find_by_percent_returns_none_for_unknown_percent() {
+    fn ordered_splits_follow_the_route_instead_of_the_percent() {
+        let split = |name: &str, percent| Split {
+            name: name.into(),
+            percent,
+            min_time: None,
+            time: None,
+            best_segment: None,
+            notes: None,
+            history: vec![],
+        };
+        let dir = tempdir().unwrap();
+        let mut splits = Splits::create_ordered_with_history(
+            dir.path().join("splits.json"),
+            false,
+            None,
+            Vec::new(),
+            vec![split("Hub", 30), split("Side area", 10), split("End", 40)],
+        )
+        .unwrap();
+        assert_eq!(splits.splits[0].name, "Hub");
+
+        let igt = |percent, secs| InGameTime {
+            percent,
+            duration: Duration::from_secs(secs),
+        };
+        splits.update_with_igt(&igt(30, 100));
+        // A lower percent that comes next in the route continues the run
+        splits.update_with_igt(&igt(10, 150));
+        assert_eq!(splits.runs().len(), 1);
+        assert_eq!(splits.splits[1].history.len(), 1);
+        splits.update_with_igt(&igt(40, 200));
+        assert_eq!(
+            splits.personal_best().unwrap().final_time,
+            Some(Duration::from_secs(200))
+        );
+
+        // Back at the start of the route
+        splits.update_with_igt(&igt(30, 90));
+        assert_eq!(splits.runs().len(), 2);
+        // A split before the one the run is at means a reset
+        splits.update_with_igt(&igt(10, 140));
+        splits.update_with_igt(&igt(30, 95));
+        assert_eq!(splits.runs().len(), 3);
+    }
+
+    #[test]
+    fn lower_percent_after_reading_mid_run_starts_a_new_run() {
+        let split = |percent| Split {
+            name: format!("{}%", percent),
+            percent,
+            min_time: None,
+            time: None,
+            best_segment: None,
+            notes: None,
+            history: vec![],
+        };
+        let dir = tempdir().unwrap();
+        let mut splits =
+            Splits::create(dir.path().join("splits.json"), vec![split(10), split(20)]).unwrap();
+
+        // Tracking started at a percent without a split
+        splits.initialize_active_run(&InGameTime {
+            percent: 15,
+            duration: Duration::from_secs(300),
+        });
+        splits.update_with_igt(&InGameTime {
+            percent: 20,
+            duration: Duration::from_secs(400),
+        });
+        assert!(splits.runs().is_empty());
+
+        splits.update_with_igt(&InGameTime {
+            percent: 10,
+            duration: Duration::from_secs(60),
+        });
+        assert_eq!(splits.runs().len(), 1);
+    }
+
+    #[test]
+    fn splits_at_the_same_percent_are_told_apart_by_min_time() {
+        let split = |name: &str, percent, min_secs: Option<u64>| Split {
+            name: name.into(),
+            percent,
+            min_time: min_secs.map(Duration::from_secs),
+            time: None,
+            best_segment: None,
+            notes: None,
+            history: vec![],
+        };
+        let dir = tempdir().unwrap();
+        let mut splits = Splits::create(
+            dir.path().join("splits.json"),
+            vec![
+                split("Second visit", 50, Some(600)),
+                split("First visit", 50, None),
+                split("End", 60, None),
+            ],
+        )
+        .unwrap();
+        assert_eq!(splits.splits[0].name, "First visit");
+
+        for (percent, secs) in [(50, 300), (50, 700), (60, 800)] {
+            splits.update_with_igt(&InGameTime {
+                percent,
+                duration: Duration::from_secs(secs),
+            });
+        }
+
+        assert_eq!(splits.splits[0].time, Some(Duration::from_secs(300)));
+        assert_eq!(splits.splits[1].time, Some(Duration::from_secs(700)));
+        assert_eq!(
+            splits.splits[1].best_segment,
+            Some(Duration::from_secs(400))
+        );
+        assert_eq!(splits.splits[2].time, Some(Duration::from_secs(800)));
+        assert_eq!(splits.runs().len(), 1);
+
+        assert!(
+            Splits::create(
+                PathBuf::from("dummy"),
+                vec![split("A", 50, Some(600)), split("B", 50, Some(600))],
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn find_split_returns_none_for_unknown_percent() {
         let split1 = Split {
             name: "One".to_string(),
             percent: 30,
+            min_time: None,
             time: Some(Duration::from_secs(3 * 60)),
             best_segment: None,
+            notes: None,
             history: vec![],
         };
         let splits = Splits::create(PathBuf::from("dummy_path"), vec![split1])
             .expect("splits should be valid");
 
-        let result = splits.find_by_percent(&make_ingame_time(40, 0, 0, 0));
+        let result = splits.find_split(&make_ingame_time(40, 0, 0, 0));
         assert!(result.is_none());
     }
 
@@ -1210,22 +3080,28 @@ mod tests {
         let split_a = Split {
             name: "A".into(),
             percent: 10,
+            min_time: None,
             time: None,
             best_segment: None,
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id,
                 duration: Duration::from_secs(60),
+                real_time: None,
             }],
         };
 
         let split_b = Split {
             name: "B".into(),
             percent: 20,
+            min_time: None,
             time: None,
             best_segment: None,
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id,
                 duration: Duration::from_secs(150), // 2:30
+                real_time: None,
             }],
         };
 
@@ -1259,30 +3135,38 @@ mod tests {
         let split_a = Split {
             name: "A".into(),
             percent: 10,
+            min_time: None,
             time: None,
             best_segment: None,
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id,
                 duration: Duration::from_secs(60), // 1:00
+                real_time: None,
             }],
         };
 
         let split_b = Split {
             name: "B".into(),
             percent: 20,
+            min_time: None,
             time: None,
             best_segment: None,
+            notes: None,
             history: vec![], // skipped in this run
         };
 
         let split_c = Split {
             name: "C".into(),
             percent: 30,
+            min_time: None,
             time: None,
             best_segment: None,
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id,
                 duration: Duration::from_secs(150), // 2:30
+                real_time: None,
             }],
         };
 
@@ -1318,16 +3202,20 @@ mod tests {
         let split_a = Split {
             name: "A".into(),
             percent: 10,
+            min_time: None,
             time: None,
             best_segment: None,
+            notes: None,
             history: vec![
                 HistoricalSplit {
                     run_id: run1,
                     duration: Duration::from_secs(60),
+                    real_time: None,
                 },
                 HistoricalSplit {
                     run_id: run2,
                     duration: Duration::from_secs(50),
+                    real_time: None,
                 },
             ],
         };
@@ -1335,16 +3223,20 @@ mod tests {
         let split_b = Split {
             name: "B".into(),
             percent: 20,
+            min_time: None,
             time: None,
             best_segment: None,
+            notes: None,
             history: vec![
                 HistoricalSplit {
                     run_id: run1,
                     duration: Duration::from_secs(145),
+                    real_time: None,
                 },
                 HistoricalSplit {
                     run_id: run2,
                     duration: Duration::from_secs(140),
+                    real_time: None,
                 },
             ],
         };
@@ -1352,11 +3244,11 @@ mod tests {
         let splits = Splits::create(PathBuf::from("dummy"), vec![split_a, split_b]).unwrap();
 
         // Best segment for A (first split) -> min absolute time
-        let best_a = splits.compute_best_segment_for(10);
+        let best_a = splits.compute_best_segment_for(0);
         assert_eq!(best_a, Some(Duration::from_secs(50)));
 
         // Best segment for B → min delta across runs
-        let best_b = splits.compute_best_segment_for(20);
+        let best_b = splits.compute_best_segment_for(1);
         assert_eq!(best_b, Some(Duration::from_secs(85))); // 1:25
     }
 
@@ -1368,48 +3260,60 @@ mod tests {
         let split_a = Split {
             name: "A".into(),
             percent: 10,
+            min_time: None,
             time: Some(Duration::from_secs(50)),
             best_segment: Some(Duration::from_secs(50)),
+            notes: None,
             history: vec![
                 HistoricalSplit {
                     run_id: run1,
                     duration: Duration::from_secs(50),
+                    real_time: None,
                 },
                 HistoricalSplit {
                     run_id: run2,
                     duration: Duration::from_secs(60),
+                    real_time: None,
                 },
             ],
         };
         let split_b = Split {
             name: "B".into(),
             percent: 20,
+            min_time: None,
             time: Some(Duration::from_secs(140)),
             best_segment: Some(Duration::from_secs(70)),
+            notes: None,
             history: vec![
                 HistoricalSplit {
                     run_id: run1,
                     duration: Duration::from_secs(140),
+                    real_time: None,
                 },
                 HistoricalSplit {
                     run_id: run2,
                     duration: Duration::from_secs(130),
+                    real_time: None,
                 },
             ],
         };
         let split_c = Split {
             name: "C".into(),
             percent: 30,
+            min_time: None,
             time: Some(Duration::from_secs(240)),
             best_segment: Some(Duration::from_secs(100)),
+            notes: None,
             history: vec![
                 HistoricalSplit {
                     run_id: run1,
                     duration: Duration::from_secs(240),
+                    real_time: None,
                 },
                 HistoricalSplit {
                     run_id: run2,
                     duration: Duration::from_secs(250),
+                    real_time: None,
                 },
             ],
         };
@@ -1428,19 +3332,24 @@ mod tests {
         let split_a = Split {
             name: "A".into(),
             percent: 10,
+            min_time: None,
             time: Some(Duration::from_secs(50)),
             best_segment: Some(Duration::from_secs(50)),
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id: run1,
                 duration: Duration::from_secs(50),
+                real_time: None,
             }],
         };
 
         let split_b = Split {
             name: "B".into(),
             percent: 20,
+            min_time: None,
             time: None,
             best_segment: None, // last split never finished
+            notes: None,
             history: vec![],
         };
 
@@ -1457,33 +3366,42 @@ mod tests {
         let split_a = Split {
             name: "A".into(),
             percent: 10,
+            min_time: None,
             time: Some(Duration::from_secs(50)),
             best_segment: Some(Duration::from_secs(50)),
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id: run1,
                 duration: Duration::from_secs(50),
+                real_time: None,
             }],
         };
 
         let split_b = Split {
             name: "B".into(),
             percent: 20,
+            min_time: None,
             time: Some(Duration::from_secs(120)),
             best_segment: Some(Duration::from_secs(70)),
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id: run1,
                 duration: Duration::from_secs(120),
+                real_time: None,
             }],
         };
 
         let split_c = Split {
             name: "C".into(),
             percent: 30,
+            min_time: None,
             time: Some(Duration::from_secs(240)),
             best_segment: Some(Duration::from_secs(120)),
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id: run1,
                 duration: Duration::from_secs(240),
+                real_time: None,
             }],
         };
 
@@ -1522,19 +3440,24 @@ mod tests {
         let split_a = Split {
             name: "A".into(),
             percent: 10,
+            min_time: None,
             time: Some(Duration::from_secs(50)),
             best_segment: Some(Duration::from_secs(50)),
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id: run1,
                 duration: Duration::from_secs(50),
+                real_time: None,
             }],
         };
 
         let split_b = Split {
             name: "B".into(),
             percent: 20,
+            min_time: None,
             time: None,
             best_segment: None, // last split never finished
+            notes: None,
             history: vec![],
         };
 
@@ -1544,14 +3467,389 @@ mod tests {
         )
         .unwrap();
 
-        // Simulate an active run halfway through split B
-        splits.update_with_igt(&InGameTime {
-            percent: 10,
-            duration: Duration::from_secs(60),
-        });
-
-        // Expect None because the last split has no best segment
-        assert_eq!(splits.best_possible_time(), None);
+        // Simulate an active run halfway through split B
+        splits.update_with_igt(&InGameTime {
+            percent: 10,
+            duration: Duration::from_secs(60),
+        });
+
+        // Expect None because the last split has no best segment
+        assert_eq!(splits.best_possible_time(), None);
+    }
+
+    #[test]
+    fn stats_show_pb_sum_of_bests_and_timesave() {
+        let run1 = Uuid::new_v4();
+        let run2 = Uuid::new_v4();
+
+        let pb = RunSummary {
+            id: run1,
+            start_time: Utc::now(),
+            end_time: Some(Utc::now()),
+            final_time: Some(Duration::from_secs(130)),
+            category: None,
+            tags: Vec::new(),
+            notes: None,
+        };
+        let reset = RunSummary {
+            id: run2,
+            start_time: Utc::now(),
+            end_time: Some(Utc::now()),
+            final_time: None,
+            category: None,
+            tags: Vec::new(),
+            notes: None,
+        };
+
+        let splits = Splits::create_with_history(
+            tempdir().unwrap().path().join("splits.json"),
+            Some(pb.clone()),
+            vec![pb, reset],
+            vec![
+                Split {
+                    name: "A".into(),
+                    percent: 10,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: vec![
+                        HistoricalSplit {
+                            run_id: run1,
+                            duration: Duration::from_secs(50),
+                            real_time: None,
+                        },
+                        HistoricalSplit {
+                            run_id: run2,
+                            duration: Duration::from_secs(40),
+                            real_time: None,
+                        },
+                    ],
+                },
+                Split {
+                    name: "B".into(),
+                    percent: 20,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: vec![HistoricalSplit {
+                        run_id: run1,
+                        duration: Duration::from_secs(130),
+                        real_time: None,
+                    }],
+                },
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            splits.format_stats(),
+            "PB 0:02:10  SoB 0:02:00  possible timesave 0:00:10  2 runs (1 finished)"
+        );
+
+        assert_eq!(
+            Splits::create(tempdir().unwrap().path().join("splits.json"), vec![])
+                .unwrap()
+                .format_stats(),
+            "PB -:--:--  SoB -:--:--  possible timesave -:--:--  0 runs (0 finished)"
+        );
+    }
+
+    #[test]
+    fn run_statistics_count_runs_reaching_each_split() {
+        let runs: Vec<RunSummary> = [Some(100), None, None]
+            .iter()
+            .map(|final_time| RunSummary {
+                id: Uuid::new_v4(),
+                start_time: Utc::now(),
+                end_time: Some(Utc::now()),
+                final_time: final_time.map(Duration::from_secs),
+                category: None,
+                tags: Vec::new(),
+                notes: None,
+            })
+            .collect();
+        let history = |runs: &[&RunSummary]| -> Vec<HistoricalSplit> {
+            runs.iter()
+                .map(|run| HistoricalSplit {
+                    run_id: run.id,
+                    duration: Duration::from_secs(50),
+                    real_time: None,
+                })
+                .collect()
+        };
+
+        let splits = Splits::create_with_history(
+            PathBuf::from("dummy_path"),
+            None,
+            runs.clone(),
+            vec![
+                Split {
+                    name: "A".into(),
+                    percent: 10,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: history(&[&runs[0], &runs[1]]),
+                },
+                Split {
+                    name: "B".into(),
+                    percent: 20,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: history(&[&runs[2]]),
+                },
+                Split {
+                    name: "C".into(),
+                    percent: 30,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: history(&[&runs[0]]),
+                },
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(splits.survival_counts(), vec![3, 2, 1]);
+        assert_eq!(splits.average_reset_percent(), Some(15.0));
+        assert_eq!(
+            splits.format_run_statistics(),
+            vec![
+                "Completed 1/3 runs (33%)",
+                "Average reset at 15%",
+                "A     3/3 (100%)",
+                "B     2/3 (67%)",
+                "C     1/3 (33%)",
+            ]
+        );
+    }
+
+    #[test]
+    fn segment_statistics_cover_all_runs() {
+        let runs: Vec<RunSummary> = (0..3)
+            .map(|_| RunSummary {
+                id: Uuid::new_v4(),
+                start_time: Utc::now(),
+                end_time: Some(Utc::now()),
+                final_time: None,
+                category: None,
+                tags: Vec::new(),
+                notes: None,
+            })
+            .collect();
+        let history = |secs: &[u64]| -> Vec<HistoricalSplit> {
+            runs.iter()
+                .zip(secs)
+                .map(|(run, &secs)| HistoricalSplit {
+                    run_id: run.id,
+                    duration: Duration::from_secs(secs),
+                    real_time: None,
+                })
+                .collect()
+        };
+
+        let splits = Splits::create_with_history(
+            PathBuf::from("dummy_path"),
+            None,
+            runs.clone(),
+            vec![
+                Split {
+                    name: "A".into(),
+                    percent: 10,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: history(&[60, 60, 60]),
+                },
+                Split {
+                    name: "B".into(),
+                    percent: 20,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: history(&[100, 120, 140]),
+                },
+                Split {
+                    name: "C".into(),
+                    percent: 30,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: vec![],
+                },
+            ],
+        )
+        .unwrap();
+
+        let statistics = splits.segment_statistics();
+        let a = statistics[0].as_ref().unwrap();
+        assert_eq!(a.count, 3);
+        assert_eq!(a.std_dev, Duration::ZERO);
+
+        // Segments of 40, 60 and 80 seconds
+        let b = statistics[1].as_ref().unwrap();
+        assert_eq!(b.min, Duration::from_secs(40));
+        assert_eq!(b.max, Duration::from_secs(80));
+        assert_eq!(b.mean, Duration::from_secs(60));
+        assert_eq!(b.std_dev.as_secs(), 16);
+
+        assert_eq!(statistics[2], None);
+    }
+
+    #[test]
+    fn bpt_row_shows_best_possible_time() {
+        let splits = Splits::create(
+            tempdir().unwrap().path().join("splits.json"),
+            vec![
+                Split {
+                    name: "A".into(),
+                    percent: 10,
+                    min_time: None,
+                    time: None,
+                    best_segment: Some(Duration::from_secs(50)),
+                    notes: None,
+                    history: vec![],
+                },
+                Split {
+                    name: "B".into(),
+                    percent: 20,
+                    min_time: None,
+                    time: None,
+                    best_segment: Some(Duration::from_secs(70)),
+                    notes: None,
+                    history: vec![],
+                },
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            splits.format_best_possible_time(4),
+            "BPT:           0:02:00"
+        );
+    }
+
+    #[test]
+    fn next_comparison_wraps_around() {
+        assert_eq!(Comparison::PersonalBest.next(), Comparison::BestSegments);
+        assert_eq!(Comparison::Latest.next(), Comparison::PersonalBest);
+        for &comparison in Comparison::value_variants() {
+            assert_eq!(
+                Comparison::from_str(comparison.name(), false),
+                Ok(comparison)
+            );
+        }
+    }
+
+    #[test]
+    fn comparison_times_follow_the_comparison() {
+        let runs: Vec<RunSummary> = (0..3)
+            .map(|_| RunSummary {
+                id: Uuid::new_v4(),
+                start_time: Utc::now(),
+                end_time: Some(Utc::now()),
+                final_time: None,
+                category: None,
+                tags: Vec::new(),
+                notes: None,
+            })
+            .collect();
+        let history = |secs: &[u64]| -> Vec<HistoricalSplit> {
+            runs.iter()
+                .zip(secs)
+                .map(|(run, &secs)| HistoricalSplit {
+                    run_id: run.id,
+                    duration: Duration::from_secs(secs),
+                    real_time: None,
+                })
+                .collect()
+        };
+
+        let splits = Splits::create_with_history(
+            PathBuf::from("dummy_path"),
+            None,
+            runs.clone(),
+            vec![
+                Split {
+                    name: "A".into(),
+                    percent: 10,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: history(&[50, 40, 90]),
+                },
+                Split {
+                    name: "B".into(),
+                    percent: 20,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: history(&[100, 120]),
+                },
+            ],
+        )
+        .unwrap();
+
+        let secs = |secs: &[Option<u64>]| -> Vec<Option<Duration>> {
+            secs.iter().map(|s| s.map(Duration::from_secs)).collect()
+        };
+        assert_eq!(
+            splits.comparison_times(Comparison::BestSegments),
+            secs(&[Some(40), Some(90)])
+        );
+        assert_eq!(
+            splits.comparison_times(Comparison::Average),
+            secs(&[Some(60), Some(110)])
+        );
+        assert_eq!(
+            splits.comparison_times(Comparison::Median),
+            secs(&[Some(50), Some(110)])
+        );
+        assert_eq!(
+            splits.comparison_times(Comparison::Latest),
+            secs(&[Some(90), None])
+        );
+    }
+
+    #[test]
+    fn comparison_row_without_comparison_time_shows_dash() {
+        colored::control::set_override(false);
+        let splits = Splits::create(
+            PathBuf::from("dummy_path"),
+            vec![Split {
+                name: "One".to_string(),
+                percent: 50,
+                min_time: None,
+                time: None,
+                best_segment: None,
+                notes: None,
+                history: vec![],
+            }],
+        )
+        .unwrap();
+
+        assert_eq!(
+            splits
+                .format_comparison(&make_ingame_time(50, 0, 11, 0), Comparison::PersonalBest)
+                .unwrap(),
+            "One        -  0:11:00"
+        );
+        assert!(
+            splits
+                .format_comparison(&make_ingame_time(60, 0, 11, 0), Comparison::PersonalBest)
+                .is_none()
+        );
     }
 
     #[test]
@@ -1560,21 +3858,27 @@ mod tests {
         let split1 = Split {
             name: "One".to_string(),
             percent: 50,
+            min_time: None,
             time: Some(Duration::from_secs(8 * 60 + 30)),
             best_segment: None,
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id: id,
                 duration: Duration::from_secs(8 * 60 + 30),
+                real_time: None,
             }],
         };
         let split2 = Split {
             name: "Two".to_string(),
             percent: 60,
+            min_time: None,
             time: Some(Duration::from_secs(10 * 60)),
             best_segment: None,
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id: id,
                 duration: Duration::from_secs(10 * 60),
+                real_time: None,
             }],
         };
         let personal_best = RunSummary {
@@ -1582,6 +3886,9 @@ mod tests {
             start_time: Utc::now(),
             end_time: None,
             final_time: None,
+            category: None,
+            tags: Vec::new(),
+            notes: None,
         };
         let splits = Splits::create_with_history(
             PathBuf::from("dummy_path"),
@@ -1592,7 +3899,7 @@ mod tests {
         .expect("splits should be valid");
 
         let current = make_ingame_time(60, 0, 11, 0); // +60s
-        let result = splits.compare(&current);
+        let result = splits.compare(&current, Comparison::PersonalBest);
 
         assert!(result.is_some());
         let (delta, split) = result.unwrap();
@@ -1606,11 +3913,14 @@ mod tests {
         let split1 = Split {
             name: "One".to_string(),
             percent: 70,
+            min_time: None,
             time: Some(Duration::from_secs(15 * 60)),
             best_segment: None,
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id: id,
                 duration: Duration::from_secs(15 * 60),
+                real_time: None,
             }],
         };
         let personal_best = RunSummary {
@@ -1618,6 +3928,9 @@ mod tests {
             start_time: Utc::now(),
             end_time: None,
             final_time: None,
+            category: None,
+            tags: Vec::new(),
+            notes: None,
         };
         let splits = Splits::create_with_history(
             PathBuf::from("dummy_path"),
@@ -1628,7 +3941,7 @@ mod tests {
         .expect("splits should be valid");
 
         let current = make_ingame_time(70, 0, 14, 30); // -30s
-        let result = splits.compare(&current);
+        let result = splits.compare(&current, Comparison::PersonalBest);
 
         assert!(result.is_some());
         let (delta, split) = result.unwrap();
@@ -1643,11 +3956,14 @@ mod tests {
         let split1 = Split {
             name: "One".to_string(),
             percent: time.percent,
+            min_time: None,
             time: Some(time.duration),
             best_segment: None,
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id: id,
                 duration: time.duration,
+                real_time: None,
             }],
         };
         let personal_best = RunSummary {
@@ -1655,6 +3971,9 @@ mod tests {
             start_time: Utc::now(),
             end_time: None,
             final_time: None,
+            category: None,
+            tags: Vec::new(),
+            notes: None,
         };
         let splits = Splits::create_with_history(
             PathBuf::from("dummy_path"),
@@ -1664,7 +3983,7 @@ mod tests {
         )
         .expect("splits should be valid");
 
-        let result = splits.compare(&time);
+        let result = splits.compare(&time, Comparison::PersonalBest);
         assert!(result.is_some());
 
         let (delta, split) = result.unwrap();
@@ -1677,14 +3996,16 @@ mod tests {
         let split1 = Split {
             name: "Unrelated".to_string(),
             percent: 10,
+            min_time: None,
             time: Some(Duration::from_secs(1 * 60)),
             best_segment: None,
+            notes: None,
             history: vec![],
         };
         let splits = Splits::create(PathBuf::from("dummy_path"), vec![split1])
             .expect("splits should be valid");
 
-        let result = splits.compare(&make_ingame_time(99, 0, 2, 0));
+        let result = splits.compare(&make_ingame_time(99, 0, 2, 0), Comparison::PersonalBest);
         assert!(result.is_none());
     }
 
@@ -1693,8 +4014,10 @@ mod tests {
         let split = Split {
             name: "First Split".into(),
             percent: 10,
+            min_time: None,
             time: Some(Duration::from_secs(20)),
             best_segment: None,
+            notes: None,
             history: vec![],
         };
 
@@ -1728,13 +4051,16 @@ mod tests {
         let existing_entry = HistoricalSplit {
             run_id: existing_run_id,
             duration: Duration::from_secs(25),
+            real_time: None,
         };
 
         let split = Split {
             name: "First Split".into(),
             percent: 10,
+            min_time: None,
             time: Some(Duration::from_secs(20)),
             best_segment: None,
+            notes: None,
             history: vec![existing_entry.clone()],
         };
 
@@ -1759,249 +4085,894 @@ mod tests {
         let split_1 = Split {
             name: "First Split".into(),
             percent: 10,
+            min_time: None,
             time: Some(Duration::from_secs(20)),
             best_segment: None,
+            notes: None,
             history: vec![],
         };
         let split_2 = Split {
             name: "Second Split".into(),
             percent: 20,
+            min_time: None,
+            time: Some(Duration::from_secs(40)),
+            best_segment: None,
+            notes: None,
+            history: vec![],
+        };
+
+        let mut splits = Splits::create(
+            tempdir().unwrap().path().join("splits.json"),
+            vec![split_1, split_2],
+        )
+        .expect("splits should be valid");
+
+        let igt = InGameTime {
+            percent: 10,
+            duration: Duration::from_secs(30),
+        };
+
+        // When: update with IGT
+        let now = Utc::now();
+        splits.update_with_igt(&igt);
+
+        // Then: a new run is added to the runs list
+        let run_summary = splits
+            .runs()
+            .last()
+            .expect("Expected a RunSummary to be added");
+
+        let active_run = splits.active_run().expect("Expected active run");
+
+        assert_eq!(run_summary.id, active_run.id);
+        assert!(run_summary.start_time >= now - Duration::from_secs(1));
+        assert!(run_summary.start_time <= now + Duration::from_secs(1));
+        assert_eq!(run_summary.end_time, None);
+        assert_eq!(run_summary.final_time, None);
+    }
+
+    #[test]
+    fn update_existing_split_overwrites_duration_for_same_run() {
+        let run_id = Uuid::new_v4();
+
+        let original_duration = Duration::from_secs(25);
+        let updated_duration = Duration::from_secs(30);
+
+        let split = Split {
+            name: "20% Split".into(),
+            percent: 20,
+            min_time: None,
+            time: Some(original_duration),
+            best_segment: None,
+            notes: None,
+            history: vec![HistoricalSplit {
+                run_id,
+                duration: original_duration,
+                real_time: None,
+            }],
+        };
+
+        let mut splits = Splits::create(tempdir().unwrap().path().join("splits.json"), vec![split])
+            .expect("splits should be valid");
+
+        // Pre-existing active run
+        splits.active_run = Some(ActiveRun {
+            id: run_id,
+            start_time: Utc::now(),
+            end_time: None,
+            latest_split: InGameTime {
+                percent: 20,
+                duration: original_duration,
+            },
+        });
+
+        // Incoming update with same percent, updated duration
+        let current = InGameTime {
+            percent: 20,
+            duration: updated_duration,
+        };
+
+        // Act
+        splits.update_with_igt(&current);
+
+        // Assert: history was updated
+        let updated_split = &splits.splits()[0];
+        let hist = &updated_split.history[0];
+        assert_eq!(hist.run_id, run_id);
+        assert_eq!(hist.duration, updated_duration);
+
+        // Also: active run was not reset
+        let active_run = splits.active_run().expect("active run should exist");
+        assert_eq!(active_run.id, run_id);
+        assert_eq!(active_run.latest_split, current);
+    }
+
+    #[test]
+    fn advance_to_next_split_adds_new_history_entry() {
+        let split_10 = Split {
+            name: "First Split".into(),
+            percent: 10,
+            min_time: None,
+            time: Some(Duration::from_secs(20)),
+            best_segment: None,
+            notes: None,
+            history: vec![],
+        };
+
+        let split_20 = Split {
+            name: "Second Split".into(),
+            percent: 20,
+            min_time: None,
             time: Some(Duration::from_secs(40)),
             best_segment: None,
+            notes: None,
+            history: vec![],
+        };
+
+        let mut splits = Splits::create(
+            tempdir().unwrap().path().join("splits.json"),
+            vec![split_10, split_20],
+        )
+        .expect("splits should be valid");
+
+        // Simulate first update at 10% to create the run
+        let run_start_igt = InGameTime {
+            percent: 10,
+            duration: Duration::from_secs(25),
+        };
+        splits.update_with_igt(&run_start_igt);
+
+        // Store active run ID
+        let run_id = splits.active_run().expect("Expected active run").id;
+
+        // Advance to next split (20%)
+        let next_igt = InGameTime {
+            percent: 20,
+            duration: Duration::from_secs(55),
+        };
+        splits.update_with_igt(&next_igt);
+
+        // active_run.latest_split should now be at 20%
+        let active_run = splits.active_run().expect("Expected active run");
+        assert_eq!(active_run.latest_split.percent, 20);
+        assert_eq!(active_run.latest_split.duration, Duration::from_secs(55));
+
+        // The 10% split history should contain only the initial entry
+        let first_split = &splits.splits()[0];
+        assert_eq!(first_split.history.len(), 1);
+        assert_eq!(first_split.history[0].run_id, run_id);
+        assert_eq!(first_split.history[0].duration, Duration::from_secs(25));
+
+        // The 20% split history should now contain a new entry
+        let second_split = &splits.splits()[1];
+        assert_eq!(second_split.history.len(), 1);
+        assert_eq!(second_split.history[0].run_id, run_id);
+        assert_eq!(second_split.history[0].duration, Duration::from_secs(55));
+
+        // No new run summary should have been added
+        assert_eq!(splits.runs().len(), 1);
+    }
+
+    #[test]
+    fn recording_new_split_updates_best_segment_if_better() {
+        let run1 = Uuid::new_v4();
+        let split_a = Split {
+            name: "A".into(),
+            percent: 10,
+            min_time: None,
+            time: None,
+            best_segment: Some(Duration::from_secs(60)),
+            notes: None,
+            history: vec![HistoricalSplit {
+                run_id: run1,
+                duration: Duration::from_secs(60),
+                real_time: None,
+            }],
+        };
+        let split_b = Split {
+            name: "B".into(),
+            percent: 20,
+            min_time: None,
+            time: None,
+            best_segment: Some(Duration::from_secs(90)),
+            notes: None,
+            history: vec![HistoricalSplit {
+                run_id: run1,
+                duration: Duration::from_secs(150),
+                real_time: None,
+            }],
+        };
+        let split_c = Split {
+            name: "C".into(),
+            percent: 30,
+            min_time: None,
+            time: None,
+            best_segment: Some(Duration::from_secs(100)),
+            notes: None,
+            history: vec![HistoricalSplit {
+                run_id: run1,
+                duration: Duration::from_secs(250),
+                real_time: None,
+            }],
+        };
+        let mut splits = Splits::create(
+            tempdir().unwrap().path().join("splits.json"),
+            vec![split_a, split_b, split_c],
+        )
+        .unwrap();
+
+        // Record a new faster run2 entry at 0:50
+        splits.update_with_igt(&InGameTime {
+            percent: 10,
+            duration: Duration::from_secs(50),
+        });
+        splits.update_with_igt(&InGameTime {
+            percent: 20,
+            duration: Duration::from_secs(130),
+        });
+        // Third split is slower
+        splits.update_with_igt(&InGameTime {
+            percent: 30,
+            duration: Duration::from_secs(240),
+        });
+
+        // Best segment should now be updated to 0:50 and 1:20, respectively
+        assert_eq!(splits.splits[0].best_segment, Some(Duration::from_secs(50)));
+        assert_eq!(splits.splits[1].best_segment, Some(Duration::from_secs(80)));
+
+        // Best segment of last split should remain the same
+        assert_eq!(
+            splits.splits[2].best_segment,
+            Some(Duration::from_secs(100))
+        );
+    }
+
+    #[test]
+    fn recording_new_split_sets_best_segment_when_none() {
+        let split_a = Split {
+            name: "A".into(),
+            percent: 10,
+            min_time: None,
+            time: None,
+            best_segment: None, // no previous best
+            notes: None,
+            history: vec![],
+        };
+
+        let mut splits =
+            Splits::create(tempdir().unwrap().path().join("splits.json"), vec![split_a]).unwrap();
+
+        // Record a first IGT
+        splits.update_with_igt(&InGameTime {
+            percent: 10,
+            duration: Duration::from_secs(60),
+        });
+
+        // Best segment should now be set to 1:00
+        assert_eq!(splits.splits[0].best_segment, Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn segment_after_skipped_split_is_no_best_segment() {
+        let split = |name: &str, percent| Split {
+            name: name.into(),
+            percent,
+            min_time: None,
+            time: None,
+            best_segment: None,
+            notes: None,
             history: vec![],
         };
 
         let mut splits = Splits::create(
             tempdir().unwrap().path().join("splits.json"),
-            vec![split_1, split_2],
+            vec![split("A", 10), split("B", 20), split("C", 30)],
         )
-        .expect("splits should be valid");
+        .unwrap();
 
-        let igt = InGameTime {
+        splits.update_with_igt(&InGameTime {
             percent: 10,
-            duration: Duration::from_secs(30),
-        };
-
-        // When: update with IGT
-        let now = Utc::now();
-        splits.update_with_igt(&igt);
+            duration: Duration::from_secs(60),
+        });
+        // B was never read
+        splits.update_with_igt(&InGameTime {
+            percent: 30,
+            duration: Duration::from_secs(150),
+        });
 
-        // Then: a new run is added to the runs list
-        let run_summary = splits
-            .runs()
-            .last()
-            .expect("Expected a RunSummary to be added");
+        let run_id = splits.runs()[0].id;
+        assert!(!splits.is_skipped(run_id, 0));
+        assert!(splits.is_skipped(run_id, 1));
+        assert!(!splits.is_skipped(run_id, 2));
 
-        let active_run = splits.active_run().expect("Expected active run");
+        assert_eq!(splits.splits[0].best_segment, Some(Duration::from_secs(60)));
+        assert_eq!(splits.splits[1].best_segment, None);
+        assert_eq!(splits.splits[2].best_segment, None);
 
-        assert_eq!(run_summary.id, active_run.id);
-        assert!(run_summary.start_time >= now - Duration::from_secs(1));
-        assert!(run_summary.start_time <= now + Duration::from_secs(1));
-        assert_eq!(run_summary.end_time, None);
-        assert_eq!(run_summary.final_time, None);
+        // Also after reloading
+        splits.validate().unwrap();
+        assert_eq!(splits.splits[2].best_segment, None);
     }
 
     #[test]
-    fn update_existing_split_overwrites_duration_for_same_run() {
-        let run_id = Uuid::new_v4();
+    fn undo_removes_last_split_time_of_active_run() {
+        let split = |name: &str, percent| Split {
+            name: name.into(),
+            percent,
+            min_time: None,
+            time: None,
+            best_segment: None,
+            notes: None,
+            history: vec![],
+        };
 
-        let original_duration = Duration::from_secs(25);
-        let updated_duration = Duration::from_secs(30);
+        let dir = tempdir().unwrap();
+        let mut splits = Splits::create(
+            dir.path().join("splits.json"),
+            vec![split("A", 10), split("B", 20), split("C", 30)],
+        )
+        .unwrap();
+        assert!(splits.undo_last_split().is_err());
 
-        let split = Split {
-            name: "20% Split".into(),
+        splits.update_with_igt(&InGameTime {
+            percent: 10,
+            duration: Duration::from_secs(60),
+        });
+        // Misread
+        splits.update_with_igt(&InGameTime {
             percent: 20,
-            time: Some(original_duration),
+            duration: Duration::from_secs(61),
+        });
+
+        assert_eq!(
+            splits.undo_last_split().unwrap(),
+            InGameTime {
+                percent: 20,
+                duration: Duration::from_secs(61),
+            }
+        );
+        assert!(splits.splits[1].history.is_empty());
+        assert_eq!(splits.splits[1].best_segment, None);
+        assert_eq!(
+            splits.active_run().unwrap().latest_split,
+            InGameTime {
+                percent: 10,
+                duration: Duration::from_secs(60),
+            }
+        );
+
+        // The run continues
+        splits.update_with_igt(&InGameTime {
+            percent: 20,
+            duration: Duration::from_secs(130),
+        });
+        assert_eq!(splits.splits[1].best_segment, Some(Duration::from_secs(70)));
+
+        // Undoing all split times discards the run
+        splits.undo_last_split().unwrap();
+        splits.undo_last_split().unwrap();
+        assert!(splits.active_run().is_none());
+        assert!(splits.runs().is_empty());
+    }
+
+    #[test]
+    fn segment_times_are_differences_of_split_times() {
+        let split = |name: &str, percent| Split {
+            name: name.into(),
+            percent,
+            min_time: None,
+            time: None,
             best_segment: None,
-            history: vec![HistoricalSplit {
-                run_id,
-                duration: original_duration,
-            }],
+            notes: None,
+            history: vec![],
         };
 
-        let mut splits = Splits::create(tempdir().unwrap().path().join("splits.json"), vec![split])
-            .expect("splits should be valid");
+        let dir = tempdir().unwrap();
+        let mut splits = Splits::create(
+            dir.path().join("splits.json"),
+            vec![
+                split("A", 10),
+                split("B", 20),
+                split("C", 30),
+                split("D", 40),
+            ],
+        )
+        .unwrap();
+        for (percent, secs) in [(10, 60), (20, 130), (40, 300)] {
+            splits.update_with_igt(&InGameTime {
+                percent,
+                duration: Duration::from_secs(secs),
+            });
+        }
 
-        // Pre-existing active run
-        splits.active_run = Some(ActiveRun {
-            id: run_id,
+        let secs = |secs: &[Option<u64>]| -> Vec<Option<Duration>> {
+            secs.iter().map(|s| s.map(Duration::from_secs)).collect()
+        };
+        // C was skipped, so D has no segment time
+        let run_id = splits.runs()[0].id;
+        assert_eq!(
+            splits.segment_times(run_id),
+            secs(&[Some(60), Some(70), None, None])
+        );
+        assert_eq!(
+            Splits::segments(&secs(&[Some(60), Some(130), None, Some(300)])),
+            secs(&[Some(60), Some(70), None, None])
+        );
+    }
+
+    #[test]
+    fn run_metadata_is_shown_in_the_run_listing() {
+        let run = RunSummary {
+            id: Uuid::new_v4(),
             start_time: Utc::now(),
             end_time: None,
-            latest_split: InGameTime {
-                percent: 20,
-                duration: original_duration,
-            },
-        });
+            final_time: None,
+            category: None,
+            tags: Vec::new(),
+            notes: None,
+        };
+        let dir = tempdir().unwrap();
+        let mut splits = Splits::create_with_history(
+            dir.path().join("splits.json"),
+            None,
+            vec![run.clone()],
+            vec![],
+        )
+        .unwrap();
 
-        // Incoming update with same percent, updated duration
-        let current = InGameTime {
-            percent: 20,
-            duration: updated_duration,
+        assert!(
+            splits
+                .set_run_metadata(Uuid::new_v4(), Some("Any%".into()), None, None)
+                .is_err()
+        );
+        splits
+            .set_run_metadata(
+                run.id,
+                Some("Any%".into()),
+                Some(vec!["practice".into(), "new route".into()]),
+                None,
+            )
+            .unwrap();
+        splits
+            .set_run_metadata(run.id, None, None, Some("Died to the boss".into()))
+            .unwrap();
+
+        assert_eq!(splits.runs()[0].category.as_deref(), Some("Any%"));
+        assert!(
+            splits.format_runs()[0]
+                .ends_with("no splits  [Any%]  #practice #new route  \"Died to the boss\"")
+        );
+    }
+
+    #[test]
+    fn deleting_pb_run_picks_next_pb_and_recomputes_golds() {
+        let runs: Vec<RunSummary> = [100, 130, 120]
+            .iter()
+            .map(|&secs| RunSummary {
+                id: Uuid::new_v4(),
+                start_time: Utc::now(),
+                end_time: Some(Utc::now()),
+                final_time: Some(Duration::from_secs(secs)),
+                category: None,
+                tags: Vec::new(),
+                notes: None,
+            })
+            .collect();
+        let history = |secs: &[u64]| -> Vec<HistoricalSplit> {
+            runs.iter()
+                .zip(secs)
+                .map(|(run, &secs)| HistoricalSplit {
+                    run_id: run.id,
+                    duration: Duration::from_secs(secs),
+                    real_time: None,
+                })
+                .collect()
         };
 
-        // Act
-        splits.update_with_igt(&current);
+        let dir = tempdir().unwrap();
+        let mut splits = Splits::create_with_history(
+            dir.path().join("splits.json"),
+            Some(runs[0].clone()),
+            runs.clone(),
+            vec![
+                Split {
+                    name: "A".into(),
+                    percent: 10,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: history(&[40, 50, 60]),
+                },
+                Split {
+                    name: "B".into(),
+                    percent: 20,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: history(&[100, 130, 120]),
+                },
+            ],
+        )
+        .unwrap();
+        assert_eq!(splits.splits[0].best_segment, Some(Duration::from_secs(40)));
 
-        // Assert: history was updated
-        let updated_split = &splits.splits()[0];
-        let hist = &updated_split.history[0];
-        assert_eq!(hist.run_id, run_id);
-        assert_eq!(hist.duration, updated_duration);
+        assert!(splits.delete_run(Uuid::new_v4()).is_err());
+        splits.delete_run(runs[0].id).unwrap();
 
-        // Also: active run was not reset
-        let active_run = splits.active_run().expect("active run should exist");
-        assert_eq!(active_run.id, run_id);
-        assert_eq!(active_run.latest_split, current);
+        assert_eq!(splits.runs().len(), 2);
+        assert_eq!(splits.personal_best().unwrap().id, runs[2].id);
+        assert_eq!(splits.splits[0].time, Some(Duration::from_secs(60)));
+        assert_eq!(splits.splits[0].best_segment, Some(Duration::from_secs(50)));
+        assert_eq!(splits.splits[1].best_segment, Some(Duration::from_secs(60)));
     }
 
     #[test]
-    fn advance_to_next_split_adds_new_history_entry() {
-        let split_10 = Split {
-            name: "First Split".into(),
-            percent: 10,
-            time: Some(Duration::from_secs(20)),
-            best_segment: None,
-            history: vec![],
-        };
-
-        let split_20 = Split {
-            name: "Second Split".into(),
-            percent: 20,
-            time: Some(Duration::from_secs(40)),
-            best_segment: None,
-            history: vec![],
+    fn correcting_misread_final_time_picks_next_pb_and_recomputes_golds() {
+        let runs: Vec<RunSummary> = [60, 130]
+            .iter()
+            .enumerate()
+            .map(|(i, &secs)| RunSummary {
+                id: Uuid::new_v4(),
+                start_time: Utc::now() + chrono::Duration::seconds(i as i64),
+                end_time: Some(Utc::now()),
+                final_time: Some(Duration::from_secs(secs)),
+                category: None,
+                tags: Vec::new(),
+                notes: None,
+            })
+            .collect();
+        let history = |secs: [u64; 2]| -> Vec<HistoricalSplit> {
+            runs.iter()
+                .zip(secs)
+                .map(|(run, secs)| HistoricalSplit {
+                    run_id: run.id,
+                    duration: Duration::from_secs(secs),
+                    real_time: None,
+                })
+                .collect()
         };
 
-        let mut splits = Splits::create(
-            tempdir().unwrap().path().join("splits.json"),
-            vec![split_10, split_20],
+        let dir = tempdir().unwrap();
+        let mut splits = Splits::create_with_history(
+            dir.path().join("splits.json"),
+            Some(runs[0].clone()),
+            runs.clone(),
+            vec![
+                Split {
+                    name: "A".into(),
+                    percent: 10,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: history([40, 50]),
+                },
+                Split {
+                    name: "B".into(),
+                    percent: 20,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: history([60, 130]),
+                },
+            ],
         )
-        .expect("splits should be valid");
+        .unwrap();
+        assert_eq!(splits.splits[1].best_segment, Some(Duration::from_secs(20)));
 
-        // Simulate first update at 10% to create the run
-        let run_start_igt = InGameTime {
-            percent: 10,
-            duration: Duration::from_secs(25),
+        assert!(
+            splits
+                .correct_split_time(runs[0].id, Some("C"), Duration::from_secs(140))
+                .is_err()
+        );
+        // Not after the split before
+        assert!(
+            splits
+                .correct_split_time(runs[0].id, None, Duration::from_secs(30))
+                .is_err()
+        );
+
+        let previous = splits
+            .correct_split_time(runs[0].id, None, Duration::from_secs(140))
+            .unwrap();
+        assert_eq!(previous, Duration::from_secs(60));
+
+        assert_eq!(splits.runs()[0].final_time, Some(Duration::from_secs(140)));
+        assert_eq!(splits.personal_best().unwrap().id, runs[1].id);
+        assert_eq!(splits.splits[1].time, Some(Duration::from_secs(130)));
+        assert_eq!(splits.splits[1].best_segment, Some(Duration::from_secs(80)));
+        assert_eq!(splits.splits[0].best_segment, Some(Duration::from_secs(40)));
+    }
+
+    #[test]
+    fn check_finds_inconsistencies_and_repair_fixes_the_fixable_ones() {
+        let runs: Vec<RunSummary> = [Some(130), Some(60), None]
+            .iter()
+            .enumerate()
+            .map(|(i, final_time)| RunSummary {
+                id: Uuid::new_v4(),
+                start_time: Utc::now() + chrono::Duration::seconds(i as i64),
+                end_time: Some(Utc::now()),
+                final_time: final_time.map(Duration::from_secs),
+                category: None,
+                tags: Vec::new(),
+                notes: None,
+            })
+            .collect();
+        let unknown_run_id = Uuid::new_v4();
+        let history = |times: &[(Uuid, u64)]| -> Vec<HistoricalSplit> {
+            times
+                .iter()
+                .map(|&(run_id, secs)| HistoricalSplit {
+                    run_id,
+                    duration: Duration::from_secs(secs),
+                    real_time: None,
+                })
+                .collect()
         };
-        splits.update_with_igt(&run_start_igt);
 
-        // Store active run ID
-        let run_id = splits.active_run().expect("Expected active run").id;
+        let dir = tempdir().unwrap();
+        let mut splits = Splits::create_with_history(
+            dir.path().join("splits.json"),
+            Some(runs[0].clone()),
+            runs.clone(),
+            vec![
+                Split {
+                    name: "A".into(),
+                    percent: 10,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: history(&[
+                        (unknown_run_id, 30),
+                        (runs[0].id, 50),
+                        (runs[1].id, 40),
+                        (runs[2].id, 100),
+                    ]),
+                },
+                Split {
+                    name: "B".into(),
+                    percent: 20,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: history(&[(runs[0].id, 130), (runs[1].id, 60), (runs[2].id, 90)]),
+                },
+            ],
+        )
+        .unwrap();
 
-        // Advance to next split (20%)
-        let next_igt = InGameTime {
-            percent: 20,
-            duration: Duration::from_secs(55),
+        let decreasing = Inconsistency::DecreasingTime {
+            run_id: runs[2].id,
+            split: "B".into(),
+            time: Duration::from_secs(90),
+            previous: Duration::from_secs(100),
         };
-        splits.update_with_igt(&next_igt);
+        assert_eq!(
+            splits.check(),
+            vec![
+                Inconsistency::UnknownRun {
+                    split: "A".into(),
+                    run_id: unknown_run_id,
+                },
+                Inconsistency::PbNotFastest {
+                    pb: Some(Duration::from_secs(130)),
+                    fastest: Some(Duration::from_secs(60)),
+                },
+                decreasing.clone(),
+            ]
+        );
 
-        // active_run.latest_split should now be at 20%
-        let active_run = splits.active_run().expect("Expected active run");
-        assert_eq!(active_run.latest_split.percent, 20);
-        assert_eq!(active_run.latest_split.duration, Duration::from_secs(55));
+        splits.repair().unwrap();
+        assert_eq!(splits.check(), vec![decreasing]);
+        assert_eq!(splits.personal_best().unwrap().id, runs[1].id);
+        assert_eq!(splits.splits[1].time, Some(Duration::from_secs(60)));
+        assert_eq!(splits.splits[0].best_segment, Some(Duration::from_secs(40)));
+    }
+
+    #[test]
+    fn pruning_keeps_latest_runs_pb_and_golds() {
+        let runs: Vec<RunSummary> = [Some(100), None, None, None]
+            .iter()
+            .enumerate()
+            .map(|(i, final_time)| RunSummary {
+                id: Uuid::new_v4(),
+                start_time: Utc::now() + chrono::Duration::seconds(i as i64),
+                end_time: Some(Utc::now()),
+                final_time: final_time.map(Duration::from_secs),
+                category: None,
+                tags: Vec::new(),
+                notes: None,
+            })
+            .collect();
 
-        // The 10% split history should contain only the initial entry
-        let first_split = &splits.splits()[0];
-        assert_eq!(first_split.history.len(), 1);
-        assert_eq!(first_split.history[0].run_id, run_id);
-        assert_eq!(first_split.history[0].duration, Duration::from_secs(25));
+        let dir = tempdir().unwrap();
+        let mut splits = Splits::create_with_history(
+            dir.path().join("splits.json"),
+            Some(runs[0].clone()),
+            runs.clone(),
+            vec![Split {
+                name: "A".into(),
+                percent: 10,
+                min_time: None,
+                time: None,
+                best_segment: None,
+                notes: None,
+                history: runs
+                    .iter()
+                    .zip([100, 90, 110, 120])
+                    .map(|(run, secs)| HistoricalSplit {
+                        run_id: run.id,
+                        duration: Duration::from_secs(secs),
+                        real_time: None,
+                    })
+                    .collect(),
+            }],
+        )
+        .unwrap();
 
-        // The 20% split history should now contain a new entry
-        let second_split = &splits.splits()[1];
-        assert_eq!(second_split.history.len(), 1);
-        assert_eq!(second_split.history[0].run_id, run_id);
-        assert_eq!(second_split.history[0].duration, Duration::from_secs(55));
+        assert_eq!(splits.prune_runs(2).unwrap(), 1);
 
-        // No new run summary should have been added
-        assert_eq!(splits.runs().len(), 1);
+        let ids: Vec<Uuid> = splits.runs().iter().map(|run| run.id).collect();
+        assert_eq!(ids, vec![runs[0].id, runs[2].id, runs[3].id]);
+        assert_eq!(splits.splits[0].history.len(), 3);
+        // The gold of the pruned run is kept
+        assert_eq!(splits.splits[0].best_segment, Some(Duration::from_secs(90)));
     }
 
     #[test]
-    fn recording_new_split_updates_best_segment_if_better() {
-        let run1 = Uuid::new_v4();
-        let split_a = Split {
-            name: "A".into(),
-            percent: 10,
-            time: None,
-            best_segment: Some(Duration::from_secs(60)),
-            history: vec![HistoricalSplit {
-                run_id: run1,
-                duration: Duration::from_secs(60),
-            }],
-        };
-        let split_b = Split {
-            name: "B".into(),
-            percent: 20,
-            time: None,
-            best_segment: Some(Duration::from_secs(90)),
-            history: vec![HistoricalSplit {
-                run_id: run1,
-                duration: Duration::from_secs(150),
-            }],
-        };
-        let split_c = Split {
-            name: "C".into(),
-            percent: 30,
-            time: None,
-            best_segment: Some(Duration::from_secs(100)),
-            history: vec![HistoricalSplit {
-                run_id: run1,
-                duration: Duration::from_secs(250),
+    fn archiving_moves_old_runs_and_keeps_golds_and_averages() {
+        let runs: Vec<RunSummary> = [Some(100), None, None, None]
+            .iter()
+            .enumerate()
+            .map(|(i, final_time)| RunSummary {
+                id: Uuid::new_v4(),
+                start_time: Utc::now() + chrono::Duration::seconds(i as i64),
+                end_time: Some(Utc::now()),
+                final_time: final_time.map(Duration::from_secs),
+                category: None,
+                tags: Vec::new(),
+                notes: None,
+            })
+            .collect();
+
+        let dir = tempdir().unwrap();
+        let mut splits = Splits::create_with_history(
+            dir.path().join("splits.json"),
+            Some(runs[0].clone()),
+            runs.clone(),
+            vec![Split {
+                name: "A".into(),
+                percent: 10,
+                min_time: None,
+                time: None,
+                best_segment: None,
+                notes: None,
+                history: runs
+                    .iter()
+                    .zip([100, 90, 110, 120])
+                    .map(|(run, secs)| HistoricalSplit {
+                        run_id: run.id,
+                        duration: Duration::from_secs(secs),
+                        real_time: None,
+                    })
+                    .collect(),
             }],
-        };
-        let mut splits = Splits::create(
-            tempdir().unwrap().path().join("splits.json"),
-            vec![split_a, split_b, split_c],
         )
         .unwrap();
+        let archive_path = splits.archive_path().unwrap();
+        assert_eq!(archive_path, dir.path().join("splits.archive.json"));
 
-        // Record a new faster run2 entry at 0:50
-        splits.update_with_igt(&InGameTime {
-            percent: 10,
-            duration: Duration::from_secs(50),
-        });
-        splits.update_with_igt(&InGameTime {
-            percent: 20,
-            duration: Duration::from_secs(130),
-        });
-        // Third split is slower
-        splits.update_with_igt(&InGameTime {
-            percent: 30,
-            duration: Duration::from_secs(240),
-        });
+        assert_eq!(splits.archive_runs(2, &archive_path).unwrap(), 1);
+        assert_eq!(splits.runs().len(), 3);
+        assert_eq!(splits.splits[0].best_segment, Some(Duration::from_secs(90)));
+        assert_eq!(
+            splits.comparison_times(Comparison::Average),
+            vec![Some(Duration::from_secs(105))]
+        );
 
-        // Best segment should now be updated to 0:50 and 1:20, respectively
-        assert_eq!(splits.splits[0].best_segment, Some(Duration::from_secs(50)));
-        assert_eq!(splits.splits[1].best_segment, Some(Duration::from_secs(80)));
+        // Merged into the existing archive
+        assert_eq!(splits.archive_runs(1, &archive_path).unwrap(), 1);
+        let archive = Splits::load_from_file(&archive_path).unwrap();
+        let archived: Vec<Uuid> = archive.runs().iter().map(|run| run.id).collect();
+        assert_eq!(archived, vec![runs[1].id, runs[2].id]);
+        assert_eq!(archive.splits[0].history.len(), 2);
 
-        // Best segment of last split should remain the same
+        let reloaded = Splits::load_from_file(&dir.path().join("splits.json")).unwrap();
+        assert_eq!(reloaded.runs().len(), 2);
         assert_eq!(
-            splits.splits[2].best_segment,
-            Some(Duration::from_secs(100))
+            reloaded.comparison_times(Comparison::Average),
+            vec![Some(Duration::from_secs(105))]
         );
     }
 
     #[test]
-    fn recording_new_split_sets_best_segment_when_none() {
-        let split_a = Split {
-            name: "A".into(),
-            percent: 10,
-            time: None,
-            best_segment: None, // no previous best
-            history: vec![],
+    fn merging_unions_runs_and_recomputes_pb_and_golds() {
+        let runs: Vec<RunSummary> = [Some(120), Some(100), None]
+            .iter()
+            .enumerate()
+            .map(|(i, final_time)| RunSummary {
+                id: Uuid::new_v4(),
+                start_time: Utc::now() + chrono::Duration::seconds(i as i64),
+                end_time: Some(Utc::now()),
+                final_time: final_time.map(Duration::from_secs),
+                category: None,
+                tags: Vec::new(),
+                notes: None,
+            })
+            .collect();
+        let make_splits = |runs: &[RunSummary], secs: &[(u64, u64)]| {
+            let history = |split: usize| -> Vec<HistoricalSplit> {
+                runs.iter()
+                    .zip(secs)
+                    .map(|(run, secs)| HistoricalSplit {
+                        run_id: run.id,
+                        duration: Duration::from_secs([secs.0, secs.1][split]),
+                        real_time: None,
+                    })
+                    .collect()
+            };
+            Splits::create_with_history(
+                PathBuf::from("splits.json"),
+                runs.iter().find(|run| run.final_time.is_some()).cloned(),
+                runs.to_vec(),
+                vec![
+                    Split {
+                        name: "A".into(),
+                        percent: 10,
+                        min_time: None,
+                        time: None,
+                        best_segment: None,
+                        notes: None,
+                        history: history(0),
+                    },
+                    Split {
+                        name: "B".into(),
+                        percent: 20,
+                        min_time: None,
+                        time: None,
+                        best_segment: None,
+                        notes: None,
+                        history: history(1),
+                    },
+                ],
+            )
+            .unwrap()
         };
 
-        let mut splits =
-            Splits::create(tempdir().unwrap().path().join("splits.json"), vec![split_a]).unwrap();
+        // The first run was copied to both machines
+        let mut splits = make_splits(&[runs[0].clone(), runs[2].clone()], &[(50, 120), (40, 130)]);
+        let other = make_splits(&[runs[0].clone(), runs[1].clone()], &[(50, 120), (60, 100)]);
 
-        // Record a first IGT
-        splits.update_with_igt(&InGameTime {
-            percent: 10,
-            duration: Duration::from_secs(60),
-        });
+        assert_eq!(splits.merge(other).unwrap(), 1);
 
-        // Best segment should now be set to 1:00
-        assert_eq!(splits.splits[0].best_segment, Some(Duration::from_secs(60)));
+        let ids: Vec<Uuid> = splits.runs().iter().map(|run| run.id).collect();
+        assert_eq!(ids, vec![runs[0].id, runs[1].id, runs[2].id]);
+        assert_eq!(splits.personal_best().unwrap().id, runs[1].id);
+        assert_eq!(splits.splits[0].history.len(), 3);
+        assert_eq!(splits.splits[0].time, Some(Duration::from_secs(60)));
+        assert_eq!(splits.splits[0].best_segment, Some(Duration::from_secs(40)));
+        assert_eq!(splits.splits[1].best_segment, Some(Duration::from_secs(40)));
+
+        let mismatched = Splits::create(
+            PathBuf::from("other.json"),
+            vec![Split {
+                name: "C".into(),
+                percent: 30,
+                min_time: None,
+                time: None,
+                best_segment: None,
+                notes: None,
+                history: Vec::new(),
+            }],
+        )
+        .unwrap();
+        assert!(splits.merge(mismatched).is_err());
     }
 
     #[test]
@@ -2011,21 +4982,27 @@ mod tests {
         let split_a = Split {
             name: "A".into(),
             percent: 10,
+            min_time: None,
             time: None,
             best_segment: Some(Duration::from_secs(60)),
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id: run1,
                 duration: Duration::from_secs(60),
+                real_time: None,
             }],
         };
         let split_b = Split {
             name: "B".into(),
             percent: 50,
+            min_time: None,
             time: None,
             best_segment: Some(Duration::from_secs(70)),
+            notes: None,
             history: vec![HistoricalSplit {
                 run_id: run1,
                 duration: Duration::from_secs(130),
+                real_time: None,
             }],
         };
 
@@ -2059,16 +5036,20 @@ mod tests {
         let split_5 = Split {
             name: "Intro".into(),
             percent: 5,
+            min_time: None,
             time: Some(Duration::from_secs(10)),
             best_segment: None,
+            notes: None,
             history: vec![],
         };
 
         let split_40 = Split {
             name: "Mid Game".into(),
             percent: 40,
+            min_time: None,
             time: Some(Duration::from_secs(80)),
             best_segment: None,
+            notes: None,
             history: vec![],
         };
 
@@ -2130,22 +5111,28 @@ mod tests {
             Split {
                 name: "Split 1".into(),
                 percent: 10,
+                min_time: None,
                 time: Some(Duration::from_secs(10)),
                 best_segment: None,
+                notes: None,
                 history: vec![],
             },
             Split {
                 name: "Split 2".into(),
                 percent: 50,
+                min_time: None,
                 time: Some(Duration::from_secs(50)),
                 best_segment: None,
+                notes: None,
                 history: vec![],
             },
             Split {
                 name: "Final Split".into(),
                 percent: 100,
+                min_time: None,
                 time: Some(Duration::from_secs(100)),
                 best_segment: None,
+                notes: None,
                 history: vec![],
             },
         ];
@@ -2198,8 +5185,10 @@ mod tests {
         let final_split = Split {
             name: "Final Split".into(),
             percent: 100,
+            min_time: None,
             time: Some(Duration::from_secs(120)),
             best_segment: None,
+            notes: None,
             history: vec![],
         };
 
@@ -2255,15 +5244,19 @@ mod tests {
             Split {
                 name: "First Split".into(),
                 percent: 10,
+                min_time: None,
                 time: Some(Duration::from_secs(20)),
                 best_segment: None,
+                notes: None,
                 history: vec![],
             },
             Split {
                 name: "Final Split".into(),
                 percent: 100,
+                min_time: None,
                 time: Some(Duration::from_secs(200)),
                 best_segment: None,
+                notes: None,
                 history: vec![],
             },
         ];
@@ -2309,8 +5302,10 @@ mod tests {
         let split = Split {
             name: "Known Split".into(),
             percent: 50,
+            min_time: None,
             time: Some(Duration::from_secs(100)),
             best_segment: None,
+            notes: None,
             history: vec![],
         };
 
@@ -2348,8 +5343,10 @@ mod tests {
         let split = Split {
             name: "First Split".into(),
             percent: 10,
+            min_time: None,
             time: Some(Duration::from_secs(20)),
             best_segment: None,
+            notes: None,
             history: vec![],
         };
 
@@ -2387,15 +5384,19 @@ mod tests {
                 Split {
                     name: "Split 1".into(),
                     percent: 10,
+                    min_time: None,
                     time: None,
                     best_segment: None,
+                    notes: None,
                     history: vec![],
                 },
                 Split {
                     name: "Split 2".into(),
                     percent: 20,
+                    min_time: None,
                     time: None,
                     best_segment: None,
+                    notes: None,
                     history: vec![],
                 },
             ],
@@ -2434,15 +5435,19 @@ mod tests {
                 Split {
                     name: "Split 1".into(),
                     percent: 10,
+                    min_time: None,
                     time: None,
                     best_segment: None,
+                    notes: None,
                     history: vec![],
                 },
                 Split {
                     name: "Split 2".into(),
                     percent: 20,
+                    min_time: None,
                     time: None,
                     best_segment: None,
+                    notes: None,
                     history: vec![],
                 },
             ],
@@ -2497,15 +5502,19 @@ mod tests {
                 Split {
                     name: "Split 1".into(),
                     percent: 10,
+                    min_time: None,
                     time: None,
                     best_segment: None,
+                    notes: None,
                     history: vec![],
                 },
                 Split {
                     name: "Split 2".into(),
                     percent: 20,
+                    min_time: None,
                     time: None,
                     best_segment: None,
+                    notes: None,
                     history: vec![],
                 },
             ],
@@ -2550,6 +5559,56 @@ mod tests {
         assert_eq!(splits.splits()[1].time, Some(Duration::from_secs(65)));
     }
 
+    #[test]
+    fn new_pb_keeps_previous_pb_in_history() {
+        let dir = tempdir().unwrap();
+        let mut splits = Splits::create(
+            dir.path().join("splits.json"),
+            vec![
+                Split {
+                    name: "Start".into(),
+                    percent: 10,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: vec![],
+                },
+                Split {
+                    name: "End".into(),
+                    percent: 20,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: vec![],
+                },
+            ],
+        )
+        .unwrap();
+
+        for secs in [70, 80, 65] {
+            splits.update_with_igt(&InGameTime {
+                percent: 10,
+                duration: Duration::from_secs(30),
+            });
+            splits.update_with_igt(&InGameTime {
+                percent: 20,
+                duration: Duration::from_secs(secs),
+            });
+        }
+        let first_pb = splits.runs()[0].clone();
+
+        assert_eq!(splits.previous_pbs().len(), 1);
+        assert_eq!(splits.previous_pbs()[0].id, first_pb.id);
+        assert_eq!(splits.previous_pbs()[0].final_time, Duration::from_secs(70));
+
+        let history = splits.format_pb_history();
+        assert_eq!(history.len(), 2);
+        assert!(history[0].ends_with("0:01:10"), "{}", history[0]);
+        assert!(history[1].ends_with("0:01:05  -0:00:05"), "{}", history[1]);
+    }
+
     #[test]
     fn tie_run_does_not_overwrite_personal_best() {
         use std::time::Duration;
@@ -2560,15 +5619,19 @@ mod tests {
                 Split {
                     name: "Split 1".into(),
                     percent: 10,
+                    min_time: None,
                     time: None,
                     best_segment: None,
+                    notes: None,
                     history: vec![],
                 },
                 Split {
                     name: "Split 2".into(),
                     percent: 20,
+                    min_time: None,
                     time: None,
                     best_segment: None,
+                    notes: None,
                     history: vec![],
                 },
             ],
@@ -2609,4 +5672,232 @@ mod tests {
             "PB time should remain unchanged"
         );
     }
+
+    fn make_gem_splits() -> Splits {
+        let splits = vec![
+            Split {
+                name: "Split 1".into(),
+                percent: 10,
+                min_time: None,
+                time: None,
+                best_segment: None,
+                notes: None,
+                history: vec![],
+            },
+            Split {
+                name: "Gems".into(),
+                percent: 50,
+                min_time: None,
+                time: None,
+                best_segment: None,
+                notes: None,
+                history: vec![],
+            },
+            Split {
+                name: "Final Split".into(),
+                percent: 100,
+                min_time: None,
+                time: None,
+                best_segment: None,
+                notes: None,
+                history: vec![],
+            },
+        ];
+
+        Splits::create(tempdir().unwrap().path().join("splits.json"), splits)
+            .expect("splits should be valid")
+            .with_triggers(vec![CounterTrigger {
+                percent: 50,
+                counter: "gems".into(),
+                value: 100,
+            }])
+            .expect("trigger should be valid")
+    }
+
+    #[test]
+    fn trigger_with_unknown_percent_is_rejected() {
+        let splits = Splits::create(PathBuf::from("dummy_path"), vec![]).unwrap();
+        let result = splits.with_triggers(vec![CounterTrigger {
+            percent: 42,
+            counter: "gems".into(),
+            value: 100,
+        }]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn max_percent_below_a_split_is_rejected() {
+        assert!(make_gem_splits().with_max_percent(Some(99)).is_err());
+
+        let splits = make_gem_splits()
+            .with_max_percent(Some(100))
+            .expect("maximum should be valid");
+        assert_eq!(splits.max_percent(), Some(100));
+    }
+
+    #[test]
+    fn counter_reaching_trigger_value_records_split_once() {
+        let mut splits = make_gem_splits();
+        splits.update_with_igt(&make_ingame_time(10, 0, 0, 11));
+        let run_id = splits.active_run().unwrap().id;
+
+        assert!(
+            splits
+                .update_with_counter("gems", 99, Duration::from_secs(20))
+                .is_empty()
+        );
+        assert!(
+            splits
+                .update_with_counter("orbs", 100, Duration::from_secs(25))
+                .is_empty()
+        );
+        assert!(matches!(
+            splits.update_with_counter("gems", 100, Duration::from_secs(30))[..],
+            [TimerEvent::SplitRecorded { index: 1, .. }, ..]
+        ));
+        assert!(
+            splits
+                .update_with_counter("gems", 101, Duration::from_secs(40))
+                .is_empty()
+        );
+
+        let split = &splits.splits()[1];
+        assert_eq!(split.history.len(), 1);
+        assert_eq!(split.history[0].run_id, run_id);
+        assert_eq!(split.history[0].duration, Duration::from_secs(30));
+        assert!(split.history[0].real_time.is_some());
+        // The percentage didn't advance, so a later IGT at 10% must not reset the run
+        assert_eq!(splits.active_run().unwrap().latest_split.percent, 10);
+    }
+
+    #[test]
+    fn updates_emit_the_events_of_the_run() {
+        let mut splits = make_gem_splits();
+        let events = splits.update_with_igt(&make_ingame_time(10, 0, 0, 11));
+        let run_id = splits.active_run().unwrap().id;
+        assert_eq!(
+            events,
+            vec![
+                TimerEvent::RunStarted { run_id },
+                TimerEvent::SplitRecorded {
+                    run_id,
+                    index: 0,
+                    time: make_ingame_time(10, 0, 0, 11),
+                },
+                TimerEvent::GoldAchieved {
+                    run_id,
+                    index: 0,
+                    segment: Duration::from_secs(11),
+                },
+            ]
+        );
+        assert!(
+            splits
+                .update_with_igt(&make_ingame_time(42, 0, 0, 12))
+                .is_empty()
+        );
+
+        splits.update_with_igt(&make_ingame_time(50, 0, 0, 30));
+        let events = splits.update_with_igt(&make_ingame_time(100, 0, 1, 0));
+        assert!(events.contains(&TimerEvent::RunFinished {
+            run_id,
+            final_time: Duration::from_secs(60),
+        }));
+        assert!(events.contains(&TimerEvent::PbSet {
+            run_id,
+            final_time: Duration::from_secs(60),
+        }));
+
+        splits.update_with_igt(&make_ingame_time(10, 0, 0, 12));
+        let second_run = splits.active_run().unwrap().id;
+        splits.update_with_igt(&make_ingame_time(50, 0, 0, 40));
+        let events = splits.update_with_igt(&make_ingame_time(10, 0, 0, 5));
+        assert_eq!(events[0], TimerEvent::RunReset { run_id: second_run });
+        assert!(matches!(events[1], TimerEvent::RunStarted { .. }));
+    }
+
+    #[test]
+    fn counter_does_not_start_a_run() {
+        let mut splits = make_gem_splits();
+        assert!(
+            splits
+                .update_with_counter("gems", 100, Duration::from_secs(30))
+                .is_empty()
+        );
+        assert!(splits.active_run().is_none());
+        assert!(splits.runs().is_empty());
+    }
+
+    #[test]
+    fn interrupting_active_run_sets_end_time_without_final_time() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let splits = vec![
+            Split {
+                name: "Split 1".into(),
+                percent: 10,
+                min_time: None,
+                time: None,
+                best_segment: None,
+                notes: None,
+                history: vec![],
+            },
+            Split {
+                name: "Final Split".into(),
+                percent: 100,
+                min_time: None,
+                time: None,
+                best_segment: None,
+                notes: None,
+                history: vec![],
+            },
+        ];
+        let mut splits = Splits::create(dir.path().join("splits.json"), splits)?;
+        splits.update_with_igt(&make_ingame_time(10, 0, 0, 11));
+        let run_id = splits.active_run().unwrap().id;
+
+        splits.interrupt_active_run()?;
+
+        assert!(splits.active_run().unwrap().end_time.is_some());
+        let run = splits.runs().iter().find(|r| r.id == run_id).unwrap();
+        assert!(run.end_time.is_some());
+        assert_eq!(run.final_time, None);
+
+        // Further updates are ignored, like after finishing a run
+        splits.update_with_igt(&make_ingame_time(10, 0, 0, 20));
+        assert_eq!(
+            splits.splits()[0].history[0].duration,
+            Duration::from_secs(11)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn level_trigger_records_split_when_entering_level() {
+        let mut splits = make_gem_splits()
+            .with_level_triggers(vec![LevelTrigger {
+                percent: 50,
+                level: "Sunny  Villa".into(),
+            }])
+            .expect("trigger should be valid");
+        splits.update_with_igt(&make_ingame_time(10, 0, 0, 11));
+
+        assert!(
+            splits
+                .update_with_level("ARTISANS", Duration::from_secs(20))
+                .is_empty()
+        );
+        assert!(matches!(
+            splits.update_with_level("SUNNY VILLA", Duration::from_secs(30))[..],
+            [TimerEvent::SplitRecorded { index: 1, .. }, ..]
+        ));
+        assert!(
+            splits
+                .update_with_level("SUNNY VILLA", Duration::from_secs(40))
+                .is_empty()
+        );
+        assert_eq!(
+            splits.splits()[1].history[0].duration,
+            Duration::from_secs(30)
+        );
+    }
 }
@@ -0,0 +1,300 @@
+use std::io::ErrorKind;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use serde_json::{Value, json};
+use tungstenite::{Error, Message, WebSocket};
+
+use crate::in_game_time::InGameTime;
+use crate::splits::events::igt_json;
+use crate::splits::{Comparison, Splits, TimerEvent, TimerObserver};
+
+/// How long a connection waits for messages from its client before sending pending broadcasts
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+struct Clients {
+    senders: Vec<Sender<String>>,
+    /// Latest state, sent to clients when they connect
+    state: Option<String>,
+}
+
+/// Pushes the timer events and the state of the splits as JSON to every connected WebSocket client,
+/// e.g. overlays or dashboards. Every event is sent as `{"type": "event", "run_id": ..., "event":
+/// "split_recorded", ...}` (see `TimerEvent::to_json`), followed by the state after it as `{"type":
+/// "state", ...}`. The state is also sent when a client connects and whenever the IGT changed.
+/// Clones broadcast to the same clients, e.g. for the tracker of another game.
+#[derive(Clone)]
+pub struct WebSocketBroadcast {
+    clients: Arc<Mutex<Clients>>,
+    address: SocketAddr,
+    /// What the deltas of the state are against
+    comparison: Comparison,
+    /// IGT of the state sent last
+    last_igt: Option<InGameTime>,
+}
+
+impl WebSocketBroadcast {
+    /// Listens on `address` (e.g. 127.0.0.1:16835) for WebSocket connections, each served on a
+    /// thread of its own.
+    pub fn start(address: SocketAddr, comparison: Comparison) -> Result<Self> {
+        let listener = TcpListener::bind(address).map_err(|e| {
+            anyhow!(
+                "Failed to listen on {} for WebSocket connections: {}",
+                address,
+                e
+            )
+        })?;
+        let address = listener.local_addr()?;
+        let clients = Arc::new(Mutex::new(Clients {
+            senders: Vec::new(),
+            state: None,
+        }));
+        let shared = clients.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let clients = shared.clone();
+                thread::spawn(move || {
+                    if let Err(e) = serve(stream, &clients) {
+                        println!("WebSocket connection failed: {}", e);
+                    }
+                });
+            }
+        });
+        Ok(Self {
+            clients,
+            address,
+            comparison,
+            last_igt: None,
+        })
+    }
+
+    /// Address the server listens on, e.g. with port 0 the port picked
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Sends `message` to every client, dropping the clients that disconnected
+    fn broadcast(&self, message: Value, state: bool) {
+        let message = message.to_string();
+        let mut clients = self.clients.lock().unwrap_or_else(PoisonError::into_inner);
+        clients
+            .senders
+            .retain(|sender| sender.send(message.clone()).is_ok());
+        if state {
+            clients.state = Some(message);
+        }
+    }
+
+    fn broadcast_state(&mut self, splits: &Splits) {
+        self.last_igt = splits.active_run().map(|run| run.latest_split);
+        self.broadcast(state_json(splits, self.comparison), true);
+    }
+}
+
+/// Registers the client connected through `stream` and sends it the broadcasts until it
+/// disconnects
+fn serve(stream: TcpStream, clients: &Mutex<Clients>) -> Result<()> {
+    let mut socket = tungstenite::accept(stream).map_err(|e| anyhow!("{}", e))?;
+    let (sender, receiver) = mpsc::channel();
+    let state = {
+        let mut clients = clients.lock().unwrap_or_else(PoisonError::into_inner);
+        clients.senders.push(sender);
+        clients.state.clone()
+    };
+    if let Some(state) = state {
+        socket.send(Message::text(state))?;
+    }
+    // Reading in between, e.g. to answer pings and notice the client closing the connection
+    socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+    loop {
+        if !send_pending(&mut socket, &receiver)? {
+            return Ok(());
+        }
+        match socket.read() {
+            Ok(_) => {}
+            Err(Error::Io(e))
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(Error::ConnectionClosed | Error::AlreadyClosed) => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Sends the broadcasts received so far, `false` once the broadcast stopped
+fn send_pending(socket: &mut WebSocket<TcpStream>, receiver: &Receiver<String>) -> Result<bool> {
+    loop {
+        match receiver.try_recv() {
+            Ok(message) => socket.send(Message::text(message))?,
+            Err(TryRecvError::Empty) => return Ok(true),
+            Err(TryRecvError::Disconnected) => return Ok(false),
+        }
+    }
+}
+
+/// The state of the splits and of the active run: its phase, IGT and the times, deltas against
+/// `comparison` and best segments of every split.
+pub fn state_json(splits: &Splits, comparison: Comparison) -> Value {
+    let run = splits.active_run().and_then(|active_run| {
+        splits
+            .runs()
+            .iter()
+            .find(|run| run.id == active_run.id)
+            .map(|run| (active_run, run))
+    });
+    let phase = match run {
+        Some((_, run)) if run.final_time.is_some() => "ended",
+        // Interrupted, i.e. ended without finishing
+        Some((active_run, _)) if active_run.end_time.is_some() => "not_running",
+        Some(_) => "running",
+        None => "not_running",
+    };
+    let run_id = run
+        .filter(|_| phase != "not_running")
+        .map(|(_, run)| run.id);
+    let compared = splits.comparison_times(comparison);
+    let ms = |time: Option<Duration>| time.map(|time| time.as_millis() as u64);
+    let split_states: Vec<Value> = splits
+        .splits()
+        .iter()
+        .zip(&compared)
+        .map(|(split, &compared)| {
+            let time = run_id.and_then(|run_id| {
+                split
+                    .history
+                    .iter()
+                    .find(|hs| hs.run_id == run_id)
+                    .map(|hs| hs.duration)
+            });
+            let delta = time
+                .zip(compared)
+                .map(|(time, compared)| time.as_millis() as i64 - compared.as_millis() as i64);
+            json!({
+                "name": split.name,
+                "percent": split.percent,
+                "time_ms": ms(time),
+                "comparison_ms": ms(compared),
+                "delta_ms": delta,
+                "best_segment_ms": ms(split.best_segment),
+            })
+        })
+        .collect();
+
+    json!({
+        "type": "state",
+        "file": splits.path().map(|path| path.display().to_string()),
+        "phase": phase,
+        "run_id": run_id,
+        "igt": run
+            .filter(|_| run_id.is_some())
+            .map(|(active_run, _)| igt_json(&active_run.latest_split)),
+        "comparison": comparison.name(),
+        "splits": split_states,
+        "pb_ms": ms(splits.personal_best().and_then(|pb| pb.final_time)),
+        "sum_of_best_ms": ms(splits.sum_of_bests()),
+        "best_possible_time_ms": ms(splits.best_possible_time()),
+        "attempts": splits.runs().len(),
+        "completed": splits.runs().iter().filter(|run| run.final_time.is_some()).count(),
+    })
+}
+
+impl TimerObserver for WebSocketBroadcast {
+    fn on_event(&mut self, splits: &Splits, event: &TimerEvent) {
+        let mut message = event.to_json(splits);
+        message["type"] = json!("event");
+        message["run_id"] = json!(event.run_id());
+        self.broadcast(message, false);
+        self.broadcast_state(splits);
+    }
+
+    fn on_observation(&mut self, splits: &Splits, igt: &InGameTime) {
+        if self.last_igt != Some(*igt) {
+            self.broadcast_state(splits);
+        }
+    }
+
+    fn on_reload(&mut self, splits: &Splits) {
+        self.broadcast_state(splits);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    use crate::splits::splits::Split;
+
+    fn read_json(socket: &mut WebSocket<tungstenite::stream::MaybeTlsStream<TcpStream>>) -> Value {
+        let message = socket.read().unwrap();
+        serde_json::from_str(message.to_text().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn events_and_state_are_pushed_to_every_client() {
+        let dir = tempdir().unwrap();
+        let mut splits = Splits::create(
+            dir.path().join("splits.json"),
+            ["Sunny Villa", "Cloud Spires"]
+                .iter()
+                .zip([10, 20])
+                .map(|(name, percent)| Split {
+                    name: name.to_string(),
+                    percent,
+                    min_time: None,
+                    time: None,
+                    best_segment: None,
+                    notes: None,
+                    history: Vec::new(),
+                })
+                .collect(),
+        )
+        .unwrap();
+        for (percent, secs) in [(10, 60), (20, 150)] {
+            splits.update_with_igt(&InGameTime::from_secs(percent, secs));
+        }
+
+        let mut broadcast =
+            WebSocketBroadcast::start("127.0.0.1:0".parse().unwrap(), Comparison::PersonalBest)
+                .unwrap();
+        broadcast.on_reload(&splits);
+        let url = format!("ws://{}", broadcast.address());
+        let (mut first, _) = tungstenite::connect(&url).unwrap();
+        let (mut second, _) = tungstenite::connect(&url).unwrap();
+        for client in [&mut first, &mut second] {
+            let state = read_json(client);
+            assert_eq!(state["type"], "state");
+            assert_eq!(state["phase"], "ended");
+            assert_eq!(state["pb_ms"], 150_000);
+        }
+
+        let igt = InGameTime::from_secs(10, 55);
+        let events = splits.update_with_igt(&igt);
+        broadcast.on_observation(&splits, &igt);
+        for event in &events {
+            broadcast.on_event(&splits, event);
+        }
+        for client in [&mut first, &mut second] {
+            // The state after the new IGT, then every event followed by the state after it
+            let mut messages = Vec::new();
+            while messages.len() < 1 + 2 * events.len() {
+                messages.push(read_json(client));
+            }
+            assert_eq!(messages[0]["phase"], "running");
+            assert_eq!(messages[1]["type"], "event");
+            assert_eq!(messages[1]["event"], "run_started");
+            let split = &messages[3];
+            assert_eq!(split["event"], "split_recorded");
+            assert_eq!(split["split"], "Sunny Villa");
+            assert_eq!(split["igt_ms"], 55_000);
+            let state = messages.last().unwrap();
+            assert_eq!(state["igt"]["igt_ms"], 55_000);
+            assert_eq!(state["splits"][0]["time_ms"], 55_000);
+            assert_eq!(state["splits"][0]["delta_ms"], -5_000);
+            assert_eq!(state["splits"][1]["time_ms"], Value::Null);
+        }
+    }
+}
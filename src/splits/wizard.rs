@@ -0,0 +1,243 @@
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Result, anyhow, bail};
+
+use crate::in_game_time::parse_time;
+use crate::splits::{Splits, splits::Split};
+
+/// Parses a split given as NAME=PERCENT, e.g. "Sunny Villa=10"
+pub fn parse_split(s: &str) -> Result<(String, u32)> {
+    let (name, percent) = s
+        .rsplit_once('=')
+        .ok_or_else(|| anyhow!("Invalid split '{}': expected NAME=PERCENT", s))?;
+    let name = name.trim();
+    if name.is_empty() {
+        bail!("Invalid split '{}': the name is empty", s);
+    }
+    Ok((name.to_string(), parse_percent(percent)?))
+}
+
+fn parse_percent(s: &str) -> Result<u32> {
+    let s = s.trim();
+    s.strip_suffix('%')
+        .unwrap_or(s)
+        .trim()
+        .parse()
+        .map_err(|_| {
+            anyhow!(
+                "Invalid percentage '{}': expected a whole number, e.g. 12",
+                s
+            )
+        })
+}
+
+fn new_split(name: String, percent: u32, min_time: Option<Duration>) -> Split {
+    Split {
+        name,
+        percent,
+        min_time,
+        time: None,
+        best_segment: None,
+        notes: None,
+        history: Vec::new(),
+    }
+}
+
+/// Creates a new splits file at `path`, which mustn't exist yet. The splits are `splits` if given,
+/// else the splits, triggers and options of the splits file `from` (without its times and runs),
+/// else asked for on the terminal.
+pub fn create_splits_file(
+    path: PathBuf,
+    splits: &[(String, u32)],
+    from: Option<&Path>,
+) -> Result<Splits> {
+    if path.exists() {
+        bail!(
+            "{} already exists, choose another file or delete it first",
+            path.display()
+        );
+    }
+
+    let created = match from {
+        Some(from) => layout_of(&Splits::load_from_file(from)?, path)?,
+        None => {
+            let splits = if splits.is_empty() {
+                prompt_splits(&mut io::stdin().lock(), &mut io::stdout())?
+            } else {
+                splits
+                    .iter()
+                    .map(|(name, percent)| new_split(name.clone(), *percent, None))
+                    .collect()
+            };
+            if splits.is_empty() {
+                bail!("No splits entered");
+            }
+            Splits::create(path, splits)?
+        }
+    };
+    created.save_to_file()?;
+    Ok(created)
+}
+
+/// Splits with the same splits, triggers and options as `template` but no times or runs
+fn layout_of(template: &Splits, path: PathBuf) -> Result<Splits> {
+    let splits = template
+        .splits()
+        .iter()
+        .map(|split| Split {
+            notes: split.notes.clone(),
+            ..new_split(split.name.clone(), split.percent, split.min_time)
+        })
+        .collect();
+    let splits = if template.ordered() {
+        Splits::create_ordered_with_history(path, template.segmented(), None, Vec::new(), splits)?
+    } else {
+        Splits::create_segmented_with_history(path, template.segmented(), None, Vec::new(), splits)?
+    };
+    splits
+        .with_triggers(template.triggers().clone())?
+        .with_level_triggers(template.level_triggers().clone())?
+        .with_max_percent(template.max_percent())
+}
+
+/// Prints `question` and reads the answer, `None` once the input ended.
+fn prompt(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    question: &str,
+) -> Result<Option<String>> {
+    write!(output, "{}: ", question)?;
+    output.flush()?;
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        writeln!(output)?;
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_string()))
+}
+
+/// Asks for the name and percentage of every split until an empty name is entered. Invalid
+/// answers are asked again. Splits at the same percentage as an earlier one also need the minimum
+/// IGT at which they are reached.
+fn prompt_splits(input: &mut impl BufRead, output: &mut impl Write) -> Result<Vec<Split>> {
+    writeln!(
+        output,
+        "Enter the splits in the order of the route, e.g. \"Sunny Villa\" at 10%. Leave the name empty when done."
+    )?;
+    let mut splits: Vec<Split> = Vec::new();
+    loop {
+        let question = format!("Split {} name", splits.len() + 1);
+        let Some(name) = prompt(input, output, &question)? else {
+            break;
+        };
+        if name.is_empty() {
+            break;
+        }
+
+        let split = loop {
+            let Some(answer) = prompt(input, output, "Percentage")? else {
+                bail!("Input ended before the percentage of {}", name);
+            };
+            let percent = match parse_percent(&answer) {
+                Ok(percent) => percent,
+                Err(e) => {
+                    writeln!(output, "{}", e)?;
+                    continue;
+                }
+            };
+            if !splits.iter().any(|s| s.percent == percent) {
+                break new_split(name.clone(), percent, None);
+            }
+
+            writeln!(
+                output,
+                "Another split is at {}%, the minimum IGT tells them apart",
+                percent
+            )?;
+            let question = "Minimum IGT (H:MM:SS, empty to change the percentage)";
+            let Some(answer) = prompt(input, output, question)? else {
+                bail!("Input ended before the minimum IGT of {}", name);
+            };
+            if answer.is_empty() {
+                continue;
+            }
+            match parse_time(&answer) {
+                Ok(min_time)
+                    if !splits
+                        .iter()
+                        .any(|s| s.percent == percent && s.min_time == Some(min_time)) =>
+                {
+                    break new_split(name.clone(), percent, Some(min_time));
+                }
+                Ok(_) => writeln!(output, "Another split has the same percentage and IGT")?,
+                Err(e) => writeln!(output, "{}", e)?,
+            }
+        };
+        if splits
+            .last()
+            .is_some_and(|last| last.percent > split.percent)
+        {
+            writeln!(
+                output,
+                "{}% is below the previous split, the splits will be sorted by percentage",
+                split.percent
+            )?;
+        }
+        splits.push(split);
+    }
+    Ok(splits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn splits_are_asked_for_until_an_empty_name() {
+        let answers = "Sunny Villa\n10\nCloud Spires\nabc\n12%\nSunny Villa 2\n10\n0:05:00\n\n";
+        let mut output = Vec::new();
+        let splits = prompt_splits(&mut answers.as_bytes(), &mut output).unwrap();
+
+        let entered: Vec<_> = splits
+            .iter()
+            .map(|s| (s.name.as_str(), s.percent, s.min_time))
+            .collect();
+        assert_eq!(
+            entered,
+            vec![
+                ("Sunny Villa", 10, None),
+                ("Cloud Spires", 12, None),
+                ("Sunny Villa 2", 10, Some(Duration::from_secs(300))),
+            ]
+        );
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Invalid percentage 'abc'"), "{}", output);
+        assert!(output.contains("Another split is at 10%"), "{}", output);
+    }
+
+    #[test]
+    fn new_file_from_splits_or_another_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("spyro3.json");
+        let splits = [
+            ("Sunny Villa".to_string(), 10),
+            ("Cloud Spires".to_string(), 20),
+        ];
+        let created = create_splits_file(path.clone(), &splits, None).unwrap();
+        assert_eq!(Splits::load_from_file(&path).unwrap(), created);
+        assert!(create_splits_file(path.clone(), &splits, None).is_err());
+
+        let copy = dir.path().join("spyro3-2.json");
+        let copied = create_splits_file(copy, &[], Some(&path)).unwrap();
+        assert_eq!(copied.splits(), created.splits());
+
+        assert_eq!(
+            parse_split("Sunny Villa=10%").unwrap(),
+            ("Sunny Villa".to_string(), 10)
+        );
+        assert!(parse_split("Sunny Villa").is_err());
+    }
+}
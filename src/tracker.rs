@@ -0,0 +1,820 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use opencv::core::Rect;
+use unicode_width::UnicodeWidthStr;
+
+use crate::filter::{Debounce, MajorityVote};
+use crate::in_game_time::InGameTime;
+use crate::ocr::SlotLock;
+use crate::splits::{
+    BackgroundSave, Comparison, DEFAULT_SAVE_INTERVAL, Splits, SplitsDisplay, TimerEvent,
+    TimerObserver,
+};
+use crate::watchdog::Watchdog;
+
+/// Accepts a new value of a secondary reading (counter, level name) once it was read in a number of
+/// consecutive frames.
+#[derive(Debug, Clone)]
+struct Confirmed<T> {
+    value: Option<T>,
+    candidate: Option<T>,
+    seen: usize,
+}
+
+impl<T: Clone + PartialEq> Confirmed<T> {
+    fn new() -> Self {
+        Self {
+            value: None,
+            candidate: None,
+            seen: 0,
+        }
+    }
+
+    /// Adds the reading of the latest frame (`None` if nothing could be read) and returns the value
+    /// if it was just confirmed after `count` consecutive reads.
+    fn push(&mut self, reading: Option<T>, count: usize) -> Option<T> {
+        if reading.is_some() && reading == self.candidate {
+            self.seen += 1;
+        } else {
+            self.candidate = reading;
+            self.seen = 1;
+        }
+
+        let candidate = self.candidate.clone()?;
+        if self.seen < count || self.value.as_ref() == Some(&candidate) {
+            return None;
+        }
+        self.value = Some(candidate.clone());
+        Some(candidate)
+    }
+}
+
+/// A collectible counter in the HUD (e.g. gems), read from its own ROI with the digit templates.
+#[derive(Debug, Clone)]
+pub struct Counter {
+    name: String,
+    roi: Rect,
+    reading: Confirmed<u32>,
+}
+
+impl Counter {
+    pub fn new(name: String, roi: Rect) -> Self {
+        Self {
+            name,
+            roi,
+            reading: Confirmed::new(),
+        }
+    }
+
+    pub fn roi(&self) -> Rect {
+        self.roi
+    }
+
+    pub fn with_roi(mut self, roi: Rect) -> Self {
+        self.roi = roi;
+        self
+    }
+}
+
+/// Tracks the IGT of a single runner, i.e. one ROI in the captured frame feeding its own `Splits`.
+pub struct Tracker {
+    label: String,
+    roi: Rect,
+    fallback_rois: Vec<Rect>,
+    splits: Splits,
+    display: SplitsDisplay,
+    vote: MajorityVote,
+    debounce: Debounce,
+    confirmations: usize,
+    counters: Vec<Counter>,
+    level_roi: Option<Rect>,
+    level: Confirmed<String>,
+    slot_lock: Option<SlotLock>,
+    watchdog: Option<Watchdog>,
+    run_summary: bool,
+    /// Notified of the events of the splits, saving them (in the background) first
+    observers: Vec<Box<dyn TimerObserver>>,
+    last_igt: InGameTime,
+    run_finished: bool,
+    /// Whether the splits file was changed by another program and the change was reported, see
+    /// `check_external_edit`
+    external_edit: bool,
+}
+
+impl Tracker {
+    pub fn new(label: String, roi: Rect, splits: Splits) -> Self {
+        Self {
+            label,
+            roi,
+            fallback_rois: Vec::new(),
+            splits,
+            display: SplitsDisplay::new(),
+            vote: MajorityVote::new(1),
+            debounce: Debounce::new(1),
+            confirmations: 1,
+            counters: Vec::new(),
+            level_roi: None,
+            level: Confirmed::new(),
+            slot_lock: None,
+            watchdog: None,
+            run_summary: false,
+            observers: vec![Box::new(BackgroundSave::new(DEFAULT_SAVE_INTERVAL))],
+            last_igt: InGameTime::default(),
+            run_finished: false,
+            external_edit: false,
+        }
+    }
+
+    /// Creates a tracker labelled after the file name of the splits file, e.g. "spyro1" for
+    /// spyro1.json or spyro1.json.gz.
+    pub fn from_splits_file(path: &Path, roi: Rect) -> anyhow::Result<Self> {
+        let splits = Splits::load_from_file(path)?;
+        let label = path
+            .file_stem()
+            .map(|stem| {
+                let stem = stem.to_string_lossy();
+                stem.strip_suffix(".json").unwrap_or(&stem).to_string()
+            })
+            .unwrap_or_else(|| path.display().to_string());
+        Ok(Self::new(label, roi, splits))
+    }
+
+    /// Only accept an IGT once it was read in the majority of the last `window` frames.
+    pub fn with_vote_window(mut self, window: usize) -> Self {
+        self.vote = MajorityVote::new(window);
+        self
+    }
+
+    /// Only accept a new IGT once it was read in `count` consecutive frames.
+    pub fn with_debounce(mut self, count: usize) -> Self {
+        self.debounce = Debounce::new(count);
+        self.confirmations = count.max(1);
+        self
+    }
+
+    /// Reads the given collectible counters as well, which can trigger splits (see `CounterTrigger`).
+    pub fn with_counters(mut self, counters: Vec<Counter>) -> Self {
+        self.counters = counters;
+        self
+    }
+
+    /// Tries the given ROIs (in order) whenever the IGT isn't found in the ROI, until one of them
+    /// shows it (see `lock_roi`).
+    pub fn with_fallback_rois(mut self, rois: Vec<Rect>) -> Self {
+        self.fallback_rois = rois;
+        self
+    }
+
+    /// Reads the level name banner in `roi` as well, which can trigger splits (see `LevelTrigger`).
+    pub fn with_level_roi(mut self, roi: Rect) -> Self {
+        self.level_roi = Some(roi);
+        self
+    }
+
+    /// Only matches around the character positions of the last read IGT (see `SlotLock`).
+    pub fn with_slot_lock(mut self) -> Self {
+        self.slot_lock = Some(SlotLock::new());
+        self
+    }
+
+    /// Shows the deltas of the split view against `comparison` instead of the PB.
+    pub fn with_comparison(mut self, comparison: Comparison) -> Self {
+        self.display = self.display.with_comparison(comparison);
+        self
+    }
+
+    /// Prints the run statistics of the splits (see `Splits::format_run_statistics`) when a run
+    /// finishes.
+    pub fn with_run_summary(mut self) -> Self {
+        self.run_summary = true;
+        self
+    }
+
+    /// Shows the wall clock time of the run (RTA) in the split view next to the IGT.
+    pub fn with_real_time(mut self) -> Self {
+        self.display = self.display.with_real_time();
+        self
+    }
+
+    /// Sets the category of the runs started from now on (see `RunSummary::category`).
+    pub fn with_category(mut self, category: String) -> Self {
+        self.splits.set_run_category(Some(category));
+        self
+    }
+
+    /// Warns when no IGT was accepted for `timeout` during an active run (see `check_watchdog`).
+    pub fn with_watchdog(mut self, timeout: Duration) -> Self {
+        self.watchdog = Some(Watchdog::new(timeout));
+        self
+    }
+
+    /// Saves intermediate changes of the splits at most every `interval`, see `BackgroundSave`.
+    pub fn with_save_interval(mut self, interval: Duration) -> Self {
+        // The first observer saves the splits
+        self.observers[0] = Box::new(BackgroundSave::new(interval));
+        self
+    }
+
+    /// Notifies `observer` of the events of the splits as well (see `TimerEvent`), e.g. an
+    /// integration. It is handed the splits right away like after a reload, e.g. to serve them
+    /// before the first event.
+    pub fn with_observer(mut self, mut observer: Box<dyn TimerObserver>) -> Self {
+        observer.on_reload(&self.splits);
+        self.observers.push(observer);
+        self
+    }
+
+    /// Filters the raw OCR reading of a frame (`None` if nothing could be read) and returns the IGT
+    /// to feed into `update`, if any.
+    pub fn accept(&mut self, reading: Option<InGameTime>) -> Option<InGameTime> {
+        let voted = self.vote.push(reading);
+        let accepted = self.debounce.push(voted);
+        if accepted.is_some()
+            && let Some(watchdog) = &mut self.watchdog
+        {
+            watchdog.feed(Instant::now());
+        }
+        accepted
+    }
+
+    /// Returns a warning if a run is active but no IGT was accepted for longer than the watchdog's
+    /// timeout, repeated once per timeout while that lasts.
+    pub fn check_watchdog(&mut self) -> Option<String> {
+        let run_active = self
+            .splits
+            .active_run()
+            .is_some_and(|run| run.end_time.is_none());
+        let silence = self.watchdog.as_mut()?.check(Instant::now(), run_active)?;
+        Some(format!(
+            "No IGT read for {} min during the active run (last: {}), check the capture and the ROI",
+            silence.as_secs() / 60,
+            self.last_igt
+        ))
+    }
+
+    /// Whether `reading` can follow the last accepted IGT, i.e. the time didn't run backwards while
+    /// the percentage stayed the same or went up. (A lower percentage is treated as a new run.)
+    pub fn is_plausible(&self, reading: &InGameTime) -> bool {
+        reading.percent < self.last_igt.percent || reading.duration >= self.last_igt.duration
+    }
+
+    /// Turns readings above the category's maximum percentage (see `Splits::with_max_percent`) into
+    /// errors, they can only be misreads (e.g. "917%").
+    pub fn check_max_percent(
+        &self,
+        reading: anyhow::Result<InGameTime>,
+    ) -> anyhow::Result<InGameTime> {
+        match (reading, self.splits.max_percent()) {
+            (Ok(igt), Some(max)) if igt.percent > max => Err(anyhow::anyhow!(
+                "{}% is above the category's maximum of {}%",
+                igt.percent,
+                max
+            )),
+            (reading, _) => reading,
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// The IGT last fed into `update`.
+    pub fn last_igt(&self) -> &InGameTime {
+        &self.last_igt
+    }
+
+    /// The ROI, followed by the fallback ROIs until one of them was locked onto.
+    pub fn roi_candidates(&self) -> Vec<Rect> {
+        std::iter::once(self.roi)
+            .chain(self.fallback_rois.iter().copied())
+            .collect()
+    }
+
+    /// Keeps using `roi` (one of the candidates, which showed the IGT) and stops trying the others.
+    pub fn lock_roi(&mut self, roi: Rect) {
+        self.roi = roi;
+        self.fallback_rois.clear();
+        if let Some(slot_lock) = &mut self.slot_lock {
+            slot_lock.reset();
+        }
+    }
+
+    pub fn slot_lock_mut(&mut self) -> Option<&mut SlotLock> {
+        self.slot_lock.as_mut()
+    }
+
+    pub fn splits(&self) -> &Splits {
+        &self.splits
+    }
+
+    pub fn counters(&self) -> &[Counter] {
+        &self.counters
+    }
+
+    pub fn level_roi(&self) -> Option<Rect> {
+        self.level_roi
+    }
+
+    /// Feeds the reading of the counter at `index` (`None` if it couldn't be read) into the splits,
+    /// once the same value was read in as many consecutive frames as required for the IGT.
+    ///
+    /// Returns the lines to print if this recorded any splits.
+    pub fn update_counter(&mut self, index: usize, reading: Option<u32>) -> Option<Vec<String>> {
+        let counter = &mut self.counters[index];
+        let value = counter.reading.push(reading, self.confirmations)?;
+
+        let events = self
+            .splits
+            .update_with_counter(&counter.name, value, self.last_igt.duration);
+        let header = format!("{}: {}", counter.name, value);
+        self.dispatch(&events);
+        let mut lines = self.render_triggered_splits(header, &events)?;
+        lines.extend(self.finish_run(&events));
+        Some(lines)
+    }
+
+    /// Feeds the reading of the level name banner (`None` if it couldn't be read) into the splits,
+    /// once the same name was read in as many consecutive frames as required for the IGT.
+    ///
+    /// Returns the lines to print if this recorded any splits.
+    pub fn update_level(&mut self, reading: Option<String>) -> Option<Vec<String>> {
+        let level = self.level.push(reading, self.confirmations)?;
+
+        let events = self
+            .splits
+            .update_with_level(&level, self.last_igt.duration);
+        self.dispatch(&events);
+        let mut lines = self.render_triggered_splits(format!("Level: {}", level), &events)?;
+        lines.extend(self.finish_run(&events));
+        Some(lines)
+    }
+
+    /// Notifies the observers of `events`.
+    fn dispatch(&mut self, events: &[TimerEvent]) {
+        for event in events {
+            for observer in &mut self.observers {
+                observer.on_event(&self.splits, event);
+            }
+        }
+    }
+
+    /// Lets the observers finish handling the events so far, so that e.g. a pending background save
+    /// doesn't overwrite the splits saved directly afterwards.
+    fn flush_observers(&mut self) {
+        for observer in &mut self.observers {
+            observer.flush();
+        }
+    }
+
+    /// Marks the run as finished if `events` finished it and returns the run statistics to print
+    /// then (if enabled, see `with_run_summary`).
+    fn finish_run(&mut self, events: &[TimerEvent]) -> Vec<String> {
+        let mut lines = Vec::new();
+        if events
+            .iter()
+            .any(|event| matches!(event, TimerEvent::RunFinished { .. }))
+        {
+            if self.run_summary {
+                lines.push(String::new());
+                lines.push(self.splits.format_stats());
+                lines.extend(self.splits.format_run_statistics());
+            }
+            self.run_finished = true;
+        }
+        lines
+    }
+
+    /// Renders the splits recorded by a trigger at the latest IGT (see `events`) below `header`.
+    fn render_triggered_splits(
+        &self,
+        header: String,
+        events: &[TimerEvent],
+    ) -> Option<Vec<String>> {
+        let recorded: Vec<usize> = events
+            .iter()
+            .filter_map(|event| match event {
+                TimerEvent::SplitRecorded { index, .. } => Some(*index),
+                _ => None,
+            })
+            .collect();
+        if recorded.is_empty() {
+            return None;
+        }
+
+        let duration = self.last_igt.duration;
+        let name_width = self.splits.compute_name_width();
+        let mut lines = vec![header];
+        for split in recorded.iter().map(|&index| &self.splits.splits()[index]) {
+            let name_fmt =
+                Splits::pad_str(&Splits::truncate_name(&split.name, name_width), name_width);
+            lines.push(format!(
+                "{} {:>8} {:>8}",
+                name_fmt,
+                "      ",
+                Splits::format_time(Some(duration))
+            ));
+        }
+
+        Some(lines)
+    }
+
+    /// Moves all runs but the latest `keep` ones and the PB to the archive file next to the splits
+    /// file (see `Splits::archive_runs`). Returns the number of archived runs.
+    pub fn archive_runs(&mut self, keep: usize) -> anyhow::Result<usize> {
+        let archive_path = self
+            .splits
+            .archive_path()
+            .ok_or_else(|| anyhow::anyhow!("The splits have no file to archive next to"))?;
+        self.flush_observers();
+        self.splits.archive_runs(keep, &archive_path)
+    }
+
+    /// Reloads the splits if another program changed their file, e.g. when edited by hand. During a
+    /// run, which would be lost, asks to reload or merge the file instead (see
+    /// `resolve_external_edit`); otherwise the next save overwrites it. Returns the message to print,
+    /// if any.
+    pub fn check_external_edit(&mut self) -> Option<String> {
+        if !self.splits.modified_externally() {
+            self.external_edit = false;
+            return None;
+        }
+        if self.external_edit {
+            return None;
+        }
+        self.external_edit = true;
+
+        let path = self.splits_path();
+        if self.splits.resumable_run().is_some() {
+            return Some(format!(
+                "{} was changed by another program during the run. Enter 'reload' to load it (dropping the run) or 'merge' to add its runs, otherwise the next save overwrites it",
+                path
+            ));
+        }
+        Some(match self.reload() {
+            Ok(()) => format!("Reloaded {} after it was changed by another program", path),
+            Err(e) => format!("Failed to reload {}: {}", path, e),
+        })
+    }
+
+    /// Loads the splits file, dropping the active run, or with `merge` adds the runs in the file
+    /// to the splits (see `Splits::merge`) and saves them. Returns a description of the result.
+    pub fn resolve_external_edit(&mut self, merge: bool) -> anyhow::Result<String> {
+        let path = self.splits_path();
+        if !merge {
+            self.reload()?;
+            return Ok(format!("Reloaded {}", path));
+        }
+
+        let file = self
+            .splits
+            .path()
+            .ok_or_else(|| anyhow::anyhow!("The splits have no file"))?;
+        let other = Splits::load_from_file(file)?;
+        self.flush_observers();
+        let added = self.splits.merge(other)?;
+        self.splits.save_to_file()?;
+        self.external_edit = false;
+        Ok(format!("Added {} runs from {}", added, path))
+    }
+
+    fn reload(&mut self) -> anyhow::Result<()> {
+        self.splits.reload()?;
+        for observer in &mut self.observers {
+            observer.on_reload(&self.splits);
+        }
+        self.last_igt = InGameTime::default();
+        self.run_finished = false;
+        self.external_edit = false;
+        Ok(())
+    }
+
+    fn splits_path(&self) -> String {
+        self.splits
+            .path()
+            .map_or_else(|| self.label.clone(), |path| path.display().to_string())
+    }
+
+    /// Marks the active run as interrupted and saves the splits, e.g. when shutting down.
+    pub fn interrupt(&mut self) -> anyhow::Result<()> {
+        self.flush_observers();
+        self.splits.interrupt_active_run()
+    }
+
+    /// Removes the split time the active run recorded last, e.g. after a misread slipped through.
+    /// Returns a description of the removed split time.
+    pub fn undo(&mut self) -> anyhow::Result<String> {
+        self.flush_observers();
+        let removed = self.splits.undo_last_split()?;
+        let name = self
+            .splits
+            .find_split(&removed)
+            .map_or("", |s| s.name.as_str());
+        Ok(format!("Removed {} at {}", name, removed))
+    }
+
+    /// Feeds a recognized IGT into the splits.
+    ///
+    /// Returns `None` if the IGT didn't change since the last update, otherwise the lines to print
+    /// (which might be empty, e.g. if the run is already finished).
+    pub fn update(&mut self, igt: &InGameTime) -> Option<Vec<String>> {
+        if *igt == self.last_igt {
+            return None;
+        }
+
+        let mut lines = Vec::new();
+
+        if self.splits.active_run().is_none() {
+            lines.push("IGT:".to_string());
+            let name_width = self.splits.compute_name_width();
+            for split in self.splits.splits().iter().take(3) {
+                lines.push(Splits::format_split(name_width, split));
+            }
+
+            lines.push(String::new());
+            lines.push(self.splits.format_best_possible_time(name_width));
+
+            // FIXME: this doesn't work if the IGT/percentage is <= first split
+            // Not sure whether there's a solution without integrating with LiveSplit / providing explicit GUI controls
+            self.splits.initialize_active_run(igt);
+            self.run_finished = true;
+        }
+
+        let events = self.splits.update_with_igt(igt);
+        for observer in &mut self.observers {
+            observer.on_observation(&self.splits, igt);
+        }
+        self.dispatch(&events);
+
+        if events
+            .iter()
+            .any(|event| matches!(event, TimerEvent::RunStarted { .. }))
+        {
+            self.run_finished = false;
+        }
+
+        if !self.run_finished {
+            let view = self.display.render_split_view(&self.splits, igt, 3);
+            if !view.is_empty() {
+                lines.push("IGT:".to_string());
+                lines.extend(view);
+            }
+        }
+
+        lines.extend(self.finish_run(&events));
+
+        self.last_igt = *igt;
+
+        Some(lines)
+    }
+
+    /// Switches the split view to the next comparison and returns the re-rendered view (empty if
+    /// no run is shown).
+    pub fn cycle_comparison(&mut self) -> Vec<String> {
+        let comparison = self.display.comparison().next();
+        self.display.set_comparison(&self.splits, comparison);
+        self.render_comparison(comparison)
+    }
+
+    /// Switches the split view to racing against the golds and back (see
+    /// `SplitsDisplay::toggle_golds`) and returns the re-rendered view (empty if no run is shown).
+    pub fn toggle_golds(&mut self) -> Vec<String> {
+        let comparison = self.display.toggle_golds(&self.splits);
+        self.render_comparison(comparison)
+    }
+
+    /// The split view after switching to `comparison`
+    fn render_comparison(&mut self, comparison: Comparison) -> Vec<String> {
+        let mut lines = vec![format!("Comparing against {}", comparison.name())];
+        if !self.run_finished {
+            let view = self
+                .display
+                .render_split_view(&self.splits, &self.last_igt, 3);
+            if !view.is_empty() {
+                lines.push("IGT:".to_string());
+                lines.extend(view);
+            }
+        }
+        lines
+    }
+
+    /// Returns the time the active run recorded for the split at `index`, if any.
+    fn active_run_time_at(&self, index: usize) -> Option<Duration> {
+        let run_id = self.splits.active_run()?.id;
+        self.splits
+            .splits()
+            .get(index)?
+            .history
+            .iter()
+            .find(|h| h.run_id == run_id)
+            .map(|h| h.duration)
+    }
+}
+
+/// Renders a combined comparison of all runners, i.e. their latest IGT and the gap to the leader.
+///
+/// The gap is computed at the latest split both the leader and the runner have recorded in their
+/// active runs. If there is no such split, only the percent difference is shown.
+pub fn render_race_view(trackers: &[Tracker]) -> Vec<String> {
+    let Some(leader) = trackers.iter().max_by(|a, b| {
+        a.last_igt
+            .percent
+            .cmp(&b.last_igt.percent)
+            .then(b.last_igt.duration.cmp(&a.last_igt.duration))
+    }) else {
+        return Vec::new();
+    };
+
+    let label_width = trackers
+        .iter()
+        .map(|t| UnicodeWidthStr::width(t.label.as_str()))
+        .max()
+        .unwrap_or(0)
+        .min(25);
+
+    let mut lines = vec!["Race:".to_string()];
+    for tracker in trackers {
+        let label = Splits::pad_str(
+            &Splits::truncate_name(&tracker.label, label_width),
+            label_width,
+        );
+        let gap = if std::ptr::eq(tracker, leader) {
+            "leader".to_string()
+        } else {
+            let leader_splits = leader.splits.splits();
+            let common = (0..leader_splits.len())
+                .rev()
+                .filter(|&index| leader_splits[index].percent <= tracker.last_igt.percent)
+                .find_map(|index| {
+                    let tracker_index =
+                        tracker.splits.matching_split_index(&leader.splits, index)?;
+                    Some((
+                        leader_splits[index].percent,
+                        leader.active_run_time_at(index)?,
+                        tracker.active_run_time_at(tracker_index)?,
+                    ))
+                });
+
+            match common {
+                Some((percent, leader_time, time)) => {
+                    let delta = time.as_secs() as i64 - leader_time.as_secs() as i64;
+                    let sign = if delta >= 0 { '+' } else { '-' };
+                    format!(
+                        "{}{:02}:{:02} @ {}%",
+                        sign,
+                        delta.abs() / 60,
+                        delta.abs() % 60,
+                        percent
+                    )
+                }
+                None => format!(
+                    "-{}%",
+                    leader
+                        .last_igt
+                        .percent
+                        .saturating_sub(tracker.last_igt.percent)
+                ),
+            }
+        };
+        lines.push(format!(
+            "{} {:>14} {}",
+            label,
+            tracker.last_igt.to_string(),
+            gap
+        ));
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::fs;
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    /// A tracker labelled `label` with the splits `splits` (JSON objects of the splits file) fed the
+    /// readings `(percent, seconds)`. The first reading is only taken as the IGT the tracker starts
+    /// at, the run starts with the next one (at a lower percentage).
+    fn tracker(dir: &Path, label: &str, splits: &str, readings: &[(u32, u64)]) -> Tracker {
+        let path = dir.join(format!("{}.json", label));
+        fs::write(
+            &path,
+            format!(
+                r#"{{ "version": 3, "splits": {{ "personal_best": null, "runs": [], "splits": [{}] }} }}"#,
+                splits
+            ),
+        )
+        .unwrap();
+        let mut tracker = Tracker::new(
+            label.to_string(),
+            Rect::default(),
+            Splits::load_from_file(&path).unwrap(),
+        );
+        for &(percent, secs) in readings {
+            tracker.update(&InGameTime::from_secs(percent, secs));
+        }
+        tracker
+    }
+
+    #[test]
+    fn compressed_splits_files_are_labelled_like_uncompressed_ones() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("spyro1.json.gz");
+        let mut encoder = GzEncoder::new(fs::File::create(&path).unwrap(), Compression::default());
+        encoder
+            .write_all(
+                br#"{ "version": 3, "splits": { "personal_best": null, "runs": [], "splits": [
+                    { "name": "Artisans", "percent": 1, "time": null, "history": [] }
+                ] } }"#,
+            )
+            .unwrap();
+        encoder.finish().unwrap();
+
+        let tracker = Tracker::from_splits_file(&path, Rect::default()).unwrap();
+        assert_eq!(tracker.label(), "spyro1");
+    }
+
+    const SPLITS: &str = r#"
+        { "name": "Start", "percent": 1, "time": null, "history": [] },
+        { "name": "Sunny Villa", "percent": 2, "time": null, "history": [] },
+        { "name": "Cloud Spires", "percent": 4, "time": null, "history": [] },
+        { "name": "Gnasty Gnorc", "percent": 12, "time": null, "history": [] }"#;
+
+    #[test]
+    fn race_view_shows_the_gap_at_the_latest_common_split() {
+        let dir = tempdir().unwrap();
+        let trackers = [
+            tracker(
+                dir.path(),
+                "Émilie",
+                SPLITS,
+                &[(4, 600), (1, 10), (2, 60), (4, 200)],
+            ),
+            tracker(dir.path(), "Bob", SPLITS, &[(4, 600), (1, 12), (2, 75)]),
+        ];
+
+        let lines = render_race_view(&trackers);
+        assert_eq!(lines.len(), 3, "{:?}", lines);
+        assert_eq!(lines[0], "Race:");
+        assert!(lines[1].starts_with("Émilie "), "{:?}", lines);
+        assert!(lines[1].ends_with("leader"), "{:?}", lines);
+        // Padded to the width of the longest label, not its length in bytes
+        assert!(lines[2].starts_with("Bob    "), "{:?}", lines);
+        assert!(lines[2].ends_with("+00:15 @ 2%"), "{:?}", lines);
+    }
+
+    #[test]
+    fn race_view_shows_the_percent_gap_without_a_common_split() {
+        let dir = tempdir().unwrap();
+        let other_splits = r#"
+            { "name": "Peace Keepers", "percent": 3, "time": null, "history": [] },
+            { "name": "Magic Crafters", "percent": 5, "time": null, "history": [] },
+            { "name": "Beast Makers", "percent": 20, "time": null, "history": [] }"#;
+        let trackers = [
+            tracker(
+                dir.path(),
+                "a",
+                SPLITS,
+                &[(4, 600), (1, 10), (2, 60), (4, 200)],
+            ),
+            tracker(dir.path(), "b", other_splits, &[(5, 600), (1, 12), (3, 70)]),
+        ];
+
+        let lines = render_race_view(&trackers);
+        assert!(lines[1].ends_with("leader"), "{:?}", lines);
+        assert!(lines[2].ends_with(" -1%"), "{:?}", lines);
+    }
+
+    #[test]
+    fn race_view_tells_apart_splits_at_the_same_percent() {
+        let dir = tempdir().unwrap();
+        let splits = r#"
+            { "name": "Start", "percent": 1, "time": null, "history": [] },
+            { "name": "Sunny Villa", "percent": 2, "time": null, "history": [] },
+            { "name": "Sunny Villa Skateboarding", "percent": 2, "min_time": "0:02:00", "time": null, "history": [] },
+            { "name": "Cloud Spires", "percent": 4, "time": null, "history": [] }"#;
+        let trackers = [
+            tracker(
+                dir.path(),
+                "a",
+                splits,
+                &[(4, 600), (1, 10), (2, 60), (2, 130)],
+            ),
+            tracker(
+                dir.path(),
+                "b",
+                splits,
+                &[(4, 600), (1, 12), (2, 65), (2, 140)],
+            ),
+        ];
+
+        let lines = render_race_view(&trackers);
+        assert!(lines[1].ends_with("leader"), "{:?}", lines);
+        // At the second split at 2%, not the first one (+00:05)
+        assert!(lines[2].ends_with("+00:10 @ 2%"), "{:?}", lines);
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use opencv::core::{Rect, Size, Vector};
+use opencv::imgproc;
+use opencv::prelude::*;
+
+use crate::ocr::{self, TEMPLATE_SCALE, binarize_roi};
+use crate::preprocess::Preprocessing;
+
+/// Background pixels kept around each segmented glyph
+const GLYPH_MARGIN: i32 = 2;
+
+/// Margin below the worst genuine match if no other character separates it from the rest
+const DEFAULT_THRESHOLD_MARGIN: f64 = 0.05;
+
+/// Creates templates from labeled screenshots.
+///
+/// Every image in `input` (`.png` / `.jpg`) needs a `.txt` file with the same name containing the
+/// text shown in the ROI, e.g. `: 117% 3:03:23`. The glyphs in the ROI are segmented, assigned to
+/// the characters of the label and averaged per character. The resulting templates are written to
+/// `output`, together with a suggested threshold for each of them (see `ocr::THRESHOLDS_FILE`).
+pub fn train_templates(
+    input: &Path,
+    output: &Path,
+    roi: Rect,
+    preprocessing: &Preprocessing,
+) -> Result<()> {
+    let mut images: Vec<PathBuf> = fs::read_dir(input)
+        .map_err(|e| anyhow!("Failed to read {}: {}", input.display(), e))?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    images.retain(|path| {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ["png", "jpg", "jpeg"].contains(&ext.to_lowercase().as_str()))
+    });
+    images.sort();
+
+    let mut samples: BTreeMap<char, Vec<Mat>> = BTreeMap::new();
+    let mut used_images = 0;
+    for path in &images {
+        let label_path = path.with_extension("txt");
+        let Ok(label) = fs::read_to_string(&label_path) else {
+            println!(
+                "Skipping {}: no label file {}",
+                path.display(),
+                label_path.display()
+            );
+            continue;
+        };
+        let characters: Vec<char> = label.chars().filter(|c| !c.is_whitespace()).collect();
+
+        let frame =
+            opencv::imgcodecs::imread(&path.to_string_lossy(), opencv::imgcodecs::IMREAD_COLOR)?;
+        if frame.empty() {
+            println!("Skipping {}: failed to load image", path.display());
+            continue;
+        }
+        let binarized = binarize_roi(&frame, roi, preprocessing, false)?;
+
+        let glyphs = segment_glyphs(&binarized)?;
+        if glyphs.len() != characters.len() {
+            println!(
+                "Skipping {}: found {} glyphs, but the label has {} characters",
+                path.display(),
+                glyphs.len(),
+                characters.len()
+            );
+            continue;
+        }
+
+        for (character, glyph) in characters.into_iter().zip(glyphs) {
+            samples
+                .entry(character)
+                .or_default()
+                .push(Mat::roi(&binarized, glyph)?.try_clone()?);
+        }
+        used_images += 1;
+    }
+
+    if used_images == 0 {
+        return Err(anyhow!("No usable labeled images in {}", input.display()));
+    }
+    println!("Segmented {} of {} images", used_images, images.len());
+
+    fs::create_dir_all(output)
+        .map_err(|e| anyhow!("Failed to create {}: {}", output.display(), e))?;
+
+    let mut templates: BTreeMap<char, Mat> = BTreeMap::new();
+    for (&character, glyphs) in &samples {
+        let Some(filename) = ocr::template_file_name(character) else {
+            println!("Ignoring '{}': no template for this character", character);
+            continue;
+        };
+
+        let template = average_glyphs(glyphs)?;
+
+        // Template files are stored larger than the ROI, see `TEMPLATE_SCALE`
+        let mut template_file = Mat::default();
+        imgproc::resize(
+            &template,
+            &mut template_file,
+            Size::default(),
+            1.0 / TEMPLATE_SCALE,
+            1.0 / TEMPLATE_SCALE,
+            imgproc::INTER_LINEAR,
+        )?;
+        opencv::imgcodecs::imwrite(
+            &output.join(filename).to_string_lossy(),
+            &template_file,
+            &Vector::new(),
+        )?;
+
+        templates.insert(character, template);
+    }
+
+    let mut thresholds: BTreeMap<char, f64> = BTreeMap::new();
+    println!();
+    println!(
+        "{:>4} {:>8} {:>8} {:>8} {:>9}",
+        "char", "samples", "genuine", "impostor", "threshold"
+    );
+    for (&character, template) in &templates {
+        let mut genuine = f64::MAX;
+        let mut impostor: Option<f64> = None;
+        for (&other, glyphs) in &samples {
+            for glyph in glyphs {
+                let score = match_score(template, glyph)?;
+                if other == character {
+                    genuine = genuine.min(score);
+                } else {
+                    impostor = Some(impostor.map_or(score, |i| i.max(score)));
+                }
+            }
+        }
+
+        let threshold = (suggest_threshold(genuine, impostor) * 100.0).round() / 100.0;
+        thresholds.insert(character, threshold);
+        println!(
+            "{:>4} {:>8} {:>8.3} {:>8} {:>9.2}{}",
+            character,
+            samples[&character].len(),
+            genuine,
+            impostor.map_or("-".to_string(), |i| format!("{:.3}", i)),
+            threshold,
+            if impostor.is_some_and(|i| i >= genuine) {
+                "  (ambiguous, add more samples)"
+            } else {
+                ""
+            }
+        );
+    }
+    let thresholds_file = fs::File::create(output.join(ocr::THRESHOLDS_FILE))?;
+    serde_json::to_writer_pretty(thresholds_file, &thresholds)?;
+
+    println!();
+    println!("Templates and thresholds written to {}", output.display());
+
+    Ok(())
+}
+
+/// Returns the bounding boxes of the glyphs in a binarized ROI, from left to right.
+///
+/// Glyphs are separated by columns without any foreground pixels, so that e.g. the two dots of a
+/// ':' end up in the same glyph.
+pub fn segment_glyphs(binarized: &Mat) -> Result<Vec<Rect>> {
+    // The foreground is whatever covers less of the ROI (depends on the capture)
+    let mut foreground = binarized.try_clone()?;
+    if opencv::core::count_non_zero(binarized)? > binarized.rows() * binarized.cols() / 2 {
+        opencv::core::bitwise_not(binarized, &mut foreground, &opencv::core::no_array())?;
+    }
+
+    let mut filled_columns = Vec::with_capacity(foreground.cols() as usize);
+    for x in 0..foreground.cols() {
+        let column = Mat::roi(&foreground, Rect::new(x, 0, 1, foreground.rows()))?;
+        filled_columns.push(opencv::core::count_non_zero(&column)? > 0);
+    }
+
+    let mut glyphs = Vec::new();
+    for (start, end) in column_runs(&filled_columns) {
+        let columns = Rect::new(start, 0, end - start, foreground.rows());
+        let bounds = imgproc::bounding_rect(&Mat::roi(&foreground, columns)?)?;
+
+        let x = (start - GLYPH_MARGIN).max(0);
+        let y = (bounds.y - GLYPH_MARGIN).max(0);
+        glyphs.push(Rect::new(
+            x,
+            y,
+            (end + GLYPH_MARGIN).min(foreground.cols()) - x,
+            (bounds.y + bounds.height + GLYPH_MARGIN).min(foreground.rows()) - y,
+        ));
+    }
+    Ok(glyphs)
+}
+
+/// Returns the `start..end` ranges of consecutive `true` entries.
+pub fn column_runs(filled: &[bool]) -> Vec<(i32, i32)> {
+    let mut runs = Vec::new();
+    let mut start = None;
+    for (x, &is_filled) in filled.iter().enumerate() {
+        match (start, is_filled) {
+            (None, true) => start = Some(x as i32),
+            (Some(s), false) => {
+                runs.push((s, x as i32));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        runs.push((s, filled.len() as i32));
+    }
+    runs
+}
+
+/// Averages the glyphs (scaled to their median size) into a single grayscale template.
+fn average_glyphs(glyphs: &[Mat]) -> Result<Mat> {
+    let median = |mut values: Vec<i32>| {
+        values.sort();
+        values[values.len() / 2]
+    };
+    let size = Size::new(
+        median(glyphs.iter().map(|g| g.cols()).collect()),
+        median(glyphs.iter().map(|g| g.rows()).collect()),
+    );
+
+    let mut sum = Mat::zeros_size(size, opencv::core::CV_32F)?.to_mat()?;
+    for glyph in glyphs {
+        let mut resized = Mat::default();
+        imgproc::resize(glyph, &mut resized, size, 0.0, 0.0, imgproc::INTER_LINEAR)?;
+        let mut converted = Mat::default();
+        resized.convert_to(&mut converted, opencv::core::CV_32F, 1.0, 0.0)?;
+
+        let mut accumulated = Mat::default();
+        opencv::core::add(
+            &sum,
+            &converted,
+            &mut accumulated,
+            &opencv::core::no_array(),
+            -1,
+        )?;
+        sum = accumulated;
+    }
+
+    let mut average = Mat::default();
+    sum.convert_to(
+        &mut average,
+        opencv::core::CV_8U,
+        1.0 / glyphs.len() as f64,
+        0.0,
+    )?;
+    Ok(average)
+}
+
+/// Normalized correlation between a template (binarized like `Template::load_from_file` does) and a
+/// glyph scaled to the template's size.
+fn match_score(template: &Mat, glyph: &Mat) -> Result<f64> {
+    let mut binarized_template = Mat::default();
+    imgproc::threshold(
+        template,
+        &mut binarized_template,
+        0.0,
+        255.0,
+        imgproc::THRESH_OTSU,
+    )?;
+
+    let mut resized = Mat::default();
+    imgproc::resize(
+        glyph,
+        &mut resized,
+        template.size()?,
+        0.0,
+        0.0,
+        imgproc::INTER_LINEAR,
+    )?;
+
+    let mut result = Mat::default();
+    imgproc::match_template(
+        &resized,
+        &binarized_template,
+        &mut result,
+        imgproc::TM_CCOEFF_NORMED,
+        &opencv::core::no_array(),
+    )?;
+    Ok(*result.at_2d::<f32>(0, 0)? as f64)
+}
+
+/// Suggests a threshold between the worst match of a character's own samples (`genuine`) and the
+/// best match of any other character's samples (`impostor`).
+fn suggest_threshold(genuine: f64, impostor: Option<f64>) -> f64 {
+    match impostor {
+        Some(impostor) if impostor < genuine => (genuine + impostor) / 2.0,
+        _ => genuine - DEFAULT_THRESHOLD_MARGIN,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn column_runs_splits_on_empty_columns() {
+        let filled = [false, true, true, false, false, true, false, true];
+        assert_eq!(column_runs(&filled), vec![(1, 3), (5, 6), (7, 8)]);
+    }
+
+    #[test]
+    fn column_runs_of_empty_roi() {
+        assert!(column_runs(&[false, false]).is_empty());
+        assert!(column_runs(&[]).is_empty());
+    }
+
+    #[test]
+    fn suggested_threshold_separates_characters() {
+        assert!((suggest_threshold(0.9, Some(0.7)) - 0.8).abs() < 1e-9);
+        assert!((suggest_threshold(0.9, None) - 0.85).abs() < 1e-9);
+        assert!((suggest_threshold(0.8, Some(0.85)) - 0.75).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use opencv::highgui;
+
+use crate::ocr::{self, Templates};
+use crate::preprocess::Preprocessing;
+
+/// Window with the trackbars, shown along with the debug view
+const TUNING_WINDOW: &str = "Tuning";
+
+/// Trackbar of the binarization threshold, 0 meaning Otsu's method
+const BINARIZATION_TRACKBAR: &str = "binarize (0 = otsu)";
+
+/// Trackbars to tune the binarization threshold and the match threshold of every character while
+/// watching the debug view. Changes apply to the next frame; `save` writes them to the templates
+/// directory.
+pub struct Tuning {
+    binarization: i32,
+    /// Trackbar name, character and position (in hundredths) of each match threshold
+    thresholds: Vec<(String, char, i32)>,
+}
+
+impl Tuning {
+    /// Opens the trackbar window, with the trackbars set to the current values.
+    pub fn open(templates: &Templates, preprocessing: &Preprocessing) -> Result<Self> {
+        highgui::named_window(TUNING_WINDOW, highgui::WINDOW_NORMAL)?;
+
+        let binarization = preprocessing.binarization_threshold().unwrap_or(0.0) as i32;
+        highgui::create_trackbar(BINARIZATION_TRACKBAR, TUNING_WINDOW, None, 255, None)?;
+        highgui::set_trackbar_pos(BINARIZATION_TRACKBAR, TUNING_WINDOW, binarization)?;
+
+        let mut thresholds = Vec::new();
+        for template in templates.iter() {
+            let name = format!("match '{}'", template.character());
+            highgui::create_trackbar(&name, TUNING_WINDOW, None, 100, None)?;
+            thresholds.push((name, template.character(), 0));
+        }
+
+        let mut tuning = Self {
+            binarization,
+            thresholds,
+        };
+        tuning.sync(templates)?;
+        Ok(tuning)
+    }
+
+    /// Moves the match threshold trackbars to the thresholds of `templates`, e.g. after reloading.
+    pub fn sync(&mut self, templates: &Templates) -> Result<()> {
+        for template in templates.iter() {
+            if let Some((name, _, position)) = self
+                .thresholds
+                .iter_mut()
+                .find(|(_, character, _)| *character == template.character())
+            {
+                *position = (template.threshold() * 100.0).round() as i32;
+                highgui::set_trackbar_pos(name, TUNING_WINDOW, *position)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the trackbars that were moved since the last call.
+    pub fn apply(
+        &mut self,
+        templates: &mut Templates,
+        preprocessing: &mut Preprocessing,
+    ) -> Result<()> {
+        let binarization = highgui::get_trackbar_pos(BINARIZATION_TRACKBAR, TUNING_WINDOW)?;
+        if binarization != self.binarization {
+            self.binarization = binarization;
+            preprocessing
+                .set_binarization_threshold((binarization > 0).then_some(binarization as f64));
+        }
+
+        for (name, character, position) in &mut self.thresholds {
+            let current = highgui::get_trackbar_pos(name, TUNING_WINDOW)?;
+            if current != *position {
+                *position = current;
+                templates.set_threshold(*character, current as f32 / 100.0);
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the match thresholds to the thresholds file of the templates directory and prints the
+    /// preprocessing to pass as `--preprocess`.
+    pub fn save(&self, templates: &Templates, preprocessing: &Preprocessing) -> Result<()> {
+        let thresholds: BTreeMap<char, f32> = templates
+            .iter()
+            .map(|template| (template.character(), template.threshold()))
+            .collect();
+        let path = ocr::save_thresholds(templates.dir(), &thresholds)?;
+        println!("Thresholds written to {}", path.display());
+        println!("Preprocessing: --preprocess {}", preprocessing);
+        Ok(())
+    }
+}
+
+impl Drop for Tuning {
+    fn drop(&mut self) {
+        let _ = highgui::destroy_window(TUNING_WINDOW);
+    }
+}
@@ -0,0 +1,63 @@
+use std::time::{Duration, Instant};
+
+/// Notices when no valid IGT was read for a while, e.g. because the capture froze or the ROI is
+/// off. Warns once per `timeout` for as long as that lasts.
+#[derive(Debug, Clone)]
+pub struct Watchdog {
+    timeout: Duration,
+    last_reading_at: Instant,
+    last_warning_at: Option<Instant>,
+}
+
+impl Watchdog {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            last_reading_at: Instant::now(),
+            last_warning_at: None,
+        }
+    }
+
+    /// Records that a valid IGT was read at `now`.
+    pub fn feed(&mut self, now: Instant) {
+        self.last_reading_at = now;
+        self.last_warning_at = None;
+    }
+
+    /// Returns how long no IGT was read if that exceeds the timeout and no warning was due since.
+    /// Only warns while a run is active, the IGT is expected to be invisible otherwise.
+    pub fn check(&mut self, now: Instant, run_active: bool) -> Option<Duration> {
+        let since_reading = now.duration_since(self.last_reading_at);
+        let since_warning = self
+            .last_warning_at
+            .map_or(since_reading, |at| now.duration_since(at));
+        if !run_active || since_reading < self.timeout || since_warning < self.timeout {
+            return None;
+        }
+
+        self.last_warning_at = Some(now);
+        Some(since_reading)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warns_once_per_timeout_while_run_is_active() {
+        let start = Instant::now();
+        let minute = Duration::from_secs(60);
+        let mut watchdog = Watchdog::new(10 * minute);
+        watchdog.feed(start);
+
+        assert_eq!(watchdog.check(start + 9 * minute, true), None);
+        assert_eq!(watchdog.check(start + 10 * minute, false), None);
+        assert_eq!(watchdog.check(start + 10 * minute, true), Some(10 * minute));
+        assert_eq!(watchdog.check(start + 15 * minute, true), None);
+        assert_eq!(watchdog.check(start + 20 * minute, true), Some(20 * minute));
+
+        watchdog.feed(start + 21 * minute);
+        assert_eq!(watchdog.check(start + 25 * minute, true), None);
+    }
+}